@@ -0,0 +1,44 @@
+//! How long planning a large system takes, which is done before every command that generates it
+
+use core::{cell::Cell, time::Duration};
+use std::time::Instant;
+
+use criterion::Criterion;
+use jdexmd::bench::Planning;
+
+/// How long planning 10,000 folders may take on average, in a release build
+const BUDGET: Duration = Duration::from_millis(50);
+
+/// Plan 10 areas of 10 categories of 100 folders, failing when it goes over the budget
+fn planning(criterion: &mut Criterion) {
+    let planning = Planning::new(10, 10, 100).expect("The system is within the limits");
+    let measured = Cell::new((Duration::ZERO, 0));
+    criterion.bench_function("plan 10k folders", |bencher| {
+        bencher.iter_custom(|iterations| {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                planning.plan().expect("The system can be planned");
+            }
+            let elapsed = start.elapsed();
+            let (total, count) = measured.get();
+            measured.set((total + elapsed, count + iterations));
+            elapsed
+        });
+    });
+    // `cargo test --benches` runs each benchmark once in a debug build, so only `cargo bench` is
+    // held to the budget
+    let (total, count) = measured.get();
+    if std::env::args().any(|arg| arg == "--bench") && count > 0 {
+        let average = total / u32::try_from(count).unwrap_or(u32::MAX);
+        assert!(
+            average <= BUDGET,
+            "Planning 10k folders took {average:?}, over the budget of {BUDGET:?}"
+        );
+    }
+}
+
+fn main() {
+    let mut criterion = Criterion::default().configure_from_args();
+    planning(&mut criterion);
+    criterion.final_summary();
+}
@@ -0,0 +1,215 @@
+use std::{
+    fs,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use color_eyre::eyre::{Error, OptionExt};
+
+use crate::{
+    config::{self, JohnnyDecimal},
+    history, label, line,
+    model::{Folder, FolderKind, System},
+    notes,
+    output::{self, Mark},
+    prompt::Prompt,
+    resolve,
+};
+
+/// Add a folder with `topic` for the id `id`, which isn't in the system yet, under its category,
+/// returning its full id
+fn add_folder(system: &mut System, id: &str, topic: &str) -> Result<String, Error> {
+    let full = resolve::id_in_name(system, id).ok_or_eyre(format!("{id} isn't an id"))?;
+    let (category_id, number) = full
+        .rsplit_once('.')
+        .and_then(|(category, number)| Some((category.to_owned(), number.parse::<u8>().ok()?)))
+        .ok_or_eyre(format!(
+            "{id} isn't in the system, and only a new folder like 21.07 can be added for it"
+        ))?;
+    let category = system
+        .areas
+        .iter_mut()
+        .flat_map(|area| &mut area.categories)
+        .find(|category| category.id.matches(&category_id))
+        .ok_or_eyre(format!(
+            "{id} isn't in the system, and neither is its category {category_id}"
+        ))?;
+    let topic: Arc<str> = Arc::from(topic);
+    let position = category
+        .folders
+        .iter()
+        .position(|folder| folder.id.folder.as_ref().is_some_and(|id| id.id > number))
+        .unwrap_or(category.folders.len());
+    category.folders.insert(
+        position,
+        Folder {
+            id: category.id.clone().folder_id(number, Arc::clone(&topic)),
+            topic,
+            // An adopted directory stays a directory, whatever the default kind is
+            kind: FolderKind::Folder,
+            retired: false,
+            frozen: false,
+            locations: Vec::new(),
+            status: None,
+            index_name: None,
+            folders: Vec::new(),
+        },
+    );
+    Ok(full)
+}
+
+/// `definition` with the line of the folder `id`, which was added to `system`, under its category
+fn with_folder(definition: &str, system: &System, id: &str) -> Result<String, Error> {
+    let added = line::format_lines(system)
+        .into_iter()
+        .find(|formatted| formatted.id() == id)
+        .ok_or_eyre(format!("No entry with the id {id} was found"))?;
+    let (entry, _) = label::entries_under(system, id)
+        .into_iter()
+        .next()
+        .ok_or_eyre(format!("No entry with the id {id} was found"))?;
+    let category = entry
+        .jid()
+        .parent()
+        .ok_or_eyre(format!("{id} isn't under a category"))?;
+    line::insert_entry(definition, &category, &added.line)
+}
+
+/// Ask for the topic of the new id `id`, suggesting the name of the directory at `path`
+fn ask_topic(
+    prompt: &mut Prompt<impl BufRead, impl Write>,
+    id: &str,
+    path: &Path,
+) -> Result<String, Error> {
+    let suggested = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let answer = prompt.ask(
+        &format!("{id} isn't in the system yet, topic for it:"),
+        Some(&suggested),
+    )?;
+    Ok(answer.unwrap_or(suggested))
+}
+
+/// The folder a directory at `path` is adopted into, the notes when it is already in them and
+/// the reference archive otherwise
+fn base_for(output_config: &config::Output, path: &Path) -> Result<PathBuf, Error> {
+    let notes = notes::expand(&output_config.base_folder)?;
+    let in_notes = notes
+        .canonicalize()
+        .is_ok_and(|notes| path.starts_with(notes));
+    if in_notes {
+        Ok(notes)
+    } else {
+        notes::expand(&output_config.reference_folder)
+    }
+}
+
+/// Move the directory at `path` to the place of `id`, adding `id` to the config file first when it
+/// isn't in the system, with `topic` or the topic asked for with `prompt`
+pub fn run(
+    config_file: &PathBuf,
+    output_config: &config::Output,
+    system: &System,
+    path: &Path,
+    id: &str,
+    topic: Option<String>,
+    mut prompt: Prompt<impl BufRead, impl Write>,
+) -> Result<(), Error> {
+    if !path.is_dir() {
+        return Err(Error::msg(format!("{} isn't a directory", path.display())));
+    }
+    let path = path.canonicalize()?;
+    let (changed, id) = if let Ok(id) = resolve::resolve(system, id, None) {
+        (None, id)
+    } else {
+        let topic = match topic {
+            Some(topic) => topic,
+            None => ask_topic(&mut prompt, id, &path)?,
+        };
+        let mut changed = System::try_from(JohnnyDecimal::from_file(config_file)?.system_config)?;
+        let id = add_folder(&mut changed, id, &topic)?;
+        (Some(changed), id)
+    };
+    let system = changed.as_ref().unwrap_or(system);
+    let (entry, _) = label::entries_under(system, &id)
+        .into_iter()
+        .next()
+        .ok_or_eyre(format!("No entry with the id {id} was found"))?;
+    let target = base_for(output_config, &path)?.join(entry.jid().as_path());
+    if target.exists() {
+        return Err(Error::msg(format!(
+            "{} already exists, file what is in {} into it instead",
+            target.display(),
+            path.display()
+        )));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&path, &target)?;
+    if changed.is_some() {
+        JohnnyDecimal::edit_definition(config_file, |definition| {
+            with_folder(definition, system, &id)
+        })?;
+    }
+    history::adopted(
+        config_file,
+        output_config.state_folder.as_deref(),
+        system,
+        &id,
+        entry.name(),
+        &path,
+    )?;
+    write!(
+        prompt.output(),
+        "{}",
+        output::line(
+            Mark::Changed,
+            "Adopted",
+            &format!("{} as {}", output::path(&path), output::path(&target))
+        )
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::SystemParameters, line::format_system, test_support::parameters};
+
+    #[test]
+    fn test_add_folder() {
+        let mut system = System::try_from(SystemParameters {
+            default_folder_kind: FolderKind::Index,
+            ..parameters("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n\t\t09 Receipts\n")
+        })
+        .unwrap();
+        assert_eq!(
+            add_folder(&mut system, "21.5", "Scans").unwrap(),
+            "L01.21.05"
+        );
+        assert_eq!(
+            format_system(&system),
+            "20-29 Finance\n\t21 Tax\n\t\t01 !Returns\n\t\t05 Scans [folder]\n\t\t09 !Receipts\n"
+        );
+        let (entry, _) = label::entries_under(&system, "L01.21.05")[0];
+        assert_eq!(
+            entry.jid().as_path(),
+            PathBuf::from("L01/L01.20-29 Finance/L01.21 Tax/L01.21.05 Scans")
+        );
+        assert_eq!(
+            with_folder(
+                "# Taxes\n20-29 Finance\n\t21 Tax\n\t\t01 !Returns\n\t\t# Kept for seven years\n\t\t09 !Receipts\n",
+                &system,
+                "L01.21.05"
+            )
+            .unwrap(),
+            "# Taxes\n20-29 Finance\n\t21 Tax\n\t\t01 !Returns\n\t\t05 Scans [folder]\n\t\t# Kept for seven years\n\t\t09 !Receipts\n"
+        );
+        assert!(add_folder(&mut system, "31.01", "Trips").is_err());
+        assert!(add_folder(&mut system, "20-29", "Money").is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use color_eyre::eyre::Error;
+use serde::Serialize;
+
+use crate::{
+    label,
+    model::{FolderKind, HasJohnnyId, System},
+    notes::Action,
+};
+
+/// An id that can be completed, with where it is in the vault
+#[derive(Debug, Serialize)]
+struct Completion {
+    /// The full id, like `L01.21.05`
+    id: String,
+    /// The topic of the entry
+    topic: String,
+    /// The path of the entry's note, or its folder when it has no note, relative to the vault
+    path: String,
+}
+
+impl Completion {
+    /// The completion for `entry`, whose note has the given extension
+    fn new(entry: &dyn HasJohnnyId, kind: Option<&FolderKind>, extension: &str) -> Self {
+        let path = label::note_path(Path::new(""), entry, kind, extension);
+        Self {
+            id: entry.jid().by_seperator("."),
+            topic: entry.name().to_owned(),
+            // Obsidian separates the folders of a vault path with `/` on every platform
+            path: path
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        }
+    }
+}
+
+/// Every id that isn't retired, from the areas down, as JSON
+fn render(system: &System, extension: &str) -> Result<String, Error> {
+    let mut completions = Vec::new();
+    for area in &system.areas {
+        completions.push(Completion::new(area, None, extension));
+        for category in area.categories.iter().filter(|category| !category.retired) {
+            completions.push(Completion::new(category, None, extension));
+            for folder in category.folders.iter().filter(|folder| !folder.retired) {
+                completions.push(Completion::new(folder, Some(&folder.kind), extension));
+                for xfolder in folder.folders.iter().filter(|xfolder| !xfolder.retired) {
+                    completions.push(Completion::new(xfolder, Some(&xfolder.kind), extension));
+                }
+            }
+        }
+    }
+    Ok(serde_json::to_string_pretty(&completions)?)
+}
+
+/// The action writing the autocomplete file at `name` in `base_path`, when the config asks for one
+pub fn action<'sys>(
+    base_path: &Path,
+    system: &System,
+    extension: &str,
+    name: Option<&str>,
+) -> Result<Option<Action<'sys>>, Error> {
+    name.map(|name| {
+        Ok(Action::WriteAutocomplete(
+            base_path.join(name),
+            render(system, extension)?,
+        ))
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_autocomplete() {
+        let system = system("20-29 Finance\n\t21 Tax\n\t\t05 -Returns\n\t\t06 Old [retired]\n");
+        let completions: Vec<serde_json::Value> =
+            serde_json::from_str(&render(&system, "md").unwrap()).unwrap();
+        assert_eq!(
+            completions,
+            vec![
+                serde_json::json!({"id": "L01.20-29", "topic": "Finance", "path": "L01/L01.20-29 Finance"}),
+                serde_json::json!({"id": "L01.21", "topic": "Tax", "path": "L01/L01.20-29 Finance/L01.21 Tax"}),
+                serde_json::json!({
+                    "id": "L01.21.05",
+                    "topic": "Returns",
+                    "path": "L01/L01.20-29 Finance/L01.21 Tax/L01.21.05 Returns.md"
+                }),
+            ]
+        );
+    }
+}
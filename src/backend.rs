@@ -0,0 +1,356 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Write as _,
+    path::{Path, PathBuf},
+    process,
+};
+
+use color_eyre::eyre::{Error, OptionExt};
+
+use crate::{
+    link::{self, LinkStyle},
+    mirror::{self, MirrorMode},
+};
+
+/// Where generated files and directories are written, so generation doesn't depend on the disk
+pub trait OutputBackend {
+    /// Whether anything exists at `path`
+    fn exists(&self, path: &Path) -> bool;
+    /// Whether `path` is a directory
+    fn is_dir(&self, path: &Path) -> bool;
+    /// The paths of everything directly inside the directory at `path`
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, Error>;
+    /// Read the file at `path`
+    fn read_to_string(&self, path: &Path) -> Result<String, Error>;
+    /// Create the directory at `path` along with any missing parents
+    fn create_dir_all(&mut self, path: &Path) -> Result<(), Error>;
+    /// Write `contents` to the file at `path`, replacing whatever was there
+    fn write(&mut self, path: &Path, contents: &str) -> Result<(), Error>;
+    /// Create a link at `link` pointing at the directory `target`
+    fn create_link(&mut self, link: &Path, target: &Path, style: LinkStyle) -> Result<(), Error>;
+    /// Mirror the file at `from` to `to`
+    fn mirror(&mut self, from: &Path, to: &Path, mode: MirrorMode) -> Result<(), Error>;
+    /// Move whatever is at `from`, along with everything under it, to `to`
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<(), Error>;
+}
+
+/// Write `contents` to a temporary file next to `path` and rename it into place.
+///
+/// The rename replaces the file in one step, so an interrupted write or a sync client reading
+/// along never sees it half written.
+fn write_atomically(path: &Path, contents: &str) -> Result<(), Error> {
+    let name = path
+        .file_name()
+        .ok_or_eyre("Unable to write a file without a name")?;
+    let temporary =
+        path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), process::id()));
+    let written = File::create(&temporary).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(err) = written.and_then(|()| fs::rename(&temporary, path)) {
+        let _ = fs::remove_file(&temporary);
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+/// Writes to the local filesystem
+#[derive(Debug, Default)]
+pub struct LocalFs;
+
+impl OutputBackend for LocalFs {
+    fn exists(&self, path: &Path) -> bool {
+        // A link counts even when its target is missing
+        fs::symlink_metadata(path).is_ok()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        Ok(fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String, Error> {
+        Ok(fs::read_to_string(path)?)
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> Result<(), Error> {
+        Ok(fs::create_dir_all(path)?)
+    }
+
+    fn write(&mut self, path: &Path, contents: &str) -> Result<(), Error> {
+        write_atomically(path, contents)
+    }
+
+    fn create_link(&mut self, link: &Path, target: &Path, style: LinkStyle) -> Result<(), Error> {
+        link::create(link, target, style)
+    }
+
+    fn mirror(&mut self, from: &Path, to: &Path, mode: MirrorMode) -> Result<(), Error> {
+        mirror::create(from, to, mode)
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<(), Error> {
+        // A rename on its own would quietly replace a file or an empty directory at `to`
+        if self.exists(to) {
+            return Err(Error::msg(format!("{} already exists", to.display())));
+        }
+        Ok(fs::rename(from, to)?)
+    }
+}
+
+/// Something kept in a `MemoryFs`
+#[derive(Debug, PartialEq, Eq)]
+pub enum Entry {
+    /// A directory
+    Directory,
+    /// A file with its contents
+    File(String),
+    /// A link to another directory
+    Link(PathBuf),
+}
+
+/// Keeps everything in memory
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    /// Every path that has been created
+    pub entries: BTreeMap<PathBuf, Entry>,
+}
+
+impl OutputBackend for MemoryFs {
+    fn exists(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        matches!(self.entries.get(path), Some(Entry::Directory))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        if !self.is_dir(path) {
+            return Err(Error::msg(format!("{} is not a directory", path.display())));
+        }
+        Ok(self
+            .entries
+            .keys()
+            .filter(|entry| entry.parent() == Some(path))
+            .cloned()
+            .collect())
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String, Error> {
+        match self.entries.get(path) {
+            Some(Entry::File(contents)) => Ok(contents.clone()),
+            _ => Err(Error::msg(format!("{} is not a file", path.display()))),
+        }
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> Result<(), Error> {
+        for ancestor in path.ancestors() {
+            if ancestor.as_os_str().is_empty() {
+                break;
+            }
+            match self.entries.get(ancestor) {
+                Some(Entry::Directory) => {}
+                Some(Entry::File(_) | Entry::Link(_)) => {
+                    return Err(Error::msg(format!(
+                        "{} is not a directory",
+                        ancestor.display()
+                    )));
+                }
+                None => {
+                    self.entries
+                        .insert(ancestor.to_path_buf(), Entry::Directory);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, path: &Path, contents: &str) -> Result<(), Error> {
+        if self.is_dir(path) {
+            return Err(Error::msg(format!("{} is a directory", path.display())));
+        }
+        self.entries
+            .insert(path.to_path_buf(), Entry::File(contents.to_owned()));
+        Ok(())
+    }
+
+    fn create_link(&mut self, link: &Path, target: &Path, _style: LinkStyle) -> Result<(), Error> {
+        if self.exists(link) {
+            return Err(Error::msg(format!("{} already exists", link.display())));
+        }
+        self.entries
+            .insert(link.to_path_buf(), Entry::Link(target.to_path_buf()));
+        Ok(())
+    }
+
+    fn mirror(&mut self, from: &Path, to: &Path, _mode: MirrorMode) -> Result<(), Error> {
+        let contents = self.read_to_string(from)?;
+        self.write(to, &contents)
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<(), Error> {
+        if !self.exists(from) {
+            return Err(Error::msg(format!("{} doesn't exist", from.display())));
+        }
+        if self.exists(to) {
+            return Err(Error::msg(format!("{} already exists", to.display())));
+        }
+        let moved: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+        for path in moved {
+            if let (Some(entry), Ok(rest)) = (self.entries.remove(&path), path.strip_prefix(from)) {
+                self.entries.insert(to.join(rest), entry);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads from another backend but only records the writes, for dry runs
+pub struct Recorder<'inner> {
+    /// The backend that is read from
+    inner: &'inner dyn OutputBackend,
+    /// What would have been written
+    pub written: MemoryFs,
+}
+
+impl<'inner> Recorder<'inner> {
+    /// Record the writes that would be made to `inner`
+    pub fn new(inner: &'inner dyn OutputBackend) -> Self {
+        Self {
+            inner,
+            written: MemoryFs::default(),
+        }
+    }
+}
+
+impl OutputBackend for Recorder<'_> {
+    fn exists(&self, path: &Path) -> bool {
+        self.written.exists(path) || self.inner.exists(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.written.is_dir(path) || self.inner.is_dir(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut entries = if self.inner.is_dir(path) {
+            self.inner.read_dir(path)?
+        } else {
+            Vec::new()
+        };
+        if self.written.is_dir(path) {
+            entries.extend(self.written.read_dir(path)?);
+        }
+        entries.sort();
+        entries.dedup();
+        Ok(entries)
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String, Error> {
+        if self.written.exists(path) {
+            self.written.read_to_string(path)
+        } else {
+            self.inner.read_to_string(path)
+        }
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> Result<(), Error> {
+        self.written.create_dir_all(path)
+    }
+
+    fn write(&mut self, path: &Path, contents: &str) -> Result<(), Error> {
+        self.written.write(path, contents)
+    }
+
+    fn create_link(&mut self, link: &Path, target: &Path, style: LinkStyle) -> Result<(), Error> {
+        self.written.create_link(link, target, style)
+    }
+
+    fn mirror(&mut self, _from: &Path, to: &Path, _mode: MirrorMode) -> Result<(), Error> {
+        // The file may not be text, and only that it would exist matters to a dry run
+        self.written.write(to, "")
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<(), Error> {
+        // Only that something would exist at `to` matters to a dry run
+        if self.is_dir(from) {
+            self.written.create_dir_all(to)
+        } else {
+            self.written.write(to, "")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_fs() {
+        let mut memory = MemoryFs::default();
+        memory.create_dir_all(Path::new("/vault/N01")).unwrap();
+        memory
+            .write(Path::new("/vault/N01/index.md"), "# Index")
+            .unwrap();
+        assert!(memory.is_dir(Path::new("/vault")));
+        assert_eq!(
+            memory.read_dir(Path::new("/vault/N01")).unwrap(),
+            vec![PathBuf::from("/vault/N01/index.md")]
+        );
+        assert!(memory.write(Path::new("/vault/N01"), "").is_err());
+        memory
+            .rename(Path::new("/vault/N01"), Path::new("/vault/N02"))
+            .unwrap();
+        assert_eq!(
+            memory
+                .read_to_string(Path::new("/vault/N02/index.md"))
+                .unwrap(),
+            "# Index"
+        );
+        assert!(!memory.exists(Path::new("/vault/N01")));
+        assert!(memory
+            .rename(Path::new("/vault/N01"), Path::new("/vault/N03"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_local_fs_writes_atomically() {
+        let folder = std::env::temp_dir().join(format!("jdexmd-atomic-{}", process::id()));
+        let mut local = LocalFs;
+        local.create_dir_all(&folder).unwrap();
+        let index = folder.join("N01.00.00 JDex.md");
+        local.write(&index, "# Old").unwrap();
+        local.write(&index, "# New").unwrap();
+        assert_eq!(local.read_to_string(&index).unwrap(), "# New");
+        // Nothing is left behind next to the index
+        assert_eq!(local.read_dir(&folder).unwrap(), vec![index]);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_recorder_only_records() {
+        let mut memory = MemoryFs::default();
+        memory.create_dir_all(Path::new("/vault")).unwrap();
+        let mut recorder = Recorder::new(&memory);
+        recorder.create_dir_all(Path::new("/vault/N01")).unwrap();
+        assert!(recorder.exists(Path::new("/vault/N01")));
+        assert_eq!(
+            recorder.read_dir(Path::new("/vault")).unwrap(),
+            vec![PathBuf::from("/vault/N01")]
+        );
+        assert!(!memory.exists(Path::new("/vault/N01")));
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::{BufRead, Write};
+
+use color_eyre::eyre::{Error, OptionExt};
+
+use crate::{
+    config,
+    label::{self, LinkKind},
+    model::{FolderKind, HasJohnnyId, System},
+    next_id, notes, resolve,
+};
+
+/// The queries a batch understands, for the answer to an unknown one
+const QUERIES: &str = "path, reference, link, file, topic, next-id";
+
+/// Answer a single query, like `path 21.05`, with one line
+fn answer(
+    output_config: &config::Output,
+    system: &System,
+    query: &str,
+    extension: &str,
+) -> Result<String, Error> {
+    let (verb, argument) = query.split_once(' ').unwrap_or((query, ""));
+    let argument = argument.trim();
+    if argument.is_empty() {
+        return Err(Error::msg(format!("{verb} needs an id")));
+    }
+    let id = resolve::resolve(system, argument, None)?;
+    if verb == "next-id" {
+        let category = system
+            .areas
+            .iter()
+            .flat_map(|area| &area.categories)
+            .find(|category| category.id.matches(&id))
+            .ok_or_eyre(format!("{id} is not a category"))?;
+        let number = next_id::next_free(category)
+            .ok_or_eyre(format!("Every folder id in {id} has been used"))?;
+        return Ok(format!("{id}.{number:02}"));
+    }
+    let (entry, kind): (&dyn HasJohnnyId, Option<&FolderKind>) = label::entries_under(system, &id)
+        .into_iter()
+        .next()
+        .ok_or_eyre(format!("No entry with the id {id} was found"))?;
+    match verb {
+        "path" => {
+            let base = notes::expand(&output_config.base_folder)?;
+            Ok(label::note_path(&base, entry, kind, extension)
+                .display()
+                .to_string())
+        }
+        "reference" => Ok(notes::expand(&output_config.reference_folder)?
+            .join(entry.jid().as_path())
+            .display()
+            .to_string()),
+        "link" => label::deep_link(output_config, entry, kind, LinkKind::Obsidian, extension),
+        "file" => label::deep_link(output_config, entry, kind, LinkKind::File, extension),
+        "topic" => Ok(entry.name().to_owned()),
+        _ => Err(Error::msg(format!(
+            "Unknown query {verb}, expected one of {QUERIES}"
+        ))),
+    }
+}
+
+/// Answer every query read from `input`, one line each, until it ends or a line says `quit`.
+///
+/// Blank lines and lines starting with `#` are skipped. A query that fails is answered with
+/// `error: ` and the reason, and the batch carries on.
+pub fn run(
+    output_config: &config::Output,
+    system: &System,
+    extension: &str,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> Result<(), Error> {
+    for line in input.lines() {
+        let line = line?;
+        let query = line.trim();
+        if query.is_empty() || query.starts_with('#') {
+            continue;
+        }
+        if query == "quit" {
+            break;
+        }
+        match answer(output_config, system, query, extension) {
+            Ok(answer) => writeln!(output, "{answer}")?,
+            Err(err) => writeln!(output, "error: {err}")?,
+        }
+        // Whoever is on the other end of a pipe is waiting for the answer
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{depth::TreeConfig, link::LinkStyle, mirror::MirrorMode, test_support::system};
+
+    #[test]
+    fn test_batch() {
+        let system = system("20-29 Finance\n\t21 Tax\n\t\t05 -Returns\n");
+        let output_config = config::Output {
+            base_folder: "/notes".to_owned(),
+            reference_folder: "/archive".to_owned(),
+            link_style: LinkStyle::None,
+            mirror: MirrorMode::None,
+            notes: TreeConfig::default(),
+            archive: TreeConfig::default(),
+            inbox: None,
+            attachments: None,
+            state_folder: None,
+            autocomplete: None,
+            area_mocs: false,
+            note_counts: false,
+            targets: Vec::new(),
+        };
+        let input = "# a comment\npath 21.5\n\ntopic 2105\nnext-id 21\nsize 21\nquit\ntopic 21\n";
+        let mut output = Vec::new();
+        run(&output_config, &system, "md", input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "/notes/L01/L01.20-29 Finance/L01.21 Tax/L01.21.05 Returns.md\nReturns\nL01.21.01\nerror: Unknown query size, expected one of {QUERIES}\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use core::fmt::Write as _;
+
+use color_eyre::eyre::Error;
+
+use crate::{
+    config::{self, SystemParameters},
+    depth::TreeConfig,
+    link::LinkStyle,
+    mirror::MirrorMode,
+    model::System,
+    planner::Planner,
+};
+
+/// Build a system with `areas` areas of `categories` categories of `folders` folders each
+pub(crate) fn sized_system(areas: u8, categories: u8, folders: u8) -> Result<System, Error> {
+    let mut config = String::new();
+    for area in 0..areas {
+        writeln!(config, "{area}0-{area}9 Area {area}")?;
+        for category in 0..categories {
+            writeln!(config, "\t{area}{category} Category {area}{category}")?;
+            for folder in 0..folders {
+                writeln!(config, "\t\t{folder:02} Folder {folder}")?;
+            }
+        }
+    }
+    System::try_from(SystemParameters {
+        system_id: "L01".to_owned(),
+        name: "Large".to_owned(),
+        config,
+        ..SystemParameters::default()
+    })
+}
+
+/// A sized system along with the folders it is planned into
+pub struct Planning {
+    /// Where the notes and the reference archive are planned into
+    output_config: config::Output,
+    /// The system to plan
+    system: System,
+}
+
+impl Planning {
+    /// A system with `areas` areas of `categories` categories of `folders` folders each, linked
+    /// from the notes into the reference archive
+    ///
+    /// # Errors
+    ///
+    /// When the system can't be built, which means there are more than 10 areas or categories
+    pub fn new(areas: u8, categories: u8, folders: u8) -> Result<Self, Error> {
+        Ok(Self {
+            output_config: config::Output {
+                base_folder: "/notes".to_owned(),
+                reference_folder: "/archive".to_owned(),
+                link_style: LinkStyle::Symlink,
+                mirror: MirrorMode::None,
+                notes: TreeConfig::default(),
+                archive: TreeConfig::default(),
+                inbox: None,
+                attachments: None,
+                state_folder: None,
+                autocomplete: None,
+                area_mocs: false,
+                note_counts: false,
+                targets: Vec::new(),
+            },
+            system: sized_system(areas, categories, folders)?,
+        })
+    }
+
+    /// Plan the whole system, returning how many actions there are
+    ///
+    /// # Errors
+    ///
+    /// When the plan can't be worked out
+    pub fn plan(&self) -> Result<usize, Error> {
+        Ok(Planner::new(&self.output_config, &self.system, "md")?
+            .all()?
+            .len())
+    }
+}
@@ -0,0 +1,474 @@
+use core::{cmp::Reverse, fmt::Write as _, iter};
+use std::{
+    fs,
+    io::{self, Write as _},
+    path::{Component, Path, PathBuf},
+};
+
+use color_eyre::eyre::Error;
+
+use crate::{
+    backend::{Entry, MemoryFs},
+    config::{self, STANDARD_STREAM},
+    markdown::MdFormatter,
+    notes::{self, Action},
+    timestamp::Timestamp,
+};
+
+/// The folder the notes are stored in, in an archive
+const NOTES_ROOT: &str = "notes";
+
+/// The folder the reference archive is stored in, in an archive
+const ARCHIVE_ROOT: &str = "archive";
+
+/// The size of a block of a tar archive, which every header and file is padded to
+const BLOCK: usize = 512;
+
+/// The version of the zip format needed to extract the archive, 2.0 for directories
+const ZIP_VERSION: u16 = 20;
+
+/// The zip flag saying the names are UTF-8
+const UTF8_NAMES: u16 = 0x0800;
+
+/// Generate every directory, file, and link of `actions` into memory, as if nothing existed yet.
+///
+/// Files mirrored into the archive are left out, since they are copies of what was already there
+/// rather than anything generated.
+pub fn generate(actions: &[Action<'_>], formatter: &MdFormatter<'_>) -> Result<MemoryFs, Error> {
+    let mut memory = MemoryFs::default();
+    for action in actions {
+        if matches!(action, Action::MirrorFile(_, _, _)) || !notes::need_to_apply(action, &memory) {
+            continue;
+        }
+        action.execute(formatter, &mut memory)?;
+    }
+    Ok(memory)
+}
+
+/// Every file and link, each after a `--- path` line, with the target of a link on that line
+fn text(memory: &MemoryFs) -> String {
+    let mut text = String::new();
+    for (path, entry) in &memory.entries {
+        match entry {
+            Entry::Directory => {}
+            Entry::Link(target) => {
+                let _ = writeln!(text, "--- {} -> {}", path.display(), target.display());
+            }
+            Entry::File(contents) => {
+                let _ = writeln!(text, "--- {}", path.display());
+                text.push_str(contents);
+                if !contents.is_empty() && !contents.ends_with('\n') {
+                    text.push('\n');
+                }
+            }
+        }
+    }
+    text
+}
+
+/// Where the notes and the reference archive were generated, which an archive stores as the
+/// folders `notes/` and `archive/` wherever they are on disk
+pub struct Roots {
+    /// Where the notes were generated
+    notes: PathBuf,
+    /// Where the reference archive was generated
+    archive: PathBuf,
+}
+
+impl Roots {
+    /// The folders of `output_config`, expanded the way generation expands them
+    pub fn new(output_config: &config::Output) -> Result<Self, Error> {
+        Ok(Self {
+            notes: notes::expand(&output_config.base_folder)?,
+            archive: notes::expand(&output_config.reference_folder)?,
+        })
+    }
+
+    /// The path as it is stored in an archive, relative to `notes/` or `archive/` so it is
+    /// extracted where the archive is opened, or `None` for the directories they are in.
+    ///
+    /// When one folder is inside the other, what is in the inner one belongs to it.
+    fn member_name(&self, path: &Path) -> Option<String> {
+        let mut roots = [(&self.notes, NOTES_ROOT), (&self.archive, ARCHIVE_ROOT)];
+        roots.sort_by_key(|(root, _)| Reverse(root.components().count()));
+        let (relative, name) = roots
+            .iter()
+            .find_map(|(root, name)| Some((path.strip_prefix(root).ok()?, name)))?;
+        let components = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            });
+        Some(
+            iter::once((*name).into())
+                .chain(components)
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+}
+
+/// Put `value` into `field` as zero padded octal digits followed by a NUL
+fn octal(field: &mut [u8], value: u64) {
+    let digits = format!("{value:0width$o}", width = field.len().saturating_sub(1));
+    copy(field, digits.as_bytes());
+}
+
+/// Copy as much of `value` as fits into the start of `field`
+fn copy(field: &mut [u8], value: &[u8]) {
+    let length = value.len().min(field.len());
+    field[..length].copy_from_slice(&value[..length]);
+}
+
+/// Pad `archive` with zeros to the next block
+fn pad(archive: &mut Vec<u8>) {
+    let remainder = archive.len() % BLOCK;
+    if remainder != 0 {
+        archive.resize(archive.len() + BLOCK - remainder, 0);
+    }
+}
+
+/// A pax record, which starts with its own length in bytes, that length included
+fn pax_record(key: &str, value: &str) -> String {
+    let rest = format!(" {key}={value}\n");
+    let mut length = rest.len();
+    // Adding the digits of the length can make the length longer by a digit
+    while format!("{length}{rest}").len() != length {
+        length = format!("{length}{rest}").len();
+    }
+    format!("{length}{rest}")
+}
+
+/// Add a ustar header for a member to `archive`, with an extended pax header before it when the
+/// name or the target of a link is too long for the header
+fn header(archive: &mut Vec<u8>, name: &str, kind: u8, size: usize, link: &str, mtime: u64) {
+    let mut extended = String::new();
+    if name.len() > 100 {
+        extended.push_str(&pax_record("path", name));
+    }
+    if link.len() > 100 {
+        extended.push_str(&pax_record("linkpath", link));
+    }
+    if !extended.is_empty() {
+        header(archive, "PaxHeader", b'x', extended.len(), "", mtime);
+        archive.extend(extended.as_bytes());
+        pad(archive);
+    }
+    let mut block = [0_u8; BLOCK];
+    copy(&mut block[0..100], name.as_bytes());
+    let mode = if kind == b'0' { 0o644 } else { 0o755 };
+    octal(&mut block[100..108], mode);
+    octal(&mut block[108..116], 0);
+    octal(&mut block[116..124], 0);
+    octal(
+        &mut block[124..136],
+        u64::try_from(size).unwrap_or_default(),
+    );
+    octal(&mut block[136..148], mtime);
+    // The checksum is taken with its own field filled with spaces
+    block[148..156].fill(b' ');
+    block[156] = kind;
+    copy(&mut block[157..257], link.as_bytes());
+    copy(&mut block[257..265], b"ustar\x0000");
+    let checksum: u64 = block.iter().map(|byte| u64::from(*byte)).sum();
+    octal(&mut block[148..155], checksum);
+    archive.extend(block);
+}
+
+/// Every directory, file, and link under `roots` as a tar archive, with `mtime` as when each was
+/// modified
+pub fn tar(memory: &MemoryFs, roots: &Roots, mtime: u64) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for (path, entry) in &memory.entries {
+        let Some(name) = roots.member_name(path) else {
+            continue;
+        };
+        match entry {
+            Entry::Directory => header(&mut archive, &format!("{name}/"), b'5', 0, "", mtime),
+            Entry::Link(target) => header(
+                &mut archive,
+                &name,
+                b'2',
+                0,
+                &target.to_string_lossy(),
+                mtime,
+            ),
+            Entry::File(contents) => {
+                header(&mut archive, &name, b'0', contents.len(), "", mtime);
+                archive.extend(contents.as_bytes());
+                pad(&mut archive);
+            }
+        }
+    }
+    // The end of the archive is marked by two empty blocks
+    archive.resize(archive.len() + 2 * BLOCK, 0);
+    archive
+}
+
+/// The CRC-32 of `bytes`, the checksum zip archives keep for every file
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Every directory, file, and link under `roots` as a zip archive, stored without compression,
+/// with `modified` as when each was modified.
+///
+/// Links are kept the way Info-ZIP keeps them, as a file holding the target with the mode of a
+/// symlink, so unzip on a unix system makes them links again.
+pub fn zip(memory: &MemoryFs, roots: &Roots, modified: Timestamp) -> Result<Vec<u8>, Error> {
+    let (time, date) = modified.dos();
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    let mut count: u16 = 0;
+    let too_large = || Error::msg("The system is too large for a zip archive");
+    for (path, entry) in &memory.entries {
+        let Some(name) = roots.member_name(path) else {
+            continue;
+        };
+        let (name, contents, mode): (String, Vec<u8>, u32) = match entry {
+            Entry::Directory => (format!("{name}/"), Vec::new(), 0o40_755),
+            Entry::Link(target) => (
+                name,
+                target.to_string_lossy().into_owned().into_bytes(),
+                0o120_777,
+            ),
+            Entry::File(contents) => (name, contents.clone().into_bytes(), 0o100_644),
+        };
+        let offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+        let size = u32::try_from(contents.len()).map_err(|_| too_large())?;
+        let name_length = u16::try_from(name.len()).map_err(|_| too_large())?;
+        let crc = crc32(&contents);
+        // The fields the local header and the central directory have in common
+        let mut common = Vec::new();
+        common.extend(ZIP_VERSION.to_le_bytes());
+        common.extend(UTF8_NAMES.to_le_bytes());
+        common.extend(0_u16.to_le_bytes());
+        common.extend(time.to_le_bytes());
+        common.extend(date.to_le_bytes());
+        common.extend(crc.to_le_bytes());
+        common.extend(size.to_le_bytes());
+        common.extend(size.to_le_bytes());
+        common.extend(name_length.to_le_bytes());
+        common.extend(0_u16.to_le_bytes());
+
+        archive.extend(0x0403_4b50_u32.to_le_bytes());
+        archive.extend(&common);
+        archive.extend(name.as_bytes());
+        archive.extend(&contents);
+
+        directory.extend(0x0201_4b50_u32.to_le_bytes());
+        // Made on unix, so the mode in the external attributes is read
+        directory.extend(((3_u16 << 8) | ZIP_VERSION).to_le_bytes());
+        directory.extend(&common);
+        // No comment, on the first disk, and not known to be text
+        directory.extend([0; 6]);
+        let dos_directory = if matches!(entry, Entry::Directory) {
+            0x10
+        } else {
+            0
+        };
+        directory.extend(((mode << 16) | dos_directory).to_le_bytes());
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+        count = count.checked_add(1).ok_or_else(too_large)?;
+    }
+    let start = u32::try_from(archive.len()).map_err(|_| too_large())?;
+    let length = u32::try_from(directory.len()).map_err(|_| too_large())?;
+    archive.extend(directory);
+    archive.extend(0x0605_4b50_u32.to_le_bytes());
+    // The archive is a single disk, so the directory starts on the first one
+    archive.extend([0; 4]);
+    archive.extend(count.to_le_bytes());
+    archive.extend(count.to_le_bytes());
+    archive.extend(length.to_le_bytes());
+    archive.extend(start.to_le_bytes());
+    archive.extend(0_u16.to_le_bytes());
+    Ok(archive)
+}
+
+/// Generate everything `actions` would make and print it instead of writing it, as text with a
+/// `--- path` line before each file, or as a tar archive of what is under `roots` written to
+/// `tar_file`, `-` for stdout
+pub fn run(
+    actions: &[Action<'_>],
+    formatter: &MdFormatter<'_>,
+    roots: &Roots,
+    tar_file: Option<&Path>,
+    started: Timestamp,
+) -> Result<(), Error> {
+    let memory = generate(actions, formatter)?;
+    match tar_file {
+        None => io::stdout().write_all(text(&memory).as_bytes())?,
+        Some(path) if path == Path::new(STANDARD_STREAM) => {
+            io::stdout().write_all(&tar(&memory, roots, started.seconds()))?;
+        }
+        Some(path) => fs::write(path, tar(&memory, roots, started.seconds()))?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{backend::OutputBackend as _, link::LinkStyle};
+
+    fn memory() -> MemoryFs {
+        let mut memory = MemoryFs::default();
+        let tax = Path::new("/notes/L01/L01.20-29 Finance/L01.21 Tax");
+        memory.create_dir_all(tax).unwrap();
+        memory
+            .write(&tax.join("L01.21.01 Returns.md"), "# Returns\n")
+            .unwrap();
+        memory
+            .create_link(
+                &tax.join("archive"),
+                Path::new("/archive/L01.21 Tax"),
+                LinkStyle::Symlink,
+            )
+            .unwrap();
+        memory
+    }
+
+    fn roots() -> Roots {
+        Roots {
+            notes: PathBuf::from("/notes"),
+            archive: PathBuf::from("/archive"),
+        }
+    }
+
+    #[test]
+    fn test_member_name() {
+        let roots = Roots {
+            notes: PathBuf::from("/home/me/Vault"),
+            archive: PathBuf::from("/home/me/Vault/Archive"),
+        };
+        let tax = "L01/L01.20-29 Finance/L01.21 Tax";
+        assert_eq!(
+            roots.member_name(&Path::new("/home/me/Vault").join(tax)),
+            Some(format!("notes/{tax}"))
+        );
+        assert_eq!(
+            roots.member_name(&Path::new("/home/me/Vault/Archive").join(tax)),
+            Some(format!("archive/{tax}"))
+        );
+        assert_eq!(
+            roots.member_name(Path::new("/home/me/Vault")),
+            Some("notes".to_owned())
+        );
+        assert_eq!(roots.member_name(Path::new("/home/me")), None);
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            text(&memory()),
+            "--- /notes/L01/L01.20-29 Finance/L01.21 Tax/L01.21.01 Returns.md\n# Returns\n\
+             --- /notes/L01/L01.20-29 Finance/L01.21 Tax/archive -> /archive/L01.21 Tax\n"
+        );
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_zip() {
+        let archive = zip(&memory(), &roots(), Timestamp::from_seconds(0)).unwrap();
+        assert!(archive.starts_with(&0x0403_4b50_u32.to_le_bytes()));
+        // The end of the central directory counts every directory, file, and link
+        let end = archive.len() - 22;
+        assert_eq!(archive[end..end + 4], 0x0605_4b50_u32.to_le_bytes());
+        assert_eq!(
+            u16::from_le_bytes([archive[end + 10], archive[end + 11]]),
+            6
+        );
+        let start = usize::try_from(u32::from_le_bytes([
+            archive[end + 16],
+            archive[end + 17],
+            archive[end + 18],
+            archive[end + 19],
+        ]))
+        .unwrap();
+        assert_eq!(archive[start..start + 4], 0x0201_4b50_u32.to_le_bytes());
+        let text = String::from_utf8_lossy(&archive);
+        assert!(
+            text.contains("notes/L01/L01.20-29 Finance/L01.21 Tax/L01.21.01 Returns.md# Returns\n")
+        );
+        assert!(text.contains("notes/L01/L01.20-29 Finance/L01.21 Tax/archive/archive/L01.21 Tax"));
+    }
+
+    #[test]
+    fn test_pax_record() {
+        assert_eq!(pax_record("path", "a"), "9 path=a\n");
+        // The length takes two digits here, and both count toward it
+        assert_eq!(pax_record("path", "abcd"), "13 path=abcd\n");
+    }
+
+    #[test]
+    fn test_tar() {
+        let archive = tar(&memory(), &roots(), 0);
+        assert_eq!(archive.len() % BLOCK, 0);
+        // The archive ends with two empty blocks
+        assert!(archive[archive.len() - 2 * BLOCK..]
+            .iter()
+            .all(|byte| *byte == 0));
+        let headers: Vec<(String, u8)> = archive
+            .chunks(BLOCK)
+            .filter(|block| &block[257..262] == b"ustar")
+            .map(|block| {
+                let name = String::from_utf8_lossy(&block[..100])
+                    .trim_end_matches('\0')
+                    .to_owned();
+                (name, block[156])
+            })
+            .collect();
+        assert_eq!(
+            headers,
+            vec![
+                ("notes/".to_owned(), b'5'),
+                ("notes/L01/".to_owned(), b'5'),
+                ("notes/L01/L01.20-29 Finance/".to_owned(), b'5'),
+                ("notes/L01/L01.20-29 Finance/L01.21 Tax/".to_owned(), b'5'),
+                (
+                    "notes/L01/L01.20-29 Finance/L01.21 Tax/L01.21.01 Returns.md".to_owned(),
+                    b'0'
+                ),
+                (
+                    "notes/L01/L01.20-29 Finance/L01.21 Tax/archive".to_owned(),
+                    b'2'
+                ),
+            ]
+        );
+        // The checksum is the sum of the bytes of the header, with the checksum as spaces
+        let first = &archive[..BLOCK];
+        let mut blank = first.to_vec();
+        blank[148..156].fill(b' ');
+        let sum: u64 = blank.iter().map(|byte| u64::from(*byte)).sum();
+        assert_eq!(
+            String::from_utf8_lossy(&first[148..154]),
+            format!("{sum:06o}")
+        );
+        let long = PathBuf::from(format!("/notes/{}.md", "x".repeat(120)));
+        let mut memory = MemoryFs::default();
+        memory.create_dir_all(Path::new("/notes")).unwrap();
+        memory.write(&long, "").unwrap();
+        let archive = String::from_utf8_lossy(&tar(&memory, &roots(), 0)).into_owned();
+        assert!(archive.contains(&format!(" path=notes/{}.md\n", "x".repeat(120))));
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Error;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::SystemParameters, jid::JohnnyId, label, model::System, state, which::entries};
+
+/// The id of the entry each directory and note belongs to, relative to the notes or the reference
+/// archive
+pub type PathIndex = BTreeMap<PathBuf, JohnnyId>;
+
+/// The system built from a config file, and the index of its paths, as they are cached
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cached {
+    /// The fingerprint of the config file, and the version of jdexmd, that the system was built by
+    key: String,
+    /// The system built from the definition
+    pub system: System,
+    /// The entry each path belongs to
+    pub paths: PathIndex,
+}
+
+/// The directory and the note of every entry, with the id of the entry.
+///
+/// In the flat layout every area shares the directory of the system, which is kept for the first
+/// of them.
+pub fn index(system: &System, extension: &str) -> PathIndex {
+    let mut paths = PathIndex::new();
+    for (entry, kind) in entries(system) {
+        let note = label::note_path(Path::new(""), entry, kind, extension);
+        for path in [entry.jid().as_path().to_path_buf(), note] {
+            paths.entry(path).or_insert_with(|| entry.jid().clone());
+        }
+    }
+    paths
+}
+
+/// The file in the platform's place for caches, like `~/.cache/jdexmd` on Linux, that the system
+/// defined in `config_file` is cached in
+fn file(config_file: &Path) -> Option<PathBuf> {
+    let directories = ProjectDirs::from("", "", "jdexmd")?;
+    Some(
+        directories
+            .cache_dir()
+            .join(format!("{}.json", state::key(config_file))),
+    )
+}
+
+/// The system cached at `cache`, unless it is missing, unreadable, or was built from another
+/// version of the config file or by another version of jdexmd
+fn read(cache: &Path, key: &str) -> Option<Cached> {
+    let cached: Cached = serde_json::from_str(&fs::read_to_string(cache).ok()?).ok()?;
+    (cached.key == key).then_some(cached)
+}
+
+/// Cache `cached` at `cache`
+fn write(cache: &Path, cached: &Cached) -> Result<(), Error> {
+    if let Some(parent) = cache.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::write(cache, serde_json::to_string(cached)?)?)
+}
+
+/// Build the system defined in `config_file`, or read it from the cache when the config file with
+/// `fingerprint` was built before, so large definitions aren't parsed again on every call.
+///
+/// A cache that can't be written only costs the next call the time to build the system again.
+pub fn load(
+    config_file: &Path,
+    parameters: SystemParameters,
+    fingerprint: &str,
+    extension: &str,
+) -> Result<Cached, Error> {
+    let key = format!("{fingerprint}-{}", env!("CARGO_PKG_VERSION"));
+    let cache = file(config_file);
+    if let Some(mut cached) = cache.as_deref().and_then(|cache| read(cache, &key)) {
+        // The default kinds aren't part of the system as templates see it, so they aren't cached
+        cached.system.default_folder_kind = parameters.default_folder_kind;
+        cached.system.xfolder_kind = parameters.xfolder_kind;
+        return Ok(cached);
+    }
+    let system = System::try_from(parameters)?;
+    let paths = index(&system, extension);
+    let cached = Cached { key, system, paths };
+    if let Some(cache) = cache {
+        let _ = write(&cache, &cached);
+    }
+    Ok(cached)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+    use crate::{
+        config::FolderNames,
+        diff,
+        layout::Layout,
+        model::{FolderKind, KindInheritance},
+    };
+
+    fn parameters() -> SystemParameters {
+        SystemParameters {
+            system_id: "L01".to_owned(),
+            separator: None,
+            name: "Life".to_owned(),
+            config: "20-29 Finance\n\t21 Tax [status=active]\n\t\t05 -Returns\n\t\t\tX01 Drafts\n"
+                .to_owned(),
+            standard_zeros: false,
+            templates: BTreeMap::new(),
+            default_folder_kind: FolderKind::Folder,
+            hide_system_id: false,
+            layout: Layout::Nested,
+            xfolder_pattern: None,
+            xfolder_kind: KindInheritance::Folder,
+            folder_names: FolderNames::default(),
+            workspace: false,
+        }
+    }
+
+    #[test]
+    fn test_cache() {
+        let system = System::try_from(parameters()).unwrap();
+        let paths = index(&system, "md");
+        let id = |path: &str| paths.get(Path::new(path)).map(|id| id.by_seperator("."));
+        let tax = "L01/L01.20-29 Finance/L01.21 Tax";
+        assert_eq!(id(tax).as_deref(), Some("L01.21"));
+        assert_eq!(
+            id(&format!("{tax}/L01.21.05 Returns.md")).as_deref(),
+            Some("L01.21.05")
+        );
+        assert_eq!(id("L01"), None);
+        let indexed: Vec<PathBuf> = paths.keys().cloned().collect();
+        let cache = env::temp_dir()
+            .join(format!("jdexmd-cache-{}", process::id()))
+            .join("garden.json");
+        let key = "abc".to_owned();
+        write(&cache, &Cached { key, system, paths }).unwrap();
+        let cached = read(&cache, "abc").unwrap();
+        assert!(cached.paths.keys().eq(indexed.iter()));
+        let built = System::try_from(parameters()).unwrap();
+        assert!(diff::diff(&built, &cached.system).is_empty());
+        assert!(read(&cache, "def").is_none());
+        fs::remove_dir_all(cache.parent().unwrap()).unwrap();
+    }
+}
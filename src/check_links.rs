@@ -0,0 +1,263 @@
+use std::{
+    collections::BTreeMap,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Error;
+use unicode_normalization::UnicodeNormalization as _;
+
+use crate::{
+    backend::{LocalFs, OutputBackend},
+    config,
+    link::LINK_NAME,
+    model::System,
+    notes::{self, Action},
+    output::{self, Mark},
+    planner::Planner,
+};
+
+/// Where a link in an index points
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    /// The note or directory with this name anywhere in the vault, like `[[L01.21 Tax]]`
+    Name(String),
+    /// The file at this path, like an Org-mode `[[file:~/Notes/L01.21 Tax.org][L01.21 Tax]]`
+    File(String),
+}
+
+impl Target {
+    /// The link as it is written in the index
+    fn link(&self) -> String {
+        match self {
+            Self::Name(name) => format!("[[{name}]]"),
+            Self::File(path) => format!("[[file:{path}]]"),
+        }
+    }
+}
+
+/// A link of an index that doesn't lead anywhere
+#[derive(Debug, PartialEq, Eq)]
+struct Broken {
+    /// The index the link is in
+    index: PathBuf,
+    /// Where the link points
+    target: Target,
+    /// What the note or directory with the same id is called now, when there is one
+    renamed: Option<String>,
+}
+
+/// Every `[[...]]` link in `contents`, without the heading or alias of a wikilink
+fn links(contents: &str) -> Vec<Target> {
+    let mut links = Vec::new();
+    let mut rest = contents;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        let inside = &rest[..end];
+        rest = &rest[end + 2..];
+        // An Org-mode link has its description after `][`
+        let inside = inside.split_once("][").map_or(inside, |(link, _)| link);
+        if let Some(path) = inside.strip_prefix("file:") {
+            links.push(Target::File(path.to_owned()));
+            continue;
+        }
+        let name = inside.split(['|', '#']).next().unwrap_or_default().trim();
+        if !name.is_empty() {
+            links.push(Target::Name(name.to_owned()));
+        }
+    }
+    links
+}
+
+/// How a name is compared, composed and without case, the way the vault finds it
+fn key(name: &str) -> String {
+    name.nfc().collect::<String>().to_lowercase()
+}
+
+/// Add the name of every note and directory under `folder` to `names`, keyed by `key`, with notes
+/// also under their names without the extension
+fn collect_names(backend: &dyn OutputBackend, folder: &Path, names: &mut BTreeMap<String, String>) {
+    let Ok(entries) = backend.read_dir(folder) else {
+        return;
+    };
+    for entry in entries {
+        let Some(name) = entry
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+        else {
+            continue;
+        };
+        // Hidden folders like `.obsidian` and the links into the reference archive aren't part of
+        // the vault's notes
+        if name.starts_with('.') || name == LINK_NAME {
+            continue;
+        }
+        if backend.is_dir(&entry) {
+            collect_names(backend, &entry, names);
+        } else if let Some(stem) = entry.file_stem() {
+            let stem = stem.to_string_lossy().into_owned();
+            names.insert(key(&stem), stem);
+        }
+        names.insert(key(&name), name);
+    }
+}
+
+/// The index files among `actions`, the jdex files and the map of content notes
+fn indexes<'act>(actions: &'act [Action<'_>]) -> impl Iterator<Item = &'act PathBuf> {
+    actions.iter().filter_map(|action| match action {
+        Action::WriteIndex(path, _, _) | Action::WriteMoc(path, _, _) => Some(path),
+        _ => None,
+    })
+}
+
+/// Check the links of every index that `actions` generate into the notes under `base_folder`,
+/// returning the broken ones and how many links were checked
+fn check(
+    backend: &dyn OutputBackend,
+    base_folder: &str,
+    actions: &[Action<'_>],
+) -> Result<(Vec<Broken>, usize), Error> {
+    let base_path = notes::expand(base_folder)?;
+    let mut names = BTreeMap::new();
+    collect_names(backend, &base_path, &mut names);
+    let mut broken = Vec::new();
+    let mut checked: usize = 0;
+    for index in indexes(actions) {
+        let Some(index) = notes::find_existing(index, backend) else {
+            continue;
+        };
+        for target in links(&backend.read_to_string(&index)?) {
+            checked = checked.saturating_add(1);
+            let renamed = match &target {
+                Target::Name(name) => {
+                    if names.contains_key(&key(name)) {
+                        continue;
+                    }
+                    // The id stays when a note is renamed by hand, only the topic changes
+                    let id = name.split(' ').next().unwrap_or_default();
+                    let prefix = key(&format!("{id} "));
+                    names
+                        .iter()
+                        .find(|(key, _)| key.starts_with(&prefix))
+                        .map(|(_, name)| name.clone())
+                }
+                Target::File(path) => {
+                    if notes::find_existing(&notes::expand(path)?, backend).is_some() {
+                        continue;
+                    }
+                    None
+                }
+            };
+            broken.push(Broken {
+                index: index.clone(),
+                target,
+                renamed,
+            });
+        }
+    }
+    Ok((broken, checked))
+}
+
+/// Check that every link in the generated indexes leads to a note or directory of the vault,
+/// listing the ones broken by renames made outside jdexmd
+pub fn run(output_config: &config::Output, system: &System, extension: &str) -> Result<(), Error> {
+    let actions = Planner::new(output_config, system, extension)?.notes()?;
+    let (broken, checked) = check(&LocalFs, &output_config.base_folder, &actions)?;
+    let mut stdout = anstream::stdout();
+    for link in &broken {
+        let renamed = link
+            .renamed
+            .as_ref()
+            .map(|name| format!(", renamed to {}?", output::path(Path::new(name))))
+            .unwrap_or_default();
+        write!(
+            stdout,
+            "{}",
+            output::line(
+                Mark::Deleted,
+                "Broken",
+                &format!(
+                    "{} in {}{renamed}",
+                    link.target.link(),
+                    output::path(&link.index)
+                )
+            )
+        )?;
+    }
+    if broken.is_empty() {
+        write!(
+            stdout,
+            "{}",
+            output::line(
+                Mark::Applied,
+                "Checked",
+                &format!("{checked} links, all of them resolve")
+            )
+        )?;
+        Ok(())
+    } else {
+        Err(Error::msg(format!(
+            "Found {} broken links out of {checked}, run jdexmd to regenerate the indexes or rename \
+             the notes back",
+            broken.len()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::MemoryFs, test_support::system};
+
+    #[test]
+    fn test_links() {
+        assert_eq!(
+            links("- [[L01.21 Tax]] and [[L01.22 Insurance|Insurance]] [[L01.23 Bank#Accounts]]"),
+            vec![
+                Target::Name("L01.21 Tax".to_owned()),
+                Target::Name("L01.22 Insurance".to_owned()),
+                Target::Name("L01.23 Bank".to_owned()),
+            ]
+        );
+        assert_eq!(
+            links("- [[file:~/Notes/L01.21 Tax.org][L01.21 Tax]]\n- [[unclosed"),
+            vec![Target::File("~/Notes/L01.21 Tax.org".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let system = system(
+            "00-09 System\n\t00 Meta\n\t\t00 !!JDex\n20-29 Finance\n\t21 Tax\n\t\t01 -Returns\n\t\t02 -Receipts\n",
+        );
+        let mut memory = MemoryFs::default();
+        let meta = Path::new("/vault/L01/L01.00-09 System/L01.00 Meta");
+        let tax = Path::new("/vault/L01/L01.20-29 Finance/L01.21 Tax");
+        memory.create_dir_all(meta).unwrap();
+        memory.create_dir_all(tax).unwrap();
+        memory
+            .write(
+                &meta.join("L01.00.00 JDex.md"),
+                "- [[L01.21 Tax]]\n  - [[L01.21.01 Returns]]\n  - [[L01.21.02 Receipts]]\n",
+            )
+            .unwrap();
+        memory.write(&tax.join("L01.21.01 Returns.md"), "").unwrap();
+        memory
+            .write(&tax.join("L01.21.02 Old Receipts.md"), "")
+            .unwrap();
+        let actions = notes::get_all_actions(Path::new("/vault"), &system, "md");
+        let (broken, checked) = check(&memory, "/vault", &actions).unwrap();
+        assert_eq!(checked, 3);
+        assert_eq!(
+            broken,
+            vec![Broken {
+                index: meta.join("L01.00.00 JDex.md"),
+                target: Target::Name("L01.21.02 Receipts".to_owned()),
+                renamed: Some("L01.21.02 Old Receipts".to_owned()),
+            }]
+        );
+    }
+}
@@ -1,17 +1,58 @@
-use std::{fs::read_to_string, path::PathBuf};
+use core::fmt::Write as _;
+use std::{
+    collections::BTreeMap,
+    fs::{read_to_string, write},
+    io::{self, Read as _},
+    iter,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use color_eyre::eyre::Error;
+use handlebars::Handlebars;
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use toml_edit::{DocumentMut, Item, Value};
+use unicode_normalization::UnicodeNormalization as _;
 
 use crate::{
+    depth::TreeConfig,
+    history,
     jid::JohnnyId,
-    line::{parse_single, ParsedKind},
+    label,
+    layout::Layout,
+    line::{expand_ranges, expand_templates, parse_single, split_attributes, ParsedKind},
+    link::LinkStyle,
     markdown::MdFormatConfig,
-    model::{Area, Category, Folder, FolderKind, System, XFolder},
+    mirror::MirrorMode,
+    model::{Area, Category, Folder, FolderKind, KindInheritance, Status, System, XFolder},
+    naming::NamingConfig,
+    notes,
+    report::ReportConfig,
+    validate::LimitsConfig,
 };
 
+/// The path that stands for stdin or stdout instead of a file
+pub const STANDARD_STREAM: &str = "-";
+
+/// The pattern extended folder ids match when the config doesn't give one
+const DEFAULT_XFOLDER_PATTERN: &str = r"^X\d{2}$";
+
+/// Read the file at `path`, or stdin when the path is `-`
+pub fn read_source(path: &Path) -> Result<String, Error> {
+    if path == Path::new(STANDARD_STREAM) {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        Ok(contents)
+    } else {
+        Ok(read_to_string(path)?)
+    }
+}
+
 /// The configuration for the Johnny Decimal system
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
 pub struct SystemParameters {
     /// The system id for the Johnny Decimal system
     pub system_id: String,
@@ -19,40 +60,353 @@ pub struct SystemParameters {
     pub separator: Option<String>,
     /// The name of the system
     pub name: String,
-    /// The configuration definition for the system
+    /// The configuration definition for the system, empty when it is kept in a `.jdex` file
+    #[serde(default)]
     pub config: String,
+    /// Add the `x0` management category to every area and the `.00` folder to every category
+    #[serde(default)]
+    pub standard_zeros: bool,
+    /// Reusable structures, expanded under any entry that has `@template(name)`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, String>,
+    /// The kind of the folders whose line doesn't give one with a directive or a tag
+    #[serde(default)]
+    pub default_folder_kind: FolderKind,
+    /// Leave the system id out of the names and ids that are written, like `21.05 Returns`
+    /// instead of `L01.21.05 Returns`
+    #[serde(default)]
+    pub hide_system_id: bool,
+    /// How the directories of the system are nested, with a directory for each area or without
+    #[serde(default)]
+    pub layout: Layout,
+    /// The pattern every extended folder id has to match, `^X\d{2}$` when not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xfolder_pattern: Option<String>,
+    /// Where the extended folders whose line doesn't give a kind get theirs from: `folder` for a
+    /// plain folder, `parent` for the kind of the folder they are in, or `default` for
+    /// `default_folder_kind`
+    #[serde(default)]
+    pub xfolder_kind: KindInheritance,
+    /// Handlebars templates naming the directories of the system, its areas, and its categories
+    /// instead of their ids and topics
+    #[serde(default)]
+    pub folder_names: FolderNames,
+    /// Generate the system as a workspace of its own, a single root folder in `base_folder` named
+    /// after the system, like `L01 Personal`, that holds the notes and the reference archive both
+    #[serde(default)]
+    pub workspace: bool,
+}
+
+/// Handlebars templates naming the directories of a system, for when several systems share one
+/// vault. A name that is left out is the id with its topic, like `L01.20-29 Finance`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct FolderNames {
+    /// The name of the directory of the system, rendered with `id` and `name`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    /// The name of the directory of each area, rendered with `id`, `topic`, and `system`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub area: Option<String>,
+    /// The name of the directory of each category, rendered with `id`, `topic`, and `system`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+impl FolderNames {
+    /// Render the template `name` of the folder names with `data`, making sure the result can
+    /// name a directory
+    fn render(
+        handlebars: &Handlebars<'_>,
+        name: &str,
+        template: &str,
+        data: &serde_json::Value,
+    ) -> Result<Arc<str>, Error> {
+        let rendered = handlebars.render_template(template, data).map_err(|err| {
+            Error::msg(format!("The {name} folder name can't be rendered: {err}"))
+        })?;
+        let rendered = rendered.trim();
+        if rendered.is_empty() || rendered.contains(['/', '\\']) {
+            return Err(Error::msg(format!(
+                "The {name} folder name \"{rendered}\" can't name a directory"
+            )));
+        }
+        Ok(Arc::from(rendered))
+    }
 }
 
 /// The output configuration for the Johnny Decimal system
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Output {
     /// The folder where your note taking system wants the system
     pub base_folder: String,
-    /// The folder where the reference archive should be created
+    /// The folder where the reference archive should be created, which a workspace doesn't need
+    #[serde(default)]
     pub reference_folder: String,
+    /// How each folder of the notes links to its folder in the reference archive
+    #[serde(default)]
+    pub link_style: LinkStyle,
+    /// How files placed in the notes are mirrored into the reference archive
+    #[serde(default)]
+    pub mirror: MirrorMode,
+    /// How the notes are generated
+    #[serde(default)]
+    pub notes: TreeConfig,
+    /// How the reference archive is generated
+    #[serde(default)]
+    pub archive: TreeConfig,
+    /// The name of the folder at the root of the system for things that haven't been filed yet
+    pub inbox: Option<String>,
+    /// The name of the folder for attachments inside each folder of the notes, like `assets`
+    pub attachments: Option<String>,
+    /// Where what jdexmd remembers between runs is kept, instead of the platform's state folder
+    pub state_folder: Option<String>,
+    /// The path of the JSON file of ids for autocompletion, relative to the notes
+    pub autocomplete: Option<String>,
+    /// Write a map of content note into the folder of each area of the notes
+    #[serde(default)]
+    pub area_mocs: bool,
+    /// Count the notes in the directory of each folder of the notes, for `note_count` in the index
+    #[serde(default)]
+    pub note_counts: bool,
+    /// More vaults the system is generated into in the same run, like a synced mirror
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<Target>,
+}
+
+/// Another vault the system is generated into along with `base_folder`, taking every option it
+/// leaves out from the main folders
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Target {
+    /// The folder of the notes of this vault
+    pub base_folder: String,
+    /// The folder of its reference archive, the main one when it isn't given
+    pub reference_folder: Option<String>,
+    /// How each folder of its notes links to its folder in the reference archive
+    pub link_style: Option<LinkStyle>,
+    /// How files placed in its notes are mirrored into the reference archive
+    pub mirror: Option<MirrorMode>,
+    /// The name of the folder at the root of the system for things that haven't been filed yet
+    pub inbox: Option<String>,
+    /// The name of the folder for attachments inside each folder of its notes
+    pub attachments: Option<String>,
+    /// The path of the JSON file of ids for autocompletion, relative to its notes
+    pub autocomplete: Option<String>,
+    /// Write a map of content note into the folder of each area of its notes
+    pub area_mocs: Option<bool>,
+    /// Count the notes in the directory of each folder of its notes
+    pub note_counts: Option<bool>,
 }
 
 /// The configuration for the Johnny Decimal system
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct JohnnyDecimal {
     /// Configuring the system
     #[serde(flatten)]
     pub system_config: SystemParameters,
+    /// The `.jdex` file the definition is kept in instead of `config`, relative to the config file
+    pub definition: Option<String>,
     /// Where we are outputting files
     #[serde(flatten)]
     pub output_config: Output,
     /// The handlebar themes for the markdown output
     #[serde(default)]
     pub format: MdFormatConfig,
+    /// Where to write a report after each generation, if anywhere
+    pub report: Option<ReportConfig>,
+    /// The rules every topic has to follow
+    #[serde(default)]
+    pub naming: NamingConfig,
+    /// How many entries each level can hold before it is worth splitting
+    #[serde(default)]
+    pub limits: LimitsConfig,
+}
+
+impl Output {
+    /// The folders `system` is generated into, where a workspace keeps the reference archive in
+    /// the same tree as the notes.
+    ///
+    /// Both folders are resolved here, so a variable that isn't set or a drive that isn't mounted
+    /// is reported before anything is generated.
+    pub fn for_system(mut self, system: &System) -> Result<Self, Error> {
+        if system.is_workspace() {
+            self.reference_folder.clone_from(&self.base_folder);
+        } else if self.reference_folder.is_empty() {
+            return Err(Error::msg(
+                "The config file needs a reference_folder, unless the system is a workspace",
+            ));
+        }
+        notes::expand(&self.base_folder)?;
+        notes::expand(&self.reference_folder)?;
+        Ok(self)
+    }
+
+    /// The folders of every target, with the options each one overrides and the rest of these
+    pub fn targets(&self) -> Vec<Self> {
+        self.targets
+            .iter()
+            .map(|target| Self {
+                base_folder: target.base_folder.clone(),
+                reference_folder: target
+                    .reference_folder
+                    .clone()
+                    .unwrap_or_else(|| self.reference_folder.clone()),
+                link_style: target.link_style.unwrap_or(self.link_style),
+                mirror: target.mirror.unwrap_or(self.mirror),
+                notes: self.notes.clone(),
+                archive: self.archive.clone(),
+                inbox: target.inbox.clone().or_else(|| self.inbox.clone()),
+                attachments: target
+                    .attachments
+                    .clone()
+                    .or_else(|| self.attachments.clone()),
+                state_folder: self.state_folder.clone(),
+                autocomplete: target
+                    .autocomplete
+                    .clone()
+                    .or_else(|| self.autocomplete.clone()),
+                area_mocs: target.area_mocs.unwrap_or(self.area_mocs),
+                note_counts: target.note_counts.unwrap_or(self.note_counts),
+                targets: Vec::new(),
+            })
+            .collect()
+    }
 }
 
 impl JohnnyDecimal {
     /// Load the configuration from a TOML file
     pub fn from_file(path: &PathBuf) -> Result<Self, Error> {
-        let contents = read_to_string(path)?;
-        let config: Self = toml::from_str(&contents)?;
+        Self::from_toml(&read_to_string(path)?)?.read_definition(path)
+    }
+
+    /// Load the configuration from the contents of a TOML file
+    pub fn from_toml(contents: &str) -> Result<Self, Error> {
+        let config: Self = toml::from_str(contents)?;
         Ok(config)
     }
+
+    /// Read the definition from the `.jdex` file the config file at `config_file` points to, if
+    /// it points to one
+    pub fn read_definition(mut self, config_file: &Path) -> Result<Self, Error> {
+        let Some(definition) = &self.definition else {
+            return Ok(self);
+        };
+        if !self.system_config.config.is_empty() {
+            return Err(Error::msg(
+                "The config file has both `config` and `definition`, keep the system in only one",
+            ));
+        }
+        self.system_config.config = read_to_string(definition_path(config_file, definition)?)?;
+        Ok(self)
+    }
+
+    /// The `.jdex` file the config file at `config_file` keeps the definition in, if it has one
+    pub fn definition_file(&self, config_file: &Path) -> Result<Option<PathBuf>, Error> {
+        self.definition
+            .as_deref()
+            .map(|definition| definition_path(config_file, definition))
+            .transpose()
+    }
+
+    /// The SHA-256 of the config file, so generated files can tell which definition produced them
+    pub fn hash(contents: &str) -> String {
+        format!("{:x}", Sha256::digest(contents))
+    }
+
+    /// The hash of the config file whose `contents` were read, along with the `.jdex` file it
+    /// points to, so changing either one counts as a new definition
+    pub fn fingerprint(&self, contents: &str) -> String {
+        if self.definition.is_some() {
+            Self::hash(&format!("{contents}{}", self.system_config.config))
+        } else {
+            Self::hash(contents)
+        }
+    }
+
+    /// Replace the system definition in a TOML file, keeping everything else as written.
+    ///
+    /// When the config file points to a `.jdex` file, that file is rewritten instead, keeping the
+    /// comments at its top.
+    pub fn update_definition(path: &PathBuf, definition: &str) -> Result<(), Error> {
+        let contents = read_to_string(path)?;
+        let mut document: DocumentMut = contents.parse()?;
+        if let Some(jdex) = document.get("definition").and_then(Item::as_str) {
+            let jdex = definition_path(path, jdex)?;
+            let header = read_to_string(&jdex)
+                .map(|old| header(&old))
+                .unwrap_or_default();
+            write(jdex, format!("{header}{definition}"))?;
+            return Ok(());
+        }
+        document["config"] = Item::Value(Self::definition_value(definition)?);
+        write(path, document.to_string())?;
+        Ok(())
+    }
+
+    /// Change the definition in the config file at `config_file` with `edit`, which is given the
+    /// definition as it was written, with its comments, counted ranges, and templates, so only the
+    /// lines it changes are changed
+    pub fn edit_definition(
+        config_file: &PathBuf,
+        edit: impl FnOnce(&str) -> Result<String, Error>,
+    ) -> Result<(), Error> {
+        let config = Self::from_file(config_file)?;
+        let edited = edit(&config.system_config.config)?;
+        if edited == config.system_config.config {
+            return Ok(());
+        }
+        match config.definition_file(config_file)? {
+            // What was read of the `.jdex` file includes the comments at its top
+            Some(jdex) => write(jdex, edited)?,
+            None => Self::update_definition(config_file, &edited)?,
+        }
+        Ok(())
+    }
+
+    /// The definition as a TOML value, in a multi-line string when it can be
+    pub fn definition_value(definition: &str) -> Result<Value, Error> {
+        // Keep the tabs readable instead of letting them be escaped
+        if definition.contains('\\') || definition.contains("\"\"\"") {
+            Ok(Value::from(definition))
+        } else {
+            Ok(format!("\"\"\"\n{definition}\"\"\"").parse()?)
+        }
+    }
+}
+
+/// The path of the `.jdex` file named `definition` in the config file at `config_file`
+fn definition_path(config_file: &Path, definition: &str) -> Result<PathBuf, Error> {
+    let config_folder = config_file.parent().unwrap_or_else(|| Path::new("."));
+    Ok(config_folder.join(notes::expand(definition)?))
+}
+
+/// The comments and blank lines at the top of a `.jdex` file, like who owns the system and when it
+/// was last reviewed, which are kept when the definition under them is rewritten
+fn header(jdex: &str) -> String {
+    jdex.lines()
+        .take_while(|line| is_comment(line))
+        .fold(String::new(), |mut header, line| {
+            let _ = writeln!(header, "{line}");
+            header
+        })
+}
+
+/// Whether a line of the definition is blank or a `#` comment, and so isn't an entry
+pub fn is_comment(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+impl SystemParameters {
+    /// The definition with every counted range and template written out in full
+    fn expanded_config(&self) -> Result<String, Error> {
+        let templates = self
+            .templates
+            .iter()
+            .map(|(name, template)| Ok((name.clone(), expand_ranges(template)?)))
+            .collect::<Result<BTreeMap<_, _>, Error>>()?;
+        expand_templates(&expand_ranges(&self.config)?, &templates)
+    }
 }
 
 impl TryFrom<SystemParameters> for System {
@@ -62,11 +416,90 @@ impl TryFrom<SystemParameters> for System {
         clippy::arithmetic_side_effects,
         reason = "The likelihood of overflow is low"
     )]
+    #[expect(
+        clippy::too_many_lines,
+        reason = "Every kind of line is turned into the model in one place"
+    )]
     fn try_from(value: SystemParameters) -> Result<Self, Self::Error> {
-        let system_id = JohnnyId::default().system_id(&value.system_id);
+        let handlebars = folder_name_renderer();
+        let system_name = value
+            .folder_names
+            .system
+            .as_deref()
+            .map(|template| {
+                let data = serde_json::json!({"id": value.system_id, "name": value.name});
+                FolderNames::render(&handlebars, "system", template, &data)
+            })
+            .transpose()?;
+        let system_id = JohnnyId::default()
+            .system_id(value.system_id.as_str())
+            .hide_system(value.hide_system_id)
+            .layout(value.layout);
+        // A workspace is always named, after the system unless a template names it otherwise
+        let system_id = if value.workspace {
+            system_id.root(Some(system_name.unwrap_or_else(|| {
+                format!("{} {}", value.system_id, value.name).into()
+            })))
+        } else {
+            system_id.directory(system_name)
+        };
         let mut system = Self::new(system_id, &value.name);
-        let lines = value.config.lines();
-        for (line_no, raw) in lines.enumerate() {
+        if value.default_folder_kind.link_target().is_some() {
+            return Err(Error::msg(
+                "The default folder kind can't be a link, it needs an id to point at",
+            ));
+        }
+        system.default_folder_kind = value.default_folder_kind.clone();
+        system.xfolder_kind = value.xfolder_kind;
+        // Topics are kept in NFC, so the paths built from them are the same whichever form the
+        // editor saved the definition in
+        let config: String = value.expanded_config()?.nfc().collect();
+        let default_kind = &value.default_folder_kind;
+        let xfolder_pattern = Regex::new(
+            value
+                .xfolder_pattern
+                .as_deref()
+                .unwrap_or(DEFAULT_XFOLDER_PATTERN),
+        )
+        .map_err(|err| Error::msg(format!("The xfolder_pattern isn't a valid pattern: {err}")))?;
+        let lines = config.lines();
+        for (line_no, raw) in lines.enumerate().filter(|(_, line)| !is_comment(line)) {
+            let (raw, attributes) = split_attributes(raw);
+            let retired = attributes.has("retired");
+            let frozen = attributes.has("frozen");
+            let locations: Vec<Arc<str>> = attributes
+                .values("location")
+                .into_iter()
+                .map(Arc::from)
+                .collect();
+            let index_name = attributes.values("index").first().copied().map(Arc::from);
+            let status = attributes
+                .values("status")
+                .first()
+                .map(|value| {
+                    Status::from_tag(value).ok_or_else(|| {
+                        Error::msg(format!(
+                            "The status {value} on line {} isn't active, on-hold, or archived",
+                            line_no + 1
+                        ))
+                    })
+                })
+                .transpose()?;
+            let reviewed = attributes
+                .values("reviewed")
+                .first()
+                .map(|day| {
+                    if history::is_date(day) {
+                        Ok(Arc::from(*day))
+                    } else {
+                        Err(Error::msg(format!(
+                            "The review date {day} on line {} isn't a date, write it like 2025-01-01",
+                            line_no + 1
+                        )))
+                    }
+                })
+                .transpose()?;
+            let tagged_kind = attributes.kind();
             let single_line = parse_single(line_no + 1, raw);
             if let Err(err) = single_line {
                 eprintln!("Invalid Line: {}", raw.trim_start());
@@ -75,21 +508,29 @@ impl TryFrom<SystemParameters> for System {
             let line = single_line?;
             match line {
                 ParsedKind::Area(start, end, topic) => {
-                    let area_id = system.id.clone().area_id(start, end, topic);
+                    let topic: Arc<str> = Arc::from(topic);
+                    let area_id = system.id.clone().area_id(start, end, Arc::clone(&topic));
                     let area = Area {
                         id: area_id,
                         id_range: (start, end),
-                        topic: topic.to_owned(),
+                        topic,
+                        reviewed,
                         categories: Vec::new(),
                     };
                     system.areas.push(area);
                 }
                 ParsedKind::Category(id, topic) => {
                     system.areas.last_mut().and_then(|area| {
-                        let category_id = area.id.clone().category_id(id, topic);
+                        let topic: Arc<str> = Arc::from(topic);
+                        let category_id = area.id.clone().category_id(id, Arc::clone(&topic));
                         let category = Category {
                             id: category_id,
-                            topic: topic.to_owned(),
+                            topic,
+                            retired,
+                            frozen,
+                            locations,
+                            status,
+                            reviewed,
                             folders: Vec::new(),
                         };
                         area.categories.push(category);
@@ -102,17 +543,26 @@ impl TryFrom<SystemParameters> for System {
                         .last_mut()
                         .and_then(|area| area.categories.last_mut())
                         .and_then(|category| {
-                            let (kind, bare_topic) = match entry_style {
-                                FolderKind::File => (FolderKind::File, &topic[1..]),
-                                FolderKind::Both => (FolderKind::Both, &topic[1..]),
-                                FolderKind::Index => (FolderKind::Index, &topic[1..]),
-                                FolderKind::Folder => (FolderKind::Folder, &topic[0..]),
+                            // A kind given as a tag leaves the topic free to start with a directive
+                            let directive = entry_style.directive().map_or(0, str::len);
+                            let (kind, bare_topic) = match (tagged_kind, entry_style) {
+                                (Some(kind), _) => (kind, topic),
+                                (None, FolderKind::Folder | FolderKind::Link(_)) => {
+                                    (default_kind.clone(), topic)
+                                }
+                                (None, style) => (style, &topic[directive..]),
                             };
-                            let folder_id = category.id.clone().folder_id(id, bare_topic);
+                            let topic: Arc<str> = Arc::from(bare_topic);
+                            let folder_id = category.id.clone().folder_id(id, Arc::clone(&topic));
                             let folder = Folder {
                                 id: folder_id,
-                                topic: bare_topic.to_owned(),
+                                topic,
                                 kind,
+                                retired,
+                                frozen,
+                                locations,
+                                status,
+                                index_name,
                                 folders: Vec::new(),
                             };
                             category.folders.push(folder);
@@ -120,24 +570,39 @@ impl TryFrom<SystemParameters> for System {
                         });
                 }
                 ParsedKind::ExtendedFolder(id, entry_style, topic) => {
+                    if !xfolder_pattern.is_match(id) {
+                        return Err(Error::msg(format!(
+                            "The extended folder id {id} on line {} doesn't match the pattern {}",
+                            line_no + 1,
+                            xfolder_pattern.as_str()
+                        )));
+                    }
+                    let number = xfolder_number(&xfolder_pattern, id);
                     system
                         .areas
                         .last_mut()
                         .and_then(|area| area.categories.last_mut())
                         .and_then(|category| category.folders.last_mut())
                         .and_then(|folder| {
-                            let (kind, bare_topic) = match entry_style {
-                                FolderKind::File => (FolderKind::File, &topic[1..]),
-                                FolderKind::Both | FolderKind::Index => {
-                                    (FolderKind::Folder, &topic[1..])
+                            let directive = entry_style.directive().map_or(0, str::len);
+                            let (kind, bare_topic) = match (tagged_kind, entry_style) {
+                                (Some(kind), _) => (kind, topic),
+                                (None, FolderKind::Folder | FolderKind::Link(_)) => {
+                                    (value.xfolder_kind.kind(&folder.kind, default_kind), topic)
                                 }
-                                FolderKind::Folder => (FolderKind::Folder, &topic[0..]),
+                                (None, style) => (style, &topic[directive..]),
                             };
-                            let folder_id = folder.id.clone().xfolder_id(id, bare_topic);
+                            let topic: Arc<str> = Arc::from(bare_topic);
+                            let folder_id = folder.id.clone().xfolder_id(id, Arc::clone(&topic));
                             let xfolder = XFolder {
                                 id: folder_id,
-                                topic: bare_topic.to_owned(),
+                                topic,
                                 kind,
+                                retired,
+                                frozen,
+                                locations,
+                                status,
+                                number,
                             };
                             folder.folders.push(xfolder);
                             None::<()>
@@ -145,6 +610,239 @@ impl TryFrom<SystemParameters> for System {
                 }
             }
         }
+        if value.standard_zeros {
+            system.add_standard_zeros();
+        }
+        name_directories(&mut system, &value.folder_names, &handlebars)?;
+        check_links(&system)?;
         Ok(system)
     }
 }
+
+/// The Handlebars registry the folder names are rendered with, which leaves the names as they
+/// are written and fails on a name that isn't in the data
+fn folder_name_renderer() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+}
+
+/// Name the directories of the areas and categories of `system` with the templates of `names`,
+/// along with the ids of everything under them, so their paths go through the named directories
+fn name_directories(
+    system: &mut System,
+    names: &FolderNames,
+    handlebars: &Handlebars<'_>,
+) -> Result<(), Error> {
+    if names.area.is_none() && names.category.is_none() {
+        return Ok(());
+    }
+    let system_data = serde_json::json!({"id": system.id.by_seperator("."), "name": system.name});
+    let render = |name: &str, template: Option<&String>, id: &JohnnyId, topic: &str| {
+        template
+            .map(|template| {
+                let data = serde_json::json!({
+                    "id": id.by_seperator("."),
+                    "topic": topic,
+                    "system": system_data,
+                });
+                FolderNames::render(handlebars, name, template, &data)
+            })
+            .transpose()
+    };
+    for area in &mut system.areas {
+        let area_name = render("area", names.area.as_ref(), &area.id, &area.topic)?;
+        area.id.name_directories(area_name.as_ref(), None);
+        for category in &mut area.categories {
+            let category_name = render(
+                "category",
+                names.category.as_ref(),
+                &category.id,
+                &category.topic,
+            )?;
+            let (area_name, category_name) = (area_name.as_ref(), category_name.as_ref());
+            category.id.name_directories(area_name, category_name);
+            for folder in &mut category.folders {
+                folder.id.name_directories(area_name, category_name);
+                for xfolder in &mut folder.folders {
+                    xfolder.id.name_directories(area_name, category_name);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The number in an extended folder id, from the first group of the pattern when it has one and
+/// from the digits of the id otherwise
+fn xfolder_number(pattern: &Regex, id: &str) -> Option<u32> {
+    let number = pattern
+        .captures(id)
+        .and_then(|captures| captures.get(1))
+        .map_or_else(
+            || id.chars().filter(char::is_ascii_digit).collect(),
+            |group| group.as_str().to_owned(),
+        );
+    number.parse().ok()
+}
+
+/// Make sure every `[link=id]` entry points at an entry of the system
+fn check_links(system: &System) -> Result<(), Error> {
+    let kinds = system
+        .areas
+        .iter()
+        .flat_map(|area| &area.categories)
+        .flat_map(|category| &category.folders)
+        .flat_map(|folder| {
+            iter::once(&folder.kind).chain(folder.folders.iter().map(|xfolder| &xfolder.kind))
+        });
+    for target in kinds.filter_map(FolderKind::link_target) {
+        if label::entries_under(system, target).is_empty() {
+            return Err(Error::msg(format!(
+                "There is a link to {target}, but no entry has that id"
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{line::format_system, test_support::system};
+
+    #[test]
+    fn test_jdex_comments() {
+        let jdex = "# Owner: Grayson\n# Reviewed: 2024-05-01\n\n20-29 Finance\n\t# Only the current year\n\t21 Tax\n";
+        assert_eq!(header(jdex), "# Owner: Grayson\n# Reviewed: 2024-05-01\n\n");
+        let system = system(jdex);
+        let topics: Vec<&str> = system
+            .areas
+            .iter()
+            .flat_map(|area| &area.categories)
+            .map(|category| &*category.topic)
+            .collect();
+        assert_eq!(topics, vec!["Tax"]);
+    }
+
+    #[test]
+    fn test_xfolder_pattern() {
+        let parameters = |config: &str, xfolder_pattern: Option<&str>| SystemParameters {
+            system_id: "L01".to_owned(),
+            name: "Life".to_owned(),
+            config: format!("20-29 Finance\n\t21 Tax\n\t\t05 Returns\n{config}"),
+            xfolder_pattern: xfolder_pattern.map(str::to_owned),
+            ..SystemParameters::default()
+        };
+        let numbers = |system: &System| -> Vec<Option<u32>> {
+            system.areas[0].categories[0].folders[0]
+                .folders
+                .iter()
+                .map(|xfolder| xfolder.number)
+                .collect()
+        };
+        let system = System::try_from(parameters("\t\t\tX20 Paid\n", None)).unwrap();
+        assert_eq!(numbers(&system), vec![Some(20)]);
+        let err = System::try_from(parameters("\t\t\t01 Admin\n", None)).unwrap_err();
+        assert!(err.to_string().contains("line 4"), "{err}");
+        let system =
+            System::try_from(parameters("\t\t\t2024-Q1 Paid\n", Some(r"^(\d{4})-Q\d$"))).unwrap();
+        assert_eq!(numbers(&system), vec![Some(2024)]);
+        assert!(System::try_from(parameters("", Some("("))).is_err());
+    }
+
+    #[test]
+    fn test_folder_names() {
+        let parameters = |folder_names: FolderNames, workspace: bool| SystemParameters {
+            system_id: "L01".to_owned(),
+            name: "Life".to_owned(),
+            config: "20-29 Finance\n\t21 Tax\n\t\t05 Returns\n\t\t\tX01 Drafts\n".to_owned(),
+            folder_names,
+            workspace,
+            ..SystemParameters::default()
+        };
+        let names = FolderNames {
+            system: Some("{{id}} {{name}}".to_owned()),
+            area: Some("{{topic}}".to_owned()),
+            category: Some("{{id}} {{topic}} ({{system.name}})".to_owned()),
+        };
+        let system = System::try_from(parameters(names, false)).unwrap();
+        let xfolder = &system.areas[0].categories[0].folders[0].folders[0];
+        assert_eq!(
+            xfolder.id.as_path(),
+            PathBuf::from(
+                "L01 Life/Finance/L01.21 Tax (Life)/L01.21.05 Returns/L01.21.05.X01 Drafts"
+            )
+        );
+        let names = FolderNames {
+            system: Some("{{name}}".to_owned()),
+            ..FolderNames::default()
+        };
+        let system = System::try_from(parameters(names, true)).unwrap();
+        assert_eq!(
+            system.areas[0].categories[0].id.as_path(),
+            PathBuf::from("Life/L01.20-29 Finance/L01.21 Tax")
+        );
+        let names = FolderNames {
+            area: Some("{{missing}}".to_owned()),
+            ..FolderNames::default()
+        };
+        assert!(System::try_from(parameters(names, false)).is_err());
+        let names = FolderNames {
+            category: Some("{{topic}}/{{id}}".to_owned()),
+            ..FolderNames::default()
+        };
+        assert!(System::try_from(parameters(names, false)).is_err());
+    }
+
+    #[test]
+    fn test_status() {
+        let parameters = |config: &str| SystemParameters {
+            system_id: "L01".to_owned(),
+            name: "Life".to_owned(),
+            config: config.to_owned(),
+            ..SystemParameters::default()
+        };
+        let config = "30-39 Projects\n\t31 Garden [status=active]\n\t\t01 Shed [status=on-hold]\n";
+        let system = System::try_from(parameters(config)).unwrap();
+        let category = &system.areas[0].categories[0];
+        assert_eq!(category.status, Some(Status::Active));
+        assert_eq!(category.folders[0].status, Some(Status::OnHold));
+        assert_eq!(format_system(&system), config);
+        let err = System::try_from(parameters("30-39 Projects\n\t31 Garden [status=live]\n"))
+            .unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+    }
+
+    #[test]
+    fn test_targets() {
+        let config = JohnnyDecimal::from_toml(
+            r#"
+system_id = "L01"
+name = "Life"
+config = "10-19 Home\n"
+base_folder = "~/Notes"
+reference_folder = "~/Archive"
+mirror = "hardlink"
+
+[[targets]]
+base_folder = "~/Dropbox/Notes"
+mirror = "copy"
+
+[[targets]]
+base_folder = "/mnt/backup/Notes"
+reference_folder = "/mnt/backup/Archive"
+"#,
+        )
+        .unwrap();
+        let targets = config.output_config.targets();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].base_folder, "~/Dropbox/Notes");
+        assert_eq!(targets[0].reference_folder, "~/Archive");
+        assert_eq!(targets[0].mirror, MirrorMode::Copy);
+        assert_eq!(targets[1].reference_folder, "/mnt/backup/Archive");
+        assert_eq!(targets[1].mirror, MirrorMode::Hardlink);
+        assert!(targets.iter().all(|target| target.targets.is_empty()));
+    }
+}
@@ -0,0 +1,134 @@
+use core::fmt::Write as _;
+use std::io::Write as _;
+
+use color_eyre::eyre::{Error, OptionExt};
+
+use crate::{
+    model::{FullId, System},
+    output,
+};
+
+/// How many numbers are in a row of the grid
+const ROW: usize = 10;
+
+/// What became of a number of an area or category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Use {
+    /// Never given out
+    Free,
+    /// Given to an entry
+    Used,
+    /// Given to an entry that is retired, so it is never given out again
+    Retired,
+}
+
+/// The numbers of the area or category `id`, the categories of an area or the folders of a
+/// category, each with what became of it, after the full id and topic of the entry
+fn numbers(system: &System, id: &str) -> Option<(String, Vec<(u8, Use)>)> {
+    let use_of = |taken: Option<bool>| match taken {
+        None => Use::Free,
+        Some(false) => Use::Used,
+        Some(true) => Use::Retired,
+    };
+    if let Some(area) = system.areas.iter().find(|area| area.id.matches(id)) {
+        let (start, end) = area.id_range;
+        let numbers = (start..=end)
+            .map(|number| {
+                let taken = area
+                    .categories
+                    .iter()
+                    .find(|category| category.id.category.as_ref().map(|id| id.id) == Some(number))
+                    .map(|category| category.retired);
+                (number, use_of(taken))
+            })
+            .collect();
+        return Some((area.id(), numbers));
+    }
+    let category = system
+        .areas
+        .iter()
+        .flat_map(|area| &area.categories)
+        .find(|category| category.id.matches(id))?;
+    let numbers = (0..=99)
+        .map(|number| {
+            let taken = category
+                .folders
+                .iter()
+                .find(|folder| folder.id.folder.as_ref().map(|id| id.id) == Some(number))
+                .map(|folder| folder.retired);
+            (number, use_of(taken))
+        })
+        .collect();
+    Some((category.id(), numbers))
+}
+
+/// How many of the numbers are used, retired, and free
+fn totals(numbers: &[(u8, Use)]) -> String {
+    let count = |kind: Use| numbers.iter().filter(|(_, used)| *used == kind).count();
+    format!(
+        "{} used, {} retired, {} free",
+        count(Use::Used),
+        count(Use::Retired),
+        count(Use::Free)
+    )
+}
+
+/// The numbers as a grid of ten to a row, with the free ones written out and the others marked
+fn grid(numbers: &[(u8, Use)]) -> String {
+    let mut grid = String::new();
+    for row in numbers.chunks(ROW) {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|(number, used)| match used {
+                Use::Free => output::id(&format!("{number:02}")),
+                Use::Used => "··".to_owned(),
+                Use::Retired => "××".to_owned(),
+            })
+            .collect();
+        let _ = writeln!(grid, "  {}", cells.join(" "));
+    }
+    grid.push_str("  ·· used, ×× retired\n");
+    grid
+}
+
+/// Print how many numbers of the area or category `id` are used, retired, and free, with `free`
+/// as a grid of which ones
+pub fn run(system: &System, id: &str, free: bool) -> Result<(), Error> {
+    let (entry, numbers) =
+        numbers(system, id).ok_or_eyre(format!("{id} isn't an area or a category"))?;
+    let mut printed = format!("{}\n  {}\n", output::heading(&entry), totals(&numbers));
+    if free {
+        printed.push_str(&grid(&numbers));
+    }
+    write!(anstream::stdout(), "{printed}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_numbers() {
+        let system = system(
+            "20-29 Finance\n\t21 Tax\n\t\t01 Returns\n\t\t02 Old [retired]\n\t\t04 Receipts\n\t23 Bank [retired]\n",
+        );
+        let (entry, area) = numbers(&system, "L01.20-29").unwrap();
+        assert_eq!(entry, "L01.20-29 Finance");
+        assert_eq!(totals(&area), "1 used, 1 retired, 8 free");
+        assert_eq!(
+            anstream::adapter::strip_str(&grid(&area)).to_string(),
+            "  20 ·· 22 ×× 24 25 26 27 28 29\n  ·· used, ×× retired\n"
+        );
+        let (_, category) = numbers(&system, "L01.21").unwrap();
+        assert_eq!(category.len(), 100);
+        assert_eq!(totals(&category), "2 used, 1 retired, 97 free");
+        let printed = anstream::adapter::strip_str(&grid(&category)).to_string();
+        assert!(
+            printed.starts_with("  00 ·· ×× 03 ·· 05 06 07 08 09\n  10 "),
+            "{printed}"
+        );
+        assert!(numbers(&system, "L01.21.01").is_none());
+    }
+}
@@ -0,0 +1,45 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How deep a generated tree goes into the system
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Depth {
+    /// Only the areas
+    Area,
+    /// The areas and their categories
+    Category,
+    /// Down to the folders
+    Folder,
+    #[default]
+    /// Everything, extended folders included
+    XFolder,
+}
+
+impl Depth {
+    /// How many path components an entry at this depth has below the base folder, where the
+    /// system is the first
+    const fn components(self) -> usize {
+        match self {
+            Self::Area => 2,
+            Self::Category => 3,
+            Self::Folder => 4,
+            Self::XFolder => 5,
+        }
+    }
+
+    /// Whether an entry with this many path components below the base folder is generated
+    pub const fn includes(self, components: usize) -> bool {
+        components <= self.components()
+    }
+}
+
+/// How one of the generated trees is built
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TreeConfig {
+    /// The deepest level of the system that gets generated
+    #[serde(default)]
+    pub depth: Depth,
+}
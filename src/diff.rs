@@ -0,0 +1,281 @@
+use std::{collections::BTreeMap, io::Write as _, path::PathBuf};
+
+use color_eyre::eyre::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::JohnnyDecimal,
+    jid::JohnnyId,
+    model::System,
+    output::{self, Mark},
+};
+
+/// A single way two versions of a system differ, by id
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum Difference {
+    /// The id is only in the newer system
+    Added {
+        /// The full id
+        id: String,
+        /// The topic in the newer system
+        topic: String,
+    },
+    /// The id is only in the older system
+    Removed {
+        /// The full id
+        id: String,
+        /// The topic in the older system
+        topic: String,
+    },
+    /// The id is in both systems with a different topic
+    Renamed {
+        /// The full id
+        id: String,
+        /// The topic in the older system
+        from: String,
+        /// The topic in the newer system
+        to: String,
+    },
+    /// The topic left one id and turned up under another
+    Moved {
+        /// The id in the older system
+        from: String,
+        /// The id in the newer system
+        to: String,
+        /// The topic the entry has in both
+        topic: String,
+    },
+    /// The id is in both systems, and only retired in the newer one
+    Retired {
+        /// The full id
+        id: String,
+        /// The topic in the newer system
+        topic: String,
+    },
+    /// A directory jdexmd didn't make was moved into the place of the id, which only the history
+    /// records
+    Adopted {
+        /// The full id
+        id: String,
+        /// The topic of the entry
+        topic: String,
+        /// Where the directory was before
+        from: String,
+    },
+}
+
+/// What is compared about an id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The topic of the entry
+    pub topic: String,
+    /// Whether the id is retired
+    #[serde(default)]
+    pub retired: bool,
+}
+
+impl Entry {
+    /// The entry with the given topic
+    fn new(topic: &str, retired: bool) -> Self {
+        Self {
+            topic: topic.to_owned(),
+            retired,
+        }
+    }
+}
+
+impl Difference {
+    /// The line describing the difference
+    pub fn line(&self) -> String {
+        match self {
+            Self::Added { id, topic } => output::line(
+                Mark::Created,
+                "Added",
+                &format!("{} {topic}", output::id(id)),
+            ),
+            Self::Removed { id, topic } => output::line(
+                Mark::Deleted,
+                "Removed",
+                &format!("{} {topic}", output::id(id)),
+            ),
+            Self::Renamed { id, from, to } => output::line(
+                Mark::Changed,
+                "Renamed",
+                &format!("{} {from} → {to}", output::id(id)),
+            ),
+            Self::Moved { from, to, topic } => output::line(
+                Mark::Changed,
+                "Moved",
+                &format!("{topic} {} → {}", output::id(from), output::id(to)),
+            ),
+            Self::Retired { id, topic } => output::line(
+                Mark::Deleted,
+                "Retired",
+                &format!("{} {topic}", output::id(id)),
+            ),
+            Self::Adopted { id, topic, from } => output::line(
+                Mark::Changed,
+                "Adopted",
+                &format!("{from} as {} {topic}", output::id(id)),
+            ),
+        }
+    }
+}
+
+/// Every id of the system with what is compared about it, sorted by id
+pub fn entries(system: &System) -> BTreeMap<String, Entry> {
+    let mut entries = BTreeMap::new();
+    let id = |jid: &JohnnyId| jid.by_seperator(".");
+    for area in &system.areas {
+        entries.insert(id(&area.id), Entry::new(&area.topic, false));
+        for category in &area.categories {
+            entries.insert(
+                id(&category.id),
+                Entry::new(&category.topic, category.retired),
+            );
+            for folder in &category.folders {
+                entries.insert(id(&folder.id), Entry::new(&folder.topic, folder.retired));
+                for xfolder in &folder.folders {
+                    entries.insert(id(&xfolder.id), Entry::new(&xfolder.topic, xfolder.retired));
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Compare two versions of a system by their ids, in id order.
+///
+/// An id that is gone while a new id has the same topic is taken to have moved there.
+pub fn diff(old: &System, new: &System) -> Vec<Difference> {
+    compare(&entries(old), entries(new))
+}
+
+/// Compare two versions of the entries of a system by their ids, in id order
+pub fn compare(old: &BTreeMap<String, Entry>, mut new: BTreeMap<String, Entry>) -> Vec<Difference> {
+    let mut added: Vec<(String, String)> = new
+        .iter()
+        .filter(|(id, _)| !old.contains_key(*id))
+        .map(|(id, entry)| (id.clone(), entry.topic.clone()))
+        .collect();
+    let mut differences = Vec::new();
+    for (id, entry) in old {
+        let topic = &entry.topic;
+        let Some(newer) = new.remove(id) else {
+            match added.iter().position(|(_, added)| added == topic) {
+                Some(index) => differences.push(Difference::Moved {
+                    from: id.clone(),
+                    to: added.remove(index).0,
+                    topic: topic.clone(),
+                }),
+                None => differences.push(Difference::Removed {
+                    id: id.clone(),
+                    topic: topic.clone(),
+                }),
+            }
+            continue;
+        };
+        if newer.topic != *topic {
+            differences.push(Difference::Renamed {
+                id: id.clone(),
+                from: topic.clone(),
+                to: newer.topic.clone(),
+            });
+        }
+        if newer.retired && !entry.retired {
+            differences.push(Difference::Retired {
+                id: id.clone(),
+                topic: newer.topic,
+            });
+        }
+    }
+    differences.extend(
+        added
+            .into_iter()
+            .map(|(id, topic)| Difference::Added { id, topic }),
+    );
+    differences
+}
+
+/// Print the differences, or that there are none
+pub fn print(differences: &[Difference]) -> Result<(), Error> {
+    let mut stdout = anstream::stdout();
+    if differences.is_empty() {
+        write!(
+            stdout,
+            "{}",
+            output::line(Mark::Applied, "No differences", "in the structure")
+        )?;
+    }
+    for difference in differences {
+        write!(stdout, "{}", difference.line())?;
+    }
+    Ok(())
+}
+
+/// Print how the system defined in `new` differs from the one defined in `old`
+pub fn run(old: &PathBuf, new: &PathBuf) -> Result<(), Error> {
+    let load = |path: &PathBuf| System::try_from(JohnnyDecimal::from_file(path)?.system_config);
+    print(&diff(&load(old)?, &load(new)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_diff() {
+        let old = system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n\t\t02 Receipts\n");
+        let new = system("20-29 Finance\n\t21 Taxes\n\t\t01 Returns\n\t\t03 Letters\n");
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Difference::Renamed {
+                    id: "L01.21".to_owned(),
+                    from: "Tax".to_owned(),
+                    to: "Taxes".to_owned(),
+                },
+                Difference::Removed {
+                    id: "L01.21.02".to_owned(),
+                    topic: "Receipts".to_owned(),
+                },
+                Difference::Added {
+                    id: "L01.21.03".to_owned(),
+                    topic: "Letters".to_owned(),
+                },
+            ]
+        );
+        assert!(diff(&old, &old).is_empty());
+        let retired =
+            system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n\t\t02 Receipts [retired]\n");
+        assert_eq!(
+            diff(&old, &retired),
+            vec![Difference::Retired {
+                id: "L01.21.02".to_owned(),
+                topic: "Receipts".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_moved_ids() {
+        let old = system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n\t\t02 Receipts\n");
+        let new = system("20-29 Finance\n\t21 Tax\n\t\t02 Receipts\n\t22 Bank\n\t\t01 Returns\n");
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Difference::Moved {
+                    from: "L01.21.01".to_owned(),
+                    to: "L01.22.01".to_owned(),
+                    topic: "Returns".to_owned(),
+                },
+                Difference::Added {
+                    id: "L01.22".to_owned(),
+                    topic: "Bank".to_owned(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,449 @@
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use std::{
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::Error;
+
+use crate::{
+    backend::OutputBackend,
+    config,
+    ignore::IgnoreRules,
+    model::{FullId, System},
+    notes::{self, Action},
+    output::{self, Mark},
+    planner::Planner,
+    prompt::Prompt,
+    reconcile::{self, Change},
+};
+
+/// The directory at the top of the system that `--fix` moves entries into when their id is no
+/// longer in the system, so nothing is deleted without someone looking at it first
+pub const REVIEW_FOLDER: &str = "@Review";
+
+/// Characters that Windows (and therefore most sync clients) refuse in a file name
+const WINDOWS_ILLEGAL: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Names that Windows reserves for devices
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The cloud storage provider the vault will be synced with
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SyncTarget {
+    /// Dropbox
+    Dropbox,
+    /// Microsoft `OneDrive`
+    Onedrive,
+    /// Google Drive for desktop
+    Gdrive,
+}
+
+impl SyncTarget {
+    /// The characters the provider refuses in a single file or directory name
+    const fn illegal_chars(self) -> &'static [char] {
+        match self {
+            Self::Dropbox | Self::Onedrive => WINDOWS_ILLEGAL,
+            Self::Gdrive => &['/'],
+        }
+    }
+
+    /// Names the provider refuses regardless of extension
+    const fn reserved_names(self) -> &'static [&'static str] {
+        match self {
+            Self::Onedrive => WINDOWS_RESERVED,
+            Self::Dropbox | Self::Gdrive => &[],
+        }
+    }
+
+    /// Whether names ending in a dot or space are rejected
+    const fn rejects_trailing_dot_or_space(self) -> bool {
+        matches!(self, Self::Dropbox | Self::Onedrive)
+    }
+
+    /// The longest full path (in characters) the provider will sync
+    const fn max_path_length(self) -> usize {
+        match self {
+            Self::Dropbox => 260,
+            Self::Onedrive => 400,
+            Self::Gdrive => 32_767,
+        }
+    }
+}
+
+impl Display for SyncTarget {
+    #[allow(
+        clippy::min_ident_chars,
+        reason = "This is the preferred default name for the variable"
+    )]
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Dropbox => write!(f, "Dropbox"),
+            Self::Onedrive => write!(f, "OneDrive"),
+            Self::Gdrive => write!(f, "Google Drive"),
+        }
+    }
+}
+
+/// A change that brings a folder that drifted from the system back in line with it
+#[derive(Debug, PartialEq, Eq)]
+pub enum Fix {
+    /// The directory is missing
+    Create(PathBuf),
+    /// The entry has the id of a planned entry, but not its name or place
+    Rename(PathBuf, PathBuf),
+    /// The entry has an id that is no longer in the system, so it is moved to the review folder
+    Review(PathBuf, PathBuf),
+    /// The entry is a conflicted copy left by a sync client, which is only moved to the review
+    /// folder when conflicts are cleaned
+    Conflict(PathBuf, PathBuf),
+}
+
+impl Fix {
+    /// Print out what is done, or what would be done for a dry run
+    fn describe(&self, dry_run: bool) -> String {
+        let (mark, verb, rest) = match self {
+            Self::Create(path) => (Mark::Created, "Create", output::path(path)),
+            Self::Rename(from, to) => (
+                Mark::Changed,
+                "Rename",
+                format!("{} to {}", output::path(from), output::path(to)),
+            ),
+            Self::Review(from, to) | Self::Conflict(from, to) => (
+                Mark::Deleted,
+                "Move",
+                format!("{} to {}", output::path(from), output::path(to)),
+            ),
+        };
+        let verb = if dry_run {
+            format!("Would {verb}")
+        } else {
+            verb.to_owned()
+        };
+        output::line(mark, &verb, &rest)
+    }
+
+    /// Make the change with `backend`
+    fn apply(&self, backend: &mut dyn OutputBackend) -> Result<(), Error> {
+        match self {
+            Self::Create(path) => backend.create_dir_all(path),
+            Self::Rename(from, to) | Self::Review(from, to) | Self::Conflict(from, to) => {
+                if let Some(parent) = to.parent() {
+                    backend.create_dir_all(parent)?;
+                }
+                backend.rename(from, to)
+            }
+        }
+    }
+}
+
+/// Find what it takes for `base_folder` to match `actions` again: the entries to rename, the ones
+/// whose id is gone to move into the review folder, and the directories that are missing, along
+/// with the conflicted copies that sync clients left behind.
+///
+/// `files` and `subtree` are passed on to [`reconcile::plan`]. Nothing is changed on disk.
+pub fn plan_fixes(
+    backend: &dyn OutputBackend,
+    base_folder: &str,
+    system: &System,
+    actions: &[Action<'_>],
+    extension: &str,
+    files: bool,
+    subtree: Option<&Path>,
+) -> Result<Vec<Fix>, Error> {
+    let changes = reconcile::plan(
+        backend,
+        base_folder,
+        system,
+        actions,
+        extension,
+        files,
+        subtree,
+    )?;
+    let base_path = notes::expand(base_folder)?;
+    let review = base_path.join(system.id.as_path()).join(REVIEW_FOLDER);
+    let mut planned = Vec::new();
+    let mut renames = Vec::new();
+    for change in changes {
+        match change {
+            Change::Rename(from, to) => {
+                renames.push((from.clone(), to.clone()));
+                planned.push(Fix::Rename(from, to));
+            }
+            Change::Delete(path) => {
+                let name = path.file_name().unwrap_or_default();
+                let to = review.join(name);
+                planned.push(Fix::Review(path, to));
+            }
+            Change::Conflict(path) => {
+                let name = path.file_name().unwrap_or_default();
+                let to = review.join(name);
+                planned.push(Fix::Conflict(path, to));
+            }
+        }
+    }
+    let ignore = IgnoreRules::load(backend, &base_path)?;
+    for action in actions {
+        let Action::CreateDirectory(path) = action else {
+            continue;
+        };
+        if renames.iter().any(|(_, to)| to == path) || ignore.is_ignored(path, true) {
+            continue;
+        }
+        // What is under an entry that is renamed is looked for where the entry is now
+        let now = renames
+            .iter()
+            .find_map(|(from, to)| path.strip_prefix(to).ok().map(|rest| from.join(rest)))
+            .unwrap_or_else(|| path.clone());
+        if notes::find_existing(&now, backend).is_none() {
+            planned.push(Fix::Create(path.clone()));
+        }
+    }
+    Ok(planned)
+}
+
+/// List the fixes and make them with `backend`, once `prompt` confirms them.
+///
+/// The conflicted copies are listed in their own section, and are only moved into the review
+/// folder with `clean_conflicts`. A dry run only lists them.
+pub fn fix(
+    backend: &mut dyn OutputBackend,
+    fixes: &[Fix],
+    dry_run: bool,
+    clean_conflicts: bool,
+    mut prompt: Prompt<impl BufRead, impl Write>,
+) -> Result<(), Error> {
+    let (conflicts, mut fixes): (Vec<&Fix>, Vec<&Fix>) = fixes
+        .iter()
+        .partition(|fix| matches!(fix, Fix::Conflict(..)));
+    for fix in &fixes {
+        write!(prompt.output(), "{}", fix.describe(true))?;
+    }
+    if !conflicts.is_empty() {
+        writeln!(prompt.output(), "\n{}", output::heading("Sync Conflicts"))?;
+        for conflict in &conflicts {
+            let line = match conflict {
+                Fix::Conflict(from, _) if !clean_conflicts => {
+                    output::line(Mark::Deleted, "Conflict", &output::path(from))
+                }
+                _ => conflict.describe(true),
+            };
+            write!(prompt.output(), "{line}")?;
+        }
+        if clean_conflicts {
+            fixes.extend(conflicts);
+        }
+    }
+    if fixes.is_empty() {
+        write!(
+            prompt.output(),
+            "{}",
+            output::line(
+                Mark::Applied,
+                "Nothing to fix",
+                "the folders match the system"
+            )
+        )?;
+        return Ok(());
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if !prompt.confirm(&format!("Apply these {} fixes?", fixes.len()))? {
+        return Err(Error::msg("Nothing was fixed"));
+    }
+    for fix in &fixes {
+        fix.apply(backend)?;
+        write!(prompt.output(), "{}", fix.describe(false))?;
+    }
+    Ok(())
+}
+
+/// Check a single name against the provider's restrictions, returning every problem found
+fn check_name(name: &str, target: SyncTarget) -> Vec<String> {
+    let mut problems = Vec::new();
+    let illegal: Vec<char> = name
+        .chars()
+        .filter(|character| target.illegal_chars().contains(character) || character.is_control())
+        .collect();
+    if !illegal.is_empty() {
+        let listed: String = illegal.into_iter().collect();
+        problems.push(format!("contains illegal characters `{listed}`"));
+    }
+    if target.rejects_trailing_dot_or_space() && (name.ends_with('.') || name.ends_with(' ')) {
+        problems.push("ends with a dot or a space".to_owned());
+    }
+    let stem = name.split('.').next().unwrap_or_default();
+    if target
+        .reserved_names()
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        problems.push(format!("`{stem}` is a reserved name"));
+    }
+    problems
+}
+
+/// Check the length of a full generated path
+fn check_path(path: &Path, target: SyncTarget) -> Option<String> {
+    let length = path.to_string_lossy().chars().count();
+    (length > target.max_path_length()).then(|| {
+        format!(
+            "path is {length} characters long, the limit is {}",
+            target.max_path_length()
+        )
+    })
+}
+
+/// Every name that the system will generate, before it is joined into a path
+fn generated_names(system: &System) -> Vec<String> {
+    let mut names = Vec::new();
+    for area in &system.areas {
+        names.push(area.id.directory_name());
+        for category in &area.categories {
+            names.push(category.id.directory_name());
+            for folder in &category.folders {
+                names.push(folder.id());
+                names.extend(folder.folders.iter().map(FullId::id));
+            }
+        }
+    }
+    names
+}
+
+/// Validate every generated name in both output trees against the sync provider's restrictions.
+///
+/// Paths listed in the `.jdexignore` of a tree are never generated there, so they aren't checked.
+pub fn run(
+    output_config: &config::Output,
+    system: &System,
+    target: SyncTarget,
+    extension: &str,
+) -> Result<(), Error> {
+    let mut problem_count: usize = 0;
+    let mut paths = Vec::new();
+    for action in Planner::new(output_config, system, extension)?.generated()? {
+        let path = action.path();
+        if let Some(problem) = check_path(path, target) {
+            println!("{}: {problem}", path.display());
+            problem_count = problem_count.saturating_add(1);
+        }
+        paths.push(path.to_string_lossy().into_owned());
+    }
+    // A name is only checked when it ends up in a path that isn't ignored
+    for name in generated_names(system)
+        .into_iter()
+        .filter(|name| paths.iter().any(|path| path.contains(name.as_str())))
+    {
+        for problem in check_name(&name, target) {
+            println!("{name}: {problem}");
+            problem_count = problem_count.saturating_add(1);
+        }
+    }
+
+    if problem_count == 0 {
+        println!("All generated names are compatible with {target}");
+        Ok(())
+    } else {
+        Err(Error::msg(format!(
+            "Found {problem_count} problems that would prevent syncing with {target}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::MemoryFs, test_support::system};
+
+    #[test]
+    fn test_illegal_characters() {
+        let problems = check_name("31 Films/Movies?", SyncTarget::Dropbox);
+        assert_eq!(problems, vec!["contains illegal characters `/?`"]);
+        assert!(check_name("31 Films: Movies", SyncTarget::Gdrive).is_empty());
+    }
+
+    #[test]
+    fn test_trailing_dot_or_space() {
+        assert_eq!(check_name("Misc.", SyncTarget::Onedrive).len(), 1);
+        assert_eq!(check_name("Misc ", SyncTarget::Dropbox).len(), 1);
+        assert!(check_name("Misc.", SyncTarget::Gdrive).is_empty());
+    }
+
+    #[test]
+    fn test_reserved_names() {
+        assert_eq!(check_name("con.md", SyncTarget::Onedrive).len(), 1);
+        assert!(check_name("Console", SyncTarget::Onedrive).is_empty());
+    }
+
+    #[test]
+    fn test_path_length() {
+        let path = Path::new("/vault").join("a".repeat(300));
+        assert!(check_path(&path, SyncTarget::Dropbox).is_some());
+        assert!(check_path(&path, SyncTarget::Onedrive).is_none());
+    }
+
+    #[test]
+    fn test_fixes() {
+        let meta = PathBuf::from("/vault/L01/L01.00-09 System/L01.00 Meta");
+        let mut memory = MemoryFs::default();
+        memory
+            .create_dir_all(&meta.join("L01.00.01 Old Name"))
+            .unwrap();
+        memory
+            .write(&meta.join("L01.00.01 Old Name/Receipt.pdf"), "")
+            .unwrap();
+        memory
+            .create_dir_all(&meta.join("L01.00.05 Removed"))
+            .unwrap();
+        let system = system("00-09 System\n\t00 Meta\n\t\t01 Inbox\n\t\t02 Backups\n");
+        let actions = notes::get_all_actions(Path::new("/vault"), &system, "md");
+        let fixes = plan_fixes(&memory, "/vault", &system, &actions, "md", true, None).unwrap();
+        assert_eq!(
+            fixes,
+            vec![
+                Fix::Rename(
+                    meta.join("L01.00.01 Old Name"),
+                    meta.join("L01.00.01 Inbox")
+                ),
+                Fix::Review(
+                    meta.join("L01.00.05 Removed"),
+                    PathBuf::from("/vault/L01/@Review/L01.00.05 Removed")
+                ),
+                Fix::Create(meta.join("L01.00.02 Backups")),
+            ]
+        );
+        // Anything but yes leaves the folders alone
+        let answer = |answer: &'static str| Prompt::new(answer.as_bytes(), Vec::new());
+        assert!(fix(&mut memory, &fixes, false, false, answer("n\n")).is_err());
+        assert!(memory.exists(&meta.join("L01.00.01 Old Name")));
+        fix(&mut memory, &fixes, false, false, answer("y\n")).unwrap();
+        assert!(memory.exists(&meta.join("L01.00.01 Inbox/Receipt.pdf")));
+        assert!(memory.is_dir(Path::new("/vault/L01/@Review/L01.00.05 Removed")));
+        assert!(memory.is_dir(&meta.join("L01.00.02 Backups")));
+        // The review folder isn't drift itself
+        let fixes = plan_fixes(&memory, "/vault", &system, &actions, "md", true, None).unwrap();
+        assert!(fixes.is_empty());
+
+        // Conflicted copies are only moved when they are cleaned
+        let conflict = meta.join("L01.00.01 Inbox (conflicted copy 2024-05-01).md");
+        memory.write(&conflict, "").unwrap();
+        memory.write(&meta.join(".DS_Store"), "").unwrap();
+        let fixes = plan_fixes(&memory, "/vault", &system, &actions, "md", true, None).unwrap();
+        assert_eq!(
+            fixes,
+            vec![Fix::Conflict(
+                conflict.clone(),
+                PathBuf::from("/vault/L01/@Review/L01.00.01 Inbox (conflicted copy 2024-05-01).md")
+            )]
+        );
+        fix(&mut memory, &fixes, false, false, answer("y\n")).unwrap();
+        assert!(memory.exists(&conflict));
+        fix(&mut memory, &fixes, false, true, answer("y\n")).unwrap();
+        assert!(!memory.exists(&conflict));
+    }
+}
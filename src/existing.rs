@@ -0,0 +1,142 @@
+use std::{
+    collections::HashSet,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Error;
+
+use crate::{
+    backend::OutputBackend,
+    ignore::IgnoreRules,
+    notes::{self, Action},
+    output,
+    prompt::Prompt,
+    reconcile::SYSTEM_FILES,
+};
+
+/// How many of the paths already in the folder are listed, before the rest are only counted
+const LISTED: usize = 10;
+
+/// Whether the entry named `name` is hidden, like `.obsidian`, or kept by the operating system,
+/// which are never counted as content
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') || SYSTEM_FILES.contains(&name)
+}
+
+/// The paths under `base_folder` that generating `actions` wouldn't make, without going into the
+/// directories among them.
+///
+/// Hidden entries and the ones listed in the `.jdexignore` of the folder are left out.
+pub fn unmanaged(
+    backend: &dyn OutputBackend,
+    base_folder: &str,
+    actions: &[Action<'_>],
+) -> Result<Vec<PathBuf>, Error> {
+    let base = notes::expand(base_folder)?;
+    if !backend.is_dir(&base) {
+        return Ok(Vec::new());
+    }
+    let planned: HashSet<&Path> = actions
+        .iter()
+        .flat_map(|action| action.path().ancestors())
+        .collect();
+    let ignore = IgnoreRules::load(backend, &base)?;
+    let mut found = Vec::new();
+    let mut pending = vec![base];
+    while let Some(directory) = pending.pop() {
+        for path in backend.read_dir(&directory)? {
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| is_hidden(&name.to_string_lossy()));
+            let is_dir = backend.is_dir(&path);
+            if hidden || ignore.is_ignored(&path, is_dir) {
+                continue;
+            }
+            if !planned.contains(path.as_path()) {
+                found.push(path);
+            } else if is_dir {
+                pending.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Before the first generation into `base_folder`, list what is already in it that isn't part of
+/// the system, and only go on once `prompt` confirms it, so the notes aren't mixed into an
+/// existing vault by surprise
+pub fn confirm(
+    backend: &dyn OutputBackend,
+    base_folder: &str,
+    actions: &[Action<'_>],
+    mut prompt: Prompt<impl BufRead, impl Write>,
+) -> Result<(), Error> {
+    let found = unmanaged(backend, base_folder, actions)?;
+    if found.is_empty() {
+        return Ok(());
+    }
+    writeln!(
+        prompt.output(),
+        "{}",
+        output::heading("Already in the Notes")
+    )?;
+    for path in found.iter().take(LISTED) {
+        writeln!(prompt.output(), "  {}", output::path(path))?;
+    }
+    if found.len() > LISTED {
+        writeln!(
+            prompt.output(),
+            "  and {} more",
+            found.len().saturating_sub(LISTED)
+        )?;
+    }
+    if !prompt.confirm(&format!(
+        "Generate the system next to these {} paths that aren't part of it?",
+        found.len()
+    ))? {
+        return Err(Error::msg(
+            "Nothing was generated, give --acknowledge-existing to generate into a folder that already has other things in it",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::MemoryFs, test_support::system};
+
+    #[test]
+    fn test_unmanaged() {
+        let system = system("20-29 Finance\n\t21 Tax\n\t\t05 Returns\n");
+        let actions = notes::get_all_actions(Path::new("/vault"), &system, "md");
+        let mut memory = MemoryFs::default();
+        assert!(unmanaged(&memory, "/vault", &actions).unwrap().is_empty());
+        let tax = Path::new("/vault/L01/L01.20-29 Finance/L01.21 Tax");
+        memory
+            .create_dir_all(&tax.join("L01.21.05 Returns"))
+            .unwrap();
+        memory.write(&tax.join("2023 Return.pdf"), "").unwrap();
+        memory
+            .create_dir_all(Path::new("/vault/.obsidian"))
+            .unwrap();
+        memory.write(Path::new("/vault/.DS_Store"), "").unwrap();
+        memory
+            .create_dir_all(Path::new("/vault/Journal/2024"))
+            .unwrap();
+        memory.write(Path::new("/vault/Ideas.md"), "").unwrap();
+        assert_eq!(
+            unmanaged(&memory, "/vault", &actions).unwrap(),
+            vec![
+                PathBuf::from("/vault/Ideas.md"),
+                PathBuf::from("/vault/Journal"),
+                tax.join("2023 Return.pdf"),
+            ]
+        );
+        let answer = |answer: &'static str| Prompt::new(answer.as_bytes(), Vec::new());
+        assert!(confirm(&memory, "/vault", &actions, answer("n\n")).is_err());
+        confirm(&memory, "/vault", &actions, answer("y\n")).unwrap();
+    }
+}
@@ -0,0 +1,372 @@
+use core::fmt::Write as _;
+use std::{io::Write as _, path::PathBuf, sync::Arc};
+
+use color_eyre::eyre::{Error, OptionExt};
+
+use crate::{
+    config,
+    jid::JohnnyId,
+    label,
+    model::{Category, FolderKind, HasJohnnyId, System},
+    next_id,
+    notes::{self, Action},
+    output,
+    planner::Planner,
+};
+
+/// Everything known about a single entry of the system
+struct Entry<'sys> {
+    /// Each level from the system down to the entry, like `Category 21 Tax`
+    breadcrumb: Vec<String>,
+    /// The entry itself
+    item: &'sys dyn HasJohnnyId,
+    /// The kind of the entry, when it is a folder
+    kind: Option<&'sys FolderKind>,
+    /// Whether the id is retired
+    retired: bool,
+    /// How many entries are directly under this one
+    children: usize,
+    /// The entry itself, when it is a category
+    category: Option<&'sys Category>,
+    /// Where the things filed under the entry physically live
+    locations: Locations<'sys>,
+}
+
+/// The storage hints of an entry, which come from the closest entry above it that has any
+#[derive(Default)]
+struct Locations<'sys> {
+    /// The hints, in the order they were written
+    hints: Vec<&'sys str>,
+    /// The id of the entry the hints were written on, when it isn't the entry itself
+    inherited_from: Option<String>,
+}
+
+impl<'sys> Locations<'sys> {
+    /// Find the hints for the last of `levels`, going up through the others until some are found
+    fn find(levels: &[(&JohnnyId, &'sys [Arc<str>])]) -> Self {
+        let Some(last) = levels.len().checked_sub(1) else {
+            return Self::default();
+        };
+        levels
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, (_, locations))| !locations.is_empty())
+            .map(|(index, (id, locations))| Self {
+                hints: locations.iter().map(|location| &**location).collect(),
+                inherited_from: (index != last).then(|| id.by_seperator(".")),
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// One step of a breadcrumb, like `Category 21 Tax`, using the last part of the id
+fn crumb(level: &str, entry: &dyn HasJohnnyId) -> String {
+    let id = entry.jid().by_seperator(".");
+    format!(
+        "{level} {} {}",
+        id.rsplit('.').next().unwrap_or_default(),
+        entry.name()
+    )
+}
+
+/// Find the entry with the given id, with or without the system id
+fn find<'sys>(system: &'sys System, id: &str) -> Option<Entry<'sys>> {
+    let system_crumb = format!("System {} {}", system.id.by_seperator("."), system.name);
+    for area in &system.areas {
+        let area_crumb = format!(
+            "Area {:02}-{:02} {}",
+            area.id_range.0, area.id_range.1, area.topic
+        );
+        if area.id.matches(id) {
+            return Some(Entry {
+                breadcrumb: vec![system_crumb, area_crumb],
+                item: area,
+                kind: None,
+                retired: false,
+                children: area.categories.len(),
+                category: None,
+                locations: Locations::default(),
+            });
+        }
+        for category in &area.categories {
+            let category_crumb = crumb("Category", category);
+            if category.id.matches(id) {
+                return Some(Entry {
+                    breadcrumb: vec![system_crumb, area_crumb, category_crumb],
+                    item: category,
+                    kind: None,
+                    retired: category.retired,
+                    children: category.folders.len(),
+                    category: Some(category),
+                    locations: Locations::find(&[(&category.id, &category.locations)]),
+                });
+            }
+            for folder in &category.folders {
+                let folder_crumb = crumb("Folder", folder);
+                if folder.id.matches(id) {
+                    return Some(Entry {
+                        breadcrumb: vec![system_crumb, area_crumb, category_crumb, folder_crumb],
+                        item: folder,
+                        kind: Some(&folder.kind),
+                        retired: category.retired || folder.retired,
+                        children: folder.folders.len(),
+                        category: None,
+                        locations: Locations::find(&[
+                            (&category.id, &category.locations),
+                            (&folder.id, &folder.locations),
+                        ]),
+                    });
+                }
+                for xfolder in &folder.folders {
+                    if xfolder.id.matches(id) {
+                        let xfolder_crumb = crumb("Extended folder", xfolder);
+                        return Some(Entry {
+                            breadcrumb: vec![
+                                system_crumb,
+                                area_crumb,
+                                category_crumb,
+                                folder_crumb,
+                                xfolder_crumb,
+                            ],
+                            item: xfolder,
+                            kind: Some(&xfolder.kind),
+                            retired: category.retired || folder.retired || xfolder.retired,
+                            children: 0,
+                            category: None,
+                            locations: Locations::find(&[
+                                (&category.id, &category.locations),
+                                (&folder.id, &folder.locations),
+                                (&xfolder.id, &xfolder.locations),
+                            ]),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Describe what a kind of folder generates
+const fn kind_description(kind: Option<&FolderKind>) -> &'static str {
+    match kind {
+        None | Some(FolderKind::Folder) => "a directory",
+        Some(FolderKind::File) => "a note in the directory above",
+        Some(FolderKind::Both) => "a directory and a note in the directory above",
+        Some(FolderKind::Index) => "the jdex note of its category or area in the directory above",
+        Some(FolderKind::FullIndex) => "the jdex note of the whole system in the directory above",
+        Some(FolderKind::Link(_)) => "a link in the directory above to another entry",
+    }
+}
+
+/// The paths under `base_folder` that belong to the entry, out of the ones `actions` generate
+fn generated_paths(
+    base_folder: &str,
+    actions: &[Action<'_>],
+    entry: &Entry<'_>,
+    extension: &str,
+) -> Result<Vec<PathBuf>, Error> {
+    let base = notes::expand(base_folder)?;
+    let directory = base.join(entry.item.jid().as_path());
+    let note = label::note_path(&base, entry.item, entry.kind, extension);
+    let mut paths = vec![directory, note];
+    paths.dedup();
+    Ok(paths
+        .into_iter()
+        .filter(|path| actions.iter().any(|action| action.path() == path))
+        .collect())
+}
+
+/// Write the paths that the notes and the reference archive have for the entry
+fn write_generated(
+    explanation: &mut String,
+    output_config: &config::Output,
+    system: &System,
+    entry: &Entry<'_>,
+    extension: &str,
+) -> Result<(), Error> {
+    let planner = Planner::new(output_config, system, extension)?;
+    write_paths(
+        explanation,
+        "Notes",
+        &generated_paths(
+            &output_config.base_folder,
+            &planner.notes()?,
+            entry,
+            extension,
+        )?,
+    );
+    write_paths(
+        explanation,
+        "Reference",
+        &generated_paths(
+            &output_config.reference_folder,
+            &planner.archive()?,
+            entry,
+            extension,
+        )?,
+    );
+    Ok(())
+}
+
+/// Write the paths under a heading, or say that none are generated
+fn write_paths(explanation: &mut String, heading: &str, paths: &[PathBuf]) {
+    if paths.is_empty() {
+        let _ = writeln!(explanation, "  {heading}: nothing is generated");
+    }
+    for path in paths {
+        let _ = writeln!(explanation, "  {heading}: {}", output::path(path));
+    }
+}
+
+/// Write where the things filed under the entry physically live, or that nobody said
+fn write_locations(explanation: &mut String, locations: &Locations<'_>) {
+    if locations.hints.is_empty() {
+        let _ = writeln!(explanation, "  Location: not given");
+    }
+    for hint in &locations.hints {
+        match &locations.inherited_from {
+            Some(id) => {
+                let _ = writeln!(explanation, "  Location: {hint}, from {}", output::id(id));
+            }
+            None => {
+                let _ = writeln!(explanation, "  Location: {hint}");
+            }
+        }
+    }
+}
+
+/// Describe the entry in plain language
+fn explain(
+    output_config: &config::Output,
+    system: &System,
+    entry: &Entry<'_>,
+    extension: &str,
+) -> Result<String, Error> {
+    let mut explanation = format!(
+        "{} {}\n  {}\n",
+        output::id(&entry.item.jid().by_seperator(".")),
+        entry.item.name(),
+        entry.breadcrumb.join(" → ")
+    );
+    let kind_name = entry.kind.map_or("folder", FolderKind::tag);
+    let _ = writeln!(
+        explanation,
+        "  Kind: {kind_name}, {}",
+        kind_description(entry.kind)
+    );
+    let mut directives: Vec<String> = entry
+        .kind
+        .and_then(FolderKind::directive)
+        .map(|directive| format!("`{directive}` {kind_name}"))
+        .into_iter()
+        .collect();
+    if let Some(target) = entry.kind.and_then(FolderKind::link_target) {
+        directives.push(format!("[link={target}]"));
+    }
+    if entry.retired {
+        directives.push("[retired]".to_owned());
+    }
+    let _ = writeln!(
+        explanation,
+        "  Directives: {}",
+        if directives.is_empty() {
+            "none".to_owned()
+        } else {
+            directives.join(", ")
+        }
+    );
+    let _ = writeln!(
+        explanation,
+        "  Status: {}",
+        if entry.retired {
+            "retired, kept in the index but never generated or reused"
+        } else {
+            "active"
+        }
+    );
+    if entry.children > 0 {
+        let _ = writeln!(explanation, "  Entries under it: {}", entry.children);
+    }
+    write_locations(&mut explanation, &entry.locations);
+    if let Some(number) = entry.category.and_then(next_id::next_free) {
+        let _ = writeln!(
+            explanation,
+            "  Next free folder: {}",
+            output::id(&format!(
+                "{}.{number:02}",
+                entry.item.jid().by_seperator(".")
+            ))
+        );
+    }
+    write_generated(&mut explanation, output_config, system, entry, extension)?;
+    Ok(explanation)
+}
+
+/// Print where the things filed under the entry with the id `id` live, along with its paths
+pub fn locate(
+    output_config: &config::Output,
+    system: &System,
+    id: &str,
+    extension: &str,
+) -> Result<(), Error> {
+    let entry = find(system, id).ok_or_eyre(format!("No entry with the id {id} was found"))?;
+    let mut located = format!(
+        "{} {}\n",
+        output::id(&entry.item.jid().by_seperator(".")),
+        entry.item.name()
+    );
+    write_locations(&mut located, &entry.locations);
+    write_generated(&mut located, output_config, system, &entry, extension)?;
+    write!(anstream::stdout(), "{located}")?;
+    Ok(())
+}
+
+/// Print a description of the entry with the id `id`
+pub fn run(
+    output_config: &config::Output,
+    system: &System,
+    id: &str,
+    extension: &str,
+) -> Result<(), Error> {
+    let entry = find(system, id).ok_or_eyre(format!("No entry with the id {id} was found"))?;
+    write!(
+        anstream::stdout(),
+        "{}",
+        explain(output_config, system, &entry, extension)?
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_explain_folder() {
+        let system = system("20-29 Finance\n\t21 Tax\n\t\t05 Returns\n\t\t06 Old [retired]\n");
+        let entry = find(&system, "21.05").unwrap();
+        assert_eq!(
+            entry.breadcrumb.join(" → "),
+            "System L01 Life → Area 20-29 Finance → Category 21 Tax → Folder 05 Returns"
+        );
+        assert!(!entry.retired);
+        assert!(find(&system, "L01.21.06").unwrap().retired);
+        assert!(find(&system, "21.07").is_none());
+    }
+
+    #[test]
+    fn test_locations_are_inherited() {
+        let system = system(
+            "20-29 Finance\n\t21 Tax [location=Filing cabinet]\n\t\t05 Returns\n\t\t06 Receipts [location=paper] [location=Dropbox]\n",
+        );
+        let returns = find(&system, "21.05").unwrap().locations;
+        assert_eq!(returns.hints, vec!["Filing cabinet"]);
+        assert_eq!(returns.inherited_from.as_deref(), Some("L01.21"));
+        let receipts = find(&system, "21.06").unwrap().locations;
+        assert_eq!(receipts.hints, vec!["paper", "Dropbox"]);
+        assert_eq!(receipts.inherited_from, None);
+    }
+}
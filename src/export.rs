@@ -0,0 +1,420 @@
+use core::fmt::Write as _;
+use std::{
+    fs,
+    io::{self, Write},
+    iter,
+    path::Path,
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::Error;
+
+use crate::{
+    bundle,
+    config::STANDARD_STREAM,
+    html,
+    markdown::MdFormatter,
+    model::{FullId, System},
+    notes::Action,
+    output::{self, Mark},
+    pdf::{self, Font, Page, PAGE_HEIGHT, PAGE_WIDTH},
+    timestamp::Timestamp,
+};
+
+/// The space around the text of a page, in points
+const MARGIN: usize = 36;
+
+/// The space between two columns, in points
+const GUTTER: usize = 12;
+
+/// How many columns the index is set in
+const COLUMNS: usize = 3;
+
+/// The font sizes the index is tried in, largest first, until it fits on one page
+const SIZES: [usize; 5] = [9, 8, 7, 6, SMALLEST];
+
+/// The smallest font size, which the index is set in over as many pages as it needs when it
+/// doesn't fit on one
+const SMALLEST: usize = 5;
+
+/// The characters of the base64 that IMAP encodes mailbox names outside of ASCII with, which has a
+/// comma where the usual one has a slash
+const MAILBOX_BASE64: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+,";
+
+/// The format the index is exported in
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// A printable PDF index, on a single page when it fits
+    Pdf,
+    /// A standalone HTML page, like the one `serve` shows
+    Html,
+    /// A zip archive of every directory and file the notes and the reference archive are generated
+    /// with
+    Zip,
+    /// A tar archive of every directory and file the notes and the reference archive are generated
+    /// with
+    Tar,
+    /// A shell script creating a mail folder for every entry on an IMAP server
+    Imap,
+    /// A bookmarks file with a folder for every entry, which browsers import
+    Bookmarks,
+}
+
+/// A line of the printed index
+#[derive(Debug, PartialEq, Eq)]
+struct Line {
+    /// How many levels the line is indented by
+    indent: usize,
+    /// The font the line is set in
+    font: Font,
+    /// The id and topic
+    text: String,
+}
+
+/// The lines of the index, leaving out what is retired since nothing is filed there anymore
+fn lines(system: &System) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for area in &system.areas {
+        lines.push(Line {
+            indent: 0,
+            font: Font::Bold,
+            text: area.id(),
+        });
+        for category in area.categories.iter().filter(|category| !category.retired) {
+            lines.push(Line {
+                indent: 1,
+                font: Font::Bold,
+                text: category.id(),
+            });
+            for folder in category.folders.iter().filter(|folder| !folder.retired) {
+                lines.push(Line {
+                    indent: 2,
+                    font: Font::Regular,
+                    text: folder.id(),
+                });
+                lines.extend(
+                    folder
+                        .folders
+                        .iter()
+                        .filter(|xfolder| !xfolder.retired)
+                        .map(|xfolder| Line {
+                            indent: 3,
+                            font: Font::Regular,
+                            text: xfolder.id(),
+                        }),
+                );
+            }
+        }
+    }
+    lines
+}
+
+/// `text` cut down to about what fits into `width` points at `size`, taking a character to be half
+/// as wide as it is high on average
+fn fit(text: &str, width: usize, size: usize) -> String {
+    let most = width.saturating_mul(2) / size.max(1);
+    if text.chars().count() <= most {
+        return text.to_owned();
+    }
+    let mut cut: String = text.chars().take(most.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// Set the lines in columns under the name of the system, at the largest size that fits them on
+/// one page
+fn pages(title: &str, lines: &[Line]) -> Vec<Page> {
+    let column_width = (PAGE_WIDTH - 2 * MARGIN - (COLUMNS - 1) * GUTTER) / COLUMNS;
+    let rows_at = |size: usize| (PAGE_HEIGHT - 2 * MARGIN - 2 * (size + 4)) / (size + 2);
+    let size = SIZES
+        .into_iter()
+        .find(|size| lines.len() <= rows_at(*size) * COLUMNS)
+        .unwrap_or(SMALLEST);
+    let leading = size + 2;
+    let rows = rows_at(size).max(1);
+    let top = PAGE_HEIGHT - MARGIN - 2 * (size + 4);
+    let mut pages = Vec::new();
+    let mut chunks = lines.chunks(rows * COLUMNS).peekable();
+    // Even an empty system gets a page with its name
+    if chunks.peek().is_none() {
+        pages.push(Page::default());
+    }
+    for chunk in chunks {
+        let mut page = Page::default();
+        for (index, line) in chunk.iter().enumerate() {
+            let indent = line.indent * size;
+            let x = MARGIN + index / rows * (column_width + GUTTER) + indent;
+            let y = top - index % rows * leading;
+            let text = fit(&line.text, column_width.saturating_sub(indent), size);
+            page.text(x, y, size, line.font, &text);
+        }
+        pages.push(page);
+    }
+    for page in &mut pages {
+        page.text(
+            MARGIN,
+            PAGE_HEIGHT - MARGIN - size - 4,
+            size + 4,
+            Font::Bold,
+            title,
+        );
+    }
+    pages
+}
+
+/// The whole system as a PDF document
+pub fn to_pdf(system: &System) -> Vec<u8> {
+    pdf::write(&pages(&system.id(), &lines(system)))
+}
+
+/// Encode the UTF-16 units of `pending` into `encoded` as a shifted run of a mailbox name
+fn shift(encoded: &mut String, pending: &mut Vec<u16>) {
+    if pending.is_empty() {
+        return;
+    }
+    let bytes: Vec<usize> = pending
+        .drain(..)
+        .flat_map(u16::to_be_bytes)
+        .map(usize::from)
+        .collect();
+    encoded.push('&');
+    for chunk in bytes.chunks(3) {
+        let bits = chunk
+            .iter()
+            .chain(iter::repeat(&0))
+            .take(3)
+            .fold(0, |bits, byte| (bits << 8) | byte);
+        // Three bytes take four characters, and the bytes a chunk is short of take none
+        for sextet in 0..=chunk.len() {
+            let index = (bits >> (18 - 6 * sextet)) & 0x3f;
+            encoded.push(char::from(MAILBOX_BASE64[index]));
+        }
+    }
+    encoded.push('-');
+}
+
+/// `name` as IMAP writes mailbox names, in modified UTF-7 where everything outside of printable
+/// ASCII is shifted into base64 between `&` and `-`
+fn mailbox_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    let mut pending = Vec::new();
+    for character in name.chars() {
+        if (' '..='~').contains(&character) {
+            shift(&mut encoded, &mut pending);
+            if character == '&' {
+                encoded.push_str("&-");
+            } else {
+                encoded.push(character);
+            }
+        } else {
+            let mut units = [0; 2];
+            pending.extend_from_slice(character.encode_utf16(&mut units));
+        }
+    }
+    shift(&mut encoded, &mut pending);
+    encoded
+}
+
+/// The path of every line below the one of the system, with the ids and topics of the lines above
+/// it, from the top down
+fn line_paths<'lines>(title: &'lines str, lines: &'lines [Line]) -> Vec<Vec<&'lines str>> {
+    let mut path = vec![title];
+    let mut paths = vec![path.clone()];
+    for line in lines {
+        path.truncate(line.indent + 1);
+        path.push(&line.text);
+        paths.push(path.clone());
+    }
+    paths
+}
+
+/// The part of the IMAP script after its header: it asks the server for the delimiter it nests
+/// mail folders with, then creates each folder from the names of it and the folders above it
+const IMAP_SCRIPT: &str = r#"imap() {
+  curl --silent --show-error --netrc --url "$IMAP_URL" --request "$1"
+}
+# Print $1 with every $2 in it replaced by $3
+replace() {
+  rest=$1 replaced=
+  while case $rest in *"$2"*) true ;; *) false ;; esac; do
+    replaced=$replaced${rest%%"$2"*}$3
+    rest=${rest#*"$2"}
+  done
+  printf '%s' "$replaced$rest"
+}
+delimiter=$(imap 'LIST "" ""' | sed -n 's/^\* LIST ([^)]*) "\([^"]*\)".*/\1/p')
+if [ -z "$delimiter" ]; then
+  echo "$IMAP_URL doesn't nest mail folders" >&2
+  exit 1
+fi
+delimiter=$(replace "$delimiter" '\\' '\')
+# Create the folder named by the last argument inside the ones before it. Every id has dots in
+# it, so the delimiter is replaced by _ in the names to keep each of them one folder
+create() {
+  mailbox=
+  for folder in "$@"; do
+    mailbox=${mailbox:+$mailbox$delimiter}$(replace "$folder" "$delimiter" _)
+  done
+  quoted=$(replace "$mailbox" '\' '\\')
+  quoted=$(replace "$quoted" '"' '\"')
+  imap "CREATE \"$quoted\"" >/dev/null || echo "Skipped $mailbox, which may already exist" >&2
+}
+"#;
+
+/// A shell script that creates a mail folder for the system and every entry in it on the IMAP
+/// server at `$IMAP_URL`, with the login from `~/.netrc`, so mail can be filed by the same numbers
+pub fn to_imap_script(system: &System) -> String {
+    let title = system.id();
+    let mut script = format!(
+        "#!/bin/sh\n# Create the mail folders of {title} on the IMAP server at $IMAP_URL, like\n\
+         # imaps://imap.example.com, logging in with the password for it in ~/.netrc\nset -eu\n\
+         {IMAP_SCRIPT}"
+    );
+    for path in line_paths(&title, &lines(system)) {
+        script.push_str("create");
+        for name in path {
+            let _ = write!(script, " '{}'", mailbox_name(name).replace('\'', "'\\''"));
+        }
+        script.push('\n');
+    }
+    script
+}
+
+/// The system as a Netscape bookmarks file, with a folder for every entry nested like the
+/// entries are, for browsers to import and file bookmarks in by number
+pub fn to_bookmarks(system: &System) -> String {
+    let mut bookmarks = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n<H1>Bookmarks</H1>\n<DL><p>\n",
+    );
+    let mut open = 0;
+    for path in line_paths(&system.id(), &lines(system)) {
+        let depth = path.len();
+        // Close the folders of the lines above that this one isn't in
+        while open >= depth {
+            let _ = writeln!(bookmarks, "{}</DL><p>", "    ".repeat(open));
+            open -= 1;
+        }
+        let name = path.last().copied().unwrap_or_default();
+        let indent = "    ".repeat(depth);
+        let _ = writeln!(bookmarks, "{indent}<DT><H3>{}</H3>", html::escape(name));
+        let _ = writeln!(bookmarks, "{indent}<DL><p>");
+        open = depth;
+    }
+    while open > 0 {
+        let _ = writeln!(bookmarks, "{}</DL><p>", "    ".repeat(open));
+        open -= 1;
+    }
+    bookmarks.push_str("</DL><p>\n");
+    bookmarks
+}
+
+/// Export the index of the whole system in `format` to `output`, or stdout when it is `-`.
+///
+/// The archive formats hold what `actions` generate instead, made in memory so nothing on disk is
+/// touched, with the folders of `roots` as `notes/` and `archive/` and `started` as when
+/// everything in them was modified.
+pub fn run(
+    system: &System,
+    format: ExportFormat,
+    output: &Path,
+    actions: &[Action<'_>],
+    formatter: &MdFormatter<'_>,
+    roots: &bundle::Roots,
+    started: Timestamp,
+) -> Result<(), Error> {
+    let contents = match format {
+        ExportFormat::Pdf => to_pdf(system),
+        ExportFormat::Html => html::page(&system.name, &html::render(system), "").into_bytes(),
+        ExportFormat::Zip => bundle::zip(&bundle::generate(actions, formatter)?, roots, started)?,
+        ExportFormat::Tar => bundle::tar(
+            &bundle::generate(actions, formatter)?,
+            roots,
+            started.seconds(),
+        ),
+        ExportFormat::Imap => to_imap_script(system).into_bytes(),
+        ExportFormat::Bookmarks => to_bookmarks(system).into_bytes(),
+    };
+    if output == Path::new(STANDARD_STREAM) {
+        io::stdout().write_all(&contents)?;
+    } else {
+        fs::write(output, contents)?;
+        write!(
+            anstream::stdout(),
+            "{}",
+            output::line(Mark::Created, "Wrote", &output::path(output))
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_lines() {
+        let lines = lines(&system(
+            "20-29 Finance\n\t21 Tax\n\t\t01 Returns\n\t\t02 Old [retired]\n\t22 Bank [retired]\n",
+        ));
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["L01.20-29 Finance", "L01.21 Tax", "L01.21.01 Returns"]
+        );
+        assert_eq!(lines[2].indent, 2);
+    }
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit("L01.21 Tax", 100, 9), "L01.21 Tax");
+        assert_eq!(fit("L01.21 Taxes and returns", 40, 8), "L01.21 Ta…");
+    }
+
+    #[test]
+    fn test_mailbox_name() {
+        assert_eq!(mailbox_name("L01.21 Tax"), "L01.21 Tax");
+        assert_eq!(mailbox_name("L01.22 Café & Co"), "L01.22 Caf&AOk- &- Co");
+        assert_eq!(mailbox_name("日本"), "&ZeVnLA-");
+    }
+
+    #[test]
+    fn test_imap_and_bookmarks() {
+        let system = system("20-29 Finance\n\t21 Tax's\n\t\t01 Returns\n\t22 Bank [retired]\n");
+        let script = to_imap_script(&system);
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("delimiter=$(imap 'LIST \"\" \"\"'"));
+        assert!(script.contains("create 'L01 Life' 'L01.20-29 Finance'\n"));
+        assert!(script.contains("create 'L01 Life' 'L01.20-29 Finance' 'L01.21 Tax'\\''s'\n"));
+        assert!(!script.contains("Bank"));
+        let bookmarks = to_bookmarks(&system);
+        assert!(bookmarks.contains("        <DT><H3>L01.20-29 Finance</H3>\n"));
+        assert!(bookmarks.contains("<DT><H3>L01.21 Tax&#39;s</H3>"));
+        assert_eq!(
+            bookmarks.matches("<DL><p>").count(),
+            bookmarks.matches("</DL><p>").count()
+        );
+    }
+
+    #[test]
+    fn test_pages() {
+        let small = system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n");
+        let pdf = String::from_utf8_lossy(&to_pdf(&small)).into_owned();
+        assert!(pdf.contains("/Count 1 >>"));
+        assert!(pdf.contains("BT /F2 9 Tf"));
+        assert!(pdf.contains("(L01.21.01 Returns) Tj"));
+        let mut config = String::from("20-29 Finance\n");
+        for category in 20..=29 {
+            let _ = writeln!(config, "\t{category} Category");
+            for folder in 1..100 {
+                let _ = writeln!(config, "\t\t{folder:02} Folder");
+            }
+        }
+        let large = String::from_utf8_lossy(&to_pdf(&system(&config))).into_owned();
+        assert!(large.contains("BT /F2 5 Tf"));
+        assert!(large.contains("/Count 4 >>"));
+    }
+}
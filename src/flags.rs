@@ -0,0 +1,267 @@
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, ArgMatches, Args, Command, FromArgMatches};
+
+/// The flag `--{long}`, which is set by giving it
+fn flag(id: &'static str, long: &'static str, help: &'static str) -> Arg {
+    Arg::new(id)
+        .long(long)
+        .action(ArgAction::SetTrue)
+        .help(help)
+}
+
+/// Whether a generation is applied, or only previewed with --dry-run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preview {
+    /// Apply the actions
+    Apply,
+    /// Only print the actions that would be taken
+    DryRun,
+    /// Only print the actions, exiting with 2 when the folders don't match the config
+    Check,
+}
+
+impl Preview {
+    /// Whether the actions are only printed
+    pub const fn is_dry_run(self) -> bool {
+        !matches!(self, Self::Apply)
+    }
+}
+
+impl FromArgMatches for Preview {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        Ok(if matches.get_flag("fail_on_changes") {
+            Self::Check
+        } else if matches.get_flag("dry_run") {
+            Self::DryRun
+        } else {
+            Self::Apply
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+impl Args for Preview {
+    fn augment_args(cmd: Command) -> Command {
+        cmd.arg(flag("dry_run", "dry-run", "Preview what actions will be taken").short('d'))
+            .arg(
+                flag(
+                    "fail_on_changes",
+                    "fail-on-changes",
+                    "With --dry-run, exit with 2 when the folders don't match the config. Indexes only count when missing",
+                )
+                .alias("check")
+                .requires("dry_run"),
+            )
+    }
+
+    fn augment_args_for_update(cmd: Command) -> Command {
+        Self::augment_args(cmd)
+    }
+}
+
+/// What a generation writes, which is everything unless --create-only or --index-only limit it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Create what is missing and rewrite what changed
+    Everything,
+    /// Only create what is missing, never rewriting anything that already exists
+    CreateOnly,
+    /// Only rewrite the jdex indexes and maps of content
+    IndexOnly,
+}
+
+impl FromArgMatches for Scope {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        Ok(if matches.get_flag("create_only") {
+            Self::CreateOnly
+        } else if matches.get_flag("index_only") {
+            Self::IndexOnly
+        } else {
+            Self::Everything
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+impl Args for Scope {
+    fn augment_args(cmd: Command) -> Command {
+        cmd.arg(flag(
+            "create_only",
+            "create-only",
+            "Only create what is missing, never rewriting an index or anything else that already exists",
+        ))
+        .arg(
+            flag(
+                "index_only",
+                "index-only",
+                "Only rewrite the jdex indexes and maps of content, leaving every directory and note as it is",
+            )
+            .conflicts_with("create_only"),
+        )
+    }
+
+    fn augment_args_for_update(cmd: Command) -> Command {
+        Self::augment_args(cmd)
+    }
+}
+
+/// Where a generation goes, which is the folders unless --stdout or --tar print it instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Destination {
+    /// Write into the folders of the config file
+    Folders,
+    /// Print every file, each after a `--- path` line
+    Stdout,
+    /// Write everything into a tar archive at the path, `-` for stdout
+    Tar(PathBuf),
+}
+
+impl FromArgMatches for Destination {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        Ok(if matches.get_flag("stdout") {
+            Self::Stdout
+        } else if let Some(path) = matches.get_one::<PathBuf>("tar") {
+            Self::Tar(path.clone())
+        } else {
+            Self::Folders
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+impl Args for Destination {
+    fn augment_args(cmd: Command) -> Command {
+        cmd.arg(
+            flag(
+                "stdout",
+                "stdout",
+                "Print every file a generation would write, each after a `--- path` line, instead of writing it",
+            )
+            .conflicts_with_all(["dry_run", "plan_out", "tar"]),
+        )
+        .arg(
+            Arg::new("tar")
+                .long("tar")
+                .value_name("TAR")
+                .value_parser(clap::value_parser!(PathBuf))
+                .conflicts_with_all(["dry_run", "plan_out"])
+                .help("Write everything a generation would make into a tar archive instead, `-` for stdout"),
+        )
+    }
+
+    fn augment_args_for_update(cmd: Command) -> Command {
+        Self::augment_args(cmd)
+    }
+}
+
+/// Who answers the questions of a command, which is whoever is at the terminal unless --yes or
+/// --non-interactive answer them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answering {
+    /// Ask on the terminal
+    Ask,
+    /// Confirm everything and take the suggested answer to every question
+    Yes,
+    /// Fail when a change needs confirming or a question needs an answer
+    NonInteractive,
+}
+
+impl FromArgMatches for Answering {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        // Confirming everything answers whatever --non-interactive would fail on
+        Ok(if matches.get_flag("yes") {
+            Self::Yes
+        } else if matches.get_flag("non_interactive") {
+            Self::NonInteractive
+        } else {
+            Self::Ask
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+impl Args for Answering {
+    fn augment_args(cmd: Command) -> Command {
+        cmd.arg(
+            flag(
+                "yes",
+                "yes",
+                "Confirm every change and take the suggested answer to every question, without asking",
+            )
+            .short('y'),
+        )
+        .arg(flag(
+            "non_interactive",
+            "non-interactive",
+            "Never ask anything, failing when a change needs confirming or a question needs an answer",
+        ))
+    }
+
+    fn augment_args_for_update(cmd: Command) -> Command {
+        Self::augment_args(cmd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    /// The flags of a generation, as they are given to jdexmd
+    #[derive(Debug, Parser)]
+    struct Flags {
+        #[clap(flatten)]
+        preview: Preview,
+        #[clap(flatten)]
+        scope: Scope,
+        #[clap(flatten)]
+        destination: Destination,
+        #[clap(flatten)]
+        answering: Answering,
+        #[clap(long)]
+        plan_out: Option<PathBuf>,
+    }
+
+    fn parse(args: &[&str]) -> Result<Flags, clap::Error> {
+        Flags::try_parse_from(core::iter::once(&"jdexmd").chain(args))
+    }
+
+    #[test]
+    fn test_flags() {
+        let flags = parse(&[]).unwrap();
+        assert_eq!(flags.preview, Preview::Apply);
+        assert_eq!(flags.scope, Scope::Everything);
+        assert_eq!(flags.destination, Destination::Folders);
+        assert_eq!(flags.answering, Answering::Ask);
+        let flags = parse(&["-d", "--check", "--index-only", "-y", "--non-interactive"]).unwrap();
+        assert_eq!(flags.preview, Preview::Check);
+        assert_eq!(flags.scope, Scope::IndexOnly);
+        assert_eq!(flags.answering, Answering::Yes);
+        assert_eq!(
+            parse(&["--tar", "-"]).unwrap().destination,
+            Destination::Tar(PathBuf::from("-"))
+        );
+        assert!(parse(&["--fail-on-changes"]).is_err());
+        assert!(parse(&["--create-only", "--index-only"]).is_err());
+        assert!(parse(&["--stdout", "--tar", "-"]).is_err());
+        assert!(parse(&["--dry-run", "--stdout"]).is_err());
+    }
+}
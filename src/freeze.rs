@@ -0,0 +1,187 @@
+use std::{
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Error;
+
+use crate::{
+    config::{self, JohnnyDecimal},
+    label, line,
+    model::{Folder, FolderKind, FullId as _, HasJohnnyId, System},
+    notes,
+    output::{self, Mark},
+    resolve,
+};
+
+/// Freeze `folder` along with its extended folders
+fn freeze_folder(folder: &mut Folder) {
+    folder.frozen = true;
+    for xfolder in &mut folder.folders {
+        xfolder.frozen = true;
+    }
+}
+
+/// Freeze the entry with the full id `id`, a category, folder, or extended folder, along with
+/// everything under it, returning its id and topic and the path of its directory below the base
+/// folders
+fn freeze(system: &mut System, id: &str) -> Result<(String, PathBuf), Error> {
+    for category in system
+        .areas
+        .iter_mut()
+        .flat_map(|area| &mut area.categories)
+    {
+        if category.id.matches(id) {
+            category.frozen = true;
+            category.folders.iter_mut().for_each(freeze_folder);
+            return Ok((category.id(), category.id.as_path().to_path_buf()));
+        }
+        for folder in &mut category.folders {
+            if folder.id.matches(id) {
+                freeze_folder(folder);
+                return Ok((folder.id(), folder.id.as_path().to_path_buf()));
+            }
+            if let Some(xfolder) = folder
+                .folders
+                .iter_mut()
+                .find(|xfolder| xfolder.id.matches(id))
+            {
+                xfolder.frozen = true;
+                return Ok((xfolder.id(), xfolder.id.as_path().to_path_buf()));
+            }
+        }
+    }
+    Err(Error::msg(format!(
+        "{id} isn't a category, folder, or extended folder, which are the only entries that can be frozen"
+    )))
+}
+
+/// `definition` with every one of `entries` tagged `[frozen]` on its own line, starting with the
+/// entry that is frozen, along with the ids of the entries under it that have no line of their
+/// own, because they come from a counted range or a template
+fn tag_frozen(
+    definition: &str,
+    entries: &[(&dyn HasJohnnyId, Option<&FolderKind>)],
+) -> Result<(String, Vec<String>), Error> {
+    let frozen = |line: &str| line::with_tag(line, "frozen");
+    let Some(((entry, _), under)) = entries.split_first() else {
+        return Ok((definition.to_owned(), Vec::new()));
+    };
+    let mut tagged = line::edit_entry(definition, entry.jid(), frozen)?;
+    let mut untagged = Vec::new();
+    for (entry, _) in under {
+        match line::edit_entry(&tagged, entry.jid(), frozen) {
+            Ok(edited) => tagged = edited,
+            Err(_) => untagged.push(entry.jid().by_seperator(".")),
+        }
+    }
+    Ok((tagged, untagged))
+}
+
+/// Make `directory` and every directory under it read-only, returning how many were made so
+fn make_read_only(directory: &Path) -> Result<usize, Error> {
+    let mut count: usize = 0;
+    for entry in fs::read_dir(directory)?.flatten() {
+        if entry.file_type()?.is_dir() {
+            count = count.saturating_add(make_read_only(&entry.path())?);
+        }
+    }
+    let mut permissions = fs::metadata(directory)?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(directory, permissions)?;
+    Ok(count.saturating_add(1))
+}
+
+/// Freeze the entry `id` and everything under it in the config file, and make its directories in
+/// the notes and the reference archive read-only where the platform allows it.
+///
+/// A frozen entry keeps its place in the index, without a link, and nothing more is generated in
+/// its directories.
+pub fn run(config_file: &PathBuf, output_config: &config::Output, id: &str) -> Result<(), Error> {
+    let mut parameters = JohnnyDecimal::from_file(config_file)?.system_config;
+    // The standard zeros aren't written in the definition, so there is nothing to tag
+    parameters.standard_zeros = false;
+    let mut system = System::try_from(parameters)?;
+    let full = resolve::resolve(&system, id, None)?;
+    let (entry, path) = freeze(&mut system, &full)?;
+    let mut untagged = Vec::new();
+    JohnnyDecimal::edit_definition(config_file, |definition| {
+        let tagged;
+        (tagged, untagged) = tag_frozen(definition, &label::entries_under(&system, &full))?;
+        Ok(tagged)
+    })?;
+    let mut stdout = anstream::stdout();
+    write!(stdout, "{}", output::line(Mark::Changed, "Froze", &entry))?;
+    for id in untagged {
+        write!(
+            stdout,
+            "{}",
+            output::line(
+                Mark::Skipped,
+                "Left Alone",
+                &format!(
+                    "{id}, which comes from a counted range or a template, tag it [frozen] there"
+                )
+            )
+        )?;
+    }
+    let mut bases = vec![&output_config.base_folder, &output_config.reference_folder];
+    // A workspace keeps both in one tree
+    bases.dedup();
+    for base in bases {
+        let directory = notes::expand(base)?.join(&path);
+        if !directory.is_dir() {
+            continue;
+        }
+        match make_read_only(&directory) {
+            Ok(count) => write!(
+                stdout,
+                "{}",
+                output::line(
+                    Mark::Changed,
+                    "Made Read-Only",
+                    &format!("{} ({count} directories)", output::path(&directory))
+                )
+            )?,
+            Err(err) => eprintln!(
+                "Unable to make {} read-only, it is only frozen in the config: {err}",
+                directory.display()
+            ),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{line::format_system, test_support::system};
+
+    #[test]
+    fn test_freeze() {
+        let mut frozen =
+            system("20-29 Finance\n\t21 Tax\n\t\t05 Returns\n\t\t\tX01 Drafts\n\t\t06 Receipts\n");
+        let (entry, path) = freeze(&mut frozen, "L01.21.05").unwrap();
+        assert_eq!(entry, "L01.21.05 Returns");
+        assert_eq!(
+            path,
+            PathBuf::from("L01/L01.20-29 Finance/L01.21 Tax/L01.21.05 Returns")
+        );
+        assert_eq!(
+            format_system(&frozen),
+            "20-29 Finance\n\t21 Tax\n\t\t05 Returns [frozen]\n\t\t\tX01 Drafts [frozen]\n\t\t06 Receipts\n"
+        );
+        assert!(freeze(&mut frozen, "L01.20-29").is_err());
+        let definition = "# Taxes\n20-29 Finance\n\t21 Tax\n\t\t05 Returns [status=active]\n\t\t\tX01..02 Draft {n}\n\t\t06 Receipts\n";
+        let system =
+            system("20-29 Finance\n\t21 Tax\n\t\t05 Returns\n\t\t\tX01 Drafts\n\t\t06 Receipts\n");
+        let (tagged, untagged) =
+            tag_frozen(definition, &label::entries_under(&system, "L01.21.05")).unwrap();
+        assert_eq!(
+            tagged,
+            "# Taxes\n20-29 Finance\n\t21 Tax\n\t\t05 Returns [status=active] [frozen]\n\t\t\tX01..02 Draft {n}\n\t\t06 Receipts\n"
+        );
+        assert_eq!(untagged, vec!["L01.21.05.X01".to_owned()]);
+    }
+}
@@ -0,0 +1,112 @@
+use core::fmt::Write as _;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    backend::{Entry, MemoryFs},
+    config::{self, JohnnyDecimal},
+    markdown::{Generation, MdFormatter},
+    model::System,
+    notes,
+    planner::Planner,
+    timestamp::Timestamp,
+};
+
+/// Where the notes are generated
+const NOTES: &str = "/notes";
+/// Where the reference archive is generated
+const ARCHIVE: &str = "/archive";
+
+/// Generate the system defined in `config_file` into memory, the same way a real run would
+fn generate(config_file: &Path) -> MemoryFs {
+    let config = JohnnyDecimal::from_file(&config_file.to_path_buf()).unwrap();
+    let system = System::try_from(config.system_config).unwrap();
+    let formatter = MdFormatter::try_from(config.format)
+        .unwrap()
+        .link_root(NOTES)
+        // Pin everything that changes between runs or releases
+        .generation(Generation {
+            jdexmd_version: "0.0.0",
+            ..Generation::new(Timestamp::from_seconds(0), "golden".to_owned())
+        });
+    let output_config = config::Output {
+        base_folder: NOTES.to_owned(),
+        reference_folder: ARCHIVE.to_owned(),
+        ..config.output_config
+    };
+    let mut memory = MemoryFs::default();
+    let actions = Planner::new(&output_config, &system, formatter.extension())
+        .unwrap()
+        .backend(&memory)
+        .all()
+        .unwrap();
+    for action in actions {
+        if notes::need_to_apply(&action, &memory) {
+            action.execute(&formatter, &mut memory).unwrap();
+        }
+    }
+    memory
+}
+
+/// Write out every directory and file, with the contents of each file indented under it
+fn snapshot(memory: &MemoryFs) -> String {
+    let mut snapshot = String::new();
+    for (path, contents) in &memory.entries {
+        if path.parent().is_none() {
+            continue;
+        }
+        match contents {
+            Entry::Directory => {
+                let _ = writeln!(snapshot, "{}/", path.display());
+            }
+            Entry::Link(target) => {
+                let _ = writeln!(snapshot, "{} -> {}", path.display(), target.display());
+            }
+            Entry::File(contents) => {
+                let _ = writeln!(snapshot, "{}", path.display());
+                for line in contents.lines() {
+                    let _ = writeln!(snapshot, "    {line}");
+                }
+            }
+        }
+    }
+    snapshot
+}
+
+/// Compare the generation of `config` against its snapshot in `tests/golden`.
+///
+/// Run with `UPDATE_GOLDEN=1 cargo test golden` to accept new output.
+fn check(name: &str, config: &Path) {
+    let actual = snapshot(&generate(config));
+    let expected_file = PathBuf::from("tests/golden").join(format!("{name}.snap"));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&expected_file, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&expected_file).unwrap_or_default();
+    assert!(
+        expected == actual,
+        "{name} doesn't match {}, rerun with UPDATE_GOLDEN=1 if the change is expected\n{actual}",
+        expected_file.display()
+    );
+}
+
+#[test]
+fn golden_example() {
+    check("example", Path::new("example.garden.toml"));
+}
+
+#[test]
+fn golden_org() {
+    check("org", Path::new("tests/golden/org.toml"));
+}
+
+#[test]
+fn golden_standard_zeros() {
+    check(
+        "standard_zeros",
+        Path::new("tests/golden/standard_zeros.toml"),
+    );
+}
@@ -0,0 +1,227 @@
+use core::fmt::Write as _;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    diff::{self, Difference, Entry},
+    model::System,
+    output, state,
+    timestamp::Timestamp,
+};
+
+/// The file the history is kept in, inside the state folder
+const HISTORY_FILE: &str = "history.toml";
+
+/// A change to the system, with when it was first seen
+#[derive(Debug, Serialize, Deserialize)]
+struct Event {
+    /// When the change was recorded
+    at: String,
+    /// What changed
+    #[serde(flatten)]
+    change: Difference,
+}
+
+/// Every change to the ids of the system since jdexmd started keeping track, so each id's first
+/// appearance is known
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct History {
+    /// The entries as they were when the history was last recorded
+    #[serde(default)]
+    entries: BTreeMap<String, Entry>,
+    /// The changes, oldest first
+    #[serde(default)]
+    events: Vec<Event>,
+}
+
+impl History {
+    /// Add the changes between the entries last recorded and `system`, returning how many there
+    /// were
+    fn record(&mut self, system: &System, at: Timestamp) -> usize {
+        let current = diff::entries(system);
+        let changes = diff::compare(&self.entries, current.clone());
+        let count = changes.len();
+        self.events.extend(changes.into_iter().map(|change| Event {
+            at: at.to_string(),
+            change,
+        }));
+        self.entries = current;
+        count
+    }
+
+    /// The changes recorded on or after the day `since`, like `2025-01-01`
+    fn since<'history>(
+        &'history self,
+        since: &'history str,
+    ) -> impl Iterator<Item = &'history Event> {
+        // The timestamps start with the date, so they sort the same as the times they stand for
+        self.events
+            .iter()
+            .filter(move |event| event.at.as_str() >= since)
+    }
+}
+
+/// The file the history of the system defined in `config_file` is kept in
+fn path(config_file: &Path, state_folder: Option<&str>) -> Result<PathBuf, Error> {
+    Ok(state::folder(config_file, state_folder)?.join(HISTORY_FILE))
+}
+
+/// Read the history at `path`, which is empty until the first time it is recorded
+fn load(path: &Path) -> Result<History, Error> {
+    if !path.is_file() {
+        return Ok(History::default());
+    }
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Whether the system defined in `config_file` was ever generated, which leaves its history behind
+pub fn is_recorded(config_file: &Path, state_folder: Option<&str>) -> Result<bool, Error> {
+    Ok(path(config_file, state_folder)?.is_file())
+}
+
+/// Record how the ids of the system defined in `config_file` changed since the last time.
+///
+/// Ids that were already in the system the first time count as added then.
+pub fn record(
+    config_file: &Path,
+    state_folder: Option<&str>,
+    system: &System,
+    at: Timestamp,
+) -> Result<(), Error> {
+    let path = path(config_file, state_folder)?;
+    let mut history = load(&path)?;
+    if history.record(system, at) > 0 {
+        save(&path, &history)?;
+    }
+    Ok(())
+}
+
+/// Write the history to `path`
+fn save(path: &Path, history: &History) -> Result<(), Error> {
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder)?;
+    }
+    fs::write(path, toml::to_string(history)?)?;
+    Ok(())
+}
+
+/// Record that the directory at `from` was adopted as the id `id`, after the changes to the system
+/// that made room for it
+pub fn adopted(
+    config_file: &Path,
+    state_folder: Option<&str>,
+    system: &System,
+    id: &str,
+    topic: &str,
+    from: &Path,
+) -> Result<(), Error> {
+    let path = path(config_file, state_folder)?;
+    let mut history = load(&path)?;
+    let now = Timestamp::now();
+    history.record(system, now);
+    history.events.push(Event {
+        at: now.to_string(),
+        change: Difference::Adopted {
+            id: id.to_owned(),
+            topic: topic.to_owned(),
+            from: from.display().to_string(),
+        },
+    });
+    save(&path, &history)
+}
+
+/// Whether `date` is a day written like `2025-01-01`
+pub fn is_date(date: &str) -> bool {
+    let lengths: Vec<usize> = date.split('-').map(str::len).collect();
+    lengths == [4, 2, 2]
+        && date
+            .chars()
+            .all(|character| character.is_ascii_digit() || character == '-')
+}
+
+/// List the ids that were added, renamed, moved, retired, or removed since `since`, the start of
+/// the month when not given, recording the current definition first
+pub fn run(
+    config_file: &Path,
+    state_folder: Option<&str>,
+    system: &System,
+    since: Option<String>,
+) -> Result<(), Error> {
+    let now = Timestamp::now();
+    let since = since.unwrap_or_else(|| now.month_start());
+    if !is_date(&since) {
+        return Err(Error::msg(format!(
+            "{since} isn't a date, write it like 2025-01-01"
+        )));
+    }
+    record(config_file, state_folder, system, now)?;
+    let history = load(&path(config_file, state_folder)?)?;
+    let mut listed = format!("{}\n", output::heading(&format!("Changes since {since}")));
+    let mut changes = history.since(&since).peekable();
+    if changes.peek().is_none() {
+        listed.push_str("  none\n");
+    }
+    for event in changes {
+        let day = event.at.get(..since.len()).unwrap_or(&event.at);
+        let _ = write!(listed, "{day} {}", event.change.line());
+    }
+    write!(anstream::stdout(), "{listed}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_history() {
+        let mut history = History::default();
+        let first = Timestamp::from_seconds(1_704_067_200);
+        let later = Timestamp::from_seconds(1_709_210_096);
+        assert_eq!(
+            history.record(&system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n"), first),
+            3
+        );
+        assert_eq!(
+            history.record(&system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n"), later),
+            0
+        );
+        let edited =
+            system("20-29 Finance\n\t21 Taxes\n\t\t01 Returns [retired]\n\t\t02 Receipts\n");
+        assert_eq!(history.record(&edited, later), 3);
+        let read: History = toml::from_str(&toml::to_string(&history).unwrap()).unwrap();
+        let changes: Vec<&Difference> = read
+            .since("2024-02-01")
+            .map(|event| &event.change)
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                &Difference::Renamed {
+                    id: "L01.21".to_owned(),
+                    from: "Tax".to_owned(),
+                    to: "Taxes".to_owned(),
+                },
+                &Difference::Retired {
+                    id: "L01.21.01".to_owned(),
+                    topic: "Returns".to_owned(),
+                },
+                &Difference::Added {
+                    id: "L01.21.02".to_owned(),
+                    topic: "Receipts".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(read.since("2024-01-01").count(), 6);
+        assert!(is_date("2025-01-01"));
+        assert!(!is_date("January"));
+    }
+}
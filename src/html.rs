@@ -0,0 +1,108 @@
+use core::fmt::Write as _;
+
+use crate::model::{FullId, HasFolderKind, System};
+
+/// Escape the characters that have a meaning in HTML
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Strike through the rendered entry when its id is retired
+fn strike(rendered: String, retired: bool) -> String {
+    if retired {
+        format!("<del>{rendered}</del>")
+    } else {
+        rendered
+    }
+}
+
+/// Render a folder or extended folder as a list item, marking the ones that are notes
+fn folder_item<F: FullId + HasFolderKind>(folder: &F, retired: bool) -> String {
+    let class = folder.kind().tag();
+    strike(
+        format!("<span class=\"{class}\">{}</span>", escape(&folder.id())),
+        retired,
+    )
+}
+
+/// Render the whole system as the body of an HTML page
+pub fn render(system: &System) -> String {
+    let mut html = String::new();
+    let _ = writeln!(html, "<h1>{}</h1>", escape(&system.name));
+    for area in &system.areas {
+        let _ = writeln!(html, "<h2>{}</h2>", escape(&area.id()));
+        html.push_str("<ul>\n");
+        for category in &area.categories {
+            let _ = writeln!(
+                html,
+                "<li>{}",
+                strike(escape(&category.id()), category.retired)
+            );
+            if !category.folders.is_empty() {
+                html.push_str("<ul>\n");
+                for folder in &category.folders {
+                    let _ = writeln!(html, "<li>{}", folder_item(folder, folder.retired));
+                    if !folder.folders.is_empty() {
+                        html.push_str("<ul>\n");
+                        for xfolder in &folder.folders {
+                            let _ = writeln!(
+                                html,
+                                "<li>{}</li>",
+                                folder_item(xfolder, xfolder.retired)
+                            );
+                        }
+                        html.push_str("</ul>\n");
+                    }
+                    html.push_str("</li>\n");
+                }
+                html.push_str("</ul>\n");
+            }
+            html.push_str("</li>\n");
+        }
+        html.push_str("</ul>\n");
+    }
+    html
+}
+
+/// Wrap a rendered body into a complete page, with an optional script at the end
+pub fn page(title: &str, body: &str, script: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{}</title>
+<style>
+body {{ font-family: sans-serif; max-width: 50em; margin: 2em auto; }}
+.file, .both, .index, .full-index {{ text-decoration: underline; }}
+.index, .full-index {{ font-weight: bold; }}
+</style>
+</head>
+<body>
+{body}{script}</body>
+</html>
+",
+        escape(title)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("Films & <Movies>"), "Films &amp; &lt;Movies&gt;");
+    }
+}
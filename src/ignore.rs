@@ -0,0 +1,238 @@
+use std::path::{Component, Path, PathBuf};
+
+use color_eyre::eyre::Error;
+
+use crate::backend::OutputBackend;
+
+/// The name of the file listing the paths jdexmd must leave alone
+pub const IGNORE_FILE: &str = ".jdexignore";
+
+/// A single line of an ignore file
+#[derive(Debug, PartialEq, Eq)]
+struct Pattern {
+    /// The glob, without the `!`, leading `/`, or trailing `/`
+    glob: Vec<char>,
+    /// Whether the line starts with `!`, bringing back a path an earlier line ignored
+    negated: bool,
+    /// Whether the line ends with `/`, so it only matches directories
+    dir_only: bool,
+    /// Whether the glob has a `/` in it, so it matches from the root instead of any name
+    anchored: bool,
+}
+
+impl Pattern {
+    /// Parse a line of an ignore file, skipping blank lines and comments
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = line
+            .strip_prefix('!')
+            .map_or((false, line), |rest| (true, rest));
+        let (dir_only, line) = line
+            .strip_suffix('/')
+            .map_or((false, line), |rest| (true, rest));
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        Some(Self {
+            glob: line.chars().collect(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the pattern matches the path, given relative to the folder of the ignore file
+    fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let text: Vec<char> = if self.anchored {
+            relative.chars().collect()
+        } else {
+            relative
+                .rsplit('/')
+                .next()
+                .unwrap_or(relative)
+                .chars()
+                .collect()
+        };
+        glob_match(&self.glob, &text)
+    }
+}
+
+/// Match a `[...]` character class at the start of `pattern`, returning what is left after it
+fn class_match(pattern: &[char], character: char) -> Option<(bool, &[char])> {
+    let close = pattern.iter().skip(2).position(|&next| next == ']')?;
+    let end = close.saturating_add(2);
+    let class = pattern.get(1..end)?;
+    let (negated, class) = match class.split_first() {
+        Some(('!' | '^', rest)) => (true, rest),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut index = 0;
+    while let Some(&start) = class.get(index) {
+        if let (Some('-'), Some(&last)) = (
+            class.get(index.saturating_add(1)),
+            class.get(index.saturating_add(2)),
+        ) {
+            found |= (start..=last).contains(&character);
+            index = index.saturating_add(3);
+        } else {
+            found |= start == character;
+            index = index.saturating_add(1);
+        }
+    }
+    Some((
+        found != negated,
+        pattern.get(end.saturating_add(1)..).unwrap_or_default(),
+    ))
+}
+
+/// Match gitignore style globs, where `*` and `?` stay within a name and `**` crosses directories
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) if rest.first() == Some(&'*') => {
+            let rest = rest.get(1..).unwrap_or_default();
+            // `**/` also matches no directories at all
+            if let Some(after) = rest.strip_prefix(&['/']) {
+                if glob_match(after, text) {
+                    return true;
+                }
+            }
+            (0..=text.len()).any(|skip| glob_match(rest, text.get(skip..).unwrap_or_default()))
+        }
+        Some(('*', rest)) => {
+            let name_length = text
+                .iter()
+                .position(|&next| next == '/')
+                .unwrap_or(text.len());
+            (0..=name_length).any(|skip| glob_match(rest, text.get(skip..).unwrap_or_default()))
+        }
+        Some(('?', rest)) => text
+            .split_first()
+            .is_some_and(|(&next, text)| next != '/' && glob_match(rest, text)),
+        Some(('[', _)) => match (
+            text.split_first(),
+            class_match(pattern, text.first().copied().unwrap_or('/')),
+        ) {
+            (Some((&next, text)), Some((matched, rest))) => {
+                next != '/' && matched && glob_match(rest, text)
+            }
+            (Some((&next, text)), None) => {
+                next == '[' && glob_match(pattern.get(1..).unwrap_or_default(), text)
+            }
+            (None, _) => false,
+        },
+        Some(('\\', rest)) if !rest.is_empty() => text.split_first().is_some_and(|(next, text)| {
+            rest.first() == Some(next) && glob_match(rest.get(1..).unwrap_or_default(), text)
+        }),
+        Some((expected, rest)) => text
+            .split_first()
+            .is_some_and(|(next, text)| next == expected && glob_match(rest, text)),
+    }
+}
+
+/// The paths that must never be created, changed, or reported, read from a `.jdexignore`
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    /// The folder the ignore file is in, which the patterns are relative to
+    root: PathBuf,
+    /// The patterns in the order they were written, so later lines win
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    /// Parse the contents of an ignore file in the folder `root`
+    pub fn parse(root: &Path, contents: &str) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            patterns: contents.lines().filter_map(Pattern::parse).collect(),
+        }
+    }
+
+    /// Read the ignore file in `root`, when there is one
+    pub fn load(backend: &dyn OutputBackend, root: &Path) -> Result<Self, Error> {
+        let file = root.join(IGNORE_FILE);
+        if !backend.exists(&file) {
+            return Ok(Self::parse(root, ""));
+        }
+        Ok(Self::parse(root, &backend.read_to_string(&file)?))
+    }
+
+    /// Whether a single path is ignored, not counting the directories it is in
+    fn is_ignored_here(&self, relative: &str, is_dir: bool) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(relative, is_dir))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+
+    /// Whether `path` is ignored, either itself or because a directory it is in is ignored
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let names: Vec<String> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        (1..=names.len()).any(|depth| {
+            let prefix = names.get(..depth).unwrap_or_default().join("/");
+            self.is_ignored_here(&prefix, depth < names.len() || is_dir)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(contents: &str) -> IgnoreRules {
+        IgnoreRules::parse(Path::new("/vault"), contents)
+    }
+
+    #[test]
+    fn test_glob_match() {
+        let glob = |pattern: &str, text: &str| {
+            glob_match(
+                &pattern.chars().collect::<Vec<_>>(),
+                &text.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert!(glob("*.md", "Inbox.md"));
+        assert!(!glob("*.md", "N01/Inbox.md"));
+        assert!(glob("N01/**/Inbox", "N01/a/b/Inbox"));
+        assert!(glob("N01/**/Inbox", "N01/Inbox"));
+        assert!(glob("N01.1[0-4] *", "N01.12 Work"));
+        assert!(!glob("N01.1[!0-4] *", "N01.12 Work"));
+        assert!(glob("Inbox?", "Inbox2"));
+    }
+
+    #[test]
+    fn test_ignored_names_anywhere() {
+        let rules = rules("# comment\n*.md\n!N01.00.00 JDex.md\nPrivate/\n");
+        assert!(rules.is_ignored(Path::new("/vault/N01/N01.00.01 Inbox.md"), false));
+        assert!(!rules.is_ignored(Path::new("/vault/N01/N01.00.00 JDex.md"), false));
+        assert!(rules.is_ignored(Path::new("/vault/N01/Private/notes"), true));
+        assert!(!rules.is_ignored(Path::new("/vault/N01/Private"), false));
+        assert!(!rules.is_ignored(Path::new("/elsewhere/Inbox.md"), false));
+    }
+
+    #[test]
+    fn test_anchored_patterns() {
+        let rules = rules("/N01/N01.10-19 Work\n");
+        assert!(rules.is_ignored(Path::new("/vault/N01/N01.10-19 Work/N01.11 Clients"), true));
+        assert!(!rules.is_ignored(Path::new("/vault/N02/N01/N01.10-19 Work"), true));
+    }
+}
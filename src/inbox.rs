@@ -0,0 +1,215 @@
+use core::{cmp::Reverse, fmt::Write as _};
+use std::{
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use color_eyre::eyre::Error;
+
+use crate::{
+    backend::{LocalFs, OutputBackend},
+    config,
+    ignore::IgnoreRules,
+    model::{HasJohnnyId, System},
+    notes::{self, Action},
+    output,
+};
+
+/// How alike two words must be to count as the same, so plurals and typos still match
+const SIMILARITY_THRESHOLD: f64 = 0.85;
+
+/// How many places to file something are suggested
+const SUGGESTIONS: usize = 3;
+
+/// What can be done with the inbox
+#[derive(Debug, Subcommand)]
+pub enum InboxCommand {
+    /// List what is waiting in the inbox, with where each thing might be filed
+    List,
+}
+
+/// The inbox named `name` at the root of the system under `base_folder`
+pub fn path(base_folder: &str, system: &System, name: &str) -> Result<PathBuf, Error> {
+    Ok(path_in(&notes::expand(base_folder)?, system, name))
+}
+
+/// The inbox named `name` at the root of the system under the already expanded `base_path`
+fn path_in(base_path: &Path, system: &System, name: &str) -> PathBuf {
+    base_path.join(system.id.as_path()).join(name)
+}
+
+/// The action creating the inbox named `name` under `base_path`
+pub fn action<'sys>(base_path: &Path, system: &System, name: &str) -> Action<'sys> {
+    Action::CreateDirectory(path_in(base_path, system, name))
+}
+
+/// The words of a name or topic that are worth matching, in lowercase
+fn words(text: &str) -> Vec<String> {
+    text.split(|character: char| !character.is_alphanumeric())
+        .filter(|word| {
+            word.chars().count() > 2 && !word.chars().all(|character| character.is_ascii_digit())
+        })
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The entries whose topics share the most words with `name`, best first
+fn suggest<'sys>(system: &'sys System, name: &str) -> Vec<&'sys dyn HasJohnnyId> {
+    let wanted = words(name);
+    let mut entries: Vec<&dyn HasJohnnyId> = Vec::new();
+    for category in system
+        .areas
+        .iter()
+        .flat_map(|area| &area.categories)
+        .filter(|category| !category.retired)
+    {
+        entries.push(category);
+        for folder in category.folders.iter().filter(|folder| !folder.retired) {
+            entries.push(folder);
+            for xfolder in folder.folders.iter().filter(|xfolder| !xfolder.retired) {
+                entries.push(xfolder);
+            }
+        }
+    }
+    let mut scored: Vec<(usize, &dyn HasJohnnyId)> = entries
+        .into_iter()
+        .map(|entry| {
+            let topic = words(entry.name());
+            let score = wanted
+                .iter()
+                .filter(|word| {
+                    topic.iter().any(|other| {
+                        strsim::normalized_levenshtein(word, other) >= SIMILARITY_THRESHOLD
+                    })
+                })
+                .count();
+            (score, entry)
+        })
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    // The sort is stable, so equally good matches stay in the order of the system
+    scored.sort_by_key(|scored| Reverse(scored.0));
+    scored
+        .into_iter()
+        .take(SUGGESTIONS)
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+/// Describe what is in the inbox at `inbox`, with where each thing might be filed
+fn describe(
+    backend: &dyn OutputBackend,
+    system: &System,
+    title: &str,
+    inbox: &Path,
+) -> Result<String, Error> {
+    let mut described = format!("{} {}\n", output::heading(title), output::path(inbox));
+    if !backend.is_dir(inbox) {
+        let _ = writeln!(described, "  The inbox hasn't been generated yet");
+        return Ok(described);
+    }
+    let ignore = IgnoreRules::load(backend, inbox)?;
+    let mut items = backend.read_dir(inbox)?;
+    items.sort();
+    items.retain(|item| {
+        let hidden = item
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        !hidden && !ignore.is_ignored(item, backend.is_dir(item))
+    });
+    if items.is_empty() {
+        let _ = writeln!(described, "  Nothing to file");
+    }
+    for item in &items {
+        let name = item
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let _ = writeln!(
+            described,
+            "  {}",
+            item.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        );
+        let suggestions = suggest(system, &name);
+        if suggestions.is_empty() {
+            let _ = writeln!(described, "    No suggestions");
+        }
+        for entry in suggestions {
+            let _ = writeln!(
+                described,
+                "    → {} {}",
+                output::id(&entry.jid().by_seperator(".")),
+                entry.name()
+            );
+        }
+    }
+    Ok(described)
+}
+
+/// Run an inbox command
+pub fn run(
+    output_config: &config::Output,
+    system: &System,
+    command: &InboxCommand,
+) -> Result<(), Error> {
+    let Some(name) = output_config.inbox.as_deref() else {
+        return Err(Error::msg(
+            "There is no inbox, set `inbox` in the config file to generate one",
+        ));
+    };
+    match command {
+        InboxCommand::List => {
+            let notes = describe(
+                &LocalFs,
+                system,
+                "Notes inbox",
+                &path(&output_config.base_folder, system, name)?,
+            )?;
+            let archive = describe(
+                &LocalFs,
+                system,
+                "Reference archive inbox",
+                &path(&output_config.reference_folder, system, name)?,
+            )?;
+            write!(anstream::stdout(), "{notes}\n{archive}")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::MemoryFs, test_support::system};
+
+    #[test]
+    fn test_inbox_suggestions() {
+        let system = system(
+            "20-29 Finance\n\t21 Tax\n\t\t05 Tax Returns\n\t22 Insurance\n\t\t01 Car Insurance\n\t\t02 Old Car [retired]\n",
+        );
+        let inbox = path("/notes", &system, "@Inbox").unwrap();
+        let mut memory = MemoryFs::default();
+        memory.create_dir_all(&inbox).unwrap();
+        memory
+            .write(&inbox.join("2024 tax return.pdf"), "")
+            .unwrap();
+        memory.write(&inbox.join("car photos.jpg"), "").unwrap();
+        memory.write(&inbox.join("holiday.jpg"), "").unwrap();
+        let described = describe(&memory, &system, "Inbox", &inbox).unwrap();
+        assert_eq!(
+            anstream::adapter::strip_str(&described).to_string(),
+            "Inbox /notes/L01/@Inbox
+  2024 tax return.pdf
+    → L01.21.05 Tax Returns
+    → L01.21 Tax
+  car photos.jpg
+    → L01.22.01 Car Insurance
+  holiday.jpg
+    No suggestions
+"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    fs,
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::{Error, OptionExt};
+use toml_edit::{value, DocumentMut, Item};
+
+use crate::{
+    config::{JohnnyDecimal, SystemParameters},
+    model::System,
+    naming::NamingConfig,
+    output::{self, Mark},
+    prompt::Prompt,
+    validate::{self, LimitsConfig},
+};
+
+/// The definition a config file starts with when it isn't built with the wizard
+const STARTER: &str = "00-09 System\n\t00 Meta\n\t\t01 Inbox\n";
+
+/// A complete system to start from, shipped with jdexmd
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Starter {
+    /// Life admin, work, and interests of one person
+    Personal,
+    /// Administration, finance, people, sales, and operations of a small company
+    SmallBusiness,
+    /// Courses, campus life, money, and plans of a student
+    Student,
+}
+
+impl Starter {
+    /// The id and name the system starts out with
+    const fn system(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Personal => ("P01", "Personal"),
+            Self::SmallBusiness => ("B01", "Business"),
+            Self::Student => ("S01", "Student"),
+        }
+    }
+
+    /// The definition of the system
+    const fn definition(self) -> &'static str {
+        match self {
+            Self::Personal => include_str!("../starters/personal.jdex"),
+            Self::SmallBusiness => include_str!("../starters/small-business.jdex"),
+            Self::Student => include_str!("../starters/student.jdex"),
+        }
+    }
+}
+
+/// What the wizard asks for at each level of the definition, with an example answer
+const LEVELS: [(&str, &str); 3] = [
+    ("Area", "10-19 Finance"),
+    ("Category", "11 Taxes"),
+    ("Folder", "01 Returns, or 01 -Returns for a single note"),
+];
+
+/// The system the wizard is building, as far as it has got
+struct Draft {
+    /// The id of the system, like `L01`
+    system_id: String,
+    /// The name of the system
+    name: String,
+    /// The definition so far
+    definition: String,
+}
+
+impl Draft {
+    /// Check that `line` can be added to the definition, failing with why it can't.
+    ///
+    /// Even the mistakes that `validate` only warns about are turned away, so the new system
+    /// starts out clean.
+    fn check(&self, line: &str) -> Result<(), Error> {
+        let system = System::try_from(SystemParameters {
+            system_id: self.system_id.clone(),
+            name: self.name.clone(),
+            config: format!("{}{line}", self.definition),
+            ..SystemParameters::default()
+        })?;
+        let definition = format!("{}{line}", self.definition);
+        validate::validate(
+            &system,
+            &definition,
+            &NamingConfig::default(),
+            LimitsConfig::default(),
+        )
+        .first()
+        .map_or_else(
+            || Ok(()),
+            |finding| Err(Error::msg(finding.message.clone())),
+        )
+    }
+}
+
+/// Ask `question` until it gets an answer that isn't empty
+fn require(prompt: &mut Prompt<impl BufRead, impl Write>, question: &str) -> Result<String, Error> {
+    loop {
+        let answer = prompt
+            .ask(question, None)?
+            .ok_or_eyre("The input ended before the wizard was finished")?;
+        if !answer.is_empty() {
+            return Ok(answer);
+        }
+    }
+}
+
+/// Ask for the entries at `depth` under `parent`, and everything under each of them, until an
+/// empty answer
+fn entries(
+    prompt: &mut Prompt<impl BufRead, impl Write>,
+    draft: &mut Draft,
+    depth: usize,
+    parent: &str,
+) -> Result<(), Error> {
+    let Some((level, example)) = LEVELS.get(depth) else {
+        return Ok(());
+    };
+    loop {
+        let question = format!("{level} in {parent}, like `{example}` (empty when done):");
+        let Some(answer) = prompt.ask(&question, None)? else {
+            return Ok(());
+        };
+        if answer.is_empty() {
+            return Ok(());
+        }
+        let line = format!("{}{answer}\n", "\t".repeat(depth));
+        if let Err(err) = draft.check(&line) {
+            writeln!(prompt.output(), "  {err}, try again")?;
+            continue;
+        }
+        draft.definition.push_str(&line);
+        let output = prompt.output();
+        writeln!(output, "\n{}", output::heading("Definition so far"))?;
+        write!(output, "{}", draft.definition)?;
+        writeln!(output)?;
+        entries(prompt, draft, depth.saturating_add(1), &answer)?;
+    }
+}
+
+/// The config file for a system
+fn document(
+    system_id: &str,
+    name: &str,
+    base_folder: &str,
+    reference_folder: &str,
+    definition: &str,
+) -> Result<String, Error> {
+    let mut document = DocumentMut::new();
+    document["system_id"] = value(system_id);
+    document["name"] = value(name);
+    document["base_folder"] = value(base_folder);
+    document["reference_folder"] = value(reference_folder);
+    document["config"] = Item::Value(JohnnyDecimal::definition_value(definition)?);
+    Ok(document.to_string())
+}
+
+/// Build the config file of a system from the answers to `prompt`, showing the definition after
+/// every entry
+fn wizard(prompt: &mut Prompt<impl BufRead, impl Write>) -> Result<String, Error> {
+    let system_id = loop {
+        let answer = require(prompt, "System id, like `L01`:")?;
+        if answer
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric())
+        {
+            break answer;
+        }
+        writeln!(
+            prompt.output(),
+            "  The system id can only have letters and digits, try again"
+        )?;
+    };
+    let name = require(prompt, "Name of the system:")?;
+    let base_folder = require(prompt, "Folder of your notes, like `~/Notes`:")?;
+    let reference_folder = require(prompt, "Folder for everything else, like `~/Documents`:")?;
+    let mut draft = Draft {
+        system_id,
+        name: name.clone(),
+        definition: String::new(),
+    };
+    entries(prompt, &mut draft, 0, &name)?;
+    if draft.definition.is_empty() {
+        return Err(Error::msg("The system needs at least one area"));
+    }
+    document(
+        &draft.system_id,
+        &draft.name,
+        &base_folder,
+        &reference_folder,
+        &draft.definition,
+    )
+}
+
+/// Write a new config file at `config_file`, built from the answers to `prompt` with `wizard`,
+/// from one of the shipped systems with `from_template`, and otherwise from a small starter system
+/// to edit
+pub fn run(
+    config_file: &Path,
+    wizard: bool,
+    from_template: Option<Starter>,
+    mut prompt: Prompt<impl BufRead, impl Write>,
+) -> Result<(), Error> {
+    if config_file.exists() {
+        return Err(Error::msg(format!(
+            "{} already exists, pick another path or remove it first",
+            config_file.display()
+        )));
+    }
+    let contents = if wizard {
+        self::wizard(&mut prompt)?
+    } else if let Some(starter) = from_template {
+        let (system_id, name) = starter.system();
+        document(
+            system_id,
+            name,
+            "~/Notes",
+            "~/Documents/Archive",
+            starter.definition(),
+        )?
+    } else {
+        document("N01", "Notes", "~/Notes", "~/Documents/Archive", STARTER)?
+    };
+    fs::write(config_file, contents)?;
+    write!(
+        prompt.output(),
+        "{}",
+        output::line(Mark::Created, "Wrote", &output::path(config_file))
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wizard() {
+        let answers = "L01\nLife\n~/Notes\n~/Documents\n10-19 Home\n11 House\n01 Repairs\n\n\n20-29 Money\n31 Tax\n21 Tax\n\n\n";
+        let mut printed = Vec::new();
+        let contents = wizard(&mut Prompt::new(answers.as_bytes(), &mut printed)).unwrap();
+        let config = JohnnyDecimal::from_toml(&contents).unwrap();
+        assert_eq!(config.system_config.system_id, "L01");
+        assert_eq!(
+            config.system_config.config,
+            "10-19 Home\n\t11 House\n\t\t01 Repairs\n20-29 Money\n\t21 Tax\n"
+        );
+        let printed = String::from_utf8(printed).unwrap();
+        assert!(printed.contains("try again"), "{printed}");
+        assert!(wizard(&mut Prompt::new(&b"L01\nLife\n"[..], Vec::new())).is_err());
+        // Nothing can be asked without a terminal
+        let mut scripted = Prompt::new(answers.as_bytes(), Vec::new()).answering(false, true);
+        assert!(wizard(&mut scripted).is_err());
+    }
+
+    #[test]
+    fn test_starters() {
+        for starter in Starter::value_variants() {
+            let (system_id, name) = starter.system();
+            let system = System::try_from(SystemParameters {
+                system_id: system_id.to_owned(),
+                name: name.to_owned(),
+                config: starter.definition().to_owned(),
+                ..SystemParameters::default()
+            })
+            .unwrap();
+            let findings = validate::validate(
+                &system,
+                starter.definition(),
+                &NamingConfig::default(),
+                LimitsConfig::default(),
+            );
+            assert!(findings.is_empty(), "{starter:?}: {findings:?}");
+        }
+    }
+}
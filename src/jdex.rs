@@ -0,0 +1,307 @@
+use core::fmt::Write as _;
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    io::Write as _,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use color_eyre::eyre::{Error, OptionExt};
+
+use crate::{
+    config::{JohnnyDecimal, SystemParameters},
+    jid::JohnnyId,
+    line::format_system,
+    model::{FolderKind, FullId, System},
+    notes::MANAGED_END,
+    output::{self, Mark},
+};
+
+/// A single entry read from the jdex, before it is turned back into a configuration line
+#[derive(Debug, PartialEq, Eq)]
+struct IndexEntry<'line> {
+    /// The system id the entry was prefixed with, when it wasn't written as a bare id like `21.05`
+    system_id: Option<&'line str>,
+    /// The id parts after the system id
+    parts: Vec<&'line str>,
+    /// The topic of the entry
+    topic: &'line str,
+    /// Whether the entry was rendered as a wikilink
+    linked: bool,
+    /// Whether the entry was struck through because its id is retired
+    retired: bool,
+}
+
+/// Whether `part` is the number or range at the start of a bare id, like `21` or `20-29`
+fn is_bare(part: &str) -> bool {
+    let two_digits = |number: &str| {
+        number.len() == 2 && number.chars().all(|character| character.is_ascii_digit())
+    };
+    part.split_once('-').map_or_else(
+        || two_digits(part),
+        |(start, end)| two_digits(start) && two_digits(end),
+    )
+}
+
+/// Parse a single rendered entry line like `  - [[N01.00.01 System Inbox]]`, or with a bare id like
+/// `  - [[00.01 System Inbox]]`
+fn parse_entry(line: &str) -> Option<IndexEntry<'_>> {
+    let bare = line.trim_start_matches(|character: char| {
+        character == '#' || character == '-' || character == '*' || character.is_whitespace()
+    });
+    let (retired, bare) = bare
+        .strip_prefix("~~")
+        .and_then(|struck| struck.trim_end().strip_suffix("~~"))
+        .map_or((false, bare), |struck| (true, struck));
+    let (linked, bare) = bare.strip_prefix("[[").map_or((false, bare), |link| {
+        let target = link.trim_end().trim_end_matches("]]");
+        (true, target.split('|').next().unwrap_or(target))
+    });
+    let (id, topic) = bare.trim().split_once(' ')?;
+    // A link entry is followed by the entry it points at
+    let topic = topic.split_once(" → ").map_or(topic, |(topic, _)| topic);
+    let mut parts = id.split('.').peekable();
+    let system_id = if is_bare(parts.peek()?) {
+        None
+    } else {
+        parts.next()
+    };
+    Some(IndexEntry {
+        system_id,
+        parts: parts.collect(),
+        topic: topic.trim(),
+        linked,
+        retired,
+    })
+}
+
+/// Read a jdex rendered with the default templates back into the parameters that define the system.
+///
+/// `index_name` is the name of the jdex note itself, which lets us recover the `!!` directive. Bare
+/// ids like `21.05` belong to the `configured` system id.
+pub fn read_index(
+    contents: &str,
+    index_name: &str,
+    configured: &str,
+) -> Result<SystemParameters, Error> {
+    let mut name = None;
+    let mut system_id = None;
+    let mut config = String::new();
+    // What is below the end of the managed region was written by hand
+    let managed = contents
+        .split_once(MANAGED_END)
+        .map_or(contents, |(managed, _)| managed);
+    for line in managed.lines() {
+        let trimmed = line.trim();
+        if let Some(title) = trimmed.strip_prefix("# ") {
+            name.get_or_insert_with(|| title.trim().to_owned());
+            continue;
+        }
+        let Some(entry) = parse_entry(trimmed) else {
+            continue;
+        };
+        let bare = format!("{} {}", entry.parts.join("."), entry.topic);
+        let directive = if !entry.linked {
+            ""
+        } else if index_name == bare
+            || index_name
+                .strip_suffix(&bare)
+                .and_then(|prefix| prefix.strip_suffix('.'))
+                .is_some_and(|prefix| Some(prefix) == entry.system_id)
+        {
+            "!!"
+        } else {
+            "-"
+        };
+        let topic = entry.topic;
+        let attributes = if entry.retired { " [retired]" } else { "" };
+        let _ = match entry.parts.as_slice() {
+            [range] if range.contains('-') => writeln!(config, "{range} {topic}"),
+            [category] => writeln!(config, "\t{category} {topic}{attributes}"),
+            [_, folder] => writeln!(config, "\t\t{folder} {directive}{topic}{attributes}"),
+            [_, _, xfolder] => {
+                writeln!(config, "\t\t\t{xfolder} {directive}{topic}{attributes}")
+            }
+            _ => continue,
+        };
+        system_id.get_or_insert_with(|| entry.system_id.unwrap_or(configured).to_owned());
+    }
+
+    Ok(SystemParameters {
+        system_id: system_id.ok_or_eyre("No Johnny Decimal ids found in the index")?,
+        name: name.unwrap_or_default(),
+        config,
+        ..SystemParameters::default()
+    })
+}
+
+/// Read the jdex at `path` back into the parameters that define the system, with bare ids in the
+/// `configured` system
+pub fn read_index_file(path: &Path, configured: &str) -> Result<SystemParameters, Error> {
+    let contents = read_to_string(path)?;
+    let index_name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    read_index(&contents, &index_name, configured)
+}
+
+/// A jdex only knows whether an entry was linked, so restore the `+` and link entries from the
+/// current system
+pub fn restore_kinds(read: &mut System, current: &System) {
+    let mut kinds = HashMap::new();
+    for folder in current
+        .areas
+        .iter()
+        .flat_map(|area| &area.categories)
+        .flat_map(|category| &category.folders)
+    {
+        kinds.insert(folder.id(), &folder.kind);
+    }
+    for folder in read
+        .areas
+        .iter_mut()
+        .flat_map(|area| &mut area.categories)
+        .flat_map(|category| &mut category.folders)
+    {
+        let restored = match (&folder.kind, kinds.get(&folder.id())) {
+            (FolderKind::File, Some(FolderKind::Both)) => Some(FolderKind::Both),
+            (FolderKind::Folder, Some(kind @ FolderKind::Link(_))) => Some((*kind).clone()),
+            _ => None,
+        };
+        if let Some(kind) = restored {
+            folder.kind = kind;
+        }
+    }
+}
+
+/// A jdex doesn't show where things live, so restore the locations from the current system by id
+pub fn restore_locations(read: &mut System, current: &System) {
+    let mut locations = HashMap::new();
+    for category in current.areas.iter().flat_map(|area| &area.categories) {
+        locations.insert(category.id.by_seperator("."), &category.locations);
+        for folder in &category.folders {
+            locations.insert(folder.id.by_seperator("."), &folder.locations);
+            for xfolder in &folder.folders {
+                locations.insert(xfolder.id.by_seperator("."), &xfolder.locations);
+            }
+        }
+    }
+    let restore = |id: &JohnnyId, found: &mut Vec<Arc<str>>| {
+        if let Some(current) = locations.get(&id.by_seperator(".")) {
+            found.clone_from(current);
+        }
+    };
+    for category in read.areas.iter_mut().flat_map(|area| &mut area.categories) {
+        restore(&category.id, &mut category.locations);
+        for folder in &mut category.folders {
+            restore(&folder.id, &mut folder.locations);
+            for xfolder in &mut folder.folders {
+                restore(&xfolder.id, &mut xfolder.locations);
+            }
+        }
+    }
+}
+
+/// Read the jdex at `index` and either print the matching configuration or write it into the config file
+pub fn run(
+    config_file: &PathBuf,
+    current: &System,
+    index: &Path,
+    write: bool,
+) -> Result<(), Error> {
+    let parameters = read_index_file(index, &current.id.by_seperator("."))?;
+    let mut read = System::try_from(parameters)?;
+    restore_kinds(&mut read, current);
+    restore_locations(&mut read, current);
+    // The config file the definition goes back into gives folders its own kind by default
+    read.default_folder_kind = current.default_folder_kind.clone();
+    read.xfolder_kind = current.xfolder_kind;
+    let config = format_system(&read);
+    if write {
+        JohnnyDecimal::update_definition(config_file, &config)?;
+        write!(
+            anstream::stdout(),
+            "{}",
+            output::line(Mark::Applied, "Updated", &output::path(config_file))
+        )?;
+    } else {
+        print!("{config}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDEX: &str = "# Demo System
+## N01.00-09 System
+- N01.00 Meta
+  - [[N01.00.00 JDex]]
+  - [[N01.00.01 System Inbox]]
+  - N01.00.02 WIP
+  - ~~N01.00.03 Old Inbox~~
+## N01.10-19 Technology
+- N01.10 Software Engineering
+  - N01.10.10 Snippets
+    - N01.10.10.X01 Rust
+";
+
+    #[test]
+    fn test_parse_entry() {
+        let entry = parse_entry("  - [[N01.00.01 System Inbox]]");
+        assert_eq!(
+            entry,
+            Some(IndexEntry {
+                system_id: Some("N01"),
+                parts: vec!["00", "01"],
+                topic: "System Inbox",
+                linked: true,
+                retired: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_index() {
+        let parameters = read_index(INDEX, "N01.00.00 JDex", "N01").unwrap();
+        assert_eq!(parameters.system_id, "N01");
+        assert_eq!(parameters.name, "Demo System");
+        assert_eq!(
+            parameters.config,
+            "00-09 System\n\t00 Meta\n\t\t00 !!JDex\n\t\t01 -System Inbox\n\t\t02 WIP\n\t\t03 Old Inbox [retired]\n10-19 Technology\n\t10 Software Engineering\n\t\t10 Snippets\n\t\t\tX01 Rust\n"
+        );
+        let with_notes = format!("{INDEX}{MANAGED_END}\n- N01.90 Someday\n");
+        assert_eq!(
+            read_index(&with_notes, "N01.00.00 JDex", "N01")
+                .unwrap()
+                .config,
+            parameters.config
+        );
+    }
+
+    #[test]
+    fn test_read_bare_ids() {
+        let bare = INDEX.replace("N01.", "");
+        let parameters = read_index(&bare, "00.00 JDex", "N01").unwrap();
+        assert_eq!(parameters.system_id, "N01");
+        assert_eq!(
+            parameters.config,
+            read_index(INDEX, "N01.00.00 JDex", "N01").unwrap().config
+        );
+        assert_eq!(
+            parse_entry("  - 21.05 Returns").map(|entry| (entry.system_id, entry.parts)),
+            Some((None, vec!["21", "05"]))
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let parameters = read_index(INDEX, "N01.00.00 JDex", "N01").unwrap();
+        let config = parameters.config.clone();
+        let system = System::try_from(parameters).unwrap();
+        assert_eq!(format_system(&system), config);
+    }
+}
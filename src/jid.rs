@@ -1,10 +1,15 @@
 use core::fmt::Formatter;
 use core::fmt::Write as _;
 use core::fmt::{Debug, Display, Result as FmtResult};
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
 
 use serde::{Deserialize, Serialize};
 
+use crate::layout::Layout;
+
 /// Internal Macro to either append a part of the id or return everything
 /// that has been written so far
 macro_rules! append_or_return {
@@ -21,24 +26,30 @@ macro_rules! append_or_return {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BoundRangeId {
     /// The topic of the range
-    pub topic: String,
+    pub topic: Arc<str>,
     /// The start of the range
     pub start: u8,
     /// The end of the range
     pub end: u8,
+    /// The name of the directory of the area, when a template names it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<Arc<str>>,
 }
 
 /// Binds a u8 id to a topic
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BoundU8Id {
     /// The topic of the id
-    pub topic: String,
+    pub topic: Arc<str>,
     /// The id
     pub id: u8,
+    /// The name of the directory of a category, when a template names it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<Arc<str>>,
 }
 
 impl Display for BoundU8Id {
-    #[expect(
+    #[allow(
         clippy::min_ident_chars,
         reason = "This is the preferred default name for the variable"
     )]
@@ -51,13 +62,13 @@ impl Display for BoundU8Id {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BoundStrId {
     /// The topic of the id
-    pub topic: String,
+    pub topic: Arc<str>,
     /// The id
-    pub id: String,
+    pub id: Arc<str>,
 }
 
 impl Display for BoundStrId {
-    #[expect(
+    #[allow(
         clippy::min_ident_chars,
         reason = "This is the preferred default name for the variable"
     )]
@@ -85,10 +96,12 @@ enum Level {
 }
 
 /// Represents a Johnny Decimal id
+///
+/// The strings are shared, so cloning an id to build the id of a child is cheap.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct JohnnyId {
     /// The system id `L##`
-    pub system: Option<String>,
+    pub system: Option<Arc<str>>,
     /// The area id `L##.##-##`
     pub area: Option<BoundRangeId>,
     /// The category id `L##.##` (notice that we don't include the area id here)
@@ -97,6 +110,24 @@ pub struct JohnnyId {
     pub folder: Option<BoundU8Id>,
     /// The extended folder id `L##.##.##.X##`
     pub xfolder: Option<BoundStrId>,
+    /// Whether the system id is left out when the id is written, like `21.05` instead of
+    /// `L01.21.05`. It is still kept, for telling the ids of different systems apart
+    #[serde(default)]
+    pub hide_system: bool,
+    /// How the directories of the system are nested, which decides the path of the id
+    #[serde(default)]
+    pub layout: Layout,
+    /// The name of the directory of the system when it is a workspace of its own, named after the
+    /// system like `L01 Personal` instead of only its id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<Arc<str>>,
+    /// The name of the directory of the system, when a template names it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<Arc<str>>,
+    /// The path of the id, worked out the first time it is asked for. Everything that changes
+    /// the id starts it over
+    #[serde(skip)]
+    path: OnceLock<PathBuf>,
 }
 
 impl JohnnyId {
@@ -121,60 +152,139 @@ impl JohnnyId {
     }
 
     /// Set the system id
-    pub fn system_id(self, system_id: &str) -> Self {
+    pub fn system_id(self, system_id: impl Into<Arc<str>>) -> Self {
+        Self {
+            system: Some(system_id.into()),
+            path: OnceLock::new(),
+            ..self
+        }
+    }
+
+    /// Set whether the system id is left out when the id is written
+    pub fn hide_system(self, hide_system: bool) -> Self {
+        Self {
+            hide_system,
+            path: OnceLock::new(),
+            ..self
+        }
+    }
+
+    /// Set how the directories of the system are nested
+    pub fn layout(self, layout: Layout) -> Self {
+        Self {
+            layout,
+            path: OnceLock::new(),
+            ..self
+        }
+    }
+
+    /// Set the name of the directory of the system, when it is a workspace of its own
+    pub fn root(self, root: Option<Arc<str>>) -> Self {
+        Self {
+            root,
+            path: OnceLock::new(),
+            ..self
+        }
+    }
+
+    /// Set the name of the directory of the system, instead of naming it after the system id
+    pub fn directory(self, directory: Option<Arc<str>>) -> Self {
         Self {
-            system: Some(system_id.to_owned()),
+            directory,
+            path: OnceLock::new(),
             ..self
         }
     }
 
+    /// Name the directories of the area and the category of the id, instead of naming them after
+    /// their ids and topics
+    pub fn name_directories(&mut self, area: Option<&Arc<str>>, category: Option<&Arc<str>>) {
+        if let (Some(bound), Some(name)) = (&mut self.area, area) {
+            bound.directory = Some(Arc::clone(name));
+        }
+        if let (Some(bound), Some(name)) = (&mut self.category, category) {
+            bound.directory = Some(Arc::clone(name));
+        }
+        self.path = OnceLock::new();
+    }
+
+    /// The id written without the system id in front, when it is hidden and this isn't the id of
+    /// the system itself
+    fn without_system(&self, written: String, sep: &str) -> String {
+        if !self.hide_system || self.level() <= Level::System {
+            return written;
+        }
+        let Some(bare) = self
+            .system
+            .as_deref()
+            .and_then(|system_id| written.strip_prefix(system_id))
+            .and_then(|rest| rest.strip_prefix(sep))
+            .map(str::to_owned)
+        else {
+            return written;
+        };
+        bare
+    }
+
     /// Set the area id
-    pub fn area_id(self, start: u8, end: u8, topic: &str) -> Self {
+    pub fn area_id(self, start: u8, end: u8, topic: impl Into<Arc<str>>) -> Self {
         Self {
             area: Some(BoundRangeId {
-                topic: topic.to_owned(),
+                topic: topic.into(),
                 start,
                 end,
+                directory: None,
             }),
+            path: OnceLock::new(),
             ..self
         }
     }
 
     /// Set the category id
-    pub fn category_id(self, category_id: u8, topic: &str) -> Self {
+    pub fn category_id(self, category_id: u8, topic: impl Into<Arc<str>>) -> Self {
         Self {
             category: Some(BoundU8Id {
-                topic: topic.to_owned(),
+                topic: topic.into(),
                 id: category_id,
+                directory: None,
             }),
+            path: OnceLock::new(),
             ..self
         }
     }
 
     /// Set the folder id
-    pub fn folder_id(self, folder_id: u8, topic: &str) -> Self {
+    pub fn folder_id(self, folder_id: u8, topic: impl Into<Arc<str>>) -> Self {
         Self {
             folder: Some(BoundU8Id {
-                topic: topic.to_owned(),
+                topic: topic.into(),
                 id: folder_id,
+                directory: None,
             }),
+            path: OnceLock::new(),
             ..self
         }
     }
 
     /// Set the extended folder id
-    pub fn xfolder_id(self, xfolder_id: &str, topic: &str) -> Self {
+    pub fn xfolder_id(self, xfolder_id: impl Into<Arc<str>>, topic: impl Into<Arc<str>>) -> Self {
         Self {
             xfolder: Some(BoundStrId {
-                topic: topic.to_owned(),
-                id: xfolder_id.to_owned(),
+                topic: topic.into(),
+                id: xfolder_id.into(),
             }),
+            path: OnceLock::new(),
             ..self
         }
     }
 
     /// Get the id by a separator
     pub fn by_seperator(&self, sep: &str) -> String {
+        self.without_system(self.with_system(sep), sep)
+    }
+
+    /// Get the id by a separator, always starting with the system id
+    fn with_system(&self, sep: &str) -> String {
         let mut id = String::new();
         if let Some(system_id) = &self.system {
             id.push_str(system_id);
@@ -191,19 +301,32 @@ impl JohnnyId {
         id
     }
 
+    /// Whether `id` names this id, written with or without the system id
+    pub fn matches(&self, id: &str) -> bool {
+        let full = self.with_system(".");
+        let bare = self
+            .system
+            .as_deref()
+            .and_then(|system_id| full.strip_prefix(system_id))
+            .and_then(|rest| rest.strip_prefix('.'))
+            .unwrap_or(&full);
+        full == id || bare == id
+    }
+
     /// Get the id by a separator with the names included in the id parts
     pub fn by_seperator_bound(&self, sep: &str) -> String {
+        self.without_system(self.bound_with_system(sep), sep)
+    }
+
+    /// Get the id by a separator with the names included, always starting with the system id
+    fn bound_with_system(&self, sep: &str) -> String {
         let mut id: Vec<String> = Vec::new();
         let maybe_topic = self
             .xfolder
             .as_ref()
-            .map(|bound_id| bound_id.topic.as_str())
-            .or_else(|| self.folder.as_ref().map(|bound_id| bound_id.topic.as_str()))
-            .or_else(|| {
-                self.category
-                    .as_ref()
-                    .map(|bound_id| bound_id.topic.as_str())
-            });
+            .map(|bound_id| &*bound_id.topic)
+            .or_else(|| self.folder.as_ref().map(|bound_id| &*bound_id.topic))
+            .or_else(|| self.category.as_ref().map(|bound_id| &*bound_id.topic));
         if let Some(system_id) = &self.system {
             id.push(system_id.to_string());
         }
@@ -222,7 +345,7 @@ impl JohnnyId {
             id.push(format!("{:02}", folder_id.id));
         }
         if let Some(xfolder_id) = &(self.xfolder) {
-            id.push(xfolder_id.id.clone());
+            id.push(xfolder_id.id.to_string());
         }
 
         maybe_topic.map_or_else(
@@ -231,25 +354,50 @@ impl JohnnyId {
         )
     }
 
-    /// Convert the id into a full path
-    pub fn as_path(&self) -> PathBuf {
-        let mut result = PathBuf::from(self.by_seperator_bound("."));
-        let mut parent = self.parent();
-        while parent
-            .as_ref()
-            .and_then(|current_parent| current_parent.system.as_deref())
-            .is_some()
-        {
-            #[expect(clippy::expect_used, reason = "parent should always be set")]
-            let parent_id = parent
+    /// The name of the directory of the id, which is the name a template gives the system, an
+    /// area, or a category, and the id with its topic otherwise
+    pub fn directory_name(&self) -> String {
+        let named = match self.level() {
+            Level::System => self.root.as_ref().or(self.directory.as_ref()),
+            Level::Area => self.area.as_ref().and_then(|area| area.directory.as_ref()),
+            Level::Category => self
+                .category
                 .as_ref()
-                .expect("Parent should always be valid")
-                .by_seperator_bound(".");
-            result = PathBuf::from(parent_id).join(result);
-            parent = parent.and_then(|new_parent| new_parent.parent());
+                .and_then(|category| category.directory.as_ref()),
+            Level::Empty | Level::Folder | Level::ExtendedFolder => None,
+        };
+        named.map_or_else(|| self.by_seperator_bound("."), ToString::to_string)
+    }
+
+    /// Convert the id into a full path, laid out the way the system's layout asks for
+    pub fn as_path(&self) -> &Path {
+        self.path.get_or_init(|| {
+            let scheme = self.layout.scheme();
+            let lineage = self.lineage();
+            let Some((system, below)) = lineage.split_first() else {
+                return PathBuf::new();
+            };
+            below.iter().fold(scheme.system_path(system), |path, id| {
+                scheme.child_path(&path, id)
+            })
+        })
+    }
+
+    /// The ids from the system down to this one
+    pub fn lineage(&self) -> Vec<Self> {
+        let mut lineage = Vec::new();
+        let mut current = Some(self.to_owned());
+        while let Some(id) = current.filter(|id| id.system.is_some()) {
+            current = id.parent();
+            lineage.push(id);
         }
+        lineage.reverse();
+        lineage
+    }
 
-        result
+    /// Whether this is the id of an area
+    pub const fn is_area(&self) -> bool {
+        matches!(self.level(), Level::Area)
     }
 
     /// Get the parent of the current id
@@ -263,6 +411,7 @@ impl JohnnyId {
         if self.category.is_none() {
             return Some(Self {
                 area: None,
+                path: OnceLock::new(),
                 ..self.to_owned()
             });
         }
@@ -270,6 +419,7 @@ impl JohnnyId {
         if self.folder.is_none() {
             return Some(Self {
                 category: None,
+                path: OnceLock::new(),
                 ..self.to_owned()
             });
         }
@@ -277,19 +427,21 @@ impl JohnnyId {
         if self.xfolder.is_none() {
             return Some(Self {
                 folder: None,
+                path: OnceLock::new(),
                 ..self.to_owned()
             });
         }
 
         Some(Self {
             xfolder: None,
+            path: OnceLock::new(),
             ..self.to_owned()
         })
     }
 }
 
 impl Display for JohnnyId {
-    #[expect(
+    #[allow(
         clippy::min_ident_chars,
         reason = "This is the preferred default name for the variable"
     )]
@@ -315,6 +467,45 @@ mod tests {
         assert_eq!(id.by_seperator_bound("."), "system.01.02.xfolder xfold");
     }
 
+    #[test]
+    fn test_hidden_system() {
+        let id = JohnnyId::default()
+            .system_id("L01")
+            .hide_system(true)
+            .area_id(20, 29, "Finance")
+            .category_id(21, "Tax")
+            .folder_id(5, "Returns");
+        assert_eq!(id.by_seperator("."), "21.05");
+        assert_eq!(id.by_seperator_bound("."), "21.05 Returns");
+        assert_eq!(
+            id.as_path().to_str().unwrap(),
+            "L01/20-29 Finance/21 Tax/21.05 Returns"
+        );
+        assert!(id.matches("L01.21.05"));
+        assert!(id.matches("21.05"));
+        assert_eq!(
+            JohnnyId::default()
+                .system_id("L01")
+                .hide_system(true)
+                .by_seperator("."),
+            "L01"
+        );
+    }
+
+    #[test]
+    fn test_flat_layout() {
+        let area = JohnnyId::default()
+            .system_id("L01")
+            .layout(Layout::Flat)
+            .area_id(20, 29, "Finance");
+        let folder = area.clone().category_id(21, "Tax").folder_id(5, "Returns");
+        assert_eq!(area.as_path().to_str().unwrap(), "L01");
+        assert_eq!(
+            folder.as_path().to_str().unwrap(),
+            "L01/L01.21 Tax/L01.21.05 Returns"
+        );
+    }
+
     #[test]
     fn test_system_jid() {
         let id = JohnnyId::default().system_id("system");
@@ -0,0 +1,296 @@
+use core::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use color_eyre::eyre::{Error, OptionExt};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use url::Url;
+
+use crate::{
+    config, html, layout,
+    model::{FolderKind, HasJohnnyId, System},
+    notes,
+    output::{self, Mark},
+    qr::{QrCode, QUIET_ZONE},
+};
+
+/// The format that labels are printed in
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LabelFormat {
+    /// One label per row as CSV, ready for a label printer's mail merge
+    Csv,
+    /// An SVG page of labels with QR codes for the deep links
+    Qr,
+}
+
+/// The kind of deep link a label points at
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LinkKind {
+    /// Open the note (or folder) in Obsidian
+    Obsidian,
+    /// Open the folder in the reference archive
+    File,
+}
+
+/// A single printable label
+#[derive(Debug)]
+struct Label {
+    /// The full id of the entry
+    id: String,
+    /// The topic of the entry
+    topic: String,
+    /// The deep link to the entry
+    link: String,
+}
+
+/// Build the deep link for an entry
+pub fn deep_link(
+    output_config: &config::Output,
+    entry: &dyn HasJohnnyId,
+    kind: Option<&FolderKind>,
+    link: LinkKind,
+    extension: &str,
+) -> Result<String, Error> {
+    let url = match link {
+        LinkKind::Obsidian => {
+            let base = notes::expand(&output_config.base_folder)?;
+            let path = note_path(&base, entry, kind, extension);
+            // Obsidian decodes with `decodeURIComponent`, so spaces must not become `+`
+            let encoded =
+                utf8_percent_encode(&path.to_string_lossy(), NON_ALPHANUMERIC).to_string();
+            Url::parse(&format!("obsidian://open?path={encoded}"))?
+        }
+        LinkKind::File => {
+            let base = notes::expand(&output_config.reference_folder)?;
+            Url::from_directory_path(base.join(entry.jid().as_path()))
+                .map_err(|()| Error::msg("The reference folder must be an absolute path"))?
+        }
+    };
+    Ok(url.to_string())
+}
+
+/// The path of the note for an entry, or its directory when the entry has no note
+pub fn note_path(
+    base: &Path,
+    entry: &dyn HasJohnnyId,
+    kind: Option<&FolderKind>,
+    extension: &str,
+) -> PathBuf {
+    let path = base.join(entry.jid().as_path());
+    match kind {
+        Some(FolderKind::File | FolderKind::Both | FolderKind::Index | FolderKind::FullIndex) => {
+            entry.file_stem().map_or_else(
+                || layout::note_file(&path, extension),
+                |stem| path.with_file_name(format!("{stem}.{extension}")),
+            )
+        }
+        Some(FolderKind::Folder | FolderKind::Link(_)) | None => path,
+    }
+}
+
+/// Collect every entry at or under `id`, along with its folder kind
+pub fn entries_under<'sys>(
+    system: &'sys System,
+    id: &str,
+) -> Vec<(&'sys dyn HasJohnnyId, Option<&'sys FolderKind>)> {
+    let mut entries: Vec<(&dyn HasJohnnyId, Option<&FolderKind>)> = Vec::new();
+    for area in &system.areas {
+        let area_matched = area.id.matches(id);
+        if area_matched {
+            entries.push((area, None));
+        }
+        for category in &area.categories {
+            let category_matched = area_matched || category.id.matches(id);
+            if category_matched {
+                entries.push((category, None));
+            }
+            for folder in &category.folders {
+                let folder_matched = category_matched || folder.id.matches(id);
+                if folder_matched {
+                    entries.push((folder, Some(&folder.kind)));
+                }
+                for xfolder in &folder.folders {
+                    if folder_matched || xfolder.id.matches(id) {
+                        entries.push((xfolder, Some(&xfolder.kind)));
+                    }
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Quote a field for CSV output
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Render the labels as CSV
+fn to_csv(labels: &[Label]) -> String {
+    let mut csv = String::from("id,topic,link\n");
+    for label in labels {
+        let _ = writeln!(
+            csv,
+            "{},{},{}",
+            csv_field(&label.id),
+            csv_field(&label.topic),
+            csv_field(&label.link)
+        );
+    }
+    csv
+}
+
+/// Render the labels as an SVG page, two labels to a row, along with the ids of the labels whose
+/// links are too long for a QR code, which have a code of their id instead
+fn to_svg(labels: &[Label]) -> Result<(String, Vec<&str>), Error> {
+    /// The width of a single label
+    const WIDTH: usize = 420;
+    /// The height of a single label
+    const HEIGHT: usize = 140;
+    /// The size of the QR code on each label
+    const CODE_SIZE: usize = 120;
+
+    let rows = labels.len().div_ceil(2);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        WIDTH * 2,
+        HEIGHT * rows,
+        WIDTH * 2,
+        HEIGHT * rows
+    );
+    let mut shortened = Vec::new();
+    for (index, label) in labels.iter().enumerate() {
+        let x = (index % 2) * WIDTH;
+        let y = (index / 2) * HEIGHT;
+        let code = if let Ok(code) = QrCode::encode(&label.link) {
+            code
+        } else {
+            shortened.push(label.id.as_str());
+            QrCode::encode(&label.id)?
+        };
+        // The quiet zone is part of the space for the code, so the code shrinks to leave it light
+        let scale = (CODE_SIZE / (code.size() + 2 * QUIET_ZONE)).max(1);
+        let margin = 10 + QUIET_ZONE * scale;
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{WIDTH}\" height=\"{HEIGHT}\" fill=\"white\" stroke=\"#ccc\"/>"
+        );
+        let _ = writeln!(
+            svg,
+            "<path d=\"{}\" fill=\"black\"/>",
+            code.svg_path(x + margin, y + margin, scale)
+        );
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"28\" font-weight=\"bold\">{}</text>",
+            x + CODE_SIZE + 30,
+            y + 60,
+            html::escape(&label.id)
+        );
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"18\">{}</text>",
+            x + CODE_SIZE + 30,
+            y + 95,
+            html::escape(&label.topic)
+        );
+    }
+    svg.push_str("</svg>\n");
+    Ok((svg, shortened))
+}
+
+/// Print labels for `id` and everything under it
+pub fn run(
+    output_config: &config::Output,
+    system: &System,
+    id: &str,
+    format: LabelFormat,
+    link: LinkKind,
+    extension: &str,
+) -> Result<(), Error> {
+    let entries = entries_under(system, id);
+    entries
+        .first()
+        .ok_or_eyre(format!("No entry with the id {id} was found"))?;
+    let labels = entries
+        .into_iter()
+        .map(|(entry, kind)| {
+            Ok(Label {
+                id: entry.jid().by_seperator("."),
+                topic: entry.name().to_owned(),
+                link: deep_link(output_config, entry, kind, link, extension)?,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    match format {
+        LabelFormat::Csv => print!("{}", to_csv(&labels)),
+        LabelFormat::Qr => {
+            let (svg, shortened) = to_svg(&labels)?;
+            for id in shortened {
+                // The page goes to stdout, so it isn't mixed up with the warnings
+                eprint!(
+                    "{}",
+                    output::line(
+                        Mark::Skipped,
+                        "Shortened",
+                        &format!("{id}, whose link is too long for a QR code, the label has its id instead")
+                    )
+                );
+            }
+            print!("{svg}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("Tax"), "Tax");
+        assert_eq!(csv_field("Tax, \"Returns\""), "\"Tax, \"\"Returns\"\"\"");
+    }
+
+    #[test]
+    fn test_entries_under() {
+        let system = System::try_from(config::SystemParameters {
+            system_id: "N01".to_owned(),
+            name: "Test".to_owned(),
+            config: "20-29 Finance\n\t21 Tax\n\t\t05 -Returns\n\t22 Banking\n".to_owned(),
+            ..config::SystemParameters::default()
+        })
+        .unwrap();
+        let ids: Vec<String> = entries_under(&system, "21")
+            .into_iter()
+            .map(|(entry, _)| entry.jid().by_seperator("."))
+            .collect();
+        assert_eq!(ids, vec!["N01.21", "N01.21.05"]);
+        assert_eq!(entries_under(&system, "N01.20-29").len(), 4);
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let label = |id: &str, link: String| Label {
+            id: id.to_owned(),
+            topic: "Tax".to_owned(),
+            link,
+        };
+        let labels = [
+            label("N01.21", "obsidian://open?path=N01.21".to_owned()),
+            label(
+                "N01.22",
+                format!("obsidian://open?path={}", "x".repeat(400)),
+            ),
+        ];
+        let (svg, shortened) = to_svg(&labels).unwrap();
+        assert_eq!(shortened, vec!["N01.22"]);
+        // The first code is scaled to 3 pixels a module, so its quiet zone takes 12 pixels
+        assert!(svg.contains("<path d=\"M22,22h3v3h-3z"), "{svg}");
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    ffi::OsString,
+    path::{Component, Path, PathBuf},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{jid::JohnnyId, timestamp::Timestamp};
+
+/// What joins the names of an id and the ids above it into the name of a Logseq page, like
+/// `L01.20-29 Finance___L01.21 Tax`
+pub const NAMESPACE_SEPARATOR: &str = "___";
+
+/// How the directories of the system are nested under the directory of the system
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    /// Every area has a directory, with the directories of its categories inside
+    Nested,
+    /// The directories of the categories are right in the directory of the system, and the areas
+    /// are only headings of the index
+    Flat,
+    /// Every id is named after the ids above it, like a Logseq namespace page, all right in the
+    /// directory of the system
+    Logseq,
+    /// Nested, inside a directory for the current year, so each year starts a fresh tree
+    Yearly,
+}
+
+impl Layout {
+    /// The scheme that lays out the directories this way
+    pub fn scheme(self) -> Box<dyn Scheme> {
+        match self {
+            Self::Nested => Box::new(Nested),
+            Self::Flat => Box::new(Flat),
+            Self::Logseq => Box::new(Logseq),
+            Self::Yearly => Box::new(Yearly {
+                year: Timestamp::now().year(),
+            }),
+        }
+    }
+}
+
+/// A way of laying out the directories of the system.
+///
+/// A new directory convention is a new scheme, and every path of the system goes through one.
+pub trait Scheme {
+    /// The path of the directory of the system, below the base folder
+    fn system_path(&self, system: &JohnnyId) -> PathBuf;
+
+    /// The path of `id`, given the path of the id above it
+    fn child_path(&self, parent: &Path, id: &JohnnyId) -> PathBuf;
+
+    /// How many levels of the system a path below the base folder goes down, with the system as
+    /// the first, so the depths of the system mean the same in every layout
+    fn depth_of(&self, relative: &Path) -> usize {
+        relative.components().count()
+    }
+
+    /// Whether each area has a directory of its own
+    fn has_area_directories(&self) -> bool {
+        true
+    }
+}
+
+/// Every area has a directory, with the directories of its categories inside
+struct Nested;
+
+impl Scheme for Nested {
+    fn system_path(&self, system: &JohnnyId) -> PathBuf {
+        PathBuf::from(system.directory_name())
+    }
+
+    fn child_path(&self, parent: &Path, id: &JohnnyId) -> PathBuf {
+        parent.join(id.directory_name())
+    }
+}
+
+/// The categories are right in the directory of the system
+struct Flat;
+
+impl Scheme for Flat {
+    fn system_path(&self, system: &JohnnyId) -> PathBuf {
+        Nested.system_path(system)
+    }
+
+    fn child_path(&self, parent: &Path, id: &JohnnyId) -> PathBuf {
+        if id.is_area() {
+            parent.to_path_buf()
+        } else {
+            Nested.child_path(parent, id)
+        }
+    }
+
+    fn depth_of(&self, relative: &Path) -> usize {
+        let components = relative.components().count();
+        // Only what is inside the directory of the system is missing a level
+        if components > 1 {
+            components.saturating_add(1)
+        } else {
+            components
+        }
+    }
+
+    fn has_area_directories(&self) -> bool {
+        false
+    }
+}
+
+/// Every id is named after the ids above it, right in the directory of the system
+struct Logseq;
+
+impl Scheme for Logseq {
+    fn system_path(&self, system: &JohnnyId) -> PathBuf {
+        Nested.system_path(system)
+    }
+
+    fn child_path(&self, parent: &Path, id: &JohnnyId) -> PathBuf {
+        let name = id.directory_name();
+        if id.is_area() {
+            return parent.join(name);
+        }
+        let mut page = parent.file_name().map(OsString::from).unwrap_or_default();
+        page.push(NAMESPACE_SEPARATOR);
+        page.push(name);
+        parent.with_file_name(page)
+    }
+
+    fn depth_of(&self, relative: &Path) -> usize {
+        relative
+            .components()
+            .map(|component| match component {
+                Component::Normal(name) => {
+                    name.to_string_lossy().split(NAMESPACE_SEPARATOR).count()
+                }
+                _ => 1,
+            })
+            .sum()
+    }
+}
+
+/// Nested, inside a directory for the year
+struct Yearly {
+    /// The year the tree is for
+    year: u64,
+}
+
+impl Scheme for Yearly {
+    fn system_path(&self, system: &JohnnyId) -> PathBuf {
+        Nested.system_path(system).join(self.year.to_string())
+    }
+
+    fn child_path(&self, parent: &Path, id: &JohnnyId) -> PathBuf {
+        Nested.child_path(parent, id)
+    }
+
+    fn depth_of(&self, relative: &Path) -> usize {
+        let components = relative.components().count();
+        // The directory of the year isn't a level of the system
+        if components > 1 {
+            components.saturating_sub(1)
+        } else {
+            components
+        }
+    }
+}
+
+/// The path of the note of the entry at `path`, which is the path with the extension added, since
+/// the dots in an id aren't an extension
+pub fn note_file(path: &Path, extension: &str) -> PathBuf {
+    let mut file = path.as_os_str().to_owned();
+    file.push(".");
+    file.push(extension);
+    PathBuf::from(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(scheme: &dyn Scheme) -> PathBuf {
+        let folder = JohnnyId::default()
+            .system_id("L01")
+            .area_id(20, 29, "Finance")
+            .category_id(21, "Tax")
+            .folder_id(5, "Returns");
+        let lineage = folder.lineage();
+        let (system, below) = lineage.split_first().unwrap();
+        below.iter().fold(scheme.system_path(system), |path, id| {
+            scheme.child_path(&path, id)
+        })
+    }
+
+    #[test]
+    fn test_schemes() {
+        assert_eq!(
+            path(&Nested),
+            PathBuf::from("L01/L01.20-29 Finance/L01.21 Tax/L01.21.05 Returns")
+        );
+        assert_eq!(
+            path(&Flat),
+            PathBuf::from("L01/L01.21 Tax/L01.21.05 Returns")
+        );
+        assert_eq!(
+            path(&Logseq),
+            PathBuf::from("L01/L01.20-29 Finance___L01.21 Tax___L01.21.05 Returns")
+        );
+        assert_eq!(
+            path(&Yearly { year: 2025 }),
+            PathBuf::from("L01/2025/L01.20-29 Finance/L01.21 Tax/L01.21.05 Returns")
+        );
+    }
+
+    #[test]
+    fn test_depth_of() {
+        assert_eq!(Nested.depth_of(Path::new("L01/A/B")), 3);
+        assert_eq!(Flat.depth_of(Path::new("L01")), 1);
+        assert_eq!(Flat.depth_of(Path::new("L01/B/C")), 4);
+        assert_eq!(Logseq.depth_of(Path::new("L01/A___B___C.md")), 4);
+        assert_eq!(Yearly { year: 2025 }.depth_of(Path::new("L01/2025/A")), 2);
+    }
+
+    #[test]
+    fn test_note_file() {
+        assert_eq!(
+            note_file(Path::new("L01/L01.21.05 Returns"), "md"),
+            PathBuf::from("L01/L01.21.05 Returns.md")
+        );
+    }
+}
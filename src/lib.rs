@@ -0,0 +1,1256 @@
+#![deny(clippy::all, clippy::pedantic, clippy::nursery, clippy::cargo)]
+#![allow(
+    clippy::single_call_fn,
+    clippy::implicit_return,
+    clippy::question_mark_used,
+    clippy::missing_trait_methods,
+    clippy::string_slice,
+    clippy::pattern_type_mismatch, // This one is giving too many false positives
+    clippy::let_underscore_must_use, // This goes against other clippy lints
+    clippy::multiple_crate_versions, // rhai, through handlebars, pulls in two versions of getrandom on its own
+    clippy::print_stdout,
+    clippy::print_stderr,
+    reason = "These are ones I don't want to enforce for this project"
+)]
+
+/// Moving directories jdexmd didn't make into the place of an id
+mod adopt;
+/// The ids of the system written for autocompletion in the vault
+mod autocomplete;
+/// Where generated files are written
+mod backend;
+/// Answering queries read from stdin without starting over for each one
+mod batch;
+/// A large system to measure planning with, for the benchmarks
+#[doc(hidden)]
+pub mod bench;
+/// Bundling everything a generation would write into text or an archive, instead of writing it
+mod bundle;
+/// Keeping the system built from the config file between calls, until the config file changes
+mod cache;
+/// Checking that the links of the generated indexes lead somewhere
+mod check_links;
+/// The configuration for the Johnny Decimal system
+mod config;
+/// How many numbers of an area or category are used, retired, and free
+mod count;
+/// How deep the generated trees go into the system
+mod depth;
+/// Comparing two versions of a system by their ids
+mod diff;
+/// Checks the generated system against the restrictions of cloud storage providers
+mod doctor;
+/// What is already in a folder before the system is first generated into it
+mod existing;
+/// Describing a single id in plain language
+mod explain;
+/// Exporting a printable index of the whole system, or an archive of everything it generates
+mod export;
+/// The modes of a generation picked with command line flags that go together
+mod flags;
+/// Freezing an entry that is done with, so nothing more is filed or generated in it
+mod freeze;
+/// Golden tests of the whole generation, run in memory
+#[cfg(test)]
+mod golden;
+/// When each id appeared, and how it changed since
+mod history;
+/// Rendering the system as HTML
+mod html;
+/// Reading the `.jdexignore` files that protect paths from jdexmd
+mod ignore;
+/// The folder for things that haven't been filed yet
+mod inbox;
+/// Writing a new config file, from prompts or a starter system
+mod init;
+/// Reading a generated jdex back into a system definition
+mod jdex;
+/// The Johnny Decimal Identifier
+mod jid;
+/// Printable labels for physical folders
+mod label;
+/// How the directories of the system are nested
+mod layout;
+/// The line parser for the system configuration
+mod line;
+/// Platform native links from the notes to the reference archive
+mod link;
+/// The markdown formatter for the system
+mod markdown;
+/// Merging two definitions that came from the same one, entry by entry
+mod merge;
+/// Mirroring files placed in the notes into the reference archive
+mod mirror;
+/// The model for the Johnny Decimal system
+mod model;
+/// The rules for how topics are written
+mod naming;
+/// Finding the next folder id that has never been issued
+mod next_id;
+/// Everything needed for generating the system for a notetaking system
+mod notes;
+/// The colors and icons of what is printed
+mod output;
+/// Showing long output through the pager the user chose
+mod pager;
+/// Resolving the paths of the config file, with `~`, Windows environment variables, and drive letters
+mod paths;
+/// A minimal writer of PDF documents with text in the standard fonts
+mod pdf;
+/// Plans of what a generation would do, reviewed before they are applied
+mod plan;
+/// Working out the actions that generate the notes and the reference archive, in order
+mod planner;
+/// Asking the questions of a command, or answering them for a script
+mod prompt;
+/// A minimal QR code encoder for printable labels
+mod qr;
+/// Finding what would be renamed or deleted to match the system
+mod reconcile;
+/// A report of what each generation did
+mod report;
+/// Turning partial or unpadded ids into the ids of the system
+mod resolve;
+/// Marking areas and categories as reviewed, and listing the ones overdue for a review
+mod review;
+/// The JSON Schema of the config file
+mod schema;
+/// The segment of a shell prompt with the entry of the current directory
+mod segment;
+/// A small HTTP server previewing the index
+mod serve;
+/// Stored versions of the structure of the system
+mod snapshot;
+/// Where what jdexmd remembers between runs is kept
+mod state;
+/// Counting what the system has and what is filed under it
+mod stats;
+/// Systems to test with, built from a few lines of config
+#[cfg(test)]
+mod test_support;
+/// Timestamps without a calendar dependency
+mod timestamp;
+/// The system printed as a tree, colored by the status of each entry
+mod tree;
+/// Checks for common mistakes in the system definition
+mod validate;
+/// Finding the entry a path on disk belongs to
+mod which;
+/// Remembering what the indexes were written with, so edits made elsewhere aren't overwritten
+mod written;
+
+use backend::{LocalFs, OutputBackend, Recorder};
+use clap::{Parser, Subcommand};
+use color_eyre::eyre::{Error, OptionExt};
+use ignore::IgnoreRules;
+use markdown::{Generation, MdFormatter};
+use output::Mark;
+use planner::Planner;
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{self, Write as _},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use crate::{model::System, prompt::Prompt, report::Report, timestamp::Timestamp};
+
+/// Command line arguments for running the process to generate the Johnny Decimal system
+#[derive(Debug, Parser)]
+#[clap(version, about, author, long_about=None)]
+struct Arguments {
+    #[clap(flatten)]
+    /// Whether the generation is only previewed, with --dry-run and --fail-on-changes
+    preview: flags::Preview,
+    #[clap(flatten)]
+    /// What the generation writes, with --create-only and --index-only
+    scope: flags::Scope,
+    #[clap(long, default_value = "false")]
+    /// Generate into notes that already hold things that aren't part of the system without asking
+    acknowledge_existing: bool,
+    #[clap(long, default_value = "false")]
+    /// Overwrite indexes that were edited since they were last written, like on another device
+    force: bool,
+    #[clap(long)]
+    /// Only generate the area, category, or folder with this id and everything under it
+    filter: Option<String>,
+    #[clap(long, default_value = "false")]
+    /// Print without colors, the same as setting `NO_COLOR`
+    no_color: bool,
+    #[clap(flatten)]
+    /// Who answers the questions, with --yes and --non-interactive
+    answering: flags::Answering,
+    #[clap(env = "JDEX_CONFIG", short, long)]
+    /// The Path to a toml file that defines the system, or `-` to read it from stdin
+    config_file: PathBuf,
+    #[clap(long)]
+    /// Write the actions a generation would take as JSON instead of applying them, `-` for stdout
+    plan_out: Option<PathBuf>,
+    #[clap(flatten)]
+    /// Where the generation goes, with --stdout and --tar
+    destination: flags::Destination,
+    #[clap(subcommand)]
+    /// The command to run, generating the system when none is given
+    command: Option<Command>,
+}
+
+/// The subcommands beyond generating the system
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Move a directory jdexmd didn't make into the place of an id, adding the id when it is new
+    Adopt {
+        /// The directory to move
+        path: PathBuf,
+        /// The id to move it to, which can be a new folder id in an existing category
+        id: String,
+        #[clap(long)]
+        /// The topic of a new id, asked for when not given
+        topic: Option<String>,
+    },
+    /// Apply a plan written by --plan-out, if the config file hasn't changed since
+    Apply {
+        /// The plan to apply, or `-` to read it from stdin
+        plan: PathBuf,
+    },
+    /// Answer queries like `path 21.05` or `link 32.01`, one per line from stdin
+    Batch,
+    /// Check that every wikilink in the generated indexes leads to a note or directory of the vault
+    CheckLinks,
+    /// Work with the config file itself, like printing its JSON Schema
+    Config {
+        #[clap(subcommand)]
+        /// What to do with the config file
+        command: schema::ConfigCommand,
+    },
+    /// Count the used, retired, and free numbers of an area or category
+    Count {
+        /// The area or category to count the numbers of, with or without the system id
+        id: String,
+        #[clap(long, default_value = "false")]
+        /// Show which numbers are free in a grid
+        free: bool,
+    },
+    /// Compare two system definitions and list the ids that were added, removed, renamed, or moved
+    DiffConfig {
+        /// The config file with the older definition
+        old: PathBuf,
+        /// The config file with the newer definition, the one given by --config-file when not given
+        new: Option<PathBuf>,
+    },
+    /// Check that every generated name can be synced by a cloud storage provider, or fix where the
+    /// folders drifted from the system
+    Doctor {
+        #[clap(
+            short,
+            long,
+            value_enum,
+            required_unless_present_any = ["fix", "clean_conflicts"]
+        )]
+        /// The cloud storage provider the vault is synced with
+        target: Option<doctor::SyncTarget>,
+        #[clap(long, default_value = "false")]
+        /// Create missing directories, rename misnamed ones, and move the ones whose id is gone into
+        /// `@Review`, listing them first and asking before changing anything
+        fix: bool,
+        #[clap(long, default_value = "false")]
+        /// Move the conflicted copies that sync clients left in the folders into `@Review`, which
+        /// `--fix` only lists
+        clean_conflicts: bool,
+    },
+    /// Describe an id in plain language, with where it is on disk
+    Explain {
+        /// The id to describe, with or without the system id
+        id: String,
+        #[clap(long = "in")]
+        /// The category a bare folder number like `5` is in
+        within: Option<String>,
+    },
+    /// Export a printable index of the whole system, an archive of what it generates, or its mail
+    /// folders and bookmarks
+    Export {
+        #[clap(short, long, value_enum, default_value = "pdf")]
+        /// The format of the index
+        format: export::ExportFormat,
+        #[clap(short, long, default_value = "-")]
+        /// The file to write the index to, or - for stdout
+        output: PathBuf,
+    },
+    /// Freeze an id and everything under it, making its directories read-only
+    Freeze {
+        /// The id to freeze, like 21.05
+        id: String,
+    },
+    /// List what is waiting in the inbox, with where each thing might be filed
+    Inbox {
+        #[clap(subcommand)]
+        /// What to do with the inbox
+        command: inbox::InboxCommand,
+    },
+    /// Write a new config file at the path given by --config-file
+    Init {
+        #[clap(long, default_value = "false")]
+        /// Build the system from prompts, showing the definition as it grows
+        wizard: bool,
+        #[clap(long, value_enum, conflicts_with = "wizard")]
+        /// Start from one of the complete systems shipped with jdexmd
+        from_template: Option<init::Starter>,
+    },
+    /// Read a previously generated jdex and rebuild the system definition from it
+    Import {
+        /// The jdex markdown file to read
+        index: PathBuf,
+        #[clap(short, long, default_value = "false")]
+        /// Write the definition into the config file instead of printing it
+        write: bool,
+    },
+    /// Print the jdex of the whole system, through `$PAGER` when it is set
+    Index {
+        #[clap(long, default_value = "false")]
+        /// Print the jdex straight to stdout instead of through the pager
+        no_pager: bool,
+    },
+    /// Print labels with deep links for an id and everything under it
+    Label {
+        /// The id to print labels for, with or without the system id
+        id: String,
+        #[clap(long = "in")]
+        /// The category a bare folder number like `5` is in
+        within: Option<String>,
+        #[clap(short, long, value_enum, default_value = "csv")]
+        /// The format of the labels
+        format: label::LabelFormat,
+        #[clap(short, long, value_enum, default_value = "obsidian")]
+        /// Where the deep links on the labels point
+        link: label::LinkKind,
+    },
+    /// Merge two definitions that both started from a base, entry by entry instead of line by line
+    Merge {
+        /// The config file both sides started from
+        base: PathBuf,
+        /// The config file with our changes
+        ours: PathBuf,
+        /// The config file with their changes
+        theirs: PathBuf,
+        #[clap(short, long, default_value = "false")]
+        /// Write the merged definition into ours instead of printing it
+        write: bool,
+    },
+    /// Print the next folder id in a category that has never been issued, retired ids included
+    NextId {
+        /// The category to find a folder id in, with or without the system id
+        category: String,
+    },
+    /// List the ids that were added, renamed, moved, or retired since a date
+    Report {
+        #[clap(long)]
+        /// The first day to list changes from, like 2025-01-01, the start of the month when not given
+        since: Option<String>,
+        #[clap(long, conflicts_with = "since")]
+        /// List the areas and categories that weren't reviewed within an age like 90d instead
+        stale: Option<String>,
+    },
+    /// Mark an area or category as reviewed today
+    Review {
+        /// The area or category to mark, with or without the system id
+        id: String,
+    },
+    /// Save, list, compare, and restore versions of the structure of the system
+    Snapshot {
+        #[clap(subcommand)]
+        /// What to do with the snapshots
+        command: snapshot::SnapshotCommand,
+    },
+    /// Count the entries of the system, and what is filed under them on disk
+    Stats {
+        #[clap(long, default_value = "false")]
+        /// Count the files and their sizes in the notes and the reference archive too
+        on_disk: bool,
+    },
+    /// Print the system as a tree, colored by the status of each entry
+    Tree,
+    /// Check the system definition for common mistakes like duplicated topics
+    Validate {
+        #[clap(long, default_value = "false")]
+        /// Treat every warning as an error
+        strict: bool,
+        #[clap(long, default_value = "false")]
+        /// Rewrite the topics in the config file to follow the naming rules first
+        fix: bool,
+    },
+    /// Print where the things filed under an id physically live, along with its paths
+    Where {
+        /// The id to look up, with or without the system id
+        id: String,
+        #[clap(long = "in")]
+        /// The category a bare folder number like `5` is in
+        within: Option<String>,
+    },
+    /// Print a segment like `[21.05 Taxes]` for a shell prompt, with the entry of the current directory
+    PromptSegment,
+    /// Print the id and topic of the entry a path in the notes or the reference archive belongs to
+    Which {
+        /// The path to look up, like `.` for the current directory
+        path: PathBuf,
+    },
+    /// Serve the rendered index over HTTP, reloading when the config file changes
+    Serve {
+        #[clap(long, default_value = "127.0.0.1:7399")]
+        /// The address to listen on
+        http: SocketAddr,
+    },
+}
+
+impl Arguments {
+    /// Whether the actions are only previewed, which writing a plan implies
+    const fn previewing(&self) -> bool {
+        self.preview.is_dry_run() || self.plan_out.is_some()
+    }
+
+    /// Asks the questions of a command on the terminal, unless --yes or --non-interactive answer
+    /// them
+    fn prompt(&self) -> Prompt<io::StdinLock<'static>, anstream::Stdout> {
+        Prompt::new(io::stdin().lock(), anstream::stdout()).answering(
+            self.answering == flags::Answering::Yes,
+            self.answering == flags::Answering::NonInteractive,
+        )
+    }
+
+    /// Where to print progress, which is stderr when the plan is written to stdout
+    fn progress(&self) -> Box<dyn io::Write> {
+        if self
+            .plan_out
+            .as_deref()
+            .is_some_and(|path| path == Path::new(config::STANDARD_STREAM))
+        {
+            Box::new(anstream::stderr())
+        } else {
+            Box::new(anstream::stdout())
+        }
+    }
+
+    /// The planner of the actions generating `system` into `output_config`, limited to --filter
+    /// and --index-only when they are given
+    fn planner<'cfg, 'sys>(
+        &self,
+        output_config: &'cfg config::Output,
+        system: &'sys System,
+        formatter: &'cfg MdFormatter<'_>,
+    ) -> Result<Planner<'cfg, 'sys>, Error> {
+        Ok(Planner::new(output_config, system, formatter.extension())?
+            .subtree(self.subtree(system)?)
+            .index_only(self.scope == flags::Scope::IndexOnly))
+    }
+
+    /// The full id of the entry `id` of `system`, asking which one is meant when it is ambiguous
+    fn resolve(&self, system: &System, id: &str, within: Option<&str>) -> Result<String, Error> {
+        resolve::resolve_asking(system, id, within, &mut self.prompt())
+    }
+
+    /// Where the entry given by --filter is, relative to the base folders, when generation is
+    /// limited to it
+    fn subtree(&self, system: &System) -> Result<Option<PathBuf>, Error> {
+        let Some(filter) = &self.filter else {
+            return Ok(None);
+        };
+        let id = self.resolve(system, filter, None)?;
+        let (_, entry) = system
+            .iter()
+            .find(|(_, entry)| entry.jid().matches(&id))
+            .ok_or_eyre(format!("{filter} isn't an id of the system"))?;
+        Ok(Some(entry.jid().as_path().to_path_buf()))
+    }
+}
+
+/// The exit code when the folders needed changes, whether they were made or found by a checked dry
+/// run, which tells them apart from errors exiting with 1
+const CHANGES_NEEDED: u8 = 2;
+
+/// The exit code for a generation where the folders needed `changes`
+fn exit_code(changes: usize) -> ExitCode {
+    if changes == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(CHANGES_NEEDED)
+    }
+}
+
+/// The system defined by the config file, with what the commands working on it share
+struct Loaded<'hbar> {
+    /// The folders the system is generated into
+    output_config: config::Output,
+    /// The other folders the system is generated into after them
+    targets: Vec<config::Output>,
+    /// The system built from the definition
+    system: System,
+    /// The entry each path belongs to
+    paths: cache::PathIndex,
+    /// The formatter of the notes, pointed at the notes of `output_config`
+    formatter: MdFormatter<'hbar>,
+    /// The definition as it was written, for pointing at its lines
+    definition: String,
+    /// Where the report of each generation is written, when it is
+    report: Option<report::ReportConfig>,
+    /// The rules for how topics are written
+    naming: naming::NamingConfig,
+    /// The limits validate warns about
+    limits: validate::LimitsConfig,
+    /// The fingerprint of the config file
+    config_hash: String,
+    /// When jdexmd was started
+    started: Timestamp,
+}
+
+impl Loaded<'_> {
+    /// Build the system `config` defines, or take it from the cache when the config file with
+    /// the fingerprint `config_hash` didn't change
+    fn new(
+        args: &Arguments,
+        config: config::JohnnyDecimal,
+        config_hash: String,
+    ) -> Result<Self, Error> {
+        // The definition as it was written, for pointing at its lines
+        let definition = config.system_config.config.clone();
+        let cache::Cached { system, paths, .. } = cache::load(
+            &args.config_file,
+            config.system_config,
+            &config_hash,
+            config.format.extension(),
+        )?;
+        let output_config = config.output_config.for_system(&system)?;
+        let started = Timestamp::now();
+        let generation = Generation::new(started, config_hash.clone());
+        let formatter = retarget(
+            MdFormatter::try_from(config.format)?.generation(generation),
+            &output_config,
+            &system,
+        )?;
+        let targets = output_config
+            .targets()
+            .into_iter()
+            .map(|target| target.for_system(&system))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            output_config,
+            targets,
+            system,
+            paths,
+            formatter,
+            definition,
+            report: config.report,
+            naming: config.naming,
+            limits: config.limits,
+            config_hash,
+            started,
+        })
+    }
+}
+
+/// Manage a Johnny Decimal System of markdown files and directories, as the command line asks
+///
+/// # Errors
+///
+/// When the config file can't be read, or the command fails
+pub fn run() -> Result<ExitCode, Error> {
+    color_eyre::install()?;
+    let args: Arguments = Arguments::parse();
+    output::init(args.no_color);
+    // There is no config file to read yet
+    match args.command {
+        Some(Command::Init {
+            wizard,
+            from_template,
+        }) => {
+            init::run(&args.config_file, wizard, from_template, args.prompt())?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Config { ref command }) => {
+            schema::run(command)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        _ => {}
+    }
+    let contents = config::read_source(&args.config_file)?;
+    let config = config::JohnnyDecimal::from_toml(&contents)?.read_definition(&args.config_file)?;
+    let config_hash = config.fingerprint(&contents);
+    // A prompt is drawn all the time, so the segment doesn't even read the system when it can help it
+    if matches!(args.command, Some(Command::PromptSegment)) {
+        segment::run(&args.config_file, config, &config_hash)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let loaded = Loaded::new(&args, config, config_hash)?;
+    match args.command {
+        None => generate_system(&args, loaded),
+        Some(Command::Apply { ref plan }) => apply_plan(&args, loaded, plan),
+        Some(ref command) => {
+            run_command(&args, &loaded, command)?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// Run `command`, one of the commands that work on the system without generating it, where
+/// the ones that only show something are left to `show`
+fn run_command(args: &Arguments, loaded: &Loaded<'_>, command: &Command) -> Result<(), Error> {
+    let Loaded {
+        output_config,
+        system,
+        ..
+    } = loaded;
+    match *command {
+        Command::Adopt {
+            ref path,
+            ref id,
+            ref topic,
+        } => adopt::run(
+            &args.config_file,
+            output_config,
+            system,
+            path,
+            id,
+            topic.clone(),
+            args.prompt(),
+        )?,
+        Command::Doctor {
+            target,
+            fix,
+            clean_conflicts,
+        } => run_doctor(args, loaded, target, fix, clean_conflicts)?,
+        Command::Freeze { ref id } => {
+            freeze::run(
+                &args.config_file,
+                output_config,
+                &args.resolve(system, id, None)?,
+            )?;
+        }
+        Command::Inbox { ref command } => inbox::run(output_config, system, command)?,
+        Command::Import { ref index, write } => {
+            jdex::run(&args.config_file, system, index, write)?;
+        }
+        Command::Merge {
+            ref base,
+            ref ours,
+            ref theirs,
+            write,
+        } => merge::run(base, ours, theirs, write)?,
+        Command::Review { ref id } => {
+            review::run(&args.config_file, &args.resolve(system, id, None)?)?;
+        }
+        Command::Snapshot { ref command } => snapshot::run(
+            &args.config_file,
+            output_config.state_folder.as_deref(),
+            command,
+        )?,
+        Command::Validate { strict, fix } => run_validate(args, loaded, strict, fix)?,
+        _ => show(args, loaded, command)?,
+    }
+    Ok(())
+}
+
+/// Run `command`, one of the commands that show something about the system without changing
+/// anything
+fn show(args: &Arguments, loaded: &Loaded<'_>, command: &Command) -> Result<(), Error> {
+    let Loaded {
+        output_config,
+        system,
+        formatter,
+        ..
+    } = loaded;
+    match *command {
+        Command::Batch => batch::run(
+            output_config,
+            system,
+            formatter.extension(),
+            io::stdin().lock(),
+            io::stdout().lock(),
+        )?,
+        Command::CheckLinks => check_links::run(output_config, system, formatter.extension())?,
+        Command::Count { ref id, free } => {
+            count::run(system, &args.resolve(system, id, None)?, free)?;
+        }
+        Command::DiffConfig { ref old, ref new } => {
+            diff::run(old, new.as_ref().unwrap_or(&args.config_file))?;
+        }
+        Command::Explain { ref id, ref within } => {
+            let id = args.resolve(system, id, within.as_deref())?;
+            explain::run(output_config, system, &id, formatter.extension())?;
+        }
+        Command::Export { format, ref output } => {
+            let actions = args.planner(output_config, system, formatter)?.all()?;
+            let roots = bundle::Roots::new(output_config)?;
+            export::run(
+                system,
+                format,
+                output,
+                &actions,
+                formatter,
+                &roots,
+                loaded.started,
+            )?;
+        }
+        Command::Index { no_pager } => pager::page(&formatter.system(system)?, no_pager)?,
+        Command::Label {
+            ref id,
+            ref within,
+            format,
+            link,
+        } => label::run(
+            output_config,
+            system,
+            &args.resolve(system, id, within.as_deref())?,
+            format,
+            link,
+            formatter.extension(),
+        )?,
+        Command::NextId { ref category } => {
+            next_id::run(system, &args.resolve(system, category, None)?)?;
+        }
+        Command::Where { ref id, ref within } => {
+            let id = args.resolve(system, id, within.as_deref())?;
+            explain::locate(output_config, system, &id, formatter.extension())?;
+        }
+        Command::Which { ref path } => which::run(output_config, system, &loaded.paths, path)?,
+        Command::Report {
+            stale: Some(ref age),
+            ..
+        } => review::report(system, age)?,
+        Command::Report { ref since, .. } => history::run(
+            &args.config_file,
+            output_config.state_folder.as_deref(),
+            system,
+            since.clone(),
+        )?,
+        Command::Serve { http } => serve::run(&args.config_file, http)?,
+        Command::Stats { on_disk } => stats::run(
+            output_config,
+            system,
+            loaded.limits,
+            on_disk,
+            formatter.extension(),
+        )?,
+        Command::Tree => tree::run(system)?,
+        // The rest are run before the system is loaded, or change it
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Fix where the folders drifted from the system, or check that every generated name can be
+/// synced by the cloud storage provider `target`
+fn run_doctor(
+    args: &Arguments,
+    loaded: &Loaded<'_>,
+    target: Option<doctor::SyncTarget>,
+    fix: bool,
+    clean_conflicts: bool,
+) -> Result<(), Error> {
+    if fix || clean_conflicts {
+        fix_drift(
+            &loaded.output_config,
+            &loaded.system,
+            args,
+            &loaded.formatter,
+            fix,
+            clean_conflicts,
+        )?;
+    }
+    if let Some(target) = target {
+        doctor::run(
+            &loaded.output_config,
+            &loaded.system,
+            target,
+            loaded.formatter.extension(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Check the system definition for common mistakes, after rewriting its topics to follow the
+/// naming rules with `fix`
+fn run_validate(
+    args: &Arguments,
+    loaded: &Loaded<'_>,
+    strict: bool,
+    fix: bool,
+) -> Result<(), Error> {
+    let fixed;
+    let system = if fix {
+        fixed = naming::fix(&args.config_file, &loaded.naming)?;
+        &fixed
+    } else {
+        &loaded.system
+    };
+    validate::run(
+        system,
+        &loaded.definition,
+        &loaded.naming,
+        loaded.limits,
+        strict,
+    )
+}
+
+/// Apply the actions of the reviewed plan at `plan`, returning the exit code of the generation
+fn apply_plan(args: &Arguments, loaded: Loaded<'_>, plan: &Path) -> Result<ExitCode, Error> {
+    let Loaded {
+        output_config,
+        targets,
+        system,
+        formatter,
+        definition,
+        report: report_config,
+        naming,
+        limits,
+        config_hash,
+        started,
+        ..
+    } = loaded;
+    let planned = plan::Plan::read(plan)?.check(&config_hash)?;
+    let mut report = Report::new(&args.config_file, started);
+    let changes = generate(
+        &output_config,
+        &system,
+        args,
+        &formatter,
+        Some(&planned),
+        &mut report,
+    )? + generate_targets(
+        &targets,
+        &system,
+        args,
+        formatter,
+        Some(&planned),
+        &mut report,
+    )?;
+    if !args.preview.is_dry_run() {
+        history::record(
+            &args.config_file,
+            output_config.state_folder.as_deref(),
+            &system,
+            started,
+        )?;
+    }
+    if let Some(report_config) = &report_config {
+        write_report(
+            report_config,
+            &system,
+            &definition,
+            &naming,
+            limits,
+            &mut report,
+        )?;
+    }
+    Ok(exit_code(changes))
+}
+
+/// Generate the system into its folders, or print everything the generation would write with
+/// --stdout or --tar, returning the exit code of the generation
+fn generate_system(args: &Arguments, loaded: Loaded<'_>) -> Result<ExitCode, Error> {
+    let tar = match args.destination {
+        flags::Destination::Folders => return generate_folders(args, loaded),
+        flags::Destination::Stdout => None,
+        flags::Destination::Tar(ref path) => Some(path.as_path()),
+    };
+    let actions = args
+        .planner(&loaded.output_config, &loaded.system, &loaded.formatter)?
+        .all()?;
+    let roots = bundle::Roots::new(&loaded.output_config)?;
+    bundle::run(&actions, &loaded.formatter, &roots, tar, loaded.started)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Generate the system into the folders of the config file and its targets, returning the exit
+/// code of the generation
+fn generate_folders(args: &Arguments, loaded: Loaded<'_>) -> Result<ExitCode, Error> {
+    let Loaded {
+        output_config,
+        targets,
+        system,
+        formatter,
+        definition,
+        report: report_config,
+        naming,
+        limits,
+        config_hash,
+        started,
+        ..
+    } = loaded;
+    // The first generation into an existing vault is confirmed, unless it is acknowledged
+    if !args.previewing()
+        && !args.acknowledge_existing
+        && !history::is_recorded(&args.config_file, output_config.state_folder.as_deref())?
+    {
+        let actions = Planner::new(&output_config, &system, formatter.extension())?.notes()?;
+        existing::confirm(
+            &LocalFs,
+            &output_config.base_folder,
+            &actions,
+            args.prompt(),
+        )?;
+    }
+    let mut report = Report::new(&args.config_file, started);
+    let changes = generate(&output_config, &system, args, &formatter, None, &mut report)?
+        + generate_targets(&targets, &system, args, formatter, None, &mut report)?;
+    if args.preview == flags::Preview::Check && changes > 0 {
+        eprintln!("The folders don't match the config, {changes} changes are needed");
+        return Ok(exit_code(changes));
+    }
+    if let Some(path) = &args.plan_out {
+        plan::Plan::from_report(&report, config_hash).write(path)?;
+    } else if let (Some(report_config), false) = (&report_config, args.preview.is_dry_run()) {
+        write_report(
+            report_config,
+            &system,
+            &definition,
+            &naming,
+            limits,
+            &mut report,
+        )?;
+    }
+    // Only a checked dry run reports what would change
+    if args.previewing() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    history::record(
+        &args.config_file,
+        output_config.state_folder.as_deref(),
+        &system,
+        started,
+    )?;
+    Ok(exit_code(changes))
+}
+
+/// Generate the notes and the reference archive, returning how many changes the folders need.
+///
+/// With `planned`, only the actions of a reviewed plan are applied.
+fn generate(
+    output_config: &config::Output,
+    system: &System,
+    args: &Arguments,
+    formatter: &MdFormatter<'_>,
+    planned: Option<&plan::PlannedActions>,
+    report: &mut Report,
+) -> Result<usize, Error> {
+    // The archive has the same folders under another base, so checking the notes covers both
+    notes::check_collisions(&Planner::new(output_config, system, formatter.extension())?.notes()?)?;
+    let notes = generate_notes(output_config, system, args, formatter, planned, report)?;
+    // A workspace keeps the reference archive in the tree of the notes, so there is nothing else
+    if system.is_workspace() {
+        return Ok(notes);
+    }
+    Ok(notes + generate_archive(output_config, system, args, formatter, planned, report)?)
+}
+
+/// Point `formatter` at the notes of `output_config`, counting them when the index asks for it
+fn retarget<'hbar>(
+    formatter: MdFormatter<'hbar>,
+    output_config: &config::Output,
+    system: &System,
+) -> Result<MdFormatter<'hbar>, Error> {
+    let formatter = formatter
+        .link_root(&output_config.base_folder)
+        .attachments(output_config.attachments.as_deref());
+    // Counting reads the whole vault, so it is only done when the index asks for it
+    let counts = if output_config.note_counts {
+        stats::note_counts(
+            &LocalFs,
+            &output_config.base_folder,
+            system,
+            &Planner::new(output_config, system, formatter.extension())?.notes()?,
+            formatter.extension(),
+        )?
+    } else {
+        BTreeMap::new()
+    };
+    Ok(formatter.note_counts(counts))
+}
+
+/// Generate into every target of the config file after the main folders, returning how many
+/// changes they need altogether
+fn generate_targets(
+    targets: &[config::Output],
+    system: &System,
+    args: &Arguments,
+    mut formatter: MdFormatter<'_>,
+    planned: Option<&plan::PlannedActions>,
+    report: &mut Report,
+) -> Result<usize, Error> {
+    let mut changes = 0;
+    for target in targets {
+        formatter = retarget(formatter, target, system)?;
+        changes += generate(target, system, args, &formatter, planned, report)?;
+    }
+    Ok(changes)
+}
+
+/// Write the report of a generation, along with the warnings about the system
+fn write_report(
+    report_config: &report::ReportConfig,
+    system: &System,
+    definition: &str,
+    rules: &naming::NamingConfig,
+    limits: validate::LimitsConfig,
+    report: &mut Report,
+) -> Result<(), Error> {
+    report.warnings = validate::validate(system, definition, rules, limits)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let path = report.write(report_config)?;
+    write!(
+        anstream::stdout(),
+        "{}",
+        output::line(Mark::Applied, "Wrote report", &output::path(&path))
+    )?;
+    Ok(())
+}
+
+/// Generate the Johnny Decimal notes folder structure for a markdown based note taking system like
+/// logseq or obsidian.
+fn generate_notes(
+    output_config: &config::Output,
+    system: &System,
+    args: &Arguments,
+    formatter: &MdFormatter<'_>,
+    planned: Option<&plan::PlannedActions>,
+    report: &mut Report,
+) -> Result<usize, Error> {
+    writeln!(args.progress(), "{}", output::heading("Notes Folders"))?;
+    let mut actions = args.planner(output_config, system, formatter)?.notes()?;
+    if let Some(planned) = planned {
+        actions.retain(|action| planned.contains(action));
+    }
+    apply_actions(
+        output_config,
+        system,
+        &actions,
+        true,
+        args,
+        formatter,
+        report,
+    )
+}
+
+/// Bring the notes and the reference archive back in line with the system where they drifted
+/// from it, asking first, or only listing the fixes for a dry run.
+///
+/// Without `fix` only the conflicted copies are looked at, which `clean_conflicts` moves away.
+fn fix_drift(
+    output_config: &config::Output,
+    system: &System,
+    args: &Arguments,
+    formatter: &MdFormatter<'_>,
+    fix: bool,
+    clean_conflicts: bool,
+) -> Result<(), Error> {
+    let subtree = args.subtree(system)?;
+    let planner = args.planner(output_config, system, formatter)?;
+    let mut fixes = doctor::plan_fixes(
+        &LocalFs,
+        &output_config.base_folder,
+        system,
+        &planner.notes()?,
+        formatter.extension(),
+        true,
+        subtree.as_deref(),
+    )?;
+    // A workspace has no reference archive of its own
+    if !system.is_workspace() {
+        fixes.extend(doctor::plan_fixes(
+            &LocalFs,
+            &output_config.reference_folder,
+            system,
+            &planner.archive()?,
+            formatter.extension(),
+            false,
+            subtree.as_deref(),
+        )?);
+    }
+    // Cleaning conflicts on its own leaves the rest of the drift alone
+    if !fix {
+        fixes.retain(|fix| matches!(fix, doctor::Fix::Conflict(..)));
+    }
+    doctor::fix(
+        &mut LocalFs,
+        &fixes,
+        args.preview.is_dry_run(),
+        clean_conflicts,
+        args.prompt(),
+    )
+}
+
+/// Generate the reference archive folder structure.
+fn generate_archive(
+    output_config: &config::Output,
+    system: &System,
+    args: &Arguments,
+    formatter: &MdFormatter<'_>,
+    planned: Option<&plan::PlannedActions>,
+    report: &mut Report,
+) -> Result<usize, Error> {
+    writeln!(
+        args.progress(),
+        "\n{}",
+        output::heading("Reference Archive")
+    )?;
+    let mut actions = args.planner(output_config, system, formatter)?.archive()?;
+    if let Some(planned) = planned {
+        actions.retain(|action| planned.contains(action));
+    }
+    apply_actions(
+        output_config,
+        system,
+        &actions,
+        false,
+        args,
+        formatter,
+        report,
+    )
+}
+
+/// Whether the index or map of content at `path` was edited since `written` says it was last
+/// written, like on another device of a synced vault, printing that it is left alone when it was
+fn edited_elsewhere(
+    path: &Path,
+    backend: &dyn OutputBackend,
+    written: &written::Written,
+    stdout: &mut impl io::Write,
+) -> Result<bool, Error> {
+    let edited = notes::find_existing(path, backend)
+        .and_then(|existing| backend.read_to_string(&existing).ok())
+        .is_some_and(|existing| written.edited(path, &existing));
+    if edited {
+        write!(
+            stdout,
+            "{}",
+            output::line(
+                Mark::Skipped,
+                "Left Alone",
+                &format!(
+                    "{}, edited since it was last written, maybe on another device. --force overwrites it",
+                    output::path(path)
+                )
+            )
+        )?;
+    }
+    Ok(edited)
+}
+
+/// Apply the actions that are needed, recording what was done, or just print them for a dry run.
+///
+/// A dry run also lists what would be renamed or deleted to make `base_folder` match the system,
+/// unless only what is missing is created or only the indexes are rewritten.
+/// Returns how many changes the folder needs, where `files` says whether the folder is the notes
+/// or the reference archive of `output_config`.
+///
+/// An index or map of content that was edited since it was last written, like on another device
+/// of a synced vault, is only overwritten with --force.
+fn apply_actions(
+    output_config: &config::Output,
+    system: &System,
+    actions: &[notes::Action<'_>],
+    files: bool,
+    args: &Arguments,
+    formatter: &MdFormatter<'_>,
+    report: &mut Report,
+) -> Result<usize, Error> {
+    let base_folder = if files {
+        &output_config.base_folder
+    } else {
+        &output_config.reference_folder
+    };
+    let state_folder = output_config.state_folder.as_deref();
+    let mut written = written::Written::load(&args.config_file, state_folder)?;
+    let mut stdout = args.progress();
+    let mut local = LocalFs;
+    // A dry run still renders everything, but only records what would be written
+    let mut recorder = Recorder::new(&LocalFs);
+    let backend: &mut dyn OutputBackend = if args.previewing() {
+        &mut recorder
+    } else {
+        &mut local
+    };
+    // Renaming and deleting aren't creating, so they aren't even listed when only creating
+    let reconcile = if args.previewing() && args.scope == flags::Scope::Everything {
+        reconcile::plan(
+            backend,
+            base_folder,
+            system,
+            actions,
+            formatter.extension(),
+            files,
+            args.subtree(system)?.as_deref(),
+        )?
+        .into_iter()
+        // Generating leaves conflicted copies alone, `doctor` is what lists them
+        .filter(|change| !matches!(change, reconcile::Change::Conflict(_)))
+        .collect()
+    } else {
+        Vec::new()
+    };
+    // A path that something would be renamed to doesn't also need creating
+    let renamed: HashSet<&Path> = reconcile
+        .iter()
+        .filter_map(reconcile::Change::renamed_to)
+        .collect();
+    let ignore = IgnoreRules::load(backend, &notes::expand(base_folder)?)?;
+    let mut changes = reconcile.len();
+    let mut applied: usize = 0;
+    for action in actions
+        .iter()
+        .filter(|action| !renamed.contains(action.path().as_path()))
+    {
+        if action.is_ignored(&ignore) {
+            report.skipped(action);
+            continue;
+        }
+        if notes::changes_structure(action, backend) {
+            changes = changes.saturating_add(1);
+        }
+        if !notes::need_to_apply(action, backend)
+            || (args.scope == flags::Scope::CreateOnly
+                && !notes::changes_structure(action, backend))
+        {
+            report.skipped(action);
+            continue;
+        }
+        let rewrites = matches!(
+            action,
+            notes::Action::WriteIndex(..) | notes::Action::WriteMoc(..)
+        );
+        let path = action.path();
+        if rewrites && !args.force && edited_elsewhere(path, backend, &written, &mut stdout)? {
+            report.skipped(action);
+            continue;
+        }
+        write!(stdout, "{}", action.describe(backend, args.previewing()))?;
+        action.execute(formatter, backend)?;
+        report.created(action);
+        applied = applied.saturating_add(1);
+        if rewrites {
+            if let Ok(contents) = backend.read_to_string(path) {
+                written.record(path, &contents);
+            }
+        }
+    }
+    for change in &reconcile {
+        write!(stdout, "{}", change.dry_run())?;
+    }
+    if !args.previewing() {
+        written.save(&args.config_file, state_folder)?;
+        let done = if applied == 0 {
+            "Nothing to do in".to_owned()
+        } else {
+            format!("Applied {applied} actions to")
+        };
+        write!(
+            stdout,
+            "{}",
+            output::line(
+                Mark::Applied,
+                &done,
+                &output::path(&notes::expand(base_folder)?)
+            )
+        )?;
+    }
+    Ok(changes)
+}
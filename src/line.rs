@@ -1,6 +1,13 @@
 use color_eyre::eyre::{Error, OptionExt};
 
-use crate::model::FolderKind;
+use core::fmt::Write as _;
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    config::is_comment,
+    jid::JohnnyId,
+    model::{FolderKind, Status, System},
+};
 
 /// The different kinds of lines that can be parsed
 #[derive(Debug)]
@@ -15,12 +22,218 @@ pub enum ParsedKind<'topic> {
     ExtendedFolder(&'topic str, FolderKind, &'topic str),
 }
 
+/// Attributes given to an entry with trailing `[name]` or `[name=value]` tags
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Attributes<'line> {
+    /// The tags in the order they were written
+    tags: Vec<(&'line str, Option<&'line str>)>,
+}
+
+impl Attributes<'_> {
+    /// Whether the tag `name` was given
+    pub fn has(&self, name: &str) -> bool {
+        self.tags.iter().any(|(tag, _)| *tag == name)
+    }
+
+    /// The values of every `[name=value]` tag with the given name, in the order they were written
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.tags
+            .iter()
+            .filter(|(tag, _)| *tag == name)
+            .filter_map(|(_, value)| *value)
+            .collect()
+    }
+
+    /// The kind given with a `[folder]`, `[file]`, `[both]`, or `[index]` tag, which takes the
+    /// place of a directive at the start of the topic, or with a `[link=id]` tag
+    pub fn kind(&self) -> Option<FolderKind> {
+        if let Some(target) = self.values("link").first() {
+            return Some(FolderKind::Link(Arc::from(*target)));
+        }
+        self.tags
+            .iter()
+            .rev()
+            .filter(|(_, value)| value.is_none())
+            .find_map(|(tag, _)| FolderKind::from_tag(tag))
+    }
+}
+
+/// Split the trailing `[name]` or `[name=value]` tags off of a line, keeping its indentation
+pub fn split_attributes(line: &str) -> (&str, Attributes<'_>) {
+    let mut rest = line.trim_end();
+    let mut tags = Vec::new();
+    // `]]` is the end of a wikilink in a topic, not a tag
+    while rest.ends_with(']') && !rest.ends_with("]]") {
+        let Some(open) = rest.rfind('[') else {
+            break;
+        };
+        let tag = &rest[open.saturating_add(1)..rest.len().saturating_sub(1)];
+        let (name, value) = tag
+            .split_once('=')
+            .map_or((tag, None), |(name, value)| (name, Some(value.trim())));
+        tags.push((name.trim(), value));
+        rest = rest[..open].trim_end();
+    }
+    tags.reverse();
+    (rest, Attributes { tags })
+}
+
+/// `line` with the tag `tag`, like `frozen` or `reviewed=2025-01-01`, after the other tags, in
+/// place of any tag with the same name
+pub fn with_tag(line: &str, tag: &str) -> String {
+    let name = tag.split_once('=').map_or(tag, |(name, _)| name);
+    let (rest, attributes) = split_attributes(line);
+    let mut tagged = rest.to_owned();
+    for (other, value) in attributes.tags.iter().filter(|(other, _)| *other != name) {
+        match value {
+            Some(value) => {
+                let _ = write!(tagged, " [{other}={value}]");
+            }
+            None => {
+                let _ = write!(tagged, " [{other}]");
+            }
+        }
+    }
+    let _ = write!(tagged, " [{tag}]");
+    tagged
+}
+
+/// The names of the months, for `{month}` in a counted range
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// The width of the indentation at the start of a line
+fn indent_of(line: &str) -> usize {
+    line.len().saturating_sub(line.trim_start().len())
+}
+
+/// Parse the `01..12` at the start of an entry into its first and last number
+fn parse_range(trimmed: &str) -> Option<(u8, u8, &str)> {
+    let (range, topic) = trimmed.split_once(' ').unwrap_or((trimmed, ""));
+    let (first, last) = range.split_once("..")?;
+    Some((first.parse().ok()?, last.parse().ok()?, topic))
+}
+
+/// Fill in `{n}` and `{month}` for one number of a counted range
+#[expect(
+    clippy::literal_string_with_formatting_args,
+    reason = "These are the placeholders of a counted range, not format arguments"
+)]
+fn fill_range(line: &str, number: u8, line_no: usize) -> Result<String, Error> {
+    let mut filled = line.replace("{n}", &format!("{number:02}"));
+    if filled.contains("{month}") {
+        let month = MONTHS
+            .get(usize::from(number).wrapping_sub(1))
+            .ok_or_eyre(format!(
+                "{number} isn't a month, so line {line_no} can't use {{month}}"
+            ))?;
+        filled = filled.replace("{month}", month);
+    }
+    Ok(filled)
+}
+
+/// Expand every counted range like `01..12 Monthly Reports {month}` into one entry per number,
+/// along with a copy of the entries under it for each.
+///
+/// `{n}` in the topic is replaced with the number and `{month}` with the name of the month.
+pub fn expand_ranges(config: &str) -> Result<String, Error> {
+    let mut expanded = String::new();
+    let lines: Vec<&str> = config.lines().collect();
+    let mut index = 0;
+    while let Some(&line) = lines.get(index) {
+        let line_no = index.saturating_add(1);
+        index = line_no;
+        let Some((first, last, topic)) = parse_range(line.trim()) else {
+            let _ = writeln!(expanded, "{line}");
+            continue;
+        };
+        if first > last {
+            return Err(Error::msg(format!(
+                "The range {first:02}..{last:02} on line {line_no} counts backwards"
+            )));
+        }
+        let indent = &line[..indent_of(line)];
+        // Everything indented under the range is repeated for each number
+        let block: Vec<&str> = lines
+            .get(index..)
+            .unwrap_or_default()
+            .iter()
+            .take_while(|child| child.trim().is_empty() || indent_of(child) > indent.len())
+            .copied()
+            .collect();
+        index = index.saturating_add(block.len());
+        for number in first..=last {
+            let _ = writeln!(
+                expanded,
+                "{indent}{number:02} {}",
+                fill_range(topic, number, line_no)?
+            );
+            for child in &block {
+                let _ = writeln!(expanded, "{}", fill_range(child, number, line_no)?);
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+/// The start of the directive that expands a template under an entry, like `@template(project)`
+const TEMPLATE_DIRECTIVE: &str = "@template(";
+
+/// Replace every `@template(name)` directive with the lines of the named template, indented one
+/// level deeper than the entry that uses it
+pub fn expand_templates(
+    config: &str,
+    templates: &BTreeMap<String, String>,
+) -> Result<String, Error> {
+    let mut expanded = String::new();
+    for (line_no, line) in config.lines().enumerate() {
+        let Some(start) = line.find(TEMPLATE_DIRECTIVE) else {
+            let _ = writeln!(expanded, "{line}");
+            continue;
+        };
+        let after = &line[start.saturating_add(TEMPLATE_DIRECTIVE.len())..];
+        let (name, rest) = after.split_once(')').ok_or_eyre(format!(
+            "The template on line {} is missing its closing `)`",
+            line_no.saturating_add(1)
+        ))?;
+        let template = templates.get(name.trim()).ok_or_eyre(format!(
+            "Unknown template {} on line {}",
+            name.trim(),
+            line_no.saturating_add(1)
+        ))?;
+        if template.contains(TEMPLATE_DIRECTIVE) {
+            return Err(Error::msg(format!(
+                "The template {} can't use another template",
+                name.trim()
+            )));
+        }
+        let _ = writeln!(expanded, "{}{rest}", line[..start].trim_end());
+        let indent = &line[..indent_of(line)];
+        for template_line in template.lines().filter(|line| !line.trim().is_empty()) {
+            let _ = writeln!(expanded, "{indent}\t{template_line}");
+        }
+    }
+    Ok(expanded)
+}
+
 /// Parses a generic line which could be a category, system, or folder
 fn parse_entry(_line_no: usize, trimmed: &str) -> Result<(u8, &str, Option<FolderKind>), Error> {
     let mut parts = trimmed.splitn(2, ' ');
     let id = parts.next().ok_or_eyre("no id found")?;
     let rest = parts.next().unwrap_or_default();
-    let style = rest.chars().next().map(FolderKind::from_char);
+    let style = (!rest.is_empty()).then(|| FolderKind::from_topic(rest));
     let parsed_id = id.parse()?;
     Ok((parsed_id, rest, style))
 }
@@ -42,11 +255,7 @@ fn parse_extended_folder(_line_no: usize, line: &str) -> Result<(&str, FolderKin
     let mut parts = line.splitn(2, ' ');
     let id = parts.next().ok_or_eyre("no id found")?;
     let rest = parts.next().unwrap_or_default();
-    let style = rest
-        .chars()
-        .next()
-        .map(FolderKind::from_char)
-        .unwrap_or_default();
+    let style = FolderKind::from_topic(rest);
 
     Ok((id, style, rest))
 }
@@ -83,3 +292,394 @@ pub fn parse_single(line_no: usize, line: &str) -> Result<ParsedKind<'_>, Error>
         ))),
     }
 }
+
+/// The trailing tags for an entry
+fn format_attributes(
+    retired: bool,
+    frozen: bool,
+    locations: &[Arc<str>],
+    status: Option<Status>,
+    reviewed: Option<&str>,
+    index_name: Option<&str>,
+) -> String {
+    let mut attributes = String::new();
+    if retired {
+        attributes.push_str(" [retired]");
+    }
+    if frozen {
+        attributes.push_str(" [frozen]");
+    }
+    for location in locations {
+        let _ = write!(attributes, " [location={location}]");
+    }
+    if let Some(status) = status {
+        let _ = write!(attributes, " [status={}]", status.tag());
+    }
+    if let Some(reviewed) = reviewed {
+        let _ = write!(attributes, " [reviewed={reviewed}]");
+    }
+    if let Some(index_name) = index_name {
+        let _ = write!(attributes, " [index={index_name}]");
+    }
+    attributes
+}
+
+/// Write a single folder or extended folder entry at the given indentation
+fn format_entry(
+    indent: &str,
+    id: &str,
+    kind: &FolderKind,
+    topic: &str,
+    attributes: &str,
+    default: &FolderKind,
+) -> String {
+    if let FolderKind::Link(target) = kind {
+        return format!("{indent}{id} {topic} [link={target}]{attributes}\n");
+    }
+    // A topic that itself starts with a directive needs the kind as a tag to be read back the same,
+    // and so does a plain folder when the config gives folders another kind by default
+    let starts_with_directive = topic
+        .chars()
+        .next()
+        .is_some_and(|first| FolderKind::from_char(first).directive().is_some());
+    if starts_with_directive || (kind.is_folder() && !default.is_folder()) {
+        return format!("{indent}{id} {topic} [{}]{attributes}\n", kind.tag());
+    }
+    let directive = kind.directive().map(String::from).unwrap_or_default();
+    format!("{indent}{id} {directive}{topic}{attributes}\n")
+}
+
+/// A single line of the configuration along with the entry it defines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormattedLine {
+    /// The full ids from the area down to the entry, so sorting them sorts the lines
+    pub path: Vec<String>,
+    /// The line, indented and with its newline
+    pub line: String,
+}
+
+impl FormattedLine {
+    /// The full id of the entry
+    pub fn id(&self) -> &str {
+        self.path.last().map_or("", String::as_str)
+    }
+
+    /// The full id of the entry this one is under, when it isn't an area
+    pub fn parent(&self) -> Option<&str> {
+        self.path.iter().rev().nth(1).map(String::as_str)
+    }
+}
+
+/// Format every entry of a system into the line that `parse_single` reads, in order
+pub fn format_lines(system: &System) -> Vec<FormattedLine> {
+    let mut lines = Vec::new();
+    for area in &system.areas {
+        let (start, end) = area.id_range;
+        let area_path = vec![area.id.by_seperator(".")];
+        lines.push(FormattedLine {
+            path: area_path.clone(),
+            line: format!(
+                "{start:02}-{end:02} {}{}\n",
+                area.topic,
+                format_attributes(false, false, &[], None, area.reviewed.as_deref(), None)
+            ),
+        });
+        for category in &area.categories {
+            let mut category_path = area_path.clone();
+            category_path.push(category.id.by_seperator("."));
+            if let Some(category_id) = &category.id.category {
+                lines.push(FormattedLine {
+                    path: category_path.clone(),
+                    line: format!(
+                        "\t{category_id} {}{}\n",
+                        category.topic,
+                        format_attributes(
+                            category.retired,
+                            category.frozen,
+                            &category.locations,
+                            category.status,
+                            category.reviewed.as_deref(),
+                            None
+                        )
+                    ),
+                });
+            }
+            for folder in &category.folders {
+                let mut folder_path = category_path.clone();
+                folder_path.push(folder.id.by_seperator("."));
+                if let Some(folder_id) = &folder.id.folder {
+                    lines.push(FormattedLine {
+                        path: folder_path.clone(),
+                        line: format_entry(
+                            "\t\t",
+                            &folder_id.to_string(),
+                            &folder.kind,
+                            &folder.topic,
+                            &format_attributes(
+                                folder.retired,
+                                folder.frozen,
+                                &folder.locations,
+                                folder.status,
+                                None,
+                                folder.index_name.as_deref(),
+                            ),
+                            &system.default_folder_kind,
+                        ),
+                    });
+                }
+                for xfolder in &folder.folders {
+                    if let Some(xfolder_id) = &xfolder.id.xfolder {
+                        let mut xfolder_path = folder_path.clone();
+                        xfolder_path.push(xfolder.id.by_seperator("."));
+                        lines.push(FormattedLine {
+                            path: xfolder_path,
+                            line: format_entry(
+                                "\t\t\t",
+                                &xfolder_id.to_string(),
+                                &xfolder.kind,
+                                &xfolder.topic,
+                                &format_attributes(
+                                    xfolder.retired,
+                                    xfolder.frozen,
+                                    &xfolder.locations,
+                                    xfolder.status,
+                                    None,
+                                    None,
+                                ),
+                                &system
+                                    .xfolder_kind
+                                    .kind(&folder.kind, &system.default_folder_kind),
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// Format a system back into the line based configuration that `parse_single` reads
+pub fn format_system(system: &System) -> String {
+    format_lines(system)
+        .into_iter()
+        .map(|formatted| formatted.line)
+        .collect()
+}
+
+/// The id a line of the definition at the indentation `depth` gives its entry, written the way
+/// `level_ids` writes them, or nothing for a line like a counted range that isn't one entry
+fn line_id(depth: usize, token: &str) -> Option<String> {
+    match depth {
+        0 => {
+            let (start, end) = token.split_once('-')?;
+            Some(format!(
+                "{:02}-{:02}",
+                start.parse::<u8>().ok()?,
+                end.parse::<u8>().ok()?
+            ))
+        }
+        1 | 2 => Some(format!("{:02}", token.parse::<u8>().ok()?)),
+        3 => Some(token.to_owned()),
+        _ => None,
+    }
+}
+
+/// The ids of every level of `id` from its area down, one for each indentation of the definition
+fn level_ids(id: &JohnnyId) -> Vec<String> {
+    let mut ids = Vec::new();
+    if let Some(area) = &id.area {
+        ids.push(format!("{:02}-{:02}", area.start, area.end));
+    }
+    if let Some(category) = &id.category {
+        ids.push(format!("{:02}", category.id));
+    }
+    if let Some(folder) = &id.folder {
+        ids.push(format!("{:02}", folder.id));
+    }
+    if let Some(xfolder) = &id.xfolder {
+        ids.push(xfolder.id.to_string());
+    }
+    ids
+}
+
+/// The number of the line of `lines` that defines the entry `id`, which an entry that comes from
+/// a counted range or a template doesn't have
+fn entry_line(lines: &[&str], id: &JohnnyId) -> Option<usize> {
+    let wanted = level_ids(id);
+    let mut levels: Vec<Option<String>> = Vec::new();
+    for (line_no, line) in lines.iter().enumerate() {
+        if is_comment(line) {
+            continue;
+        }
+        let depth = indent_of(line);
+        let token = line.trim().split(' ').next().unwrap_or_default();
+        levels.resize(depth, None);
+        levels.push(line_id(depth, token));
+        if levels
+            .iter()
+            .map(Option::as_deref)
+            .eq(wanted.iter().map(|id| Some(id.as_str())))
+        {
+            return Some(line_no);
+        }
+    }
+    None
+}
+
+/// Put `lines` back together, ending with a newline when `definition` did
+fn join_lines(lines: &[String], definition: &str) -> String {
+    let mut joined = lines.join("\n");
+    if definition.ends_with('\n') {
+        joined.push('\n');
+    }
+    joined
+}
+
+/// Change the line of `definition` that defines the entry `id` with `edit`, leaving every other
+/// line, comments, counted ranges, and templates included, as it was written
+pub fn edit_entry(
+    definition: &str,
+    id: &JohnnyId,
+    edit: impl FnOnce(&str) -> String,
+) -> Result<String, Error> {
+    let lines: Vec<&str> = definition.lines().collect();
+    let line_no = entry_line(&lines, id).ok_or_eyre(format!(
+        "{} comes from a counted range or a template, so it has no line of its own to change",
+        id.by_seperator(".")
+    ))?;
+    let mut edited: Vec<String> = lines.iter().map(|line| (*line).to_owned()).collect();
+    if let Some(line) = edited.get_mut(line_no) {
+        *line = edit(line);
+    }
+    Ok(join_lines(&edited, definition))
+}
+
+/// Add `line`, the line of a new entry under the entry `parent`, to `definition` among the other
+/// entries under `parent` in the order of their ids, leaving every other line as it was written
+pub fn insert_entry(definition: &str, parent: &JohnnyId, line: &str) -> Result<String, Error> {
+    let lines: Vec<&str> = definition.lines().collect();
+    let parent_no = entry_line(&lines, parent).ok_or_eyre(format!(
+        "{} comes from a counted range or a template, so nothing can be added under it",
+        parent.by_seperator(".")
+    ))?;
+    let line = line.trim_end();
+    let depth = indent_of(line);
+    let first = |line: &str| line.trim().split(' ').next().unwrap_or_default().to_owned();
+    let id = line_id(depth, &first(line));
+    // Right after the entry before it and everything under that, ahead of any comment on the next
+    let mut position = parent_no.saturating_add(1);
+    for (line_no, other) in lines.iter().enumerate().skip(position) {
+        if is_comment(other) {
+            continue;
+        }
+        let other_depth = indent_of(other);
+        if other_depth < depth || (other_depth == depth && line_id(depth, &first(other)) > id) {
+            break;
+        }
+        position = line_no.saturating_add(1);
+    }
+    let mut inserted: Vec<String> = lines.iter().map(|line| (*line).to_owned()).collect();
+    inserted.insert(position, line.to_owned());
+    Ok(join_lines(&inserted, definition))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_attributes() {
+        let (line, attributes) = split_attributes("\t\t01 Inbox [retired] [location=paper]");
+        assert_eq!(line, "\t\t01 Inbox");
+        assert!(attributes.has("retired"));
+        assert_eq!(attributes.values("location"), vec!["paper"]);
+        let (line, attributes) = split_attributes("\t\t01 [[Inbox]]");
+        assert_eq!(line, "\t\t01 [[Inbox]]");
+        assert_eq!(attributes, Attributes::default());
+        assert_eq!(
+            with_tag(
+                "\t21 Tax [reviewed=2024-06-01] [status=active]",
+                "reviewed=2025-02-03"
+            ),
+            "\t21 Tax [status=active] [reviewed=2025-02-03]"
+        );
+        assert_eq!(
+            with_tag("\t\t01 [[Inbox]]", "frozen"),
+            "\t\t01 [[Inbox]] [frozen]"
+        );
+    }
+
+    #[test]
+    fn test_kind_tags() {
+        let (_, attributes) = split_attributes("\t\t01 Plans [both] [location=paper]");
+        assert!(matches!(attributes.kind(), Some(FolderKind::Both)));
+        // Naming the index file isn't the same as making the entry an index
+        let (_, attributes) = split_attributes("\t\t00 JDex [index=_index]");
+        assert!(attributes.kind().is_none());
+        let (_, attributes) = split_attributes("\t\t03 Tax Returns [link=21.05]");
+        assert_eq!(attributes.kind().unwrap().link_target(), Some("21.05"));
+        assert_eq!(
+            format_entry(
+                "\t\t",
+                "01",
+                &FolderKind::Folder,
+                "-5 Degrees",
+                "",
+                &FolderKind::Folder
+            ),
+            "\t\t01 -5 Degrees [folder]\n"
+        );
+        assert_eq!(
+            format_entry(
+                "\t\t",
+                "02",
+                &FolderKind::File,
+                "Plans",
+                " [retired]",
+                &FolderKind::Folder
+            ),
+            "\t\t02 -Plans [retired]\n"
+        );
+        assert_eq!(
+            format_entry(
+                "\t\t",
+                "03",
+                &FolderKind::Folder,
+                "Inbox",
+                "",
+                &FolderKind::Both
+            ),
+            "\t\t03 Inbox [folder]\n"
+        );
+    }
+
+    #[test]
+    fn test_expand_templates() {
+        let templates =
+            BTreeMap::from([("project".to_owned(), "01 Admin\n02 Assets\n\n".to_owned())]);
+        let expanded = expand_templates(
+            "20-29 Work\n\t21 Clients\n\t\t05 New Client @template(project) [retired]\n",
+            &templates,
+        )
+        .unwrap();
+        assert_eq!(
+            expanded,
+            "20-29 Work\n\t21 Clients\n\t\t05 New Client [retired]\n\t\t\t01 Admin\n\t\t\t02 Assets\n"
+        );
+        assert!(expand_templates("20-29 Work @template(missing)", &templates).is_err());
+    }
+
+    #[test]
+    fn test_expand_ranges() {
+        let expanded =
+            expand_ranges("\t21 Reports\n\t\t01..03 {month} {n}\n\t\t\tX1 Draft {n}\n\t22 Bank\n")
+                .unwrap();
+        assert_eq!(
+            expanded,
+            "\t21 Reports\n\t\t01 January 01\n\t\t\tX1 Draft 01\n\t\t02 February 02\n\t\t\tX1 Draft 02\n\t\t03 March 03\n\t\t\tX1 Draft 03\n\t22 Bank\n"
+        );
+        assert!(expand_ranges("\t\t11..13 {month}\n").is_err());
+        assert!(expand_ranges("\t\t05..01 Backwards\n").is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::Path;
+#[cfg(any(windows, target_os = "macos"))]
+use std::process::Command;
+
+use color_eyre::eyre::Error;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The name of the link to the reference archive in each folder of the notes
+pub const LINK_NAME: &str = "Reference";
+
+/// How the folders of the notes are linked to their folders in the reference archive
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStyle {
+    #[default]
+    /// Don't link the folders
+    None,
+    /// A symbolic link, which works everywhere
+    Symlink,
+    /// An NTFS junction, on Windows
+    Junction,
+    /// A Finder alias, on macOS
+    Alias,
+}
+
+impl Display for LinkStyle {
+    #[allow(
+        clippy::min_ident_chars,
+        reason = "This is the preferred default name for the variable"
+    )]
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Symlink => write!(f, "symlink"),
+            Self::Junction => write!(f, "junction"),
+            Self::Alias => write!(f, "alias"),
+        }
+    }
+}
+
+/// Run a command, failing when it doesn't succeed
+#[cfg(any(windows, target_os = "macos"))]
+fn run(command: &mut Command) -> Result<(), Error> {
+    let output = command.output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::msg(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ))
+    }
+}
+
+/// Create a symbolic link at `link` pointing at the directory `target`
+#[cfg(unix)]
+fn symlink(link: &Path, target: &Path) -> Result<(), Error> {
+    Ok(std::os::unix::fs::symlink(target, link)?)
+}
+
+/// Create a symbolic link at `link` pointing at the directory `target`
+#[cfg(windows)]
+fn symlink(link: &Path, target: &Path) -> Result<(), Error> {
+    Ok(std::os::windows::fs::symlink_dir(target, link)?)
+}
+
+/// Create an NTFS junction at `link` pointing at `target`
+#[cfg(windows)]
+fn junction(link: &Path, target: &Path) -> Result<(), Error> {
+    run(Command::new("cmd")
+        .args(["/C", "mklink", "/J"])
+        .arg(link)
+        .arg(target))
+}
+
+/// Junctions only exist on Windows
+#[cfg(not(windows))]
+fn junction(_link: &Path, _target: &Path) -> Result<(), Error> {
+    Err(Error::msg("junctions are only supported on Windows"))
+}
+
+/// Create a Finder alias at `link` pointing at `target`
+#[cfg(target_os = "macos")]
+fn alias(link: &Path, target: &Path) -> Result<(), Error> {
+    let quote = |path: &Path| {
+        path.display()
+            .to_string()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+    };
+    let folder = link.parent().unwrap_or(link);
+    let name = link
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    run(Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "tell application \"Finder\" to set made to make new alias file at (POSIX file \"{}\" as alias) to (POSIX file \"{}\" as alias)",
+            quote(folder),
+            quote(target)
+        ))
+        .arg("-e")
+        .arg(format!(
+            "tell application \"Finder\" to set name of made to \"{}\"",
+            name.replace('"', "\\\"")
+        )))
+}
+
+/// Finder aliases only exist on macOS
+#[cfg(not(target_os = "macos"))]
+fn alias(_link: &Path, _target: &Path) -> Result<(), Error> {
+    Err(Error::msg("Finder aliases are only supported on macOS"))
+}
+
+/// Create a link at `link` pointing at `target` in the given style.
+///
+/// When the style isn't supported on this platform, a symbolic link is created instead.
+pub fn create(link: &Path, target: &Path, style: LinkStyle) -> Result<(), Error> {
+    let created = match style {
+        LinkStyle::None => return Ok(()),
+        LinkStyle::Symlink => return symlink(link, target),
+        LinkStyle::Junction => junction(link, target),
+        LinkStyle::Alias => alias(link, target),
+    };
+    if let Err(err) = created {
+        eprintln!(
+            "Unable to create a {style} at {}, using a symlink instead: {err}",
+            link.display()
+        );
+        return symlink(link, target);
+    }
+    Ok(())
+}
@@ -1,58 +1,471 @@
 use color_eyre::eyre::Error;
-use handlebars::{handlebars_helper, Handlebars};
+use icu_collator::{Collator, CollatorOptions};
+use icu_locid::Locale;
+use std::{collections::BTreeMap, iter, path::PathBuf};
+
+use handlebars::{
+    handlebars_helper, Context, Handlebars, Helper, HelperDef, JsonValue, RenderContext,
+    RenderError, RenderErrorReason, ScopedJson,
+};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     jid::JohnnyId,
-    model::{Area, Category, Folder, FolderKind, System, XFolder},
+    label, layout,
+    model::{
+        Area, Category, Folder, FolderKind, FullId, HasFolderKind, HasJohnnyId, System, XFolder,
+    },
+    timestamp::Timestamp,
 };
 
+/// The output flavor, which picks the default templates, file extension, and link syntax
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Flavor {
+    #[default]
+    /// Markdown with wikilinks, for Obsidian and Logseq
+    Markdown,
+    /// Org-mode with `file:` links, for Emacs
+    Org,
+}
+
+/// The order the entries under each entry are listed in by the index
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    #[default]
+    /// By id, the way the system is defined
+    Id,
+    /// Alphabetically by topic, in the order of the configured locale
+    Topic,
+}
+
+/// The built in templates for a flavor
+struct DefaultTemplates {
+    /// Handlebar template for systems
+    system: &'static str,
+    /// Handlebar template for areas
+    area: &'static str,
+    /// Handlebar template for the map of content note of each area
+    area_moc: &'static str,
+    /// Handlebar template for categories
+    category: &'static str,
+    /// Handlebar template for folders
+    folder: &'static str,
+    /// Handlebar template for extended folders
+    xfolder: &'static str,
+    /// Handlebar template for new files
+    markdown: &'static str,
+}
+
+/// The default templates for markdown
+const MARKDOWN_TEMPLATES: DefaultTemplates = DefaultTemplates {
+    system: "---
+generated_at: {{generated_at}}
+jdexmd_version: {{jdexmd_version}}
+config_hash: {{config_hash}}
+---
+# {{name}}",
+    area: "## {{full_id id}} {{topic}}{{#if reviewed}} (reviewed {{reviewed}}){{/if}}",
+    area_moc: "# {{full_id id}} {{topic}}
+
+```dataview
+TABLE file.folder AS Folder
+WHERE jd-area = \"{{full_id id}}\"
+SORT file.name
+```
+
+## Categories
+
+{{#each categories}}{{#unless retired}}- {{#if frozen}}{{full_id id}} {{topic}}{{else}}{{link id topic}}{{/if}}
+{{/unless}}{{/each}}",
+    category: "- {{#if retired}}~~{{full_id id}} {{topic}}~~{{else}}{{full_id id}} {{topic}}{{#if reviewed}} (reviewed {{reviewed}}){{/if}}{{/if}}",
+    folder: "  - {{#if retired}}~~{{full_id id}} {{topic}}~~{{else if frozen}}{{full_id id}} {{topic}}{{else if target}}{{full_id id}} {{topic}} → {{link target.id target.topic}}{{else if (is_folder kind)}}{{full_id id}} {{topic}}{{else}}{{link id topic}}{{/if}}",
+    xfolder:
+        "    - {{#if retired}}~~{{full_id id}} {{topic}}~~{{else if frozen}}{{full_id id}} {{topic}}{{else if target}}{{full_id id}} {{topic}} → {{link target.id target.topic}}{{else if (is_folder kind)}}{{full_id id}} {{topic}}{{else}}{{link id topic}}{{/if}}",
+    markdown: "---
+tags: [johnny-decimal, Librarian]
+created: {{generated_at}}
+{{#if attachments}}attachments: {{attachments}}
+{{/if}}---",
+};
+
+/// The default templates for Org-mode
+const ORG_TEMPLATES: DefaultTemplates = DefaultTemplates {
+    system: "#+TITLE: {{name}}
+#+DATE: {{generated_at}}
+#+JDEXMD_VERSION: {{jdexmd_version}}
+#+JDEXMD_CONFIG_HASH: {{config_hash}}",
+    area: "* {{full_id id}} {{topic}}{{#if reviewed}} (reviewed {{reviewed}}){{/if}}",
+    area_moc: "#+TITLE: {{full_id id}} {{topic}}
+
+{{#each categories}}{{#unless retired}}- {{#if frozen}}{{full_id id}} {{topic}}{{else}}{{link id topic}}{{/if}}
+{{/unless}}{{/each}}",
+    category: "** {{#if retired}}+{{full_id id}} {{topic}}+{{else}}{{full_id id}} {{topic}}{{#if reviewed}} (reviewed {{reviewed}}){{/if}}{{/if}}",
+    folder: "  - {{#if retired}}+{{full_id id}} {{topic}}+{{else if frozen}}{{full_id id}} {{topic}}{{else if target}}{{full_id id}} {{topic}} → {{link target.id target.topic}}{{else if (is_folder kind)}}{{full_id id}} {{topic}}{{else}}{{link id topic}}{{/if}}",
+    xfolder:
+        "    - {{#if retired}}+{{full_id id}} {{topic}}+{{else if frozen}}{{full_id id}} {{topic}}{{else if target}}{{full_id id}} {{topic}} → {{link target.id target.topic}}{{else if (is_folder kind)}}{{full_id id}} {{topic}}{{else}}{{link id topic}}{{/if}}",
+    markdown: "#+FILETAGS: :johnny_decimal:librarian:
+#+DATE: {{generated_at}}{{#if attachments}}
+#+PROPERTY: DIR {{attachments}}{{/if}}",
+};
+
+impl Flavor {
+    /// The file extension for the files generated in this flavor
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Org => "org",
+        }
+    }
+
+    /// The templates used when the config file doesn't set one
+    const fn templates(self) -> &'static DefaultTemplates {
+        match self {
+            Self::Markdown => &MARKDOWN_TEMPLATES,
+            Self::Org => &ORG_TEMPLATES,
+        }
+    }
+}
+
 /// Handlebar template strings from the config file
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(default)]
 pub struct MdFormatConfig {
+    /// The output flavor
+    flavor: Flavor,
     /// Handlebar template for systems
-    system: String,
+    system: Option<String>,
     /// Handlebar template for areas
-    area: String,
+    area: Option<String>,
+    /// Handlebar template for the map of content note of each area
+    area_moc: Option<String>,
     /// Handlebar template for categories
-    category: String,
+    category: Option<String>,
     /// Handlebar template for folders
-    folder: String,
+    folder: Option<String>,
     /// Handlebar template for extended folders
-    xfolder: String,
+    xfolder: Option<String>,
     /// Handlebar template for new markdown files
-    markdown: String,
+    markdown: Option<String>,
+    /// The order of the entries under each entry of the index
+    order: Order,
+    /// The locale whose alphabet topics are sorted in, like `sv` or `de`, when ordered by topic
+    locale: Option<String>,
+    /// Fail on fields the templates use but don't have, instead of rendering them as empty
+    strict: bool,
+    /// Helpers written as rhai scripts, which get their arguments as `params` and `hash`
+    helpers: BTreeMap<String, String>,
+    /// Templates that replace the ones above within an area or category, keyed by its id like
+    /// `20-29` or `21`
+    overrides: BTreeMap<String, TemplateOverrides>,
 }
 
-impl Default for MdFormatConfig {
-    fn default() -> Self {
+impl MdFormatConfig {
+    /// The file extension for generated files, without building the templates
+    pub const fn extension(&self) -> &'static str {
+        self.flavor.extension()
+    }
+}
+
+/// Handlebar template strings that replace the ones of the format within an area or category
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(default)]
+pub struct TemplateOverrides {
+    /// Handlebar template for the area
+    area: Option<String>,
+    /// Handlebar template for the categories
+    category: Option<String>,
+    /// Handlebar template for the folders
+    folder: Option<String>,
+    /// Handlebar template for the extended folders
+    xfolder: Option<String>,
+}
+
+/// Describes the run that produced a file, so every template can include it
+#[derive(Debug, Clone, Serialize)]
+pub struct Generation {
+    /// When the generation started
+    pub generated_at: String,
+    /// The version of jdexmd that generated the file
+    pub jdexmd_version: &'static str,
+    /// The SHA-256 of the config file that defined the system
+    pub config_hash: String,
+}
+
+impl Generation {
+    /// Describe a generation started at `generated_at` from a config file with the given hash
+    pub fn new(generated_at: Timestamp, config_hash: String) -> Self {
         Self {
-            system: "# {{name}}".to_owned(),
-            area: "## {{full_id id}} {{topic}}".to_owned(),
-            category: "- {{full_id id}} {{topic}}".to_owned(),
-            folder: "  - {{#if (is_folder kind)}}{{full_id id}} {{topic}}{{else}}[[{{full_id id}} {{topic}}]]{{/if}}".to_owned(),
-            xfolder: "    - {{#if (is_folder kind)}}{{full_id id}} {{topic}}{{else}}[[{{full_id id}} {{topic}}]]{{/if}}".to_owned(),
-            markdown: "---
-tags: [johnny-decimal, Librarian]
----".to_owned(),
+            generated_at: generated_at.to_string(),
+            jdexmd_version: env!("CARGO_PKG_VERSION"),
+            config_hash,
         }
     }
 }
 
+/// Adds the details of the generation to the data a template is rendered with
+#[derive(Debug, Serialize)]
+struct WithGeneration<'data, T> {
+    #[serde(flatten)]
+    /// The data for the template
+    data: &'data T,
+    #[serde(flatten)]
+    /// The details of the generation
+    generation: &'data Generation,
+}
+
+/// The data the template for new files is rendered with
+#[derive(Debug, Serialize)]
+struct NewFile<'data, T> {
+    #[serde(flatten)]
+    /// The entry the file is created for, with the entries above it
+    entry: &'data T,
+    /// The full id and topic of the entry, like `L01.21.01 Returns`, to title the file with
+    title: String,
+    /// The full id and topic of each entry above it, starting with the system
+    breadcrumbs: Vec<String>,
+    #[serde(flatten)]
+    /// The details of the generation
+    generation: &'data Generation,
+    /// The name of the attachments folder inside each folder, if there is one
+    attachments: Option<&'data str>,
+}
+
+/// The folder or extended folder a new note is created for, with the entries above it
+#[derive(Debug, Clone, Copy)]
+pub enum NewNote<'sys> {
+    /// A folder written with `-` or `+`
+    Folder(&'sys Folder, Parents<'sys>),
+    /// An extended folder written with `-`
+    XFolder(&'sys XFolder, Parents<'sys>),
+}
+
 /// A markdown formatter for Johnny Decimal
 pub struct MdFormatter<'hbar> {
     /// The handlebars instance used to generate the markdown
     handlebars: Handlebars<'hbar>,
+    /// The output flavor
+    flavor: Flavor,
+    /// The details of the generation, available to every template
+    generation: Generation,
+    /// The name of the attachments folder inside each folder, for the template of new files
+    attachments: Option<String>,
+    /// Sorts the entries of the index by topic, when they aren't listed by id
+    collator: Option<Collator>,
+    /// The ids of the areas and categories that replace some of the templates
+    overrides: Vec<String>,
+    /// How many notes are in the directory of each folder and extended folder, by full id
+    note_counts: BTreeMap<String, usize>,
+}
+
+/// The system an entry is in, without everything under it
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SystemParent<'sys> {
+    /// The id of the system
+    id: &'sys JohnnyId,
+    /// The name of the system
+    name: &'sys str,
+    #[serde(skip)]
+    /// The whole system, to find the entries that links point at
+    entries: &'sys System,
+}
+
+/// An entry above the one being rendered, without everything under it
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Parent<'sys> {
+    /// The id of the entry
+    id: &'sys JohnnyId,
+    /// The topic of the entry
+    topic: &'sys str,
+    /// The range of ids, when the entry is an area
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_range: Option<(u8, u8)>,
+}
+
+/// The chain of entries above the one being rendered, so its template can refer to them, like
+/// `{{area.topic}}` in the template of a folder
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Parents<'sys> {
+    /// The system every entry is in
+    system: SystemParent<'sys>,
+    /// The area, for categories and everything under them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    area: Option<Parent<'sys>>,
+    /// The category, for folders and extended folders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<Parent<'sys>>,
+    /// The folder, for extended folders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    folder: Option<Parent<'sys>>,
+}
+
+impl<'sys> Parents<'sys> {
+    /// The parents of an area of `system`
+    pub const fn of(system: &'sys System) -> Self {
+        Self {
+            system: SystemParent {
+                id: &system.id,
+                name: system.name.as_str(),
+                entries: system,
+            },
+            area: None,
+            category: None,
+            folder: None,
+        }
+    }
+
+    /// The parents of the entries under `area`
+    pub fn area(self, area: &'sys Area) -> Self {
+        Self {
+            area: Some(Parent {
+                id: &area.id,
+                topic: &area.topic,
+                id_range: Some(area.id_range),
+            }),
+            ..self
+        }
+    }
+
+    /// The parents of the entries under `category`
+    pub fn category(self, category: &'sys Category) -> Self {
+        Self {
+            category: Some(Parent {
+                id: &category.id,
+                topic: &category.topic,
+                id_range: None,
+            }),
+            ..self
+        }
+    }
+
+    /// The parents of the entries under `folder`
+    pub fn folder(self, folder: &'sys Folder) -> Self {
+        Self {
+            folder: Some(Parent {
+                id: &folder.id,
+                topic: &folder.topic,
+                id_range: None,
+            }),
+            ..self
+        }
+    }
+
+    /// The full id and topic of each entry in the chain, starting with the system
+    fn breadcrumbs(self) -> Vec<String> {
+        let system = format!("{} {}", self.system.id.by_seperator("."), self.system.name);
+        iter::once(system)
+            .chain(
+                [self.area, self.category, self.folder]
+                    .into_iter()
+                    .flatten()
+                    .map(|parent| format!("{} {}", parent.id.by_seperator("."), parent.topic)),
+            )
+            .collect()
+    }
+
+    /// The ids of the category and the area in the chain, innermost first
+    fn scopes(self) -> impl Iterator<Item = &'sys JohnnyId> {
+        [self.category, self.area]
+            .into_iter()
+            .flatten()
+            .map(|parent| parent.id)
+    }
+
+    /// The entry that a link entry of this kind points at
+    fn target(self, kind: &FolderKind) -> Option<Parent<'sys>> {
+        let (entry, _) = label::entries_under(self.system.entries, kind.link_target()?)
+            .into_iter()
+            .next()?;
+        Some(Parent {
+            id: entry.jid(),
+            topic: entry.name(),
+            id_range: None,
+        })
+    }
 }
 
-/// Bind the area to the system id
+/// Where an entry is among the entries listed under the same parent
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Position {
+    /// How many entries are listed before it, counting from 0 like `@index`
+    index: usize,
+    /// Whether it is the last entry listed under its parent
+    is_last: bool,
+}
+
+impl Position {
+    /// The entry at `index` of `count` entries
+    const fn new(index: usize, count: usize) -> Self {
+        Self {
+            index,
+            is_last: index.saturating_add(1) == count,
+        }
+    }
+}
+
+/// Bind an entry to the entries above it
 #[derive(Debug, Serialize)]
-pub struct AreaWithParentId<'area> {
+struct WithParents<'sys, T> {
     #[serde(flatten)]
-    /// The area to format
-    area: &'area Area,
+    /// The entry to format
+    node: &'sys T,
     /// The id of the containing system
-    system_id: &'area str,
+    system_id: String,
+    #[serde(flatten)]
+    /// The entries above it
+    parents: Parents<'sys>,
+    /// The entry it points at, when it is a link
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<Parent<'sys>>,
+    /// How many entries are directly under it, when it is listed in an index
+    #[serde(skip_serializing_if = "Option::is_none")]
+    child_count: Option<usize>,
+    #[serde(flatten)]
+    /// Where it is among the entries under its parent, when it is listed in an index
+    position: Option<Position>,
+    /// How many notes are in its directory, when the notes are counted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note_count: Option<usize>,
+}
+
+impl<'sys, T> WithParents<'sys, T> {
+    /// Bind `node` to `parents`
+    fn new(node: &'sys T, parents: Parents<'sys>) -> Self {
+        Self {
+            node,
+            system_id: parents.system.id.by_seperator("."),
+            parents,
+            target: None,
+            child_count: None,
+            position: None,
+            note_count: None,
+        }
+    }
+
+    /// List the entry at `position` under its parent, with `child_count` entries under it
+    const fn at(mut self, position: Position, child_count: usize) -> Self {
+        self.position = Some(position);
+        self.child_count = Some(child_count);
+        self
+    }
+
+    /// Give the entry the number of notes in its directory
+    const fn counted(mut self, note_count: Option<usize>) -> Self {
+        self.note_count = note_count;
+        self
+    }
+}
+
+impl<'sys, T: HasFolderKind> WithParents<'sys, T> {
+    /// Bind a folder or extended folder to `parents` and the entry it links to
+    fn linked(node: &'sys T, parents: Parents<'sys>) -> Self {
+        Self {
+            target: parents.target(node.kind()),
+            ..Self::new(node, parents)
+        }
+    }
 }
 
 handlebars_helper!(full_id: |id: JohnnyId| id.by_seperator("."));
@@ -60,91 +473,768 @@ handlebars_helper!(start: |range: (u8, u8)| format!("{:02}", range.0));
 handlebars_helper!(end: |range: (u8, u8)| format!("{:02}", range.1));
 handlebars_helper!(is_folder: |kind: FolderKind| kind.is_folder());
 
-impl<'hbar> MdFormatter<'hbar> {
+/// Renders a link to a file note in the syntax of the flavor
+struct LinkHelper {
+    /// The output flavor
+    flavor: Flavor,
+    /// The folder that `file:` links are relative to
+    root: String,
+}
+
+impl HelperDef for LinkHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        helper: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let id: JohnnyId = serde_json::from_value(
+            helper
+                .param(0)
+                .ok_or(RenderErrorReason::ParamNotFoundForIndex("link", 0))?
+                .value()
+                .clone(),
+        )
+        .map_err(|_| RenderErrorReason::InvalidParamType("JohnnyId"))?;
+        let topic = helper
+            .param(1)
+            .and_then(|topic| topic.value().as_str())
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex("link", 1))?;
+        let name = format!("{} {topic}", id.by_seperator("."));
+        let link = match self.flavor {
+            Flavor::Markdown => format!("[[{name}]]"),
+            Flavor::Org => {
+                let path = PathBuf::from(&self.root)
+                    .join(layout::note_file(id.as_path(), self.flavor.extension()));
+                format!("[[file:{}][{name}]]", path.display())
+            }
+        };
+        Ok(ScopedJson::Derived(JsonValue::String(link)))
+    }
+}
+
+/// Explain a field that the template `name` uses but `data` doesn't have, listing the ones it has
+fn missing_field<T: Serialize>(name: &str, err: RenderError, data: &T) -> Error {
+    let RenderErrorReason::MissingVariable(variable) = err.reason() else {
+        return err.into();
+    };
+    let variable = variable
+        .as_deref()
+        .map_or_else(|| "a field".to_owned(), |variable| format!("`{variable}`"));
+    let place = err
+        .line_no
+        .zip(err.column_no)
+        .map(|(line, column)| format!(" on line {line}, column {column}"))
+        .unwrap_or_default();
+    let fields = match serde_json::to_value(data) {
+        Ok(JsonValue::Object(fields)) => fields
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => String::new(),
+    };
+    Error::msg(format!(
+        "The {name} template uses {variable}{place}, which it doesn't have. It has: {fields}"
+    ))
+}
+
+impl MdFormatter<'_> {
+    /// The entries in the order the index lists them
+    fn ordered<'sys, T: HasJohnnyId>(&self, entries: &'sys [T]) -> Vec<&'sys T> {
+        let mut ordered: Vec<&T> = entries.iter().collect();
+        if let Some(collator) = &self.collator {
+            ordered.sort_by(|left, right| collator.compare(left.name(), right.name()));
+        }
+        ordered
+    }
+
+    /// The template `name`, or the override of it for the first of the entries `within`, innermost
+    /// first, that replaces it
+    fn template<'id>(&self, name: &str, within: impl IntoIterator<Item = &'id JohnnyId>) -> String {
+        within
+            .into_iter()
+            .flat_map(|id| self.overrides.iter().filter(move |key| id.matches(key)))
+            .map(|key| format!("{name}@{key}"))
+            .find(|template| self.handlebars.has_template(template))
+            .unwrap_or_else(|| name.to_owned())
+    }
+
+    /// Render a template with the details of the generation added to its data
+    fn render<T: Serialize>(&self, name: &str, data: &T) -> Result<String, Error> {
+        let data = WithGeneration {
+            data,
+            generation: &self.generation,
+        };
+        self.handlebars
+            .render(name, &data)
+            .map_err(|err| missing_field(name, err, &data))
+    }
+
     /// Create markdown for a System
     pub fn system(&self, system: &System) -> Result<String, Error> {
         let mut markdown = String::default();
-        markdown.push_str(&self.handlebars.render("system", system)?);
+        markdown.push_str(&self.render("system", system)?);
+        markdown.push('\n');
+        let parents = Parents::of(system);
+        let areas = self.ordered(&system.areas);
+        for (index, area) in areas.iter().enumerate() {
+            markdown.push_str(&self.area(area, parents, Position::new(index, areas.len()))?);
+        }
+
+        Ok(markdown)
+    }
+
+    /// Create markdown for the index of only the area or category with the id `scope`, under the
+    /// heading of the system
+    pub fn scoped(&self, system: &System, scope: &JohnnyId) -> Result<String, Error> {
+        let scope = scope.by_seperator(".");
+        let mut markdown = String::default();
+        markdown.push_str(&self.render("system", system)?);
         markdown.push('\n');
+        let parents = Parents::of(system);
+        // The area or category is the only entry listed under its parent
+        let only = Position::new(0, 1);
         for area in &system.areas {
-            markdown.push_str(&self.area(&AreaWithParentId {
-                area,
-                system_id: &system.id.by_seperator("."),
-            })?);
+            if area.id.by_seperator(".") == scope {
+                markdown.push_str(&self.area(area, parents, only)?);
+            } else if let Some(category) = area
+                .categories
+                .iter()
+                .find(|category| category.id.by_seperator(".") == scope)
+            {
+                markdown.push_str(&self.render(
+                    &self.template("area", [&area.id]),
+                    &WithParents::new(area, parents).at(only, area.categories.len()),
+                )?);
+                markdown.push('\n');
+                markdown.push_str(&self.category(category, parents.area(area), only)?);
+            }
         }
 
         Ok(markdown)
     }
 
-    /// Create markdown for an Area
-    pub fn area(&self, area: &AreaWithParentId) -> Result<String, Error> {
+    /// Create markdown for an Area at `position` among the areas
+    pub fn area<'sys>(
+        &self,
+        area: &'sys Area,
+        parents: Parents<'sys>,
+        position: Position,
+    ) -> Result<String, Error> {
         let mut markdown = String::default();
-        markdown.push_str(&self.handlebars.render("area", area)?);
+        markdown.push_str(&self.render(
+            &self.template("area", [&area.id]),
+            &WithParents::new(area, parents).at(position, area.categories.len()),
+        )?);
         markdown.push('\n');
-        for category in &area.area.categories {
-            markdown.push_str(&self.category(category)?);
+        let parents = parents.area(area);
+        let categories = self.ordered(&area.categories);
+        for (index, category) in categories.iter().enumerate() {
+            markdown.push_str(&self.category(
+                category,
+                parents,
+                Position::new(index, categories.len()),
+            )?);
         }
 
         Ok(markdown)
     }
 
-    /// Create markdown for a Category
-    pub fn category(&self, category: &Category) -> Result<String, Error> {
+    /// Create the map of content note for an area of `system`
+    pub fn area_moc(&self, system: &System, area: &Area) -> Result<String, Error> {
+        let mut markdown = self.render("area_moc", &WithParents::new(area, Parents::of(system)))?;
+        markdown.push('\n');
+        Ok(markdown)
+    }
+
+    /// Create markdown for a Category at `position` among the categories of its area
+    pub fn category<'sys>(
+        &self,
+        category: &'sys Category,
+        parents: Parents<'sys>,
+        position: Position,
+    ) -> Result<String, Error> {
         let mut markdown = String::default();
-        markdown.push_str(&self.handlebars.render("category", category)?);
+        markdown.push_str(&self.render(
+            &self.template("category", iter::once(&category.id).chain(parents.scopes())),
+            &WithParents::new(category, parents).at(position, category.folders.len()),
+        )?);
         markdown.push('\n');
-        for folder in &category.folders {
-            markdown.push_str(&self.folder(folder)?);
+        let parents = parents.category(category);
+        let folders = self.ordered(&category.folders);
+        for (index, folder) in folders.iter().enumerate() {
+            markdown.push_str(&self.folder(
+                folder,
+                parents,
+                Position::new(index, folders.len()),
+            )?);
         }
 
         Ok(markdown)
     }
 
-    /// Create markdown for a Folder
-    pub fn folder(&self, folder: &Folder) -> Result<String, Error> {
-        let mut markdown = self.handlebars.render("folder", folder)?;
+    /// Create markdown for a Folder at `position` among the folders of its category
+    pub fn folder<'sys>(
+        &self,
+        folder: &'sys Folder,
+        parents: Parents<'sys>,
+        position: Position,
+    ) -> Result<String, Error> {
+        let mut markdown = self.render(
+            &self.template("folder", parents.scopes()),
+            &WithParents::linked(folder, parents)
+                .at(position, folder.folders.len())
+                .counted(self.note_count(&folder.id)),
+        )?;
         markdown.push('\n');
-        for xfolder in &folder.folders {
-            markdown.push_str(&self.xfolder(xfolder)?);
+        let parents = parents.folder(folder);
+        let xfolders = self.ordered(&folder.folders);
+        for (index, xfolder) in xfolders.iter().enumerate() {
+            markdown.push_str(&self.xfolder(
+                xfolder,
+                parents,
+                Position::new(index, xfolders.len()),
+            )?);
         }
         Ok(markdown)
     }
 
-    /// Create markdown for an Extended Folder
-    pub fn xfolder(&self, folder: &XFolder) -> Result<String, Error> {
-        let mut markdown = self.handlebars.render("xfolder", folder)?;
+    /// Create markdown for an Extended Folder at `position` among the extended folders of its
+    /// folder
+    pub fn xfolder(
+        &self,
+        folder: &XFolder,
+        parents: Parents<'_>,
+        position: Position,
+    ) -> Result<String, Error> {
+        let mut markdown = self.render(
+            &self.template("xfolder", parents.scopes()),
+            &WithParents::linked(folder, parents)
+                .at(position, 0)
+                .counted(self.note_count(&folder.id)),
+        )?;
         markdown.push('\n');
         Ok(markdown)
     }
 
-    pub fn markdown(&self) -> Result<String, Error> {
-        let mut markdown = self.handlebars.render("md", &String::new())?;
+    /// The file extension for generated files
+    pub const fn extension(&self) -> &'static str {
+        self.flavor.extension()
+    }
+
+    /// Set the folder that `file:` links are relative to
+    pub fn link_root(mut self, root: &str) -> Self {
+        self.handlebars.register_helper(
+            "link",
+            Box::new(LinkHelper {
+                flavor: self.flavor,
+                root: root.to_owned(),
+            }),
+        );
+        self
+    }
+
+    /// Set the details of the generation that templates can include
+    pub fn generation(mut self, generation: Generation) -> Self {
+        self.generation = generation;
+        self
+    }
+
+    /// Set the name of the attachments folder that new files refer to
+    pub fn attachments(mut self, attachments: Option<&str>) -> Self {
+        self.attachments = attachments.map(ToOwned::to_owned);
+        self
+    }
+
+    /// Set how many notes are in the directory of each folder and extended folder, by full id
+    pub fn note_counts(mut self, note_counts: BTreeMap<String, usize>) -> Self {
+        self.note_counts = note_counts;
+        self
+    }
+
+    /// How many notes are in the directory of the entry `id`, when the notes were counted
+    fn note_count(&self, id: &JohnnyId) -> Option<usize> {
+        self.note_counts.get(&id.by_seperator(".")).copied()
+    }
+
+    /// Create the contents of a new note for `note`, from the `markdown` template
+    pub fn markdown(&self, note: NewNote<'_>) -> Result<String, Error> {
+        match note {
+            NewNote::Folder(folder, parents) => self.new_file(folder, parents),
+            NewNote::XFolder(xfolder, parents) => self.new_file(xfolder, parents),
+        }
+    }
+
+    /// Render the template of new files for `node` under `parents`
+    fn new_file<T: Serialize + FullId>(
+        &self,
+        node: &T,
+        parents: Parents<'_>,
+    ) -> Result<String, Error> {
+        let data = NewFile {
+            entry: &WithParents::new(node, parents),
+            title: node.id(),
+            breadcrumbs: parents.breadcrumbs(),
+            generation: &self.generation,
+            attachments: self.attachments.as_deref(),
+        };
+        let mut markdown = self
+            .handlebars
+            .render("md", &data)
+            .map_err(|err| missing_field("markdown", err, &data))?;
         markdown.push('\n');
         Ok(markdown)
     }
 }
 
-impl<'hbar> TryFrom<MdFormatConfig> for MdFormatter<'hbar> {
+/// The collator for sorting topics in the alphabet of `locale`, the root order of Unicode when no
+/// locale is given
+fn collator(locale: Option<&str>) -> Result<Collator, Error> {
+    let locale: Locale = locale.unwrap_or("und").parse().map_err(|err| {
+        Error::msg(format!(
+            "{} isn't a locale: {err}",
+            locale.unwrap_or_default()
+        ))
+    })?;
+    Collator::try_new(&locale.into(), CollatorOptions::new())
+        .map_err(|err| Error::msg(format!("Topics can't be sorted for this locale: {err}")))
+}
+
+impl TryFrom<MdFormatConfig> for MdFormatter<'_> {
     type Error = Error;
 
     fn try_from(config: MdFormatConfig) -> Result<Self, Self::Error> {
         let mut handlebars = Handlebars::new();
+        handlebars.set_strict_mode(config.strict);
         handlebars.register_helper("full_id", Box::new(full_id));
         handlebars.register_helper("start", Box::new(start));
         handlebars.register_helper("end", Box::new(end));
         handlebars.register_helper("is_folder", Box::new(is_folder));
+        handlebars.register_helper(
+            "link",
+            Box::new(LinkHelper {
+                flavor: config.flavor,
+                root: String::new(),
+            }),
+        );
+        for (name, script) in &config.helpers {
+            handlebars
+                .register_script_helper(name, script)
+                .map_err(|err| Error::msg(format!("The {name} helper can't be compiled: {err}")))?;
+        }
+        let defaults = config.flavor.templates();
         let templates = vec![
-            ("system", config.system),
-            ("area", config.area),
-            ("category", config.category),
-            ("folder", config.folder),
-            ("xfolder", config.xfolder),
-            ("md", config.markdown),
+            (
+                "system",
+                config.system.unwrap_or_else(|| defaults.system.to_owned()),
+            ),
+            (
+                "area",
+                config.area.unwrap_or_else(|| defaults.area.to_owned()),
+            ),
+            (
+                "area_moc",
+                config
+                    .area_moc
+                    .unwrap_or_else(|| defaults.area_moc.to_owned()),
+            ),
+            (
+                "category",
+                config
+                    .category
+                    .unwrap_or_else(|| defaults.category.to_owned()),
+            ),
+            (
+                "folder",
+                config.folder.unwrap_or_else(|| defaults.folder.to_owned()),
+            ),
+            (
+                "xfolder",
+                config
+                    .xfolder
+                    .unwrap_or_else(|| defaults.xfolder.to_owned()),
+            ),
+            (
+                "md",
+                config
+                    .markdown
+                    .unwrap_or_else(|| defaults.markdown.to_owned()),
+            ),
         ];
         templates
             .into_iter()
             .map(|(name, template)| handlebars.register_template_string(name, template))
             .collect::<Result<Vec<_>, _>>()?;
-        Ok(Self { handlebars })
+        for (key, overrides) in &config.overrides {
+            let templates = [
+                ("area", &overrides.area),
+                ("category", &overrides.category),
+                ("folder", &overrides.folder),
+                ("xfolder", &overrides.xfolder),
+            ];
+            for (name, template) in templates {
+                if let Some(template) = template {
+                    handlebars.register_template_string(&format!("{name}@{key}"), template)?;
+                }
+            }
+        }
+        let collator = match config.order {
+            Order::Id => None,
+            Order::Topic => Some(collator(config.locale.as_deref())?),
+        };
+        Ok(Self {
+            handlebars,
+            flavor: config.flavor,
+            generation: Generation::new(Timestamp::now(), String::new()),
+            attachments: None,
+            collator,
+            overrides: config.overrides.into_keys().collect(),
+            note_counts: BTreeMap::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::SystemParameters, test_support::system};
+
+    fn render_link(flavor: Flavor) -> String {
+        let formatter = MdFormatter::try_from(MdFormatConfig {
+            flavor,
+            ..MdFormatConfig::default()
+        })
+        .unwrap()
+        .link_root("~/notes");
+        let id = JohnnyId::default()
+            .system_id("N01")
+            .area_id(0, 9, "System")
+            .category_id(0, "Meta")
+            .folder_id(1, "Inbox");
+        formatter
+            .handlebars
+            .render_template(
+                "{{link id topic}}",
+                &serde_json::json!({"id": id, "topic": "Inbox"}),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_markdown_link() {
+        assert_eq!(render_link(Flavor::Markdown), "[[N01.00.01 Inbox]]");
+    }
+
+    #[test]
+    fn test_org_link() {
+        assert_eq!(
+            render_link(Flavor::Org),
+            "[[file:~/notes/N01/N01.00-09 System/N01.00 Meta/N01.00.01 Inbox.org][N01.00.01 Inbox]]"
+        );
+    }
+
+    #[test]
+    fn test_parents_in_templates() {
+        let formatter = MdFormatter::try_from(MdFormatConfig {
+            system: Some(String::new()),
+            area: Some(String::new()),
+            category: Some(String::new()),
+            folder: Some(
+                "{{full_id id}} in {{area.topic}} ({{start area.id_range}}-{{end area.id_range}}), {{category.topic}} of {{system.name}}"
+                    .to_owned(),
+            ),
+            xfolder: Some("{{topic}} under {{folder.topic}} in {{system_id}}".to_owned()),
+            ..MdFormatConfig::default()
+        })
+        .unwrap();
+        let system = System::try_from(SystemParameters {
+            system_id: "N01".to_owned(),
+            name: "Demo".to_owned(),
+            config: "10-19 Life\n\t11 Home\n\t\t01 Inbox\n\t\t\tX01 Mail\n".to_owned(),
+            ..SystemParameters::default()
+        })
+        .unwrap();
+        assert_eq!(
+            formatter.system(&system).unwrap(),
+            "\n\n\nN01.11.01 in Life (10-19), Home of Demo\nMail under Inbox in N01\n"
+        );
+    }
+
+    #[test]
+    fn test_positions_in_templates() {
+        let formatter = MdFormatter::try_from(MdFormatConfig {
+            system: Some(String::new()),
+            area: Some(String::new()),
+            category: Some("{{topic}} ({{child_count}} folders)".to_owned()),
+            folder: Some("{{index}} {{topic}}{{#unless is_last}},{{/unless}}".to_owned()),
+            xfolder: Some(String::new()),
+            ..MdFormatConfig::default()
+        })
+        .unwrap();
+        let system = System::try_from(SystemParameters {
+            system_id: "N01".to_owned(),
+            name: "Demo".to_owned(),
+            config: "10-19 Life\n\t11 Home\n\t\t01 Inbox\n\t\t02 Bills\n\t12 Car\n".to_owned(),
+            ..SystemParameters::default()
+        })
+        .unwrap();
+        assert_eq!(
+            formatter.system(&system).unwrap(),
+            "\n\nHome (2 folders)\n0 Inbox,\n1 Bills\nCar (0 folders)\n"
+        );
+    }
+
+    #[test]
+    fn test_note_counts_in_templates() {
+        let formatter = MdFormatter::try_from(MdFormatConfig {
+            system: Some(String::new()),
+            area: Some(String::new()),
+            category: Some(String::new()),
+            folder: Some("{{topic}}{{#if note_count}} ({{note_count}} notes){{/if}}".to_owned()),
+            ..MdFormatConfig::default()
+        })
+        .unwrap()
+        .note_counts(BTreeMap::from([("N01.11.01".to_owned(), 3)]));
+        let system = System::try_from(SystemParameters {
+            system_id: "N01".to_owned(),
+            name: "Demo".to_owned(),
+            config: "10-19 Life\n\t11 Home\n\t\t01 Inbox\n\t\t02 Bills\n".to_owned(),
+            ..SystemParameters::default()
+        })
+        .unwrap();
+        assert_eq!(
+            formatter.system(&system).unwrap(),
+            "\n\n\nInbox (3 notes)\nBills\n"
+        );
+    }
+
+    #[test]
+    fn test_overrides_of_templates() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert(
+            "20-29".to_owned(),
+            TemplateOverrides {
+                category: Some("## {{topic}}".to_owned()),
+                folder: Some("- [ ] {{topic}}".to_owned()),
+                ..TemplateOverrides::default()
+            },
+        );
+        overrides.insert(
+            "N01.22".to_owned(),
+            TemplateOverrides {
+                folder: Some("- [x] {{topic}}".to_owned()),
+                ..TemplateOverrides::default()
+            },
+        );
+        let formatter = MdFormatter::try_from(MdFormatConfig {
+            system: Some(String::new()),
+            area: Some("# {{topic}}".to_owned()),
+            category: Some("{{topic}}".to_owned()),
+            folder: Some("- {{topic}}".to_owned()),
+            overrides,
+            ..MdFormatConfig::default()
+        })
+        .unwrap();
+        let system = System::try_from(SystemParameters {
+            system_id: "N01".to_owned(),
+            name: "Demo".to_owned(),
+            config: "10-19 Life\n\t11 Home\n\t\t01 Inbox\n20-29 Work\n\t21 Clients\n\t\t01 Acme\n\t22 Done\n\t\t01 Launch\n"
+                .to_owned(),
+            ..SystemParameters::default()
+        })
+        .unwrap();
+        assert_eq!(
+            formatter.system(&system).unwrap(),
+            "\n# Life\nHome\n- Inbox\n# Work\n## Clients\n- [ ] Acme\n## Done\n- [x] Launch\n"
+        );
+    }
+
+    #[test]
+    fn test_link_entries_in_the_index() {
+        let formatter = MdFormatter::try_from(MdFormatConfig::default()).unwrap();
+        let system = system(
+            "20-29 Money\n\t21 Tax\n\t\t05 Returns\n\t22 Insurance\n\t\t03 Tax Returns [link=21.05]\n",
+        );
+        assert!(formatter
+            .system(&system)
+            .unwrap()
+            .contains("  - L01.22.03 Tax Returns → [[L01.21.05 Returns]]\n"));
+    }
+
+    #[test]
+    fn test_area_moc() {
+        let formatter = MdFormatter::try_from(MdFormatConfig::default()).unwrap();
+        let system = system("20-29 Money\n\t21 Tax\n\t22 Insurance\n\t23 Old [retired]\n");
+        let area = system.areas.first().unwrap();
+        assert_eq!(
+            formatter.area_moc(&system, area).unwrap(),
+            "# L01.20-29 Money\n\n```dataview\nTABLE file.folder AS Folder\nWHERE jd-area = \"L01.20-29\"\nSORT file.name\n```\n\n## Categories\n\n- [[L01.21 Tax]]\n- [[L01.22 Insurance]]\n\n"
+        );
+    }
+
+    #[test]
+    fn test_order_by_topic() {
+        let system = system("10-19 Life\n\t11 Öl\n\t12 Zebra\n\t13 Apfel\n");
+        let topics = |locale: &str| {
+            let formatter = MdFormatter::try_from(MdFormatConfig {
+                system: Some(String::new()),
+                area: Some(String::new()),
+                category: Some("{{topic}}".to_owned()),
+                order: Order::Topic,
+                locale: Some(locale.to_owned()),
+                ..MdFormatConfig::default()
+            })
+            .unwrap();
+            formatter.system(&system).unwrap()
+        };
+        assert_eq!(topics("de"), "\n\nApfel\nÖl\nZebra\n");
+        assert_eq!(topics("sv"), "\n\nApfel\nZebra\nÖl\n");
+        assert!(MdFormatter::try_from(MdFormatConfig {
+            order: Order::Topic,
+            locale: Some("not a locale".to_owned()),
+            ..MdFormatConfig::default()
+        })
+        .is_err());
+    }
+
+    fn inbox() -> System {
+        System::try_from(SystemParameters {
+            system_id: "N01".to_owned(),
+            name: "Demo".to_owned(),
+            config: "10-19 Life\n\t11 Home\n\t\t01 +Inbox\n\t\t\tX01 -Mail\n".to_owned(),
+            ..SystemParameters::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_attachments_in_new_files() {
+        let system = inbox();
+        let area = &system.areas[0];
+        let category = &area.categories[0];
+        let note = NewNote::Folder(
+            &category.folders[0],
+            Parents::of(&system).area(area).category(category),
+        );
+        let formatter = MdFormatter::try_from(MdFormatConfig::default())
+            .unwrap()
+            .generation(Generation::new(Timestamp::from_seconds(0), String::new()));
+        assert_eq!(
+            formatter.markdown(note).unwrap(),
+            "---\ntags: [johnny-decimal, Librarian]\ncreated: 1970-01-01T00:00:00Z\n---\n"
+        );
+        assert_eq!(
+            formatter.attachments(Some("assets")).markdown(note).unwrap(),
+            "---\ntags: [johnny-decimal, Librarian]\ncreated: 1970-01-01T00:00:00Z\nattachments: assets\n---\n"
+        );
+    }
+
+    #[test]
+    fn test_entry_in_new_files() {
+        let system = inbox();
+        let area = &system.areas[0];
+        let category = &area.categories[0];
+        let folder = &category.folders[0];
+        let parents = Parents::of(&system).area(area).category(category);
+        let formatter = MdFormatter::try_from(MdFormatConfig {
+            markdown: Some(
+                "# {{full_id id}} {{topic}}\n{{category.topic}} of {{system.name}}".to_owned(),
+            ),
+            ..MdFormatConfig::default()
+        })
+        .unwrap();
+        assert_eq!(
+            formatter
+                .markdown(NewNote::Folder(folder, parents))
+                .unwrap(),
+            "# N01.11.01 Inbox\nHome of Demo\n"
+        );
+        assert_eq!(
+            formatter
+                .markdown(NewNote::XFolder(&folder.folders[0], parents.folder(folder)))
+                .unwrap(),
+            "# N01.11.01.X01 Mail\nHome of Demo\n"
+        );
+    }
+
+    #[test]
+    fn test_title_and_breadcrumbs_in_new_files() {
+        let system = inbox();
+        let area = &system.areas[0];
+        let category = &area.categories[0];
+        let folder = &category.folders[0];
+        let formatter = MdFormatter::try_from(MdFormatConfig {
+            markdown: Some(
+                "# {{title}} ({{kind}})\n{{#each breadcrumbs}}{{this}} > {{/each}}{{topic}}"
+                    .to_owned(),
+            ),
+            ..MdFormatConfig::default()
+        })
+        .unwrap();
+        let parents = Parents::of(&system).area(area).category(category);
+        assert_eq!(
+            formatter
+                .markdown(NewNote::XFolder(&folder.folders[0], parents.folder(folder)))
+                .unwrap(),
+            "# N01.11.01.X01 Mail (File)\nN01 Demo > N01.10-19 Life > N01.11 Home > N01.11.01 Inbox > Mail\n"
+        );
+    }
+
+    #[test]
+    fn test_script_helpers() {
+        let formatter = MdFormatter::try_from(MdFormatConfig {
+            helpers: BTreeMap::from([(
+                "shout".to_owned(),
+                "params[0].to_upper() + \"!\"".to_owned(),
+            )]),
+            ..MdFormatConfig::default()
+        })
+        .unwrap();
+        assert_eq!(
+            formatter
+                .handlebars
+                .render_template("{{shout topic}}", &serde_json::json!({"topic": "Inbox"}))
+                .unwrap(),
+            "INBOX!"
+        );
+        let broken = MdFormatter::try_from(MdFormatConfig {
+            helpers: BTreeMap::from([("broken".to_owned(), "params[0".to_owned())]),
+            ..MdFormatConfig::default()
+        });
+        assert!(broken.is_err());
+    }
+
+    #[test]
+    fn test_strict_mode_names_the_missing_field() {
+        let formatter = MdFormatter::try_from(MdFormatConfig {
+            system: Some("# {{nmae}}".to_owned()),
+            strict: true,
+            ..MdFormatConfig::default()
+        })
+        .unwrap();
+        let system = System::new(JohnnyId::default().system_id("N01"), "Demo");
+        let message = formatter.system(&system).unwrap_err().to_string();
+        assert!(message.starts_with("The system template uses `nmae` on line 1"));
+        assert!(message.contains(", name"));
+    }
+
+    #[test]
+    fn test_generation_in_templates() {
+        let formatter = MdFormatter::try_from(MdFormatConfig::default())
+            .unwrap()
+            .generation(Generation::new(
+                Timestamp::from_seconds(0),
+                "abc123".to_owned(),
+            ));
+        let system = System::new(JohnnyId::default().system_id("N01"), "Demo");
+        assert_eq!(
+            formatter.system(&system).unwrap(),
+            format!(
+                "---\ngenerated_at: 1970-01-01T00:00:00Z\njdexmd_version: {}\nconfig_hash: abc123\n---\n# Demo\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
     }
 }
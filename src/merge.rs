@@ -0,0 +1,183 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write as _,
+    path::PathBuf,
+};
+
+use color_eyre::eyre::Error;
+
+use crate::{
+    config::JohnnyDecimal,
+    line::{format_lines, FormattedLine},
+    model::System,
+    output::{self, Mark},
+};
+
+/// The outcome of merging two definitions that came from the same one
+#[derive(Debug)]
+pub struct Merged {
+    /// The merged definition
+    pub definition: String,
+    /// Every entry the two sides couldn't agree on, where ours was kept
+    pub conflicts: Vec<String>,
+}
+
+/// Every line of the system by the id it defines
+fn lines_by_id(system: &System) -> BTreeMap<String, FormattedLine> {
+    format_lines(system)
+        .into_iter()
+        .map(|formatted| (formatted.id().to_owned(), formatted))
+        .collect()
+}
+
+/// Describe one side of a conflict
+fn describe(side: Option<&FormattedLine>) -> String {
+    side.map_or_else(
+        || "removed".to_owned(),
+        |formatted| format!("`{}`", formatted.line.trim()),
+    )
+}
+
+/// Merge `ours` and `theirs`, which both started from `base`, one entry at a time.
+///
+/// Whichever side changed an entry wins. When both changed it differently, like claiming the
+/// same id for different topics, ours is kept and the conflict is reported. An entry that one
+/// side removed while the other added something under it is kept too, with a conflict.
+pub fn merge(base: &System, ours: &System, theirs: &System) -> Merged {
+    let base = lines_by_id(base);
+    let ours = lines_by_id(ours);
+    let theirs = lines_by_id(theirs);
+    let ids: BTreeSet<&String> = base
+        .keys()
+        .chain(ours.keys())
+        .chain(theirs.keys())
+        .collect();
+    let mut conflicts = Vec::new();
+    let mut merged: BTreeMap<String, FormattedLine> = BTreeMap::new();
+    for id in ids {
+        let (in_base, in_ours, in_theirs) = (base.get(id), ours.get(id), theirs.get(id));
+        let kept = if in_ours == in_theirs || in_theirs == in_base {
+            in_ours
+        } else if in_ours == in_base {
+            in_theirs
+        } else {
+            conflicts.push(format!(
+                "{id} is {} in ours and {} in theirs",
+                describe(in_ours),
+                describe(in_theirs)
+            ));
+            in_ours
+        };
+        if let Some(kept) = kept {
+            merged.insert(id.clone(), kept.clone());
+        }
+    }
+    // Put back the entries that were removed from under something the other side still uses
+    loop {
+        let orphan = merged.values().find_map(|formatted| {
+            formatted
+                .parent()
+                .filter(|parent| !merged.contains_key(*parent))
+                .map(|parent| (parent.to_owned(), formatted.id().to_owned()))
+        });
+        let Some((parent, child)) = orphan else {
+            break;
+        };
+        let Some(restored) = ours
+            .get(&parent)
+            .or_else(|| theirs.get(&parent))
+            .or_else(|| base.get(&parent))
+        else {
+            break;
+        };
+        conflicts.push(format!(
+            "{parent} was removed on one side, but {child} is still under it"
+        ));
+        merged.insert(parent, restored.clone());
+    }
+    let mut lines: Vec<FormattedLine> = merged.into_values().collect();
+    lines.sort_by(|first, second| first.path.cmp(&second.path));
+    Merged {
+        definition: lines.into_iter().map(|formatted| formatted.line).collect(),
+        conflicts,
+    }
+}
+
+/// Merge the definitions in `ours` and `theirs` against `base`, printing the result or writing
+/// it into `ours`.
+///
+/// Fails when there are conflicts, after the result has been printed or written, so it can be
+/// used as a git merge driver.
+pub fn run(base: &PathBuf, ours: &PathBuf, theirs: &PathBuf, write: bool) -> Result<(), Error> {
+    let load = |path: &PathBuf| System::try_from(JohnnyDecimal::from_file(path)?.system_config);
+    let merged = merge(&load(base)?, &load(ours)?, &load(theirs)?);
+    if write {
+        JohnnyDecimal::update_definition(ours, &merged.definition)?;
+        write!(
+            anstream::stdout(),
+            "{}",
+            output::line(Mark::Applied, "Merged into", &output::path(ours))
+        )?;
+    } else {
+        print!("{}", merged.definition);
+    }
+    let mut stderr = anstream::stderr();
+    for conflict in &merged.conflicts {
+        write!(
+            stderr,
+            "{}",
+            output::line(Mark::Deleted, "Conflict", conflict)
+        )?;
+    }
+    if merged.conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::msg(format!(
+            "{} conflicts, ours was kept for each",
+            merged.conflicts.len()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_merge_entries() {
+        let base = system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n");
+        let ours = system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n\t\t02 Receipts\n");
+        let theirs = system("20-29 Finance\n\t21 Taxes\n\t\t01 Returns\n\t22 Bank\n");
+        let merged = merge(&base, &ours, &theirs);
+        assert!(merged.conflicts.is_empty(), "{:?}", merged.conflicts);
+        assert_eq!(
+            merged.definition,
+            "20-29 Finance\n\t21 Taxes\n\t\t01 Returns\n\t\t02 Receipts\n\t22 Bank\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let base = system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n");
+        let ours = system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n\t\t02 Receipts\n");
+        let theirs = system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n\t\t02 Letters\n");
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(
+            merged.conflicts,
+            vec!["L01.21.02 is `02 Receipts` in ours and `02 Letters` in theirs"]
+        );
+        assert!(merged.definition.contains("\t\t02 Receipts\n"));
+
+        let theirs = system("20-29 Finance\n");
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(
+            merged.conflicts,
+            vec!["L01.21 was removed on one side, but L01.21.02 is still under it"]
+        );
+        assert_eq!(
+            merged.definition,
+            "20-29 Finance\n\t21 Tax\n\t\t02 Receipts\n"
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use std::{fs, path::Path};
+
+use color_eyre::eyre::Error;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How files placed in the notes are mirrored into the same folder of the reference archive
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MirrorMode {
+    #[default]
+    /// Only the directories of the archive are created
+    None,
+    /// A hard link, so both views always have the same contents
+    Hardlink,
+    /// A copy, made once when the archive doesn't have the file yet
+    Copy,
+}
+
+impl MirrorMode {
+    /// What mirroring a file in this mode is called, before and after it is done
+    pub const fn verbs(self) -> (&'static str, &'static str) {
+        match self {
+            Self::None => ("Mirror File", "Mirrored File"),
+            Self::Hardlink => ("Hard Link File", "Hard Linked File"),
+            Self::Copy => ("Copy File", "Copied File"),
+        }
+    }
+}
+
+/// Mirror the file at `from` to `to` in the given mode.
+///
+/// A hard link can't cross filesystems, so a copy is made instead when one can't be made.
+pub fn create(from: &Path, to: &Path, mode: MirrorMode) -> Result<(), Error> {
+    match mode {
+        MirrorMode::None => Ok(()),
+        MirrorMode::Copy => {
+            fs::copy(from, to)?;
+            Ok(())
+        }
+        MirrorMode::Hardlink => {
+            if let Err(err) = fs::hard_link(from, to) {
+                eprintln!(
+                    "Unable to hard link {}, copying it instead: {err}",
+                    to.display()
+                );
+                fs::copy(from, to)?;
+            }
+            Ok(())
+        }
+    }
+}
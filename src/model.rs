@@ -1,8 +1,9 @@
-use std::path::PathBuf;
+use std::{iter, sync::Arc};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::jid::JohnnyId;
+use crate::{depth::Depth, jid::JohnnyId};
 
 /// An item that has a Johnny Decimal id
 pub trait HasJohnnyId {
@@ -10,6 +11,10 @@ pub trait HasJohnnyId {
     fn jid(&self) -> &JohnnyId;
     /// Returns the name of the item
     fn name(&self) -> &str;
+    /// The stem of the file the item writes, when it isn't named after its id and name
+    fn file_stem(&self) -> Option<&str> {
+        None
+    }
 }
 
 /// An item that has a full id in the Johnny Decimal system
@@ -18,11 +23,6 @@ pub trait FullId: HasJohnnyId {
     fn id(&self) -> String {
         format!("{} {}", self.jid().by_seperator("."), self.name())
     }
-
-    /// Returns the path of the item
-    fn as_path(&self) -> PathBuf {
-        self.jid().as_path()
-    }
 }
 
 /// an item that has a `FolderKind` associated with it
@@ -32,7 +32,7 @@ pub trait HasFolderKind {
 }
 
 /// Represents a system in the Johnny Decimal system
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct System {
     /// The id of the system
     pub id: JohnnyId,
@@ -40,6 +40,13 @@ pub struct System {
     pub name: String,
     /// The areas under this system
     pub areas: Vec<Area>,
+    /// The kind of the folders whose line doesn't give one, so the definition can be written back
+    #[serde(skip)]
+    pub default_folder_kind: FolderKind,
+    /// Where the kind of the extended folders whose line doesn't give one comes from, so the
+    /// definition can be written back
+    #[serde(skip)]
+    pub xfolder_kind: KindInheritance,
 }
 
 impl System {
@@ -49,6 +56,112 @@ impl System {
             id: system_id,
             name: name.to_owned(),
             areas: Vec::new(),
+            default_folder_kind: FolderKind::default(),
+            xfolder_kind: KindInheritance::default(),
+        }
+    }
+
+    /// Whether the system is a workspace of its own, with the notes and the reference archive in
+    /// one tree under a root folder named after it
+    pub const fn is_workspace(&self) -> bool {
+        self.id.root.is_some()
+    }
+
+    /// Every entry of the system from the areas down, depth first, with how deep it is
+    pub fn iter(&self) -> impl Iterator<Item = (Depth, &dyn FullId)> {
+        self.areas.iter().flat_map(|area| {
+            let entry: &dyn FullId = area;
+            iter::once((Depth::Area, entry)).chain(area.categories.iter().flat_map(|category| {
+                let entry: &dyn FullId = category;
+                iter::once((Depth::Category, entry)).chain(category.folders.iter().flat_map(
+                    |folder| {
+                        let entry: &dyn FullId = folder;
+                        iter::once((Depth::Folder, entry)).chain(folder.folders.iter().map(
+                            |xfolder| {
+                                let entry: &dyn FullId = xfolder;
+                                (Depth::XFolder, entry)
+                            },
+                        ))
+                    },
+                ))
+            }))
+        })
+    }
+
+    /// The entry with the id `id`
+    pub fn find(&self, id: &JohnnyId) -> Option<&dyn FullId> {
+        let id = id.by_seperator(".");
+        self.iter()
+            .map(|(_, entry)| entry)
+            .find(|entry| entry.jid().by_seperator(".") == id)
+    }
+
+    /// The first entry, depth first, with the topic `topic` in any case
+    pub fn find_by_topic(&self, topic: &str) -> Option<&dyn FullId> {
+        let topic = topic.to_lowercase();
+        self.iter()
+            .map(|(_, entry)| entry)
+            .find(|entry| entry.name().to_lowercase() == topic)
+    }
+
+    /// Add the `x0` management category to every area and the `.00` management folder to every
+    /// category, unless the definition already has them.
+    ///
+    /// An added management category is already the place for managing its area, so it doesn't get
+    /// a `.00` folder of its own.
+    pub fn add_standard_zeros(&mut self) {
+        for area in &mut self.areas {
+            let (start, _) = area.id_range;
+            for category in area
+                .categories
+                .iter_mut()
+                .filter(|category| !category.retired)
+            {
+                let has_zero = category
+                    .folders
+                    .iter()
+                    .any(|folder| folder.id.folder.as_ref().is_some_and(|id| id.id == 0));
+                if !has_zero {
+                    let topic: Arc<str> = Arc::from(format!("{} management", category.topic));
+                    category.folders.insert(
+                        0,
+                        Folder {
+                            id: category.id.clone().folder_id(0, Arc::clone(&topic)),
+                            topic,
+                            kind: FolderKind::default(),
+                            retired: false,
+                            frozen: false,
+                            locations: Vec::new(),
+                            status: None,
+                            index_name: None,
+                            folders: Vec::new(),
+                        },
+                    );
+                }
+            }
+            let has_zero = area.categories.iter().any(|category| {
+                category
+                    .id
+                    .category
+                    .as_ref()
+                    .is_some_and(|id| id.id == start)
+            });
+            if !has_zero {
+                let topic: Arc<str> = Arc::from(format!("{} management", area.topic));
+                area.categories.insert(
+                    0,
+                    Category {
+                        id: area.id.clone().category_id(start, Arc::clone(&topic)),
+                        topic,
+                        retired: false,
+                        frozen: false,
+                        locations: Vec::new(),
+                        status: None,
+                        reviewed: None,
+                        folders: Vec::new(),
+                    },
+                );
+            }
         }
     }
 }
@@ -64,14 +177,16 @@ impl HasJohnnyId for System {
 }
 
 /// Represents an area in the Johnny Decimal system
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Area {
     /// The id of the area
     pub id: JohnnyId,
     /// The range of ids in the area
     pub id_range: (u8, u8),
     /// The Title of the area
-    pub topic: String,
+    pub topic: Arc<str>,
+    /// The day the area was last reviewed, like `2025-01-01`
+    pub reviewed: Option<Arc<str>>,
     /// The categories under this area
     pub categories: Vec<Category>,
 }
@@ -86,13 +201,57 @@ impl HasJohnnyId for Area {
     }
 }
 
+/// How live the project behind an entry is, given with a `[status=...]` tag
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    /// Being worked on
+    Active,
+    /// Paused for now, but not finished
+    OnHold,
+    /// Finished, kept for reference
+    Archived,
+}
+
+impl Status {
+    /// Create a `Status` from the value of a `[status=...]` tag
+    pub fn from_tag(value: &str) -> Option<Self> {
+        match value {
+            "active" => Some(Self::Active),
+            "on-hold" => Some(Self::OnHold),
+            "archived" => Some(Self::Archived),
+            _ => None,
+        }
+    }
+
+    /// The value of the `[status=...]` tag that gives this status
+    pub const fn tag(self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::OnHold => "on-hold",
+            Self::Archived => "archived",
+        }
+    }
+}
+
 /// Represents a category in the Johnny Decimal system
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Category {
     /// The id of the category
     pub id: JohnnyId,
     /// The Title of the category
-    pub topic: String,
+    pub topic: Arc<str>,
+    /// Whether the id is retired, so it is kept in the index but never generated or reused
+    pub retired: bool,
+    /// Whether the entry is frozen, so its directories are read-only and nothing more is generated
+    /// in them
+    pub frozen: bool,
+    /// Where the things filed under the entry physically live, like `Dropbox` or `paper`
+    pub locations: Vec<Arc<str>>,
+    /// How live the project behind the entry is, when it is given
+    pub status: Option<Status>,
+    /// The day the category was last reviewed, like `2025-01-01`, when it was apart from its area
+    pub reviewed: Option<Arc<str>>,
     /// The folders under this category
     pub folders: Vec<Folder>,
 }
@@ -109,17 +268,27 @@ impl HasJohnnyId for Category {
 
 /// The kind of "folder" we are dealing with. "folder" is a concept in the Johnny Decimal system
 /// and does not always represent an actual folder on your file system.
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub enum FolderKind {
     #[default]
+    #[serde(alias = "folder")]
     /// This is just the directory
     Folder,
+    #[serde(alias = "file")]
     /// This is just the file in the parent directory
     File,
+    #[serde(alias = "both")]
     /// This is both the directory and a file in the parent directory
     Both,
-    /// This is a jdex file
+    #[serde(alias = "index")]
+    /// This is a jdex file of the category it is in, or of the whole area when it is in the first
+    /// category of the area
     Index,
+    #[serde(alias = "full-index")]
+    /// This is a jdex file of the whole system
+    FullIndex,
+    /// This is a link in the parent directory to the entry with the given id
+    Link(Arc<str>),
 }
 
 impl FolderKind {
@@ -133,6 +302,67 @@ impl FolderKind {
         }
     }
 
+    /// Create a `FolderKind` from the directive at the start of a topic, where `!!` is the index
+    /// of the whole system
+    pub fn from_topic(topic: &str) -> Self {
+        if topic.starts_with("!!") {
+            return Self::FullIndex;
+        }
+        topic
+            .chars()
+            .next()
+            .map(Self::from_char)
+            .unwrap_or_default()
+    }
+
+    /// The directive that selects this kind in the system definition
+    pub const fn directive(&self) -> Option<&'static str> {
+        match self {
+            Self::File => Some("-"),
+            Self::Both => Some("+"),
+            Self::Index => Some("!"),
+            Self::FullIndex => Some("!!"),
+            Self::Folder | Self::Link(_) => None,
+        }
+    }
+
+    /// Whether the kind writes a jdex file, of part of the system or all of it
+    pub const fn is_index(&self) -> bool {
+        matches!(self, Self::Index | Self::FullIndex)
+    }
+
+    /// Create a `FolderKind` from the name of a `[tag]`
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "folder" => Some(Self::Folder),
+            "file" => Some(Self::File),
+            "both" => Some(Self::Both),
+            "index" => Some(Self::Index),
+            "full-index" => Some(Self::FullIndex),
+            _ => None,
+        }
+    }
+
+    /// The name of the `[tag]` that selects this kind in the system definition
+    pub const fn tag(&self) -> &'static str {
+        match self {
+            Self::Folder => "folder",
+            Self::File => "file",
+            Self::Both => "both",
+            Self::Index => "index",
+            Self::FullIndex => "full-index",
+            Self::Link(_) => "link",
+        }
+    }
+
+    /// The id of the entry this one links to
+    pub fn link_target(&self) -> Option<&str> {
+        match self {
+            Self::Link(target) => Some(target),
+            _ => None,
+        }
+    }
+
     /// Helper function for the markdown formatter
     pub const fn is_folder(&self) -> bool {
         matches!(self, &Self::Folder)
@@ -140,14 +370,25 @@ impl FolderKind {
 }
 
 /// This is a "folder" which should have an ID in the form "##" where ## is a number
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Folder {
     /// The id of the "folder"
     pub id: JohnnyId,
     /// The Title of the "folder"
-    pub topic: String,
+    pub topic: Arc<str>,
     /// The kind of "folder"
     pub kind: FolderKind,
+    /// Whether the id is retired, so it is kept in the index but never generated or reused
+    pub retired: bool,
+    /// Whether the entry is frozen, so its directories are read-only and nothing more is generated
+    /// in them
+    pub frozen: bool,
+    /// Where the things filed under the entry physically live, like `Dropbox` or `paper`
+    pub locations: Vec<Arc<str>>,
+    /// How live the project behind the entry is, when it is given
+    pub status: Option<Status>,
+    /// The stem of the jdex file, like `_index`, when it isn't named after the id and topic
+    pub index_name: Option<Arc<str>>,
     /// The extended folders under this folder. Normally empty
     pub folders: Vec<XFolder>,
 }
@@ -160,6 +401,9 @@ impl HasJohnnyId for Folder {
     fn name(&self) -> &str {
         &self.topic
     }
+    fn file_stem(&self) -> Option<&str> {
+        self.index_name.as_deref().filter(|_| self.kind.is_index())
+    }
 }
 
 impl HasFolderKind for Folder {
@@ -168,15 +412,57 @@ impl HasFolderKind for Folder {
     }
 }
 
-/// This is an "extended folder" which should have an ID in the form "X##" where ## is a number
-#[derive(Debug, Serialize)]
+/// Where an extended folder whose line doesn't give a kind gets its kind from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KindInheritance {
+    #[default]
+    /// It is a plain folder, whatever the folder it is in is
+    Folder,
+    /// It has the kind of the folder it is in, or is a plain folder when that is a link
+    Parent,
+    /// It has the default folder kind of the system
+    Default,
+}
+
+impl KindInheritance {
+    /// The kind of an extended folder without one of its own, in a folder of kind `parent` of a
+    /// system whose folders are `default` unless they say otherwise
+    pub fn kind(self, parent: &FolderKind, default: &FolderKind) -> FolderKind {
+        let kind = match self {
+            Self::Folder => &FolderKind::Folder,
+            Self::Parent => parent,
+            Self::Default => default,
+        };
+        if kind.link_target().is_some() {
+            FolderKind::Folder
+        } else {
+            kind.clone()
+        }
+    }
+}
+
+/// This is an "extended folder" which should have an ID in the form "X##" where ## is a number, or
+/// whatever the `xfolder_pattern` of the config asks for
+#[derive(Debug, Serialize, Deserialize)]
 pub struct XFolder {
     /// The id of the "folder"
     pub id: JohnnyId,
     /// The Title of the "folder"
-    pub topic: String,
+    pub topic: Arc<str>,
     /// The kind of "folder"
     pub kind: FolderKind,
+    /// Whether the id is retired, so it is kept in the index but never generated or reused
+    pub retired: bool,
+    /// Whether the entry is frozen, so its directories are read-only and nothing more is generated
+    /// in them
+    pub frozen: bool,
+    /// Where the things filed under the entry physically live, like `Dropbox` or `paper`
+    pub locations: Vec<Arc<str>>,
+    /// How live the project behind the entry is, when it is given
+    pub status: Option<Status>,
+    /// The number in the id, like `20` for `X20`, for templates to sort and pad by
+    pub number: Option<u32>,
 }
 
 impl FullId for XFolder {}
@@ -194,3 +480,41 @@ impl HasFolderKind for XFolder {
         &self.kind
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_queries() {
+        let system = system(
+            "10-19 Home\n\t11 House\n\t\t01 Repairs\n\t\t\tX01 Roof\n20-29 Money\n\t21 Tax\n",
+        );
+        let walked: Vec<(Depth, String)> = system
+            .iter()
+            .map(|(depth, entry)| (depth, entry.id()))
+            .collect();
+        assert_eq!(
+            walked,
+            vec![
+                (Depth::Area, "L01.10-19 Home".to_owned()),
+                (Depth::Category, "L01.11 House".to_owned()),
+                (Depth::Folder, "L01.11.01 Repairs".to_owned()),
+                (Depth::XFolder, "L01.11.01.X01 Roof".to_owned()),
+                (Depth::Area, "L01.20-29 Money".to_owned()),
+                (Depth::Category, "L01.21 Tax".to_owned()),
+            ]
+        );
+        let tax = &system.areas[1].categories[0];
+        assert_eq!(
+            system.find(&tax.id).map(FullId::id).as_deref(),
+            Some("L01.21 Tax")
+        );
+        assert_eq!(
+            system.find_by_topic("repairs").map(FullId::id).as_deref(),
+            Some("L01.11.01 Repairs")
+        );
+        assert!(system.find_by_topic("Garden").is_none());
+    }
+}
@@ -0,0 +1,300 @@
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use std::{io::Write as _, path::PathBuf};
+
+use color_eyre::eyre::Error;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::JohnnyDecimal,
+    line,
+    model::System,
+    output::{self, Mark},
+};
+
+/// The words that stay lowercase in the middle of a topic in title case
+const SMALL_WORDS: [&str; 16] = [
+    "a", "an", "and", "as", "at", "but", "by", "for", "in", "nor", "of", "on", "or", "the", "to",
+    "with",
+];
+
+/// How the words of a topic are capitalized
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Case {
+    /// Every word capitalized, except the small ones in the middle, like `Taxes and Returns`
+    Title,
+    /// Only the first word capitalized, like `Taxes and returns`
+    Sentence,
+}
+
+impl Display for Case {
+    #[allow(
+        clippy::min_ident_chars,
+        reason = "This is the preferred default name for the variable"
+    )]
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Title => write!(f, "title"),
+            Self::Sentence => write!(f, "sentence"),
+        }
+    }
+}
+
+/// The rules every topic has to follow
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct NamingConfig {
+    /// How the words of a topic are capitalized
+    pub case: Option<Case>,
+    /// The most characters a topic can have
+    pub max_length: Option<usize>,
+    /// Words that no topic may use, in any case
+    #[serde(default)]
+    pub forbidden_words: Vec<String>,
+    /// Characters that no topic may use
+    #[serde(default)]
+    pub forbidden_characters: String,
+}
+
+/// Whether a word is written with its own capitals, like `ADHD` or `iPhone`, which are kept
+fn keeps_its_case(word: &str) -> bool {
+    word.chars().skip(1).any(char::is_uppercase)
+}
+
+/// The word with its first letter in uppercase
+fn capitalize(word: &str) -> String {
+    let mut characters = word.chars();
+    characters
+        .next()
+        .map(|first| first.to_uppercase().chain(characters).collect())
+        .unwrap_or_default()
+}
+
+impl Case {
+    /// The topic written in this case
+    pub fn apply(self, topic: &str) -> String {
+        let words: Vec<&str> = topic.split(' ').collect();
+        let last = words.len().saturating_sub(1);
+        words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| {
+                if keeps_its_case(word) {
+                    return (*word).to_owned();
+                }
+                let lower = word.to_lowercase();
+                match self {
+                    Self::Title
+                        if index != 0 && index != last && SMALL_WORDS.contains(&lower.as_str()) =>
+                    {
+                        lower
+                    }
+                    Self::Sentence if index != 0 => lower,
+                    Self::Title | Self::Sentence => capitalize(&lower),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl NamingConfig {
+    /// Whether `word` is one of the forbidden words
+    fn is_forbidden(&self, word: &str) -> bool {
+        self.forbidden_words
+            .iter()
+            .any(|forbidden| forbidden.to_lowercase() == word.to_lowercase())
+    }
+
+    /// Everything about `topic` that breaks the rules, in plain language
+    pub fn problems(&self, topic: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(case) = self.case {
+            let fixed = case.apply(topic);
+            if fixed != topic {
+                problems.push(format!("isn't in {case} case, try `{fixed}`"));
+            }
+        }
+        if let Some(max_length) = self.max_length {
+            let length = topic.chars().count();
+            if length > max_length {
+                problems.push(format!(
+                    "is {length} characters long, more than the {max_length} allowed"
+                ));
+            }
+        }
+        for word in topic
+            .split_whitespace()
+            .filter(|word| self.is_forbidden(word))
+        {
+            problems.push(format!("uses the forbidden word `{word}`"));
+        }
+        for character in topic
+            .chars()
+            .filter(|character| self.forbidden_characters.contains(*character))
+        {
+            problems.push(format!("uses the forbidden character `{character}`"));
+        }
+        problems
+    }
+
+    /// The topic without the forbidden characters and words, in the case the rules ask for.
+    ///
+    /// A topic that is too long is left that way, since only a person can shorten it well.
+    pub fn fix(&self, topic: &str) -> String {
+        let allowed: String = topic
+            .chars()
+            .filter(|character| !self.forbidden_characters.contains(*character))
+            .collect();
+        let kept = allowed
+            .split_whitespace()
+            .filter(|word| !self.is_forbidden(word))
+            .collect::<Vec<_>>()
+            .join(" ");
+        match self.case {
+            Some(case) => case.apply(&kept),
+            None => kept,
+        }
+    }
+}
+
+/// `line` with the topic `topic` of its entry replaced by `better`
+fn retopic(line: &str, topic: &str, better: &str) -> String {
+    line.split_once(' ').map_or_else(
+        || line.to_owned(),
+        |(id, rest)| format!("{id} {}", rest.replacen(topic, better, 1)),
+    )
+}
+
+/// Fix the topic of every entry of `system` on its line of `definition`, returning the fixed
+/// definition and how many topics were fixed, along with the entries that have no line of their
+/// own to fix, because they come from a counted range or a template
+fn fix_topics(
+    definition: &str,
+    system: &System,
+    naming: &NamingConfig,
+) -> (String, usize, Vec<String>) {
+    let mut fixed = definition.to_owned();
+    let mut count: usize = 0;
+    let mut unfixable = Vec::new();
+    for (_, entry) in system.iter() {
+        let topic = entry.name();
+        let better = naming.fix(topic);
+        if better == topic {
+            continue;
+        }
+        match line::edit_entry(&fixed, entry.jid(), |line| retopic(line, topic, &better)) {
+            Ok(edited) => {
+                fixed = edited;
+                count = count.saturating_add(1);
+            }
+            Err(_) => unfixable.push(entry.id()),
+        }
+    }
+    (fixed, count, unfixable)
+}
+
+/// Fix every topic in the system defined in `config_file` and write it back, returning the fixed
+/// system.
+///
+/// Only the lines of the entries with a topic to fix are changed. A topic that comes from a
+/// counted range or a template is left for fixing where it is written.
+pub fn fix(config_file: &PathBuf, naming: &NamingConfig) -> Result<System, Error> {
+    let mut parameters = JohnnyDecimal::from_file(config_file)?.system_config;
+    // The standard zeros aren't written in the definition
+    parameters.standard_zeros = false;
+    let system = System::try_from(parameters)?;
+    let mut stdout = anstream::stdout();
+    JohnnyDecimal::edit_definition(config_file, |definition| {
+        let (fixed, count, unfixable) = fix_topics(definition, &system, naming);
+        for id in unfixable {
+            write!(
+                stdout,
+                "{}",
+                output::line(
+                    Mark::Skipped,
+                    "Left Alone",
+                    &format!("{id}, which comes from a counted range or a template, fix it there")
+                )
+            )?;
+        }
+        if count > 0 {
+            write!(
+                stdout,
+                "{}",
+                output::line(
+                    Mark::Changed,
+                    "Fixed",
+                    &format!("{count} topics in {}", output::path(config_file))
+                )
+            )?;
+        }
+        Ok(fixed)
+    })?;
+    System::try_from(JohnnyDecimal::from_file(config_file)?.system_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_case() {
+        assert_eq!(
+            Case::Title.apply("todos and checklists for ADHD"),
+            "Todos and Checklists for ADHD"
+        );
+        assert_eq!(Case::Title.apply("the end of"), "The End Of");
+        assert_eq!(
+            Case::Sentence.apply("Generative Image Models on iPhone"),
+            "Generative image models on iPhone"
+        );
+    }
+
+    #[test]
+    fn test_naming_rules() {
+        let naming = NamingConfig {
+            case: Some(Case::Title),
+            max_length: Some(12),
+            forbidden_words: vec!["misc".to_owned()],
+            forbidden_characters: "/".to_owned(),
+        };
+        assert!(naming.problems("Tax Returns").is_empty());
+        assert_eq!(
+            naming.problems("misc notes/ideas"),
+            vec![
+                "isn't in title case, try `Misc Notes/ideas`".to_owned(),
+                "is 16 characters long, more than the 12 allowed".to_owned(),
+                "uses the forbidden word `misc`".to_owned(),
+                "uses the forbidden character `/`".to_owned(),
+            ]
+        );
+        assert_eq!(naming.fix("misc notes/ideas"), "Notesideas");
+        assert_eq!(naming.fix("misc  tax returns"), "Tax Returns");
+    }
+
+    #[test]
+    fn test_fix_topics() {
+        let naming = NamingConfig {
+            case: Some(Case::Title),
+            forbidden_words: vec!["misc".to_owned()],
+            ..NamingConfig::default()
+        };
+        let definition = "# Mine\n20-29 Finance\n\t21 misc tax [retired]\n\t\t01..02 month {n}\n";
+        let system = system(definition);
+        let (fixed, count, unfixable) = fix_topics(definition, &system, &naming);
+        assert_eq!(
+            fixed,
+            "# Mine\n20-29 Finance\n\t21 Tax [retired]\n\t\t01..02 month {n}\n"
+        );
+        assert_eq!(count, 1);
+        assert_eq!(
+            unfixable,
+            vec![
+                "L01.21.01 month 01".to_owned(),
+                "L01.21.02 month 02".to_owned()
+            ]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use std::{collections::BTreeSet, io::Write as _};
+
+use color_eyre::eyre::{Error, OptionExt};
+
+use crate::{
+    model::{Category, System},
+    output,
+};
+
+/// Every folder number in the category that is taken, including the retired ones
+pub fn used_numbers(category: &Category) -> BTreeSet<u8> {
+    category
+        .folders
+        .iter()
+        .filter_map(|folder| folder.id.folder.as_ref())
+        .map(|folder_id| folder_id.id)
+        .collect()
+}
+
+/// The first folder number in the category that has never been issued.
+///
+/// `00` is left for the category's own management folder, so numbering starts at `01`.
+pub fn next_free(category: &Category) -> Option<u8> {
+    let used = used_numbers(category);
+    (1..=99).find(|number| !used.contains(number))
+}
+
+/// Find the category with the given id, with or without the system id
+fn find_category<'sys>(system: &'sys System, id: &str) -> Option<&'sys Category> {
+    system
+        .areas
+        .iter()
+        .flat_map(|area| &area.categories)
+        .find(|category| category.id.matches(id))
+}
+
+/// Print the next free folder id in the category `id`
+pub fn run(system: &System, id: &str) -> Result<(), Error> {
+    let category =
+        find_category(system, id).ok_or_eyre(format!("No category with the id {id} was found"))?;
+    let number = next_free(category).ok_or_eyre(format!(
+        "Every folder id in {} has been used",
+        category.id.by_seperator(".")
+    ))?;
+    writeln!(
+        anstream::stdout(),
+        "{}",
+        output::id(&format!("{}.{number:02}", category.id.by_seperator(".")))
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_retired_ids_are_not_reissued() {
+        let system =
+            system("10-19 Life\n\t11 Home\n\t\t01 Inbox\n\t\t02 Old [retired]\n\t\t04 Garden\n");
+        let category = find_category(&system, "11").unwrap();
+        assert_eq!(next_free(category), Some(3));
+    }
+}
@@ -1,137 +1,1148 @@
 /// Everything needed for generating the system for a notetaking system
-use core::fmt::{Debug, Display, Formatter, Result as FmtResult};
-use std::{fs, path::PathBuf};
+use core::{
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    iter,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
 
 use color_eyre::eyre::Error;
 use color_eyre::eyre::OptionExt;
-use expanduser::expanduser;
+use unicode_normalization::UnicodeNormalization as _;
 
 use crate::{
-    markdown::MdFormatter,
-    model::{FolderKind, FullId, HasFolderKind, System},
+    backend::OutputBackend,
+    depth::Depth,
+    ignore::IgnoreRules,
+    jid::JohnnyId,
+    label,
+    layout::{note_file, Layout},
+    link::{LinkStyle, LINK_NAME},
+    markdown::{MdFormatter, NewNote, Parents},
+    mirror::MirrorMode,
+    model::{Area, FolderKind, HasFolderKind, HasJohnnyId, System},
+    output::{self, Mark},
+    paths,
 };
 
-/// Expand the `~` into the home directory path
+/// The depth of a folder in the system: system, area, category, and folder
+const FOLDER_DEPTH: usize = 4;
+
+/// The line of a jdex below which everything is written by hand and kept when it is rewritten
+pub const MANAGED_END: &str = "<!-- jdexmd:end -->";
+
+/// The generated index with what is below the `MANAGED_END` marker of the `existing` index kept
+/// after it, so only the managed region above the marker is replaced
+fn keep_manual_section(mut generated: String, existing: Option<&str>) -> String {
+    let Some(manual) =
+        existing.and_then(|existing| existing.find(MANAGED_END).map(|start| &existing[start..]))
+    else {
+        return generated;
+    };
+    if !generated.ends_with('\n') {
+        generated.push('\n');
+    }
+    generated.push_str(manual);
+    generated
+}
+
+/// What jdexmd manages of an index, everything above the `MANAGED_END` marker when it has one
+pub fn managed_section(index: &str) -> &str {
+    index.find(MANAGED_END).map_or(index, |end| &index[..end])
+}
+
+/// Expand the `~` into the home directory path, along with Windows environment variables
 pub fn expand(path: &str) -> Result<PathBuf, Error> {
-    Ok(expanduser(path)?)
+    paths::resolve(path)
 }
 
 /// Actions that can be taken to create the system
 #[derive(Debug)]
 pub enum Action<'sys> {
-    /// Create a basic markdown file
-    CreateFile(PathBuf),
+    /// Create a note from the template of new files, for the folder or extended folder
+    CreateFile(PathBuf, NewNote<'sys>),
     /// Create a directory
     CreateDirectory(PathBuf),
-    /// Write the jdex index file
-    WriteIndex(PathBuf, &'sys System),
+    /// Write the jdex index file, of only the area or category with the id when there is one
+    WriteIndex(PathBuf, &'sys System, Option<&'sys JohnnyId>),
+    /// Create a link, from a folder of the notes to its folder in the reference archive or for a
+    /// link entry
+    CreateLink(PathBuf, PathBuf, LinkStyle),
+    /// Mirror a file placed in the notes to the same folder of the reference archive
+    MirrorFile(PathBuf, PathBuf, MirrorMode),
+    /// Write the ids of the system as JSON for autocompletion in the vault
+    WriteAutocomplete(PathBuf, String),
+    /// Write the map of content note of an area
+    WriteMoc(PathBuf, &'sys System, &'sys Area),
 }
 
-impl<'sys> Action<'sys> {
+impl Action<'_> {
     /// Execute the action by creating the file or directory, or writing the jdex
-    pub fn execute(&self, formatter: &MdFormatter) -> Result<(), Error> {
+    pub fn execute(
+        &self,
+        formatter: &MdFormatter,
+        backend: &mut dyn OutputBackend,
+    ) -> Result<(), Error> {
         match self {
-            Action::CreateFile(path) => {
-                fs::create_dir_all(path.parent().ok_or_eyre("Unable to create parents")?)?;
-                let content = formatter.markdown()?;
-                fs::write(path, content)?;
+            Action::CreateFile(path, note) => {
+                backend.create_dir_all(path.parent().ok_or_eyre("Unable to create parents")?)?;
+                let content = formatter.markdown(*note)?;
+                backend.write(path, &content)?;
             }
             Action::CreateDirectory(path) => {
-                fs::create_dir_all(path)?;
+                backend.create_dir_all(path)?;
+            }
+            Action::WriteIndex(path, system, scope) => {
+                let existing = find_existing(path, backend)
+                    .and_then(|existing| backend.read_to_string(&existing).ok());
+                let generated = match scope {
+                    Some(scope) => formatter.scoped(system, scope)?,
+                    None => formatter.system(system)?,
+                };
+                let index = keep_manual_section(generated, existing.as_deref());
+                backend.write(path, &index)?;
+            }
+            Action::CreateLink(link, target, style) => {
+                backend.create_link(link, target, *style)?;
+            }
+            Action::MirrorFile(to, from, mode) => {
+                backend.mirror(from, to, *mode)?;
             }
-            Action::WriteIndex(path, system) => {
-                let index = formatter.system(system)?;
-                fs::write(path, index)?;
+            Action::WriteAutocomplete(path, completions) => {
+                backend.create_dir_all(path.parent().ok_or_eyre("Unable to create parents")?)?;
+                backend.write(path, completions)?;
+            }
+            Action::WriteMoc(path, system, area) => {
+                backend.create_dir_all(path.parent().ok_or_eyre("Unable to create parents")?)?;
+                backend.write(path, &formatter.area_moc(system, area)?)?;
             }
         }
 
         Ok(())
     }
 
-    /// Print out what would be done if the action was executed
-    pub fn dry_run(&self) -> String {
-        if need_to_apply(self) {
-            format!("Would {self}\n")
-        } else {
-            String::new()
+    /// The path that the action will create or write
+    pub const fn path(&self) -> &PathBuf {
+        match self {
+            Action::CreateFile(path, _)
+            | Action::CreateDirectory(path)
+            | Action::WriteIndex(path, _, _)
+            | Action::CreateLink(path, _, _)
+            | Action::MirrorFile(path, _, _)
+            | Action::WriteAutocomplete(path, _)
+            | Action::WriteMoc(path, _, _) => path,
         }
     }
+
+    /// Whether `ignore`, the rules of the tree the action is in, lists its path, which generation
+    /// then leaves alone
+    pub fn is_ignored(&self, ignore: &IgnoreRules) -> bool {
+        ignore.is_ignored(self.path(), matches!(self, Action::CreateDirectory(_)))
+    }
+
+    /// Describe the action for printing before it is executed against `backend`, as what would be
+    /// done for a dry run and what was done otherwise
+    pub fn describe(&self, backend: &dyn OutputBackend, dry_run: bool) -> String {
+        let mark = if changes_structure(self, backend) {
+            Mark::Created
+        } else {
+            Mark::Changed
+        };
+        let (verb, done) = match self {
+            Action::CreateFile(_, _) => ("Create File".to_owned(), "Created File".to_owned()),
+            Action::CreateDirectory(_) => (
+                "Create Directory".to_owned(),
+                "Created Directory".to_owned(),
+            ),
+            Action::WriteIndex(_, _, _) => ("Write Index".to_owned(), "Wrote Index".to_owned()),
+            Action::WriteAutocomplete(_, _) => (
+                "Write Autocomplete".to_owned(),
+                "Wrote Autocomplete".to_owned(),
+            ),
+            Action::WriteMoc(_, _, _) => (
+                "Write Map of Content".to_owned(),
+                "Wrote Map of Content".to_owned(),
+            ),
+            Action::CreateLink(_, _, style) => {
+                (format!("Create {style}"), format!("Created {style}"))
+            }
+            Action::MirrorFile(_, _, mode) => {
+                let (verb, done) = mode.verbs();
+                (verb.to_owned(), done.to_owned())
+            }
+        };
+        let verb = if dry_run {
+            format!("Would {verb}")
+        } else {
+            done
+        };
+        let rest = match self {
+            Action::CreateLink(link, target, _) => {
+                format!("{} to {}", output::path(link), output::path(target))
+            }
+            Action::MirrorFile(to, from, _) => {
+                format!("{} to {}", output::path(from), output::path(to))
+            }
+            Action::CreateFile(path, _)
+            | Action::CreateDirectory(path)
+            | Action::WriteIndex(path, _, _)
+            | Action::WriteAutocomplete(path, _)
+            | Action::WriteMoc(path, _, _) => output::path(path),
+        };
+        output::line(mark, &verb, &rest)
+    }
 }
 
-impl<'sys> Display for Action<'sys> {
-    #[expect(
+impl Display for Action<'_> {
+    #[allow(
         clippy::min_ident_chars,
         reason = "This is the preferred default name for the variable"
     )]
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
-            Action::CreateFile(path) => write!(f, "Create File {}", path.display()),
+            Action::CreateFile(path, _) => write!(f, "Create File {}", path.display()),
             Action::CreateDirectory(path) => write!(f, "Create Directory {}", path.display()),
-            Action::WriteIndex(path, _system) => write!(f, "Write Index {}", path.display()),
+            Action::WriteIndex(path, _, _) => write!(f, "Write Index {}", path.display()),
+            Action::WriteAutocomplete(path, _) => {
+                write!(f, "Write Autocomplete {}", path.display())
+            }
+            Action::WriteMoc(path, _, _) => {
+                write!(f, "Write Map of Content {}", path.display())
+            }
+            Action::CreateLink(link, target, style) => write!(
+                f,
+                "Create {style} {} to {}",
+                link.display(),
+                target.display()
+            ),
+            Action::MirrorFile(to, from, mode) => write!(
+                f,
+                "{} {} to {}",
+                mode.verbs().0,
+                from.display(),
+                to.display()
+            ),
         }
     }
 }
 
+/// Find what is at `path`, comparing names in NFC.
+///
+/// macOS stores names decomposed (NFD) while topics are kept composed (NFC), so a folder made on
+/// one is still found when the vault is synced to the other.
+pub fn find_existing(path: &Path, backend: &dyn OutputBackend) -> Option<PathBuf> {
+    if backend.exists(path) {
+        return Some(path.to_path_buf());
+    }
+    let name: String = path.file_name()?.to_string_lossy().nfc().collect();
+    let parent = find_existing(path.parent()?, backend)?;
+    backend.read_dir(&parent).ok()?.into_iter().find(|entry| {
+        entry
+            .file_name()
+            .is_some_and(|other| other.to_string_lossy().nfc().eq(name.chars()))
+    })
+}
+
 /// Check to see if the action needs to be applied to match the expected state of the system
-pub fn need_to_apply(action: &Action) -> bool {
+pub fn need_to_apply(action: &Action, backend: &dyn OutputBackend) -> bool {
     match action {
-        Action::CreateFile(path) | Action::CreateDirectory(path) => !path.exists(),
-        Action::WriteIndex(_, _) => true,
+        Action::CreateFile(path, _)
+        | Action::CreateDirectory(path)
+        | Action::CreateLink(path, _, _)
+        | Action::MirrorFile(path, _, _) => find_existing(path, backend).is_none(),
+        Action::WriteIndex(_, _, _)
+        | Action::WriteAutocomplete(_, _)
+        | Action::WriteMoc(_, _, _) => true,
     }
 }
 
+/// Fail with every path that more than one action would write, before anything is written.
+///
+/// Paths are compared without case, since a vault is usually on, or synced to, a filesystem that
+/// ignores it.
+pub fn check_collisions(actions: &[Action<'_>]) -> Result<(), Error> {
+    let mut paths: BTreeMap<String, Vec<&PathBuf>> = BTreeMap::new();
+    for action in actions {
+        paths
+            .entry(action.path().to_string_lossy().to_lowercase())
+            .or_default()
+            .push(action.path());
+    }
+    let collisions: Vec<String> = paths
+        .values()
+        .filter(|colliding| colliding.len() > 1)
+        .map(|colliding| {
+            colliding
+                .iter()
+                .map(|path| format!("  {}", path.display()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect();
+    if collisions.is_empty() {
+        return Ok(());
+    }
+    Err(Error::msg(format!(
+        "More than one entry would be written to the same path, so nothing was written:\n{}",
+        collisions.join("\n\n")
+    )))
+}
+
+/// Check whether applying the action would change the structure of the vault.
+///
+/// An index is rewritten on every run, so it only counts when it is missing.
+pub fn changes_structure(action: &Action, backend: &dyn OutputBackend) -> bool {
+    find_existing(action.path(), backend).is_none()
+}
+
 /// Get all of the actions for a system definition
-pub fn get_all_actions<'sys>(base_folder: &str, system: &'sys System) -> Vec<Action<'sys>> {
+pub fn get_all_actions<'sys>(
+    base_path: &Path,
+    system: &'sys System,
+    extension: &str,
+) -> Vec<Action<'sys>> {
     let mut actions = Vec::new();
-    #[expect(clippy::expect_used, reason = "We are not expecting ~ to fail")]
-    let base_path = expand(base_folder).expect("Cannot expand ~ in base folder");
+    // Each path is built from its parent's path so no id is turned into a path more than once
+    let scheme = system.id.layout.scheme();
+    let system_path = base_path.join(scheme.system_path(&system.id));
     for area in &system.areas {
-        let area_path = base_path.join(area.id.as_path());
-        actions.push(Action::CreateDirectory(area_path));
-        for category in &area.categories {
-            let category_path = base_path.join(category.id.as_path());
-            actions.push(Action::CreateDirectory(category_path));
-            for folder in &category.folders {
-                get_actions_for_folder(base_folder, system, category, folder)
-                    .into_iter()
-                    .for_each(|action| actions.push(action));
-                for xfolder in &folder.folders {
-                    get_actions_for_folder(base_folder, system, folder, xfolder)
-                        .into_iter()
-                        .for_each(|action| actions.push(action));
+        let area_path = scheme.child_path(&system_path, &area.id);
+        if scheme.has_area_directories() {
+            actions.push(Action::CreateDirectory(area_path.clone()));
+        }
+        // Retired and frozen entries stay in the index, but nothing is generated for them or their
+        // children
+        for category in area
+            .categories
+            .iter()
+            .filter(|category| !category.retired && !category.frozen)
+        {
+            let category_path = scheme.child_path(&area_path, &category.id);
+            actions.push(Action::CreateDirectory(category_path.clone()));
+            // The first category of an area is where the area is managed from, so an index there
+            // lists the whole area
+            let manages_area =
+                category.id.category.as_ref().map(|id| id.id) == Some(area.id_range.0);
+            let scope = if manages_area { &area.id } else { &category.id };
+            let parents = Parents::of(system).area(area).category(category);
+            for folder in category
+                .folders
+                .iter()
+                .filter(|folder| !folder.retired && !folder.frozen)
+            {
+                let folder_path = scheme.child_path(&category_path, &folder.id);
+                actions.extend(get_actions_for_folder(
+                    base_path,
+                    &folder_path,
+                    system,
+                    scope,
+                    folder,
+                    NewNote::Folder(folder, parents),
+                    extension,
+                ));
+                for xfolder in folder
+                    .folders
+                    .iter()
+                    .filter(|xfolder| !xfolder.retired && !xfolder.frozen)
+                {
+                    actions.extend(get_actions_for_folder(
+                        base_path,
+                        &scheme.child_path(&folder_path, &xfolder.id),
+                        system,
+                        scope,
+                        xfolder,
+                        NewNote::XFolder(xfolder, parents.folder(folder)),
+                        extension,
+                    ));
                 }
             }
         }
     }
+    if system.is_workspace() {
+        // The jdex of the whole system is at the top of the workspace, where it is found first
+        for action in &mut actions {
+            if let Action::WriteIndex(path, _, None) = action {
+                if let Some(name) = path.file_name() {
+                    *path = system_path.join(name);
+                }
+            }
+        }
+        actions.push(Action::CreateDirectory(system_path));
+    }
+
+    sort_actions(&mut actions, system);
+    actions
+}
+
+/// Keep only the actions for entries within `depth` of `base_path`.
+///
+/// A link or a mirrored file belongs to the folder it is put in, and the autocomplete file covers
+/// the whole system, so it is always kept.
+pub fn limit_depth(actions: &mut Vec<Action<'_>>, base_path: &Path, depth: Depth, layout: Layout) {
+    let scheme = layout.scheme();
+    actions.retain(|action| {
+        let path = match action {
+            Action::CreateLink(path, _, _) | Action::MirrorFile(path, _, _) => {
+                path.parent().unwrap_or(path)
+            }
+            Action::CreateFile(path, _)
+            | Action::CreateDirectory(path)
+            | Action::WriteIndex(path, _, _)
+            | Action::WriteMoc(path, _, _) => path.as_path(),
+            Action::WriteAutocomplete(_, _) => return true,
+        };
+        path.strip_prefix(base_path)
+            .is_ok_and(|relative| depth.includes(scheme.depth_of(relative)))
+    });
+}
+
+/// Whether `path` is the entry at `subtree`, the note named after it, or anything under it
+#[expect(
+    clippy::suspicious_operation_groupings,
+    reason = "A note is named after its directory"
+)]
+pub fn is_within(path: &Path, subtree: &Path) -> bool {
+    path.starts_with(subtree)
+        || (path.parent() == subtree.parent() && path.file_stem() == subtree.file_name())
+}
+
+/// Keep only the actions for the entry at `subtree` of `base_path` and everything under it,
+/// along with the directories it is in.
+///
+/// The autocomplete file covers the whole system, so it is left alone.
+pub fn limit_to(actions: &mut Vec<Action<'_>>, base_path: &Path, subtree: &Path) {
+    let subtree = base_path.join(subtree);
+    actions.retain(|action| match action {
+        Action::CreateLink(path, _, _) | Action::MirrorFile(path, _, _) => path
+            .parent()
+            .is_some_and(|parent| is_within(parent, &subtree)),
+        Action::CreateDirectory(path) => subtree.starts_with(path) || is_within(path, &subtree),
+        Action::CreateFile(path, _)
+        | Action::WriteIndex(path, _, _)
+        | Action::WriteMoc(path, _, _) => is_within(path, &subtree),
+        Action::WriteAutocomplete(_, _) => false,
+    });
+}
+
+/// Get the actions linking each folder of the notes to the same folder in the reference archive,
+/// from the actions of `get_all_actions` for the reference archive, whose directories the notes
+/// have as well.
+///
+/// Only folders with a directory of their own in both places, within `depth` of both, are linked.
+pub fn get_link_actions<'sys>(
+    base_path: &Path,
+    reference_path: &Path,
+    archive: &[Action<'_>],
+    layout: Layout,
+    style: LinkStyle,
+    depth: Depth,
+) -> Vec<Action<'sys>> {
+    if style == LinkStyle::None {
+        return Vec::new();
+    }
+    let scheme = layout.scheme();
+    archive
+        .iter()
+        .filter_map(|action| {
+            let Action::CreateDirectory(path) = action else {
+                return None;
+            };
+            let relative = path.strip_prefix(reference_path).ok()?;
+            let components = scheme.depth_of(relative);
+            (components >= FOLDER_DEPTH && depth.includes(components)).then(|| {
+                Action::CreateLink(
+                    base_path.join(relative).join(LINK_NAME),
+                    path.clone(),
+                    style,
+                )
+            })
+        })
+        .collect()
+}
 
+/// Get the actions writing a map of content note into the folder of each area, named after the
+/// folder so it opens as the area's folder note, or into the folder of the system when areas don't
+/// have folders
+pub fn get_moc_actions<'sys>(
+    base_path: &Path,
+    system: &'sys System,
+    extension: &str,
+) -> Vec<Action<'sys>> {
+    system
+        .areas
+        .iter()
+        .map(|area| {
+            let name = area.id.by_seperator_bound(".");
+            Action::WriteMoc(
+                base_path
+                    .join(area.id.as_path())
+                    .join(format!("{name}.{extension}")),
+                system,
+                area,
+            )
+        })
+        .collect()
+}
+
+/// Get the actions creating the attachments folder `name` inside each folder that `actions` create
+/// a directory for
+pub fn get_attachment_actions<'sys>(
+    base_path: &Path,
+    actions: &[Action<'_>],
+    name: &str,
+    layout: Layout,
+) -> Vec<Action<'sys>> {
+    let scheme = layout.scheme();
     actions
+        .iter()
+        .filter_map(|action| {
+            let Action::CreateDirectory(path) = action else {
+                return None;
+            };
+            let relative = path.strip_prefix(base_path).ok()?;
+            (scheme.depth_of(relative) >= FOLDER_DEPTH)
+                .then(|| Action::CreateDirectory(path.join(name)))
+        })
+        .collect()
 }
 
-/// Gets the actions for a folder or xfolder (or really anything that has a `FolderKind`)
-fn get_actions_for_folder<'sys, F: FullId + HasFolderKind, J: FullId + Debug>(
-    base_folder: &str,
+/// Get the actions mirroring the files placed in the notes into the same folders of the reference
+/// archive.
+///
+/// Generated files, links, and anything the `.jdexignore` of the notes lists are left out, as are
+/// files already in the archive. With `subtree`, only the folders of that entry are looked in.
+pub fn get_mirror_actions<'sys>(
+    backend: &dyn OutputBackend,
+    base_path: &Path,
+    reference_path: &Path,
+    system: &'sys System,
+    extension: &str,
+    mode: MirrorMode,
+    subtree: Option<&Path>,
+) -> Result<Vec<Action<'sys>>, Error> {
+    if mode == MirrorMode::None {
+        return Ok(Vec::new());
+    }
+    let ignore = IgnoreRules::load(backend, base_path)?;
+    let mut notes = get_all_actions(base_path, system, extension);
+    if let Some(subtree) = subtree {
+        limit_to(&mut notes, base_path, subtree);
+    }
+    let generated: HashSet<&Path> = notes.iter().map(|action| action.path().as_path()).collect();
+    let mut actions = Vec::new();
+    for action in &notes {
+        let Action::CreateDirectory(directory) = action else {
+            continue;
+        };
+        if !backend.is_dir(directory) {
+            continue;
+        }
+        let relative = directory.strip_prefix(base_path)?;
+        for file in backend.read_dir(directory)? {
+            let placed = !generated.contains(file.as_path())
+                && !backend.is_dir(&file)
+                && !file.ends_with(LINK_NAME)
+                && !ignore.is_ignored(&file, false);
+            if !placed {
+                continue;
+            }
+            if let Some(name) = file.file_name() {
+                let target = reference_path.join(relative).join(name);
+                if !backend.exists(&target) {
+                    actions.push(Action::MirrorFile(target, file, mode));
+                }
+            }
+        }
+    }
+    sort_actions(&mut actions, system);
+    Ok(actions)
+}
+
+/// The id of each directory of the system, its areas, and its categories that `folder_names`
+/// named with a template, by that name
+fn named_directories(system: &System) -> HashMap<String, String> {
+    let areas = system.areas.iter().map(|area| &area.id);
+    let categories = system
+        .areas
+        .iter()
+        .flat_map(|area| &area.categories)
+        .map(|category| &category.id);
+    iter::once(&system.id)
+        .chain(areas)
+        .chain(categories)
+        .filter(|id| id.directory_name() != id.by_seperator_bound("."))
+        .map(|id| (id.directory_name(), id.by_seperator(".")))
+        .collect()
+}
+
+/// Put the actions of `system` in their canonical order, by id with parents before their
+/// children.
+///
+/// The paths are compared component by component, so a directory always comes before anything
+/// inside it. A directory named with a template stands for its id there, since its name doesn't
+/// have to start with it, and every other name already starts with its zero padded id. The sort
+/// is stable, so the order of the config file doesn't leak into the plan.
+pub fn sort_actions(actions: &mut [Action<'_>], system: &System) {
+    let named = named_directories(system);
+    actions.sort_by(|a, b| sort_key(a.path(), &named).cmp(sort_key(b.path(), &named)));
+}
+
+/// The components of `path` that it is sorted by, with the directories in `named` standing for
+/// their ids
+fn sort_key<'path>(
+    path: &'path Path,
+    named: &'path HashMap<String, String>,
+) -> impl Iterator<Item = &'path OsStr> {
+    path.components().map(|component| {
+        let name = component.as_os_str();
+        name.to_str()
+            .and_then(|name| named.get(name))
+            .map_or(name, OsStr::new)
+    })
+}
+
+/// Gets the actions for a folder or xfolder (or really anything that has a `FolderKind`), where
+/// `scope` is the area or category that an index of the folder lists and `note` is what a note
+/// created for it is rendered from
+fn get_actions_for_folder<'sys, F: HasJohnnyId + HasFolderKind>(
+    base_path: &Path,
+    path: &Path,
     root: &'sys System,
-    parent: &J,
+    scope: &'sys JohnnyId,
     folder: &F,
+    note: NewNote<'sys>,
+    extension: &str,
 ) -> Vec<Action<'sys>> {
-    #[expect(clippy::expect_used, reason = "We are not expecting ~ to fail")]
-    let base_path = expand(base_folder)
-        .expect("Cannot expand ~ in base folder")
-        .join(parent.as_path());
     let mut actions = Vec::new();
-    let name = folder.id();
 
-    match *folder.kind() {
-        FolderKind::Folder => actions.push(Action::CreateDirectory(base_path.join(&name))),
-        FolderKind::File => actions.push(Action::CreateFile(base_path.join(format!("{name}.md")))),
-        FolderKind::Index => actions.push(Action::WriteIndex(
-            base_path.join(format!("{name}.md")),
+    match folder.kind() {
+        FolderKind::Folder => actions.push(Action::CreateDirectory(path.to_path_buf())),
+        FolderKind::File => actions.push(Action::CreateFile(note_file(path, extension), note)),
+        kind @ (FolderKind::Index | FolderKind::FullIndex) => actions.push(Action::WriteIndex(
+            folder.file_stem().map_or_else(
+                || note_file(path, extension),
+                |stem| path.with_file_name(format!("{stem}.{extension}")),
+            ),
             root,
+            matches!(kind, FolderKind::Index).then_some(scope),
         )),
         FolderKind::Both => {
-            actions.push(Action::CreateDirectory(base_path.join(&name)));
-            actions.push(Action::CreateFile(base_path.join(format!("{name}.md"))));
+            actions.push(Action::CreateDirectory(path.to_path_buf()));
+            actions.push(Action::CreateFile(note_file(path, extension), note));
+        }
+        FolderKind::Link(target) => {
+            // The target is checked when the system is read, so it is always found
+            if let Some((entry, kind)) = label::entries_under(root, target).into_iter().next() {
+                actions.push(Action::CreateLink(
+                    path.to_path_buf(),
+                    label::note_path(base_path, entry, kind, extension),
+                    LinkStyle::Symlink,
+                ));
+            }
         }
     }
     actions
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::MemoryFs,
+        bench::sized_system,
+        config::{FolderNames, SystemParameters},
+        line::format_system,
+        markdown::MdFormatConfig,
+        model::KindInheritance,
+        test_support::{parameters, system},
+    };
+
+    #[test]
+    fn test_actions_for_folders() {
+        let system = sized_system(3, 4, 10).unwrap();
+        let actions = get_all_actions(Path::new("/vault"), &system, "md");
+        assert_eq!(actions.len(), 3 + 12 + 120);
+        assert_eq!(
+            actions.get(2).map(|action| action.path().clone()),
+            Some(PathBuf::from(
+                "/vault/L01/L01.00-09 Area 0/L01.00 Category 00/L01.00.00 Folder 0"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_actions_are_sorted_by_id() {
+        let ordered = system("10-19 B\n\t11 Two\n\t\t02 +Folder\n\t10 One\n00-09 A\n\t00 Zero\n");
+        let reordered = system("00-09 A\n\t00 Zero\n10-19 B\n\t10 One\n\t11 Two\n\t\t02 +Folder\n");
+        let paths = |system: &System| -> Vec<PathBuf> {
+            get_all_actions(Path::new("/vault"), system, "md")
+                .iter()
+                .map(|action| action.path().clone())
+                .collect()
+        };
+        assert_eq!(paths(&ordered), paths(&reordered));
+        // Areas named after their topics still come in the order of their ids
+        let named = System::try_from(SystemParameters {
+            folder_names: FolderNames {
+                area: Some("{{topic}}".to_owned()),
+                ..FolderNames::default()
+            },
+            ..parameters("10-19 Apple\n\t11 Pear\n00-09 Zebra\n\t01 Lion\n")
+        })
+        .unwrap();
+        assert_eq!(
+            paths(&named),
+            vec![
+                PathBuf::from("/vault/L01/Zebra"),
+                PathBuf::from("/vault/L01/Zebra/L01.01 Lion"),
+                PathBuf::from("/vault/L01/Apple"),
+                PathBuf::from("/vault/L01/Apple/L01.11 Pear"),
+            ]
+        );
+        assert_eq!(
+            paths(&ordered),
+            vec![
+                PathBuf::from("/vault/L01/L01.00-09 A"),
+                PathBuf::from("/vault/L01/L01.00-09 A/L01.00 Zero"),
+                PathBuf::from("/vault/L01/L01.10-19 B"),
+                PathBuf::from("/vault/L01/L01.10-19 B/L01.10 One"),
+                PathBuf::from("/vault/L01/L01.10-19 B/L01.11 Two"),
+                PathBuf::from("/vault/L01/L01.10-19 B/L01.11 Two/L01.11.02 Folder"),
+                PathBuf::from("/vault/L01/L01.10-19 B/L01.11 Two/L01.11.02 Folder.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retired_entries_are_not_generated() {
+        let system =
+            system("10-19 B\n\t11 Two\n\t\t01 Old [retired]\n\t12 Gone [retired]\n\t\t01 Child\n");
+        let paths: Vec<PathBuf> = get_all_actions(Path::new("/vault"), &system, "md")
+            .iter()
+            .map(|action| action.path().clone())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/vault/L01/L01.10-19 B"),
+                PathBuf::from("/vault/L01/L01.10-19 B/L01.11 Two"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_index_name() {
+        let system = system(
+            "10-19 Life\n\t11 Home\n\t\t00 !Index [index=_index]\n\t\t01 -Plans [index=_plans]\n",
+        );
+        let paths: Vec<PathBuf> = get_all_actions(Path::new("/vault"), &system, "md")
+            .iter()
+            .map(|action| action.path().clone())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/vault/L01/L01.10-19 Life"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home/L01.11.01 Plans.md"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home/_index.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attachment_actions() {
+        let system = system("10-19 Life\n\t11 Home\n\t\t01 Inbox\n\t\t02 +Plans\n\t\t03 -Notes\n");
+        let actions = get_all_actions(Path::new("/vault"), &system, "md");
+        let paths: Vec<PathBuf> =
+            get_attachment_actions(Path::new("/vault"), &actions, "assets", Layout::Nested)
+                .iter()
+                .map(|action| action.path().clone())
+                .collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home/L01.11.01 Inbox/assets"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home/L01.11.02 Plans/assets"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_moc_actions() {
+        let system = system("10-19 Life\n\t11 Home\n20-29 Money\n");
+        let paths: Vec<PathBuf> = get_moc_actions(Path::new("/vault"), &system, "md")
+            .iter()
+            .map(|action| action.path().clone())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.10-19 Life.md"),
+                PathBuf::from("/vault/L01/L01.20-29 Money/L01.20-29 Money.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_link_entries() {
+        let parameters = |config: &str| SystemParameters {
+            system_id: "L01".to_owned(),
+            name: "Links".to_owned(),
+            config: config.to_owned(),
+            ..SystemParameters::default()
+        };
+        let system = System::try_from(parameters(
+            "20-29 Money\n\t21 Tax\n\t\t05 Returns\n\t22 Insurance\n\t\t03 Tax Returns [link=21.05]\n",
+        ))
+        .unwrap();
+        let actions = get_all_actions(Path::new("/notes"), &system, "md");
+        let links: Vec<String> = actions
+            .iter()
+            .filter(|action| matches!(action, Action::CreateLink(..)))
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            links,
+            vec!["Create symlink /notes/L01/L01.20-29 Money/L01.22 Insurance/L01.22.03 Tax Returns to /notes/L01/L01.20-29 Money/L01.21 Tax/L01.21.05 Returns"]
+        );
+        assert!(System::try_from(parameters(
+            "20-29 Money\n\t22 Insurance\n\t\t03 Tax Returns [link=21.05]\n"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_xfolder_kinds() {
+        let system = System::try_from(SystemParameters {
+            xfolder_kind: KindInheritance::Parent,
+            ..parameters(
+                "10-19 Life\n\t11 Home\n\t\t01 +Plans\n\t\t\tX01 Drafts\n\t\t\tX02 Final [folder]\n\t\t02 Mail\n\t\t\tX01 !Index\n",
+            )
+        })
+        .unwrap();
+        let home = PathBuf::from("/notes/L01/L01.10-19 Life/L01.11 Home");
+        let plans = home.join("L01.11.01 Plans");
+        let actions = get_all_actions(Path::new("/notes"), &system, "md");
+        let paths: Vec<&PathBuf> = actions.iter().map(Action::path).collect();
+        // The drafts take the kind of the plans, and the final ones say they are only a folder
+        assert!(paths.contains(&&plans.join("L01.11.01.X01 Drafts")));
+        assert!(paths.contains(&&plans.join("L01.11.01.X01 Drafts.md")));
+        assert!(paths.contains(&&plans.join("L01.11.01.X02 Final")));
+        assert!(!paths.contains(&&plans.join("L01.11.01.X02 Final.md")));
+        assert!(actions.iter().any(|action| matches!(
+            action,
+            Action::WriteIndex(path, _, Some(_))
+                if path == &home.join("L01.11.02 Mail/L01.11.02.X01 Index.md")
+        )));
+        assert_eq!(
+            format_system(&system),
+            "10-19 Life\n\t11 Home\n\t\t01 +Plans\n\t\t\tX01 +Drafts\n\t\t\tX02 Final [folder]\n\t\t02 Mail\n\t\t\tX01 !Index\n"
+        );
+    }
+
+    #[test]
+    fn test_default_folder_kind() {
+        let system = System::try_from(SystemParameters {
+            default_folder_kind: FolderKind::Both,
+            ..parameters(
+                "10-19 Life\n\t11 Home\n\t\t01 Plans\n\t\t02 Inbox [folder]\n\t\t03 -Notes\n",
+            )
+        })
+        .unwrap();
+        let home = PathBuf::from("/notes/L01/L01.10-19 Life/L01.11 Home");
+        let paths: Vec<PathBuf> = get_all_actions(Path::new("/notes"), &system, "md")
+            .iter()
+            .map(|action| action.path().clone())
+            .filter(|path| path.starts_with(&home) && path != &home)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                home.join("L01.11.01 Plans"),
+                home.join("L01.11.01 Plans.md"),
+                home.join("L01.11.02 Inbox"),
+                home.join("L01.11.03 Notes.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_colliding_paths() {
+        let system = system(
+            "10-19 Life\n\t11 Home\n\t\t00 !JDex [index=Index]\n\t\t01 !Rooms [index=index]\n",
+        );
+        let error = check_collisions(&get_all_actions(Path::new("/notes"), &system, "md"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("/notes/L01/L01.10-19 Life/L01.11 Home/Index.md\n  /notes/L01/L01.10-19 Life/L01.11 Home/index.md"),
+            "{error}"
+        );
+        assert!(check_collisions(&get_all_actions(
+            Path::new("/notes"),
+            &sized_system(3, 4, 10).unwrap(),
+            "md"
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_flat_layout() {
+        let system = System::try_from(SystemParameters {
+            layout: Layout::Flat,
+            ..parameters("10-19 Life\n\t11 Home\n\t\t01 Inbox\n")
+        })
+        .unwrap();
+        let mut actions = get_all_actions(Path::new("/vault"), &system, "md");
+        let paths: Vec<&PathBuf> = actions.iter().map(Action::path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                &PathBuf::from("/vault/L01/L01.11 Home"),
+                &PathBuf::from("/vault/L01/L01.11 Home/L01.11.01 Inbox"),
+            ]
+        );
+        limit_depth(
+            &mut actions,
+            Path::new("/vault"),
+            Depth::Category,
+            Layout::Flat,
+        );
+        assert_eq!(actions.len(), 1);
+    }
+
+    #[test]
+    fn test_limit_to() {
+        let system = system(
+            "10-19 Life\n\t11 Home\n\t\t01 Inbox\n\t\t02 -Plans\n\t12 Car\n\t\t01 Repairs\n",
+        );
+        let paths = |subtree: &str| -> Vec<PathBuf> {
+            let mut actions = get_all_actions(Path::new("/vault"), &system, "md");
+            limit_to(&mut actions, Path::new("/vault"), Path::new(subtree));
+            actions.iter().map(|action| action.path().clone()).collect()
+        };
+        assert_eq!(
+            paths("L01/L01.10-19 Life/L01.11 Home/L01.11.02 Plans"),
+            vec![
+                PathBuf::from("/vault/L01/L01.10-19 Life"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home/L01.11.02 Plans.md"),
+            ]
+        );
+        assert_eq!(paths("L01/L01.10-19 Life/L01.12 Car").len(), 3);
+        assert_eq!(paths("L01/L01.10-19 Life").len(), 6);
+    }
+
+    #[test]
+    fn test_limit_depth() {
+        let system = system("10-19 Life\n\t11 Home\n\t\t01 Inbox\n\t\t\tX01 Mail\n\t\t02 -Plans\n");
+        let paths = |depth: Depth| -> Vec<PathBuf> {
+            let mut actions = get_all_actions(Path::new("/vault"), &system, "md");
+            limit_depth(&mut actions, Path::new("/vault"), depth, Layout::Nested);
+            actions.iter().map(|action| action.path().clone()).collect()
+        };
+        assert_eq!(
+            paths(Depth::Category),
+            vec![
+                PathBuf::from("/vault/L01/L01.10-19 Life"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home"),
+            ]
+        );
+        assert_eq!(paths(Depth::Folder).len(), 4);
+        assert_eq!(paths(Depth::XFolder).len(), 5);
+        let links = |depth: Depth| {
+            get_link_actions(
+                Path::new("/vault"),
+                Path::new("/archive"),
+                &get_all_actions(Path::new("/archive"), &system, "md"),
+                Layout::Nested,
+                LinkStyle::Symlink,
+                depth,
+            )
+            .len()
+        };
+        assert_eq!(links(Depth::Category), 0);
+        assert_eq!(links(Depth::Folder), 1);
+        assert_eq!(links(Depth::XFolder), 2);
+    }
+
+    #[test]
+    fn test_mirror_placed_files() {
+        let system = system("10-19 Life\n\t11 Home\n\t\t01 Inbox\n\t\t02 -Plans\n");
+        let home = PathBuf::from("/notes/L01/L01.10-19 Life/L01.11 Home");
+        let mut memory = MemoryFs::default();
+        memory
+            .create_dir_all(&home.join("L01.11.01 Inbox"))
+            .unwrap();
+        memory.write(&home.join("L01.11.02 Plans.md"), "").unwrap();
+        memory
+            .write(&home.join("L01.11.01 Inbox/lease.pdf"), "lease")
+            .unwrap();
+        memory
+            .write(&home.join("L01.11.01 Inbox/draft.tmp"), "")
+            .unwrap();
+        memory
+            .write(Path::new("/notes/.jdexignore"), "*.tmp")
+            .unwrap();
+        let actions = get_mirror_actions(
+            &memory,
+            Path::new("/notes"),
+            Path::new("/archive"),
+            &system,
+            "md",
+            MirrorMode::Copy,
+            None,
+        )
+        .unwrap();
+        let paths: Vec<&PathBuf> = actions.iter().map(Action::path).collect();
+        assert_eq!(
+            paths,
+            vec![&PathBuf::from(
+                "/archive/L01/L01.10-19 Life/L01.11 Home/L01.11.01 Inbox/lease.pdf"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_paths_are_compared_in_nfc() {
+        // The definition and the folder on disk are both decomposed, as `e` and `◌́`, like macOS saves them
+        let system = system("10-19 Life\n\t11 Cafe\u{301}\n\t\t01 Menus\n");
+        let mut memory = MemoryFs::default();
+        memory
+            .create_dir_all(Path::new(
+                "/notes/L01/L01.10-19 Life/L01.11 Cafe\u{301}/L01.11.01 Menus",
+            ))
+            .unwrap();
+        let actions = get_all_actions(Path::new("/notes"), &system, "md");
+        assert!(
+            actions
+                .iter()
+                .any(|action| action.path()
+                    == Path::new("/notes/L01/L01.10-19 Life/L01.11 Caf\u{e9}"))
+        );
+        assert!(actions
+            .iter()
+            .filter(|action| matches!(action, Action::CreateDirectory(_)))
+            .all(|action| !need_to_apply(action, &memory)));
+    }
+
+    #[test]
+    fn test_scoped_indexes() {
+        let system = system(
+            "00-09 System\n\t00 Meta\n\t\t00 !!JDex\n10-19 Life\n\t10 Life management\n\t\t01 !Life index\n\t11 Home\n\t\t01 !Home index\n\t\t02 Repairs\n\t12 Car\n\t\t01 Insurance\n",
+        );
+        let formatter = MdFormatter::try_from(MdFormatConfig::default()).unwrap();
+        let mut memory = MemoryFs::default();
+        let actions = get_all_actions(Path::new("/vault"), &system, "md");
+        for action in &actions {
+            action.execute(&formatter, &mut memory).unwrap();
+        }
+        let index = |name: &str| {
+            let path = actions
+                .iter()
+                .map(Action::path)
+                .find(|path| path.ends_with(name))
+                .unwrap();
+            memory.read_to_string(path).unwrap()
+        };
+        let full = index("L01.00.00 JDex.md");
+        assert!(
+            full.contains("Meta") && full.contains("Insurance"),
+            "{full}"
+        );
+        let area = index("L01.10.01 Life index.md");
+        assert!(
+            !area.contains("Meta") && area.contains("Insurance"),
+            "{area}"
+        );
+        let category = index("L01.11.01 Home index.md");
+        assert!(
+            category.contains("Life") && category.contains("Repairs"),
+            "{category}"
+        );
+        assert!(!category.contains("Insurance"), "{category}");
+    }
+
+    #[test]
+    fn test_workspace() {
+        let system = System::try_from(SystemParameters {
+            workspace: true,
+            ..parameters("00-09 System\n\t00 Meta\n\t\t00 !!JDex\n\t\t01 -Inbox\n")
+        })
+        .unwrap();
+        let paths: Vec<PathBuf> = get_all_actions(Path::new("/vault"), &system, "md")
+            .iter()
+            .map(|action| action.path().clone())
+            .collect();
+        let root = Path::new("/vault/L01 Life");
+        let meta = root.join("L01.00-09 System/L01.00 Meta");
+        assert_eq!(
+            paths,
+            vec![
+                root.to_path_buf(),
+                root.join("L01.00-09 System"),
+                meta.clone(),
+                meta.join("L01.00.01 Inbox.md"),
+                root.join("L01.00.00 JDex.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_manual_section_is_kept() {
+        let system = system("00-09 System\n\t00 Meta\n\t\t00 !JDex\n");
+        let formatter = MdFormatter::try_from(MdFormatConfig::default()).unwrap();
+        let path = PathBuf::from("/vault/L01/L01.00-09 System/L01.00 Meta/L01.00.00 JDex.md");
+        let mut memory = MemoryFs::default();
+        memory.create_dir_all(path.parent().unwrap()).unwrap();
+        memory
+            .write(
+                &path,
+                &format!("# Old\n{MANAGED_END}\n## Someday\n- Scan the receipts\n"),
+            )
+            .unwrap();
+        Action::WriteIndex(path.clone(), &system, None)
+            .execute(&formatter, &mut memory)
+            .unwrap();
+        let index = memory.read_to_string(&path).unwrap();
+        assert!(!index.contains("# Old"), "{index}");
+        assert!(index.contains("L01.00.00 JDex"), "{index}");
+        assert!(
+            index.ends_with(&format!(
+                "\n{MANAGED_END}\n## Someday\n- Scan the receipts\n"
+            )),
+            "{index}"
+        );
+        assert_eq!(
+            keep_manual_section("# New\n".to_owned(), Some("# Old\n")),
+            "# New\n"
+        );
+    }
+
+    #[test]
+    fn test_standard_zeros() {
+        let system = System::try_from(SystemParameters {
+            standard_zeros: true,
+            ..parameters("10-19 Life\n\t11 Home\n\t\t01 Inbox\n")
+        })
+        .unwrap();
+        let paths: Vec<PathBuf> = get_all_actions(Path::new("/vault"), &system, "md")
+            .iter()
+            .map(|action| action.path().clone())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/vault/L01/L01.10-19 Life"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.10 Life management"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home/L01.11.00 Home management"),
+                PathBuf::from("/vault/L01/L01.10-19 Life/L01.11 Home/L01.11.01 Inbox"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::{Path, MAIN_SEPARATOR};
+
+use anstream::ColorChoice;
+use anstyle::{AnsiColor, Style};
+
+use crate::model::Status;
+
+/// The style for ids
+const ID: Style = AnsiColor::Magenta.on_default().bold();
+/// The style for paths
+const PATH: Style = AnsiColor::Cyan.on_default();
+/// The style for the verb of an action
+const VERB: Style = Style::new().bold();
+/// The style for the headings between groups of actions
+const HEADING: Style = Style::new().bold().underline();
+
+/// What happened, or would happen, to an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    /// Everything that was needed has been done
+    Applied,
+    /// A new entry is created
+    Created,
+    /// An existing entry is changed or moved
+    Changed,
+    /// An existing entry is removed
+    Deleted,
+    /// An existing entry is left alone, though it would be changed
+    Skipped,
+}
+
+impl Mark {
+    /// The icon at the start of the line
+    const fn icon(self) -> &'static str {
+        match self {
+            Self::Applied => "✓",
+            Self::Created => "+",
+            Self::Changed => "~",
+            Self::Deleted => "-",
+            Self::Skipped => "!",
+        }
+    }
+
+    /// The color of the icon and the verb
+    const fn style(self) -> Style {
+        match self {
+            Self::Applied | Self::Created => AnsiColor::Green.on_default(),
+            Self::Changed | Self::Skipped => AnsiColor::Yellow.on_default(),
+            Self::Deleted => AnsiColor::Red.on_default(),
+        }
+    }
+}
+
+/// The style of an entry with `status`, so the live ones stand out and the finished ones fade
+const fn status_style(status: Option<Status>) -> Style {
+    match status {
+        Some(Status::Active) => AnsiColor::Green.on_default().bold(),
+        Some(Status::OnHold) => AnsiColor::Yellow.on_default(),
+        Some(Status::Archived) => Style::new().dimmed(),
+        None => Style::new(),
+    }
+}
+
+/// Turn off colors when asked to.
+///
+/// `NO_COLOR`, and output that isn't a terminal, are already handled by `anstream`.
+pub fn init(no_color: bool) {
+    if no_color {
+        ColorChoice::Never.write_global();
+    }
+}
+
+/// Style an id
+pub fn id(id: &str) -> String {
+    format!("{ID}{id}{ID:#}")
+}
+
+/// Style a path, picking out the id at the start of its last name
+pub fn path(path: &Path) -> String {
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(|parent| format!("{}{MAIN_SEPARATOR}", parent.display()))
+        .unwrap_or_default();
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    let name = match name.split_once(' ') {
+        Some((first, rest)) if first.chars().any(|character| character.is_ascii_digit()) => {
+            format!("{PATH:#}{}{PATH} {rest}", self::id(first))
+        }
+        _ => name,
+    };
+    format!("{PATH}{parent}{name}{PATH:#}")
+}
+
+/// Style the text of an entry by its status
+pub fn status(text: &str, status: Option<Status>) -> String {
+    let style = status_style(status);
+    format!("{style}{text}{style:#}")
+}
+
+/// Style a heading
+pub fn heading(text: &str) -> String {
+    format!("{HEADING}{text}{HEADING:#}")
+}
+
+/// A line with the icon of `mark`, then `verb`, then whatever it acted on
+pub fn line(mark: Mark, verb: &str, rest: &str) -> String {
+    let style = mark.style();
+    format!(
+        "{style}{}{style:#} {VERB}{style}{verb}{style:#}{VERB:#} {rest}\n",
+        mark.icon()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_without_colors() {
+        let printed = line(
+            Mark::Created,
+            "Would Create Directory",
+            &path(Path::new("/vault/L01/L01.00 Meta")),
+        );
+        assert_eq!(
+            anstream::adapter::strip_str(&printed).to_string(),
+            format!("+ Would Create Directory /vault/L01{MAIN_SEPARATOR}L01.00 Meta\n")
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    env,
+    io::{self, IsTerminal as _, Write as _},
+    process::{Command, Stdio},
+};
+
+use color_eyre::eyre::Error;
+
+/// The program and arguments of the pager `pager`, like `less -R`, unless it is empty
+fn command(pager: &str) -> Option<(&str, Vec<&str>)> {
+    let mut words = pager.split_whitespace();
+    let program = words.next()?;
+    Some((program, words.collect()))
+}
+
+/// Write `text` to stdout
+fn print(text: &str) -> Result<(), Error> {
+    io::stdout().write_all(text.as_bytes())?;
+    Ok(())
+}
+
+/// Show `text` through the pager named by `$PAGER` when stdout is a terminal, and print it as it
+/// is when it isn't, when there is no pager, or with `no_pager`
+pub fn page(text: &str, no_pager: bool) -> Result<(), Error> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|_| !no_pager && io::stdout().is_terminal());
+    let Some((program, args)) = pager.as_deref().and_then(command) else {
+        return print(text);
+    };
+    match Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                // Quitting the pager before the end closes the pipe, which isn't an error
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            child.wait()?;
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("Unable to start the pager {program}, printing instead: {err}");
+            print(text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        assert_eq!(command("less -R"), Some(("less", vec!["-R"])));
+        assert_eq!(command("more"), Some(("more", Vec::new())));
+        assert_eq!(command("  "), None);
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Error, OptionExt};
+use expanduser::expanduser;
+
+/// Replace every `%NAME%` in `path` with the value `lookup` gives for `NAME`, the way Windows
+/// expands environment variables like `%USERPROFILE%`.
+///
+/// A `%` that doesn't start a name, like the one in `100% Done`, is kept as it is.
+fn expand_variables(path: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, Error> {
+    let mut expanded = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('%') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('%') else {
+            break;
+        };
+        let name = &after[..end];
+        expanded.push_str(&rest[..start]);
+        // Names like `ProgramFiles(x86)` have parentheses, but never spaces
+        let is_name = !name.is_empty()
+            && name.chars().all(|character| {
+                character.is_ascii_alphanumeric() || matches!(character, '_' | '(' | ')')
+            });
+        if !is_name {
+            // The second `%` may still start a name of its own
+            expanded.push('%');
+            rest = after;
+            continue;
+        }
+        let value = lookup(name).ok_or_eyre(format!(
+            "{path} uses the environment variable %{name}%, which isn't set"
+        ))?;
+        expanded.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// The drive letter that a Windows path like `D:\Vault` starts with
+fn drive_of(path: &str) -> Option<char> {
+    let mut characters = path.chars();
+    let letter = characters
+        .next()
+        .filter(char::is_ascii_alphabetic)?
+        .to_ascii_uppercase();
+    (characters.next() == Some(':') && matches!(characters.next(), None | Some('\\' | '/')))
+        .then_some(letter)
+}
+
+/// Fail when `path` is on a drive whose root `mounted` can't find, like a USB disk that isn't
+/// plugged in, rather than letting everything under it look missing
+fn check_drive(path: &str, mounted: impl Fn(&Path) -> bool) -> Result<(), Error> {
+    let Some(letter) = drive_of(path) else {
+        return Ok(());
+    };
+    if mounted(Path::new(&format!("{letter}:\\"))) {
+        Ok(())
+    } else {
+        Err(Error::msg(format!(
+            "{path} is on the drive {letter}:, which isn't mounted. Connect the drive or change \
+             the path in the config file"
+        )))
+    }
+}
+
+/// Resolve a path from the config file, expanding `~` and Windows environment variables like
+/// `%USERPROFILE%`, and on Windows checking that the drive it is on is mounted
+pub fn resolve(path: &str) -> Result<PathBuf, Error> {
+    let expanded = expand_variables(path, |name| env::var(name).ok())?;
+    // Elsewhere a name like `D:\Vault` is an ordinary relative path
+    if cfg!(windows) {
+        check_drive(&expanded, Path::exists)?;
+    }
+    Ok(expanduser(&expanded)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        (name == "USERPROFILE").then(|| r"C:\Users\grayson".to_owned())
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        assert_eq!(
+            expand_variables(r"%USERPROFILE%\Notes", lookup).unwrap(),
+            r"C:\Users\grayson\Notes"
+        );
+        assert_eq!(
+            expand_variables(r"D:\100% Done\%USERPROFILE%", lookup).unwrap(),
+            r"D:\100% Done\C:\Users\grayson"
+        );
+        assert_eq!(expand_variables("~/Notes", lookup).unwrap(), "~/Notes");
+        let missing = expand_variables(r"%ONEDRIVE%\Notes", lookup).unwrap_err();
+        assert!(missing.to_string().contains("%ONEDRIVE%"), "{missing}");
+    }
+
+    #[test]
+    fn test_check_drive() {
+        assert_eq!(drive_of(r"d:\Vault"), Some('D'));
+        assert_eq!(drive_of("D:"), Some('D'));
+        assert_eq!(drive_of("D:Vault"), None);
+        assert_eq!(drive_of("~/Vault"), None);
+        let mounted = |root: &Path| root == Path::new(r"C:\");
+        assert!(check_drive(r"C:\Vault", mounted).is_ok());
+        assert!(check_drive("~/Vault", mounted).is_ok());
+        let unmounted = check_drive(r"E:\Vault", mounted).unwrap_err();
+        assert!(
+            unmounted.to_string().contains("E:, which isn't mounted"),
+            "{unmounted}"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::io::Write as _;
+
+/// The width of an A4 page in points
+pub const PAGE_WIDTH: usize = 595;
+
+/// The height of an A4 page in points
+pub const PAGE_HEIGHT: usize = 842;
+
+/// The standard fonts every PDF viewer has, so none have to be embedded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    /// Helvetica
+    Regular,
+    /// Helvetica Bold
+    Bold,
+}
+
+impl Font {
+    /// The name the font has in the resources of every page
+    const fn resource(self) -> &'static str {
+        match self {
+            Self::Regular => "F1",
+            Self::Bold => "F2",
+        }
+    }
+}
+
+/// A page of text, kept as the operators of its content stream
+#[derive(Debug, Default)]
+pub struct Page {
+    /// The content stream
+    contents: Vec<u8>,
+}
+
+impl Page {
+    /// Write `text` with its baseline starting `x` points from the left and `y` points from the
+    /// bottom of the page
+    pub fn text(&mut self, x: usize, y: usize, size: usize, font: Font, text: &str) {
+        let _ = write!(
+            self.contents,
+            "BT /{} {size} Tf {x} {y} Td (",
+            font.resource()
+        );
+        self.contents.extend(literal(text));
+        self.contents.extend(b") Tj ET\n");
+    }
+}
+
+/// The bytes of `text` in a string literal, in the `WinAnsi` encoding of the standard fonts, with
+/// what they can't show replaced by `?`
+fn literal(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for character in text.chars() {
+        let byte = match character {
+            '(' | ')' | '\\' => {
+                bytes.push(b'\\');
+                u8::try_from(character).unwrap_or(b'?')
+            }
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            ' '..='~' | '\u{a0}'..='\u{ff}' => u8::try_from(character).unwrap_or(b'?'),
+            _ => b'?',
+        };
+        bytes.push(byte);
+    }
+    bytes
+}
+
+/// Write the pages into a PDF document.
+///
+/// The objects are the catalog, the page tree, the two fonts, and then each page followed by its
+/// content stream.
+pub fn write(pages: &[Page]) -> Vec<u8> {
+    let kids: Vec<String> = (0..pages.len())
+        .map(|index| format!("{} 0 R", index * 2 + 5))
+        .collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    ];
+    for (index, page) in pages.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                index * 2 + 6
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", page.contents.len()).into_bytes();
+        stream.extend(&page.contents);
+        stream.extend(b"endstream");
+        objects.push(stream);
+    }
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = writeln!(pdf, "{} 0 obj", index + 1);
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    // Every entry of the cross reference table is exactly 20 bytes, line ending included
+    let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(pdf, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal() {
+        assert_eq!(literal("Tax (2024)"), b"Tax \\(2024\\)".to_vec());
+        assert_eq!(literal("Café – 東"), b"Caf\xe9 \x96 ?".to_vec());
+    }
+
+    #[test]
+    fn test_write() {
+        let mut page = Page::default();
+        page.text(40, 800, 9, Font::Bold, "20-29 Finance");
+        let pdf = write(&[page]);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.contains("BT /F2 9 Tf 40 800 Td (20-29 Finance) Tj ET"));
+        // The cross reference table points at where each object starts
+        let xref = text.find("xref\n").unwrap();
+        let offsets: Vec<usize> = text[xref..]
+            .lines()
+            .skip(3)
+            .take(6)
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        for (index, offset) in offsets.into_iter().enumerate() {
+            assert!(text[offset..].starts_with(&format!("{} 0 obj", index + 1)));
+        }
+        assert!(text.ends_with(&format!("startxref\n{xref}\n%%EOF\n")));
+    }
+}
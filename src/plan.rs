@@ -0,0 +1,94 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Write as _},
+    path::Path,
+};
+
+use color_eyre::eyre::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{self, STANDARD_STREAM},
+    notes::Action,
+    report::Report,
+};
+
+/// The actions a generation would take, to be reviewed before they are applied
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Plan {
+    /// When the plan was made
+    pub generated_at: String,
+    /// The SHA-256 of the config file the plan was made from
+    pub config_hash: String,
+    /// Every action that would be applied, as it is written in reports
+    pub actions: Vec<String>,
+}
+
+impl Plan {
+    /// The plan for the actions a dry run recorded as created
+    pub fn from_report(report: &Report, config_hash: String) -> Self {
+        Self {
+            generated_at: report.generated_at.clone(),
+            config_hash,
+            actions: report.created.clone(),
+        }
+    }
+
+    /// Read a plan from a JSON file, or stdin when the path is `-`
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_str(&config::read_source(path)?)?)
+    }
+
+    /// Write the plan as JSON to a file, or stdout when the path is `-`
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self)?;
+        if path == Path::new(STANDARD_STREAM) {
+            writeln!(io::stdout(), "{json}")?;
+        } else {
+            fs::write(path, json)?;
+        }
+        Ok(())
+    }
+
+    /// Fail when the config file has changed since the plan was made, since the plan may no
+    /// longer be what the config asks for
+    pub fn check(&self, config_hash: &str) -> Result<PlannedActions, Error> {
+        if self.config_hash != config_hash {
+            return Err(Error::msg(
+                "The config file has changed since the plan was made, make a new plan to review",
+            ));
+        }
+        Ok(PlannedActions(self.actions.iter().cloned().collect()))
+    }
+}
+
+/// The actions of a plan that has been checked against the config file
+#[derive(Debug)]
+pub struct PlannedActions(HashSet<String>);
+
+impl PlannedActions {
+    /// Whether the action was in the plan
+    pub fn contains(&self, action: &Action<'_>) -> bool {
+        self.0.contains(&action.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::Timestamp;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_plan_round_trip() {
+        let mut report = Report::new(Path::new("-"), Timestamp::from_seconds(0));
+        report.created(&Action::CreateDirectory(PathBuf::from("/vault/N01")));
+        let plan = Plan::from_report(&report, "abc".to_owned());
+        let read: Plan = serde_json::from_str(&serde_json::to_string(&plan).unwrap()).unwrap();
+        let planned = read.check("abc").unwrap();
+        assert!(planned.contains(&Action::CreateDirectory(PathBuf::from("/vault/N01"))));
+        assert!(!planned.contains(&Action::CreateDirectory(PathBuf::from("/vault/N02"))));
+        assert!(read.check("def").is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::Error;
+
+use crate::{
+    autocomplete,
+    backend::{LocalFs, OutputBackend},
+    config,
+    ignore::IgnoreRules,
+    inbox,
+    model::System,
+    notes::{self, Action},
+};
+
+/// Works out the actions that generate the notes and the reference archive of a system, in the
+/// order they are applied, for every command that generates, previews, exports, or fixes them
+pub struct Planner<'cfg, 'sys> {
+    /// Where the notes and the reference archive are, and how they are generated
+    output_config: &'cfg config::Output,
+    /// Where the notes are, with `~` and the environment variables expanded
+    base_path: PathBuf,
+    /// Where the reference archive is, with `~` and the environment variables expanded
+    reference_path: PathBuf,
+    /// The system to generate
+    system: &'sys System,
+    /// The extension of the notes
+    extension: &'cfg str,
+    /// Where the entry that generation is limited to is, relative to the base folders
+    subtree: Option<PathBuf>,
+    /// Whether only the indexes are planned, leaving everything else on disk as it is
+    index_only: bool,
+    /// Where the files already in the notes are read from, for the ignore file and mirroring
+    backend: &'cfg dyn OutputBackend,
+}
+
+impl<'cfg, 'sys> Planner<'cfg, 'sys> {
+    /// Plan the whole of `system` into the folders of `output_config`, reading the disk
+    pub fn new(
+        output_config: &'cfg config::Output,
+        system: &'sys System,
+        extension: &'cfg str,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            output_config,
+            base_path: notes::expand(&output_config.base_folder)?,
+            reference_path: notes::expand(&output_config.reference_folder)?,
+            system,
+            extension,
+            subtree: None,
+            index_only: false,
+            backend: &LocalFs,
+        })
+    }
+
+    /// Limit the plan to the entry at `subtree`, relative to the base folders, and everything
+    /// under it
+    pub fn subtree(mut self, subtree: Option<PathBuf>) -> Self {
+        self.subtree = subtree;
+        self
+    }
+
+    /// Only plan the jdex indexes and the maps of content, for when topics changed but the
+    /// directories are already where they belong
+    pub const fn index_only(mut self, index_only: bool) -> Self {
+        self.index_only = index_only;
+        self
+    }
+
+    /// Read the files already in the notes from `backend` instead of the disk
+    #[cfg(test)]
+    pub fn backend(mut self, backend: &'cfg dyn OutputBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// The actions generating the notes
+    pub fn notes(&self) -> Result<Vec<Action<'sys>>, Error> {
+        let output_config = self.output_config;
+        let system = self.system;
+        let base_path = &self.base_path;
+        let mut actions = notes::get_all_actions(base_path, system, self.extension);
+        actions.extend(
+            output_config
+                .inbox
+                .as_deref()
+                .map(|name| inbox::action(base_path, system, name)),
+        );
+        actions.extend(autocomplete::action(
+            base_path,
+            system,
+            self.extension,
+            output_config.autocomplete.as_deref(),
+        )?);
+        if output_config.area_mocs {
+            actions.extend(notes::get_moc_actions(base_path, system, self.extension));
+        }
+        notes::sort_actions(&mut actions, system);
+        notes::limit_depth(
+            &mut actions,
+            base_path,
+            output_config.notes.depth,
+            system.id.layout,
+        );
+        if let Some(attachments) = &output_config.attachments {
+            let attachments =
+                notes::get_attachment_actions(base_path, &actions, attachments, system.id.layout);
+            actions.extend(attachments);
+            notes::sort_actions(&mut actions, system);
+        }
+        if let Some(subtree) = &self.subtree {
+            notes::limit_to(&mut actions, base_path, subtree);
+        }
+        if self.index_only {
+            actions
+                .retain(|action| matches!(action, Action::WriteIndex(..) | Action::WriteMoc(..)));
+        }
+        Ok(actions)
+    }
+
+    /// The actions generating the reference archive, which a workspace doesn't have apart from
+    /// its notes, and which has no indexes to plan when only those are
+    pub fn archive(&self) -> Result<Vec<Action<'sys>>, Error> {
+        let output_config = self.output_config;
+        let system = self.system;
+        if system.is_workspace() || self.index_only {
+            return Ok(Vec::new());
+        }
+        // The links live in the notes, so it is the notes' ignore file that protects them
+        let notes_ignore = IgnoreRules::load(self.backend, &self.base_path)?;
+        let mirrors = notes::get_mirror_actions(
+            self.backend,
+            &self.base_path,
+            &self.reference_path,
+            system,
+            self.extension,
+            output_config.mirror,
+            self.subtree.as_deref(),
+        )?;
+        let directories: Vec<_> =
+            notes::get_all_actions(&self.reference_path, system, self.extension)
+                .into_iter()
+                .filter(|action| matches!(action, &Action::CreateDirectory(_)))
+                .collect();
+        let links = notes::get_link_actions(
+            &self.base_path,
+            &self.reference_path,
+            &directories,
+            system.id.layout,
+            output_config.link_style,
+            output_config.notes.depth.min(output_config.archive.depth),
+        );
+        let mut actions: Vec<_> = directories
+            .into_iter()
+            .chain(
+                output_config
+                    .inbox
+                    .as_deref()
+                    .map(|name| inbox::action(&self.reference_path, system, name)),
+            )
+            .chain(mirrors)
+            .collect();
+        notes::limit_depth(
+            &mut actions,
+            &self.reference_path,
+            output_config.archive.depth,
+            system.id.layout,
+        );
+        // The links come last, so that what they point at already exists
+        actions.extend(
+            links
+                .into_iter()
+                .filter(|action| !notes_ignore.is_ignored(action.path(), true)),
+        );
+        if let Some(subtree) = &self.subtree {
+            notes::limit_to(&mut actions, &self.reference_path, subtree);
+        }
+        Ok(actions)
+    }
+
+    /// The actions generating the notes, followed by the ones generating the reference archive
+    pub fn all(&self) -> Result<Vec<Action<'sys>>, Error> {
+        let mut actions = self.notes()?;
+        actions.extend(self.archive()?);
+        Ok(actions)
+    }
+
+    /// The actions of `all` that generation applies, leaving out the ones whose paths the
+    /// `.jdexignore` of their tree lists
+    pub fn generated(&self) -> Result<Vec<Action<'sys>>, Error> {
+        let notes_ignore = IgnoreRules::load(self.backend, &self.base_path)?;
+        let archive_ignore = IgnoreRules::load(self.backend, &self.reference_path)?;
+        let mut actions = self.notes()?;
+        actions.retain(|action| !action.is_ignored(&notes_ignore));
+        actions.extend(
+            self.archive()?
+                .into_iter()
+                .filter(|action| !action.is_ignored(&archive_ignore)),
+        );
+        Ok(actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        backend::MemoryFs, config::SystemParameters, depth::TreeConfig, link::LinkStyle,
+        mirror::MirrorMode, test_support::parameters,
+    };
+
+    fn build(workspace: bool) -> System {
+        System::try_from(SystemParameters {
+            workspace,
+            ..parameters(
+                "20-29 Finance\n\t21 Tax\n\t\t00 !JDex\n\t\t05 -Returns\n\t22 Bank\n\t\t01 Statements\n",
+            )
+        })
+        .unwrap()
+    }
+
+    fn output_config() -> config::Output {
+        config::Output {
+            base_folder: "/notes".to_owned(),
+            reference_folder: "/archive".to_owned(),
+            link_style: LinkStyle::Symlink,
+            mirror: MirrorMode::None,
+            notes: TreeConfig::default(),
+            archive: TreeConfig::default(),
+            inbox: Some("Inbox".to_owned()),
+            attachments: None,
+            state_folder: None,
+            autocomplete: None,
+            area_mocs: false,
+            note_counts: false,
+            targets: Vec::new(),
+        }
+    }
+
+    fn paths(actions: &[Action<'_>]) -> Vec<String> {
+        actions
+            .iter()
+            .map(|action| action.path().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_planner() {
+        let system = build(false);
+        let output_config = output_config();
+        let backend = MemoryFs::default();
+        let planner = Planner::new(&output_config, &system, "md")
+            .unwrap()
+            .backend(&backend);
+        let notes = planner.notes().unwrap();
+        let finance = "/notes/L01/L01.20-29 Finance";
+        assert!(paths(&notes).contains(&format!("{finance}/L01.21 Tax/L01.21.05 Returns.md")));
+        assert!(paths(&notes).contains(&"/notes/L01/Inbox".to_owned()));
+        let archive = planner.archive().unwrap();
+        // The archive only has directories, with the links into it last
+        assert!(archive
+            .iter()
+            .all(|action| matches!(action, Action::CreateDirectory(_) | Action::CreateLink(..))));
+        assert!(matches!(archive.last(), Some(Action::CreateLink(..))));
+        assert_eq!(planner.all().unwrap().len(), notes.len() + archive.len());
+
+        let limited = Planner::new(&output_config, &system, "md")
+            .unwrap()
+            .backend(&backend)
+            .subtree(Some(
+                Path::new("L01/L01.20-29 Finance/L01.22 Bank").to_path_buf(),
+            ));
+        let limited = paths(&limited.all().unwrap());
+        assert!(limited
+            .iter()
+            .any(|path| path.contains("L01.22.01 Statements")));
+        assert!(!limited.iter().any(|path| path.contains("L01.21 Tax")));
+
+        let indexes = Planner::new(&output_config, &system, "md")
+            .unwrap()
+            .backend(&backend)
+            .index_only(true);
+        assert!(indexes.archive().unwrap().is_empty());
+        let indexes = indexes.notes().unwrap();
+        assert_eq!(indexes.len(), 1);
+        assert!(indexes
+            .iter()
+            .all(|action| matches!(action, Action::WriteIndex(..))));
+
+        let workspace = build(true);
+        let planner = Planner::new(&output_config, &workspace, "md")
+            .unwrap()
+            .backend(&backend);
+        assert!(planner.archive().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_generated() {
+        let system = build(false);
+        let output_config = output_config();
+        let mut backend = MemoryFs::default();
+        backend
+            .write(Path::new("/notes/.jdexignore"), "L01.22 Bank/\n")
+            .unwrap();
+        backend
+            .write(Path::new("/archive/.jdexignore"), "L01.21 Tax/\n")
+            .unwrap();
+        let planner = Planner::new(&output_config, &system, "md")
+            .unwrap()
+            .backend(&backend);
+        let generated = paths(&planner.generated().unwrap());
+        assert!(generated
+            .iter()
+            .any(|path| path.starts_with("/notes/") && path.contains("L01.21 Tax")));
+        assert!(!generated
+            .iter()
+            .any(|path| path.starts_with("/notes/") && path.contains("L01.22 Bank")));
+        assert!(generated
+            .iter()
+            .any(|path| path.starts_with("/archive/") && path.contains("L01.22 Bank")));
+        assert!(!generated
+            .iter()
+            .any(|path| path.starts_with("/archive/") && path.contains("L01.21 Tax")));
+    }
+}
@@ -0,0 +1,141 @@
+use std::io::{BufRead, Write};
+
+use color_eyre::eyre::Error;
+
+/// Asks the questions of a command on `input` and `output`, or answers them itself so the command
+/// can run in a script
+pub struct Prompt<R, W> {
+    /// Where the answers are read from
+    input: R,
+    /// Where the questions, and everything else the command prints, are written
+    output: W,
+    /// Confirm everything and take the suggested answer to every question, without asking
+    yes: bool,
+    /// Never wait for an answer, failing on whatever can't be answered without one
+    non_interactive: bool,
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    /// Ask on `input` and `output`
+    pub const fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            yes: false,
+            non_interactive: false,
+        }
+    }
+
+    /// Answer without asking, confirming everything when `yes` is given, and failing on what
+    /// needs an answer when `non_interactive` is given
+    pub const fn answering(mut self, yes: bool, non_interactive: bool) -> Self {
+        self.yes = yes;
+        self.non_interactive = non_interactive;
+        self
+    }
+
+    /// Whether the questions are asked, rather than answered without asking
+    pub const fn is_interactive(&self) -> bool {
+        !self.yes && !self.non_interactive
+    }
+
+    /// Where everything the command prints goes
+    pub const fn output(&mut self) -> &mut W {
+        &mut self.output
+    }
+
+    /// Ask `question` and read the answer, which is `suggested` when it is empty and `None` when
+    /// the input has ended.
+    ///
+    /// Without asking, the suggested answer is taken, and without one the question fails.
+    pub fn ask(
+        &mut self,
+        question: &str,
+        suggested: Option<&str>,
+    ) -> Result<Option<String>, Error> {
+        if self.yes || self.non_interactive {
+            let answer = suggested.ok_or_else(|| {
+                Error::msg(format!(
+                    "`{question}` needs an answer, which isn't asked for with --yes or --non-interactive"
+                ))
+            })?;
+            return Ok(Some(answer.to_owned()));
+        }
+        match suggested {
+            Some(answer) => write!(self.output, "{question} [{answer}] ")?,
+            None => write!(self.output, "{question} ")?,
+        }
+        self.output.flush()?;
+        let mut answer = String::new();
+        if self.input.read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+        let answer = answer.trim();
+        Ok(Some(match suggested {
+            Some(suggested) if answer.is_empty() => suggested.to_owned(),
+            _ => answer.to_owned(),
+        }))
+    }
+
+    /// Ask to confirm `question`, which is only confirmed by answering `y` or `yes`.
+    ///
+    /// `--yes` confirms it without asking, and `--non-interactive` alone fails instead, so
+    /// nothing is changed that wasn't confirmed.
+    pub fn confirm(&mut self, question: &str) -> Result<bool, Error> {
+        if self.yes {
+            return Ok(true);
+        }
+        if self.non_interactive {
+            return Err(Error::msg(format!(
+                "`{question}` needs confirming, give --yes to confirm it without asking"
+            )));
+        }
+        write!(self.output, "{question} [y/N] ")?;
+        self.output.flush()?;
+        let mut answer = String::new();
+        self.input.read_line(&mut answer)?;
+        Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ask() {
+        let mut printed = Vec::new();
+        let mut prompt = Prompt::new(&b"\nScans\n"[..], &mut printed);
+        assert_eq!(
+            prompt.ask("Topic:", Some("scans")).unwrap().as_deref(),
+            Some("scans")
+        );
+        assert_eq!(
+            prompt.ask("Topic:", None).unwrap().as_deref(),
+            Some("Scans")
+        );
+        assert_eq!(prompt.ask("Topic:", None).unwrap(), None);
+        assert_eq!(
+            String::from_utf8(printed).unwrap(),
+            "Topic: [scans] Topic: Topic: "
+        );
+        let mut scripted = Prompt::new(&b"Scans\n"[..], Vec::new()).answering(false, true);
+        assert_eq!(
+            scripted.ask("Topic:", Some("scans")).unwrap().as_deref(),
+            Some("scans")
+        );
+        assert!(scripted.ask("Topic:", None).is_err());
+    }
+
+    #[test]
+    fn test_confirm() {
+        let mut prompt = Prompt::new(&b"YES\nn\n"[..], Vec::new());
+        assert!(prompt.confirm("Apply?").unwrap());
+        assert!(!prompt.confirm("Apply?").unwrap());
+        assert!(!prompt.confirm("Apply?").unwrap());
+        let mut yes = Prompt::new(&b"n\n"[..], Vec::new()).answering(true, true);
+        assert!(yes.confirm("Apply?").unwrap());
+        let mut scripted = Prompt::new(&b"y\n"[..], Vec::new()).answering(false, true);
+        assert!(scripted.confirm("Apply?").is_err());
+    }
+}
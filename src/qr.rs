@@ -0,0 +1,418 @@
+use core::fmt::Write as _;
+
+use color_eyre::eyre::Error;
+
+/// The error correction layout of a version at level M:
+/// (error correction codewords per block, [(block count, data codewords per block)])
+const LEVEL_M_BLOCKS: [(usize, [(usize, usize); 2]); 10] = [
+    (10, [(1, 16), (0, 0)]),
+    (16, [(1, 28), (0, 0)]),
+    (26, [(1, 44), (0, 0)]),
+    (18, [(2, 32), (0, 0)]),
+    (24, [(2, 43), (0, 0)]),
+    (16, [(4, 27), (0, 0)]),
+    (18, [(4, 31), (0, 0)]),
+    (22, [(2, 38), (2, 39)]),
+    (22, [(3, 36), (2, 37)]),
+    (26, [(4, 43), (1, 44)]),
+];
+
+/// The centers of the alignment patterns for each version
+const ALIGNMENT_POSITIONS: [&[usize]; 10] = [
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+];
+
+/// The error correction level M, as written into the format information
+const LEVEL_M: u32 = 0b00;
+
+/// The mask pattern we always apply, `(row + column) % 2 == 0`
+const MASK: u32 = 0;
+
+/// How many light modules a scanner needs around the symbol on every side
+pub const QUIET_ZONE: usize = 4;
+
+/// A square grid of dark and light modules
+#[derive(Debug)]
+pub struct QrCode {
+    /// The number of modules on each side
+    size: usize,
+    /// Whether each module is dark, row by row
+    modules: Vec<bool>,
+    /// Whether each module belongs to a function pattern and must not hold data
+    reserved: Vec<bool>,
+}
+
+/// Multiply two elements of GF(256) with the QR code polynomial
+const fn gf_multiply(left: u8, right: u8) -> u8 {
+    let mut result: u16 = 0;
+    let mut bit = 7;
+    loop {
+        result = (result << 1) ^ ((result >> 7) * 0x11D);
+        result ^= ((right as u16 >> bit) & 1) * left as u16;
+        if bit == 0 {
+            break;
+        }
+        bit -= 1;
+    }
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "result is reduced to 8 bits"
+    )]
+    let reduced = result as u8;
+    reduced
+}
+
+/// Compute the Reed-Solomon error correction codewords for a block of data
+fn reed_solomon(data: &[u8], degree: usize) -> Vec<u8> {
+    let mut generator = vec![0_u8; degree];
+    if let Some(last) = generator.last_mut() {
+        *last = 1;
+    }
+    let mut root: u8 = 1;
+    for _ in 0..degree {
+        for index in 0..degree {
+            let next = generator.get(index.saturating_add(1)).copied().unwrap_or(0);
+            if let Some(coefficient) = generator.get_mut(index) {
+                *coefficient = gf_multiply(*coefficient, root) ^ next;
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+
+    let mut remainder = vec![0_u8; degree];
+    for byte in data {
+        let factor = byte ^ remainder.first().copied().unwrap_or(0);
+        remainder.remove(0);
+        remainder.push(0);
+        for (slot, coefficient) in remainder.iter_mut().zip(&generator) {
+            *slot ^= gf_multiply(*coefficient, factor);
+        }
+    }
+    remainder
+}
+
+/// Compute a BCH code by appending the remainder of `value` divided by `generator`
+const fn bch(value: u32, data_bits: u32, generator: u32, generator_bits: u32) -> u32 {
+    let check_bits = generator_bits - 1;
+    let mut remainder = value << check_bits;
+    let mut bit = data_bits + check_bits;
+    while bit >= generator_bits {
+        bit -= 1;
+        if (remainder >> bit) & 1 == 1 {
+            remainder ^= generator << (bit + 1 - generator_bits);
+        }
+    }
+    (value << check_bits) | remainder
+}
+
+/// The 15 bits of format information for level M with our mask
+const fn format_bits() -> u32 {
+    bch((LEVEL_M << 3) | MASK, 5, 0b101_0011_0111, 11) ^ 0b101_0100_0001_0010
+}
+
+/// The 18 bits of version information, only used from version 7 up
+const fn version_bits(version: usize) -> u32 {
+    #[expect(clippy::cast_possible_truncation, reason = "versions are at most 10")]
+    let version = version as u32;
+    bch(version, 6, 0b1_1111_0010_0101, 13)
+}
+
+/// Append `count` bits of `value` to the bit stream
+fn push_bits(bits: &mut Vec<bool>, value: usize, count: usize) {
+    for bit in (0..count).rev() {
+        bits.push((value >> bit) & 1 == 1);
+    }
+}
+
+impl QrCode {
+    /// Encode the text as bytes into the smallest version that fits it
+    pub fn encode(text: &str) -> Result<Self, Error> {
+        let bytes = text.as_bytes();
+        let (version, (ec_per_block, groups)) = LEVEL_M_BLOCKS
+            .iter()
+            .enumerate()
+            .map(|(index, layout)| (index.saturating_add(1), layout))
+            .find(|&(version, &(_, groups))| {
+                let capacity: usize = groups.iter().map(|&(count, size)| count * size).sum();
+                let count_bits = if version < 10 { 8 } else { 16 };
+                4 + count_bits + bytes.len() * 8 <= capacity * 8
+            })
+            .ok_or_else(|| Error::msg(format!("`{text}` is too long for a QR code")))?;
+
+        let capacity: usize = groups.iter().map(|&(count, size)| count * size).sum();
+        let mut bits = Vec::with_capacity(capacity * 8);
+        push_bits(&mut bits, 0b0100, 4);
+        push_bits(&mut bits, bytes.len(), if version < 10 { 8 } else { 16 });
+        for byte in bytes {
+            push_bits(&mut bits, usize::from(*byte), 8);
+        }
+        let terminator = (capacity * 8 - bits.len()).min(4);
+        push_bits(&mut bits, 0, terminator);
+        let padding = (8 - bits.len() % 8) % 8;
+        push_bits(&mut bits, 0, padding);
+        let mut data: Vec<u8> = bits
+            .chunks(8)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold(0_u8, |byte, &bit| (byte << 1) | u8::from(bit))
+            })
+            .collect();
+        for pad in [0xEC, 0x11].into_iter().cycle() {
+            if data.len() >= capacity {
+                break;
+            }
+            data.push(pad);
+        }
+
+        let mut blocks = Vec::new();
+        let mut remaining = data.as_slice();
+        for &(count, size) in groups {
+            for _ in 0..count {
+                let (block, rest) = remaining.split_at(size);
+                blocks.push((block, reed_solomon(block, *ec_per_block)));
+                remaining = rest;
+            }
+        }
+        let longest = groups.iter().map(|&(_, size)| size).max().unwrap_or(0);
+        let mut codewords = Vec::new();
+        for index in 0..longest {
+            codewords.extend(blocks.iter().filter_map(|(block, _)| block.get(index)));
+        }
+        for index in 0..*ec_per_block {
+            codewords.extend(blocks.iter().filter_map(|(_, ec)| ec.get(index)));
+        }
+
+        let mut code = Self::with_function_patterns(version);
+        code.place_data(&codewords);
+        Ok(code)
+    }
+
+    /// The number of modules on each side
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module at `row`, `column` is dark
+    pub fn is_dark(&self, row: usize, column: usize) -> bool {
+        self.modules
+            .get(row * self.size + column)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Set a module and mark it as part of a function pattern
+    fn set_function(&mut self, row: usize, column: usize, dark: bool) {
+        let index = row * self.size + column;
+        if let (Some(module), Some(reserved)) =
+            (self.modules.get_mut(index), self.reserved.get_mut(index))
+        {
+            *module = dark;
+            *reserved = true;
+        }
+    }
+
+    /// Draw a finder pattern with its separator, centered on `row`, `column`
+    fn draw_finder(&mut self, row: usize, column: usize) {
+        for delta_row in -4_isize..=4 {
+            for delta_column in -4_isize..=4 {
+                let (Some(module_row), Some(module_column)) = (
+                    row.checked_add_signed(delta_row),
+                    column.checked_add_signed(delta_column),
+                ) else {
+                    continue;
+                };
+                if module_row >= self.size || module_column >= self.size {
+                    continue;
+                }
+                let distance = delta_row.abs().max(delta_column.abs());
+                self.set_function(module_row, module_column, distance != 2 && distance != 4);
+            }
+        }
+    }
+
+    /// Draw an alignment pattern centered on `row`, `column`
+    fn draw_alignment(&mut self, row: usize, column: usize) {
+        for delta_row in -2_isize..=2 {
+            for delta_column in -2_isize..=2 {
+                let distance = delta_row.abs().max(delta_column.abs());
+                self.set_function(
+                    row.saturating_add_signed(delta_row),
+                    column.saturating_add_signed(delta_column),
+                    distance != 1,
+                );
+            }
+        }
+    }
+
+    /// Build an empty code of the given version with every function pattern drawn
+    fn with_function_patterns(version: usize) -> Self {
+        let size = version * 4 + 17;
+        let mut code = Self {
+            size,
+            modules: vec![false; size * size],
+            reserved: vec![false; size * size],
+        };
+        for index in 0..size {
+            code.set_function(6, index, index % 2 == 0);
+            code.set_function(index, 6, index % 2 == 0);
+        }
+        code.draw_finder(3, 3);
+        code.draw_finder(3, size - 4);
+        code.draw_finder(size - 4, 3);
+
+        let positions = ALIGNMENT_POSITIONS
+            .get(version.saturating_sub(1))
+            .copied()
+            .unwrap_or_default();
+        let last = positions.len().saturating_sub(1);
+        for (row_index, &row) in positions.iter().enumerate() {
+            for (column_index, &column) in positions.iter().enumerate() {
+                let overlaps_finder = (row_index == 0
+                    && (column_index == 0 || column_index == last))
+                    || (row_index == last && column_index == 0);
+                if !overlaps_finder {
+                    code.draw_alignment(row, column);
+                }
+            }
+        }
+
+        let format = format_bits();
+        for bit in 0..15 {
+            let dark = (format >> bit) & 1 == 1;
+            let (row, column) = match bit {
+                0..=5 => (bit, 8),
+                6 => (7, 8),
+                7 => (8, 8),
+                8 => (8, 7),
+                _ => (8, 14 - bit),
+            };
+            code.set_function(row, column, dark);
+            let (row, column) = if bit < 8 {
+                (8, size - 1 - bit)
+            } else {
+                (size - 15 + bit, 8)
+            };
+            code.set_function(row, column, dark);
+        }
+        code.set_function(size - 8, 8, true);
+
+        if version >= 7 {
+            let bits = version_bits(version);
+            for bit in 0..18 {
+                let dark = (bits >> bit) & 1 == 1;
+                let near = bit / 3;
+                let far = size - 11 + bit % 3;
+                code.set_function(near, far, dark);
+                code.set_function(far, near, dark);
+            }
+        }
+        code
+    }
+
+    /// Place the codewords in the zigzag order, applying the mask as we go
+    fn place_data(&mut self, codewords: &[u8]) {
+        let mut bit_index = 0;
+        let total_bits = codewords.len() * 8;
+        let mut right = self.size - 1;
+        let mut upward = true;
+        loop {
+            if right == 6 {
+                right -= 1;
+            }
+            for vertical in 0..self.size {
+                let row = if upward {
+                    self.size - 1 - vertical
+                } else {
+                    vertical
+                };
+                for column in [right, right - 1] {
+                    let index = row * self.size + column;
+                    if self.reserved.get(index).copied().unwrap_or(true) {
+                        continue;
+                    }
+                    let mut dark = false;
+                    if bit_index < total_bits {
+                        let byte = codewords.get(bit_index / 8).copied().unwrap_or(0);
+                        dark = (byte >> (7 - bit_index % 8)) & 1 == 1;
+                        bit_index += 1;
+                    }
+                    if (row + column) % 2 == 0 {
+                        dark = !dark;
+                    }
+                    if let Some(module) = self.modules.get_mut(index) {
+                        *module = dark;
+                    }
+                }
+            }
+            upward = !upward;
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    /// Draw the code as SVG path data, offset by `x`, `y` with `scale` units per module
+    pub fn svg_path(&self, x: usize, y: usize, scale: usize) -> String {
+        let mut path = String::new();
+        for row in 0..self.size {
+            for column in 0..self.size {
+                if self.is_dark(row, column) {
+                    let _ = write!(
+                        path,
+                        "M{},{}h{scale}v{scale}h-{scale}z",
+                        x + column * scale,
+                        y + row * scale
+                    );
+                }
+            }
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reed_solomon() {
+        // The "HELLO WORLD" 1-M example from the QR code specification
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            reed_solomon(&data, 10),
+            vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+    }
+
+    #[test]
+    fn test_format_bits() {
+        // Level M with mask 0 from the format information table
+        assert_eq!(format_bits(), 0b101_0100_0001_0010);
+    }
+
+    #[test]
+    fn test_version_bits() {
+        assert_eq!(version_bits(7), 0b00_0111_1100_1001_0100);
+    }
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(QrCode::encode("21.05").unwrap().size(), 21);
+        let link = "obsidian://open?path=%2Fhome%2Fuser%2Fnotes%2FN01%2FN01.20-29%20Finance";
+        assert_eq!(QrCode::encode(link).unwrap().size(), 37);
+        assert!(QrCode::encode(&"x".repeat(300)).is_err());
+    }
+}
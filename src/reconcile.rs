@@ -0,0 +1,422 @@
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Error;
+
+use crate::{
+    backend::OutputBackend,
+    ignore::IgnoreRules,
+    layout::NAMESPACE_SEPARATOR,
+    model::System,
+    notes::{self, Action},
+    output::{self, Mark},
+    resolve,
+};
+
+/// Files that operating systems and sync clients keep in every folder, which are never looked at
+pub const SYSTEM_FILES: &[&str] = &[
+    ".DS_Store",
+    "desktop.ini",
+    "Thumbs.db",
+    ".stfolder",
+    ".stversions",
+];
+
+/// A change that makes an existing folder match the system definition
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The entry has the id of a planned entry, but not its name or place
+    Rename(PathBuf, PathBuf),
+    /// The entry has an id that is no longer in the system
+    Delete(PathBuf),
+    /// The entry is a copy a sync client made when it couldn't merge two versions of a file
+    Conflict(PathBuf),
+}
+
+impl Change {
+    /// Print out what would be done to reconcile the folder
+    pub fn dry_run(&self) -> String {
+        match self {
+            Self::Rename(from, to) => output::line(
+                Mark::Changed,
+                "Would Rename",
+                &format!("{} to {}", output::path(from), output::path(to)),
+            ),
+            Self::Delete(path) => output::line(Mark::Deleted, "Would Delete", &output::path(path)),
+            Self::Conflict(path) => output::line(Mark::Deleted, "Conflict", &output::path(path)),
+        }
+    }
+
+    /// The path an entry would be renamed to
+    pub fn renamed_to(&self) -> Option<&Path> {
+        match self {
+            Self::Rename(_, to) => Some(to),
+            Self::Delete(_) | Self::Conflict(_) => None,
+        }
+    }
+}
+
+impl Display for Change {
+    #[allow(
+        clippy::min_ident_chars,
+        reason = "This is the preferred default name for the variable"
+    )]
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Rename(from, to) => {
+                write!(f, "Rename {} to {}", from.display(), to.display())
+            }
+            Self::Delete(path) => write!(f, "Delete {}", path.display()),
+            Self::Conflict(path) => write!(f, "Conflict {}", path.display()),
+        }
+    }
+}
+
+/// What the walk over an existing folder compares against
+struct Expected<'plan> {
+    /// The system, for reading the ids in names
+    system: &'plan System,
+    /// The prefix every generated name starts with
+    prefix: String,
+    /// The extension of generated files, when files are generated at all
+    extension: Option<&'plan str>,
+    /// Every planned path
+    paths: HashSet<&'plan Path>,
+    /// The planned path for each id, split by whether it is a directory
+    by_id: HashMap<(&'plan str, bool), &'plan Path>,
+    /// The ids that are retired or frozen, which are left alone wherever they are
+    retired: HashSet<String>,
+    /// The paths listed in the `.jdexignore` of the folder
+    ignore: IgnoreRules,
+    /// The entry the plan is limited to, outside of which nothing is looked at
+    within: Option<PathBuf>,
+}
+
+/// The id at the start of a generated name, like `N01.00.01` in `N01.00.01 Inbox.md`, or at the
+/// start of its last part when the name is a Logseq namespace
+fn id_of(name: &str) -> &str {
+    let last = name.rsplit(NAMESPACE_SEPARATOR).next().unwrap_or(name);
+    last.split(' ').next().unwrap_or(last)
+}
+
+/// Whether `name` is a copy made by a sync client for a conflict, like Dropbox's
+/// `Returns (conflicted copy 2024-05-01).md` or Syncthing's
+/// `Returns.sync-conflict-20240501-101500-ABCDEFG.md`
+pub fn is_conflict(name: &str) -> bool {
+    name.contains(" (conflicted copy") || name.contains(".sync-conflict-")
+}
+
+/// Whether `id` has the shape of an entry of the system, like `L01.21.05` or `L01.21.05.X20`,
+/// rather than of something filed under one, like `L01.21.05.01`, skipping the system id unless
+/// the system hides it
+fn is_entry_id(id: &str, hide_system: bool) -> bool {
+    let skipped = usize::from(!hide_system);
+    match id.split('.').skip(skipped).collect::<Vec<_>>().as_slice() {
+        [_] | [_, _] => true,
+        [_, _, xfolder] => !xfolder.chars().all(|character| character.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+/// Every retired or frozen id in the system
+fn retired_ids(system: &System) -> HashSet<String> {
+    let mut retired = HashSet::new();
+    for category in system.areas.iter().flat_map(|area| &area.categories) {
+        if category.retired || category.frozen {
+            retired.insert(category.id.by_seperator("."));
+        }
+        for folder in &category.folders {
+            if folder.retired || folder.frozen {
+                retired.insert(folder.id.by_seperator("."));
+            }
+            for xfolder in folder
+                .folders
+                .iter()
+                .filter(|xfolder| xfolder.retired || xfolder.frozen)
+            {
+                retired.insert(xfolder.id.by_seperator("."));
+            }
+        }
+    }
+    retired
+}
+
+impl Expected<'_> {
+    /// Whether the entry with `id` in `dir` is filed under the id of `dir` instead of being an
+    /// entry of the system, like `21.05.01 Invoice.md` or a note named after the folder in
+    /// `L01.21.05 Returns`
+    fn is_filed_in(&self, dir: &Path, id: &str) -> bool {
+        let dir_id = dir
+            .file_name()
+            .and_then(|name| resolve::id_in_name(self.system, &name.to_string_lossy()));
+        let (Some(dir_id), Some(id)) = (dir_id, resolve::id_in_name(self.system, id)) else {
+            return false;
+        };
+        id == dir_id
+            || (id.starts_with(&format!("{dir_id}."))
+                && !is_entry_id(&id, self.system.id.hide_system))
+    }
+
+    /// Compare the entries of `dir` against the plan, going into the directories that match it
+    fn visit(
+        &self,
+        backend: &dyn OutputBackend,
+        dir: &Path,
+        changes: &mut Vec<Change>,
+    ) -> Result<(), Error> {
+        let mut entries = backend.read_dir(dir)?;
+        entries.sort();
+        for path in entries {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let outside = self.within.as_ref().is_some_and(|within| {
+                !within.starts_with(&path) && !notes::is_within(&path, within)
+            });
+            if outside {
+                continue;
+            }
+            let is_dir = backend.is_dir(&path);
+            if SYSTEM_FILES.contains(&name.as_str()) || self.ignore.is_ignored(&path, is_dir) {
+                continue;
+            }
+            if is_conflict(&name) {
+                changes.push(Change::Conflict(path));
+                continue;
+            }
+            // Only names that jdexmd could have generated are ever touched, which are the ones
+            // that start with an id when the system id is hidden
+            let prefixed = if self.prefix.is_empty() {
+                resolve::id_in_name(self.system, &name).is_some()
+            } else {
+                name.starts_with(&self.prefix)
+            };
+            let generated = prefixed
+                && (is_dir
+                    || self.extension.is_some_and(|extension| {
+                        path.extension()
+                            .is_some_and(|found| found.to_string_lossy() == extension)
+                    }));
+            if !generated {
+                continue;
+            }
+            if self.paths.contains(path.as_path()) {
+                if is_dir {
+                    self.visit(backend, &path, changes)?;
+                }
+                continue;
+            }
+            let id = id_of(&name);
+            let id = if is_dir {
+                id
+            } else {
+                id.trim_end_matches(&format!(".{}", self.extension.unwrap_or_default()))
+            };
+            if self.retired.contains(id) || self.is_filed_in(dir, id) {
+                continue;
+            }
+            // In the flat layout, the categories in the directory of an area are moved out of it
+            // rather than deleted along with it
+            let is_area = self.system.areas.iter().any(|area| area.id.matches(id));
+            if is_dir && is_area && !self.system.id.layout.scheme().has_area_directories() {
+                self.visit(backend, &path, changes)?;
+                continue;
+            }
+            match self.by_id.get(&(id, is_dir)) {
+                Some(target)
+                    if backend.exists(target) || self.ignore.is_ignored(target, is_dir) => {}
+                Some(target) => changes.push(Change::Rename(path, target.to_path_buf())),
+                None => changes.push(Change::Delete(path)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Find what would have to be renamed or deleted under `base_folder` for it to match the plan,
+/// along with the conflicted copies that sync clients left in it.
+///
+/// `files` says whether generated files belong in the folder at all, which isn't the case for the
+/// reference archive. Anything listed in the `.jdexignore` of `base_folder` is left out, and so is
+/// everything outside of `subtree` when the plan is limited to an entry. Nothing is changed on
+/// disk.
+pub fn plan(
+    backend: &dyn OutputBackend,
+    base_folder: &str,
+    system: &System,
+    actions: &[Action<'_>],
+    extension: &str,
+    files: bool,
+    subtree: Option<&Path>,
+) -> Result<Vec<Change>, Error> {
+    let base_folder = notes::expand(base_folder)?;
+    let root = base_folder.join(system.id.as_path());
+    if !backend.is_dir(&root) {
+        return Ok(Vec::new());
+    }
+    let mut by_id = HashMap::new();
+    for action in actions {
+        let is_dir = matches!(action, Action::CreateDirectory(_));
+        let path = action.path();
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            let id = if is_dir {
+                id_of(name)
+            } else {
+                id_of(name.trim_end_matches(&format!(".{extension}")))
+            };
+            by_id.insert((id, is_dir), path.as_path());
+        }
+    }
+    let expected = Expected {
+        system,
+        prefix: if system.id.hide_system {
+            String::new()
+        } else {
+            format!("{}.", system.id.by_seperator("."))
+        },
+        extension: files.then_some(extension),
+        paths: actions
+            .iter()
+            .map(|action| action.path().as_path())
+            .collect(),
+        by_id,
+        retired: retired_ids(system),
+        ignore: IgnoreRules::load(backend, &base_folder)?,
+        within: subtree.map(|subtree| base_folder.join(subtree)),
+    };
+    let mut changes = Vec::new();
+    expected.visit(backend, &root, &mut changes)?;
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::MemoryFs,
+        config::SystemParameters,
+        layout::Layout,
+        test_support::{parameters, system},
+    };
+
+    #[test]
+    fn test_plan_renames_and_deletes() {
+        let meta = PathBuf::from("/vault/L01/L01.00-09 System/L01.00 Meta");
+        let mut memory = MemoryFs::default();
+        memory
+            .create_dir_all(&meta.join("L01.00.01 Old Name"))
+            .unwrap();
+        memory
+            .create_dir_all(&meta.join("L01.00.02 Removed"))
+            .unwrap();
+        memory
+            .create_dir_all(&meta.join("L01.00.03 Retired"))
+            .unwrap();
+        memory.create_dir_all(&meta.join("Not Managed")).unwrap();
+        memory.write(&meta.join("L01.00.04 Notes.md"), "").unwrap();
+        // Filed under the category, so they are left alone
+        memory
+            .write(&meta.join("L01.00.01.01 Invoice.md"), "")
+            .unwrap();
+        memory.write(&meta.join("L01.00 Scratch.md"), "").unwrap();
+        let system = system("00-09 System\n\t00 Meta\n\t\t01 Inbox\n\t\t03 Retired [retired]\n");
+        let actions = notes::get_all_actions(Path::new("/vault"), &system, "md");
+        let changes = plan(&memory, "/vault", &system, &actions, "md", true, None).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                Change::Rename(
+                    meta.join("L01.00.01 Old Name"),
+                    meta.join("L01.00.01 Inbox")
+                ),
+                Change::Delete(meta.join("L01.00.02 Removed")),
+                Change::Delete(meta.join("L01.00.04 Notes.md")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_leaves_ignored_paths_alone() {
+        let meta = PathBuf::from("/vault/L01/L01.00-09 System/L01.00 Meta");
+        let mut memory = MemoryFs::default();
+        memory.create_dir_all(&meta.join("L01.00.02 Mine")).unwrap();
+        memory.write(&meta.join("L01.00.05 Draft.md"), "").unwrap();
+        memory
+            .write(Path::new("/vault/.jdexignore"), "L01.00.02 Mine/\n*.md\n")
+            .unwrap();
+        let system = system("00-09 System\n\t00 Meta\n\t\t01 Inbox\n");
+        let actions = notes::get_all_actions(Path::new("/vault"), &system, "md");
+        let changes = plan(&memory, "/vault", &system, &actions, "md", true, None).unwrap();
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_is_conflict() {
+        assert!(is_conflict(
+            "L01.00.01 Inbox (conflicted copy 2024-05-01).md"
+        ));
+        assert!(is_conflict(
+            "L01.00.01 Inbox.sync-conflict-20240501-101500-ABCDEFG.md"
+        ));
+        assert!(!is_conflict("L01.00.01 Conflicts.md"));
+    }
+
+    #[test]
+    fn test_plan_flattens_areas() {
+        let meta = PathBuf::from("/vault/L01/L01.00-09 System/L01.00 Meta");
+        let mut memory = MemoryFs::default();
+        memory
+            .create_dir_all(&meta.join("L01.00.01 Inbox"))
+            .unwrap();
+        let system = System::try_from(SystemParameters {
+            layout: Layout::Flat,
+            ..parameters("00-09 System\n\t00 Meta\n\t\t01 Inbox\n")
+        })
+        .unwrap();
+        let actions = notes::get_all_actions(Path::new("/vault"), &system, "md");
+        let changes = plan(&memory, "/vault", &system, &actions, "md", true, None).unwrap();
+        assert_eq!(
+            changes,
+            vec![Change::Rename(
+                meta,
+                PathBuf::from("/vault/L01/L01.00 Meta")
+            )]
+        );
+    }
+
+    #[test]
+    fn test_plan_within_subtree() {
+        let system_path = PathBuf::from("/vault/L01/L01.00-09 System");
+        let mut memory = MemoryFs::default();
+        memory
+            .create_dir_all(&system_path.join("L01.00 Meta/L01.00.02 Removed"))
+            .unwrap();
+        memory
+            .create_dir_all(&system_path.join("L01.01 Backups/L01.01.02 Removed"))
+            .unwrap();
+        let system = system("00-09 System\n\t00 Meta\n\t\t01 Inbox\n\t01 Backups\n\t\t01 Phone\n");
+        let subtree = PathBuf::from("L01/L01.00-09 System/L01.01 Backups");
+        let mut actions = notes::get_all_actions(Path::new("/vault"), &system, "md");
+        notes::limit_to(&mut actions, Path::new("/vault"), &subtree);
+        let changes = plan(
+            &memory,
+            "/vault",
+            &system,
+            &actions,
+            "md",
+            true,
+            Some(&subtree),
+        )
+        .unwrap();
+        assert_eq!(
+            changes,
+            vec![Change::Delete(
+                system_path.join("L01.01 Backups/L01.01.02 Removed")
+            )]
+        );
+    }
+}
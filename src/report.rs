@@ -0,0 +1,154 @@
+use core::fmt::Write as _;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Error;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{notes, notes::Action, timestamp::Timestamp};
+
+/// The format the report is written in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    /// A markdown note, so the reports can live in the vault
+    Markdown,
+    /// JSON, for other tools to read
+    Json,
+}
+
+impl ReportFormat {
+    /// The file extension for reports in this format
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// Where and how the report of each generation is written
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ReportConfig {
+    /// The folder the reports are written into
+    pub folder: String,
+    /// The format of the reports
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+/// A summary of a single generation
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// When the generation started
+    pub generated_at: String,
+    /// The config file that defined the system
+    pub config_file: PathBuf,
+    /// The actions that were applied
+    pub created: Vec<String>,
+    /// The paths that already existed and were left alone
+    pub skipped: Vec<String>,
+    /// Problems found in the system definition
+    pub warnings: Vec<String>,
+    /// When the generation started, for naming the report
+    #[serde(skip)]
+    started: Timestamp,
+}
+
+impl Report {
+    /// Start a report for a generation from `config_file`
+    pub fn new(config_file: &Path, started: Timestamp) -> Self {
+        Self {
+            generated_at: started.to_string(),
+            config_file: config_file.to_path_buf(),
+            created: Vec::new(),
+            skipped: Vec::new(),
+            warnings: Vec::new(),
+            started,
+        }
+    }
+
+    /// Record an action that was applied
+    pub fn created(&mut self, action: &Action<'_>) {
+        self.created.push(action.to_string());
+    }
+
+    /// Record an action that wasn't needed because its path already exists
+    pub fn skipped(&mut self, action: &Action<'_>) {
+        self.skipped.push(action.path().display().to_string());
+    }
+
+    /// Render the report as a markdown note
+    fn to_markdown(&self) -> String {
+        let mut markdown = format!(
+            "# jdexmd report {}\n\nGenerated from `{}`\n",
+            self.generated_at,
+            self.config_file.display()
+        );
+        let sections = [
+            ("Created", &self.created),
+            ("Skipped", &self.skipped),
+            ("Warnings", &self.warnings),
+        ];
+        for (title, items) in sections {
+            let _ = writeln!(markdown, "\n## {title} ({})", items.len());
+            if !items.is_empty() {
+                markdown.push('\n');
+            }
+            for item in items {
+                let _ = writeln!(markdown, "- {item}");
+            }
+        }
+        markdown
+    }
+
+    /// Write the report into the configured folder, returning the path of the new report
+    pub fn write(&self, config: &ReportConfig) -> Result<PathBuf, Error> {
+        let folder = notes::expand(&config.folder)?;
+        fs::create_dir_all(&folder)?;
+        let path = folder.join(format!(
+            "jdexmd-report-{}.{}",
+            self.started.file_stamp(),
+            config.format.extension()
+        ));
+        let contents = match config.format {
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Json => serde_json::to_string_pretty(self)?,
+        };
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_report() {
+        let mut report = Report::new(Path::new("garden.toml"), Timestamp::from_seconds(0));
+        report.created(&Action::CreateDirectory(PathBuf::from("/vault/N01")));
+        report.skipped(&Action::CreateDirectory(PathBuf::from("/vault/N02")));
+        assert_eq!(
+            report.to_markdown(),
+            "# jdexmd report 1970-01-01T00:00:00Z
+
+Generated from `garden.toml`
+
+## Created (1)
+
+- Create Directory /vault/N01
+
+## Skipped (1)
+
+- /vault/N02
+
+## Warnings (0)
+"
+        );
+    }
+}
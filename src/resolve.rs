@@ -0,0 +1,242 @@
+use core::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+use color_eyre::eyre::Error;
+
+use crate::{jid::JohnnyId, model::System, prompt::Prompt};
+
+/// Every id in the system along with its topic, from the areas down
+pub fn all_ids(system: &System) -> Vec<(&JohnnyId, &str)> {
+    system
+        .iter()
+        .map(|(_, entry)| (entry.jid(), entry.name()))
+        .collect()
+}
+
+/// Pad a single number to two digits, leaving anything else alone
+fn pad(part: &str) -> String {
+    if let Some((start, end)) = part.split_once('-') {
+        return format!("{}-{}", pad(start), pad(end));
+    }
+    match part.parse::<u8>() {
+        Ok(number) if part.len() <= 2 => format!("{number:02}"),
+        _ => part.to_owned(),
+    }
+}
+
+/// Split a loosely written id like `21.5`, `21 05`, or `2105` into padded parts, without the
+/// system id
+fn parts(system: &System, input: &str) -> Vec<String> {
+    let mut parts: Vec<&str> = input
+        .split(['.', ' '])
+        .filter(|part| !part.is_empty())
+        .collect();
+    let system_id = system.id.by_seperator(".");
+    if parts
+        .first()
+        .is_some_and(|first| first.eq_ignore_ascii_case(&system_id))
+    {
+        parts.remove(0);
+    }
+    // Four digits run together are a category and a folder
+    if let [only] = parts.as_slice() {
+        if only.len() == 4 && only.chars().all(|character| character.is_ascii_digit()) {
+            let (category, folder) = only.split_at(2);
+            parts = vec![category, folder];
+        }
+    }
+    parts.into_iter().map(pad).collect()
+}
+
+/// The id a file or folder name starts with, like `L01.21.05.01` for `21.05.01 Invoice.pdf`,
+/// written in any of the loose ways an id can be resolved from.
+///
+/// The id can go deeper than the entries of the system, for things filed under them.
+pub fn id_in_name(system: &System, name: &str) -> Option<String> {
+    let first = name.split(' ').next()?;
+    let parts = parts(system, first);
+    let is_id = !parts.is_empty()
+        && parts.iter().all(|part| {
+            part.chars()
+                .all(|character| character.is_ascii_alphanumeric() || character == '-')
+                && part.chars().any(|character| character.is_ascii_digit())
+        });
+    // Written the same way as the ids of the system, with the system id only when it isn't hidden
+    is_id.then(|| {
+        if system.id.hide_system {
+            parts.join(".")
+        } else {
+            format!("{}.{}", system.id.by_seperator("."), parts.join("."))
+        }
+    })
+}
+
+/// List the candidates for an ambiguous id
+fn ambiguous(input: &str, candidates: &[(&JohnnyId, &str)]) -> Error {
+    let mut message = format!("{input} could be any of these, use the full id to pick one:");
+    for (id, topic) in candidates {
+        let _ = write!(message, "\n  {} {topic}", id.by_seperator("."));
+    }
+    Error::msg(message)
+}
+
+/// The entries that a partial or unpadded id could be, which are only ever more than one for a
+/// bare folder number, or the entry with the topic `input` when it isn't an id
+fn candidates<'sys>(
+    system: &'sys System,
+    input: &str,
+    within: Option<&str>,
+) -> Result<Vec<(&'sys JohnnyId, &'sys str)>, Error> {
+    let ids = all_ids(system);
+    let mut parts = parts(system, input);
+    let bare_number = matches!(parts.as_slice(), [only] if only.parse::<u8>().is_ok());
+    if let (true, Some(category)) = (bare_number, within) {
+        let category = resolve(system, category, None)?;
+        parts.insert(0, category);
+    }
+    let wanted = parts.join(".");
+    if let Some(&found) = ids.iter().find(|(id, _)| id.matches(&wanted)) {
+        return Ok(vec![found]);
+    }
+    if let Some(entry) = system.find_by_topic(input) {
+        return Ok(vec![(entry.jid(), entry.name())]);
+    }
+    Ok(if bare_number {
+        ids.into_iter()
+            .filter(|(id, _)| {
+                id.xfolder.is_none()
+                    && id
+                        .folder
+                        .as_ref()
+                        .is_some_and(|folder| format!("{:02}", folder.id) == wanted)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    })
+}
+
+/// The full id of the only one of the `candidates` for `input`
+fn only(input: &str, candidates: &[(&JohnnyId, &str)]) -> Result<String, Error> {
+    match candidates {
+        [] => Err(Error::msg(format!(
+            "No entry with the id {input} was found"
+        ))),
+        [(id, _)] => Ok(id.by_seperator(".")),
+        _ => Err(ambiguous(input, candidates)),
+    }
+}
+
+/// Turn a partial or unpadded id into the full id of an entry of the system.
+///
+/// A bare folder number like `5` is looked up in the category `within` when one is given, and
+/// otherwise in every category, failing with the candidates when more than one has it.
+pub fn resolve(system: &System, input: &str, within: Option<&str>) -> Result<String, Error> {
+    only(input, &candidates(system, input, within)?)
+}
+
+/// Turn a partial or unpadded id into the full id of an entry of the system like `resolve`, but
+/// ask on `prompt` which of the candidates is meant when more than one has it, unless the
+/// questions are answered without asking
+pub fn resolve_asking(
+    system: &System,
+    input: &str,
+    within: Option<&str>,
+    prompt: &mut Prompt<impl BufRead, impl Write>,
+) -> Result<String, Error> {
+    let candidates = candidates(system, input, within)?;
+    if candidates.len() < 2 || !prompt.is_interactive() {
+        return only(input, &candidates);
+    }
+    writeln!(prompt.output(), "{input} could be any of these:")?;
+    for (number, (id, topic)) in (1_usize..).zip(&candidates) {
+        writeln!(
+            prompt.output(),
+            "  {number}) {} {topic}",
+            id.by_seperator(".")
+        )?;
+    }
+    let answer = prompt.ask("Which one?", None)?.unwrap_or_default();
+    answer
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| candidates.get(number.checked_sub(1)?))
+        .map(|(id, _)| id.by_seperator("."))
+        .ok_or_else(|| ambiguous(input, &candidates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    const CONFIG: &str =
+        "20-29 Finance\n\t21 Tax\n\t\t05 Returns\n\t\t07 Receipts\n\t22 Bank\n\t\t05 Statements\n";
+
+    #[test]
+    fn test_resolve_loose_ids() {
+        let system = system(CONFIG);
+        for input in ["21.05", "21.5", "21 05", "2105", "L01.21.5", "l01 21 05"] {
+            assert_eq!(
+                resolve(&system, input, None).unwrap(),
+                "L01.21.05",
+                "{input}"
+            );
+        }
+        assert_eq!(resolve(&system, "21", None).unwrap(), "L01.21");
+        assert_eq!(resolve(&system, "20-29", None).unwrap(), "L01.20-29");
+        assert_eq!(resolve(&system, "returns", None).unwrap(), "L01.21.05");
+        assert!(resolve(&system, "Garden", None).is_err());
+    }
+
+    #[test]
+    fn test_resolve_bare_folder_numbers() {
+        let system = system(CONFIG);
+        assert_eq!(resolve(&system, "7", None).unwrap(), "L01.21.07");
+        assert_eq!(resolve(&system, "5", Some("22")).unwrap(), "L01.22.05");
+        let error = resolve(&system, "5", None).unwrap_err().to_string();
+        assert!(error.contains("L01.21.05 Returns"), "{error}");
+        assert!(error.contains("L01.22.05 Statements"), "{error}");
+        assert!(resolve(&system, "9", None).is_err());
+    }
+
+    #[test]
+    fn test_resolve_asking() {
+        let system = system(CONFIG);
+        let mut printed = Vec::new();
+        let mut prompt = Prompt::new(&b"2\n"[..], &mut printed);
+        assert_eq!(
+            resolve_asking(&system, "5", None, &mut prompt).unwrap(),
+            "L01.22.05"
+        );
+        assert_eq!(
+            String::from_utf8(printed).unwrap(),
+            "5 could be any of these:\n  1) L01.21.05 Returns\n  2) L01.22.05 Statements\nWhich one? "
+        );
+        let mut prompt = Prompt::new(&b"3\n"[..], Vec::new());
+        assert!(resolve_asking(&system, "5", None, &mut prompt).is_err());
+        let mut scripted = Prompt::new(&b"1\n"[..], Vec::new()).answering(false, true);
+        let error = resolve_asking(&system, "5", None, &mut scripted)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("L01.22.05 Statements"), "{error}");
+        assert_eq!(
+            resolve_asking(&system, "7", None, &mut scripted).unwrap(),
+            "L01.21.07"
+        );
+    }
+
+    #[test]
+    fn test_id_in_name() {
+        let system = system(CONFIG);
+        assert_eq!(
+            id_in_name(&system, "21.05.01 Invoice.pdf").as_deref(),
+            Some("L01.21.05.01")
+        );
+        assert_eq!(
+            id_in_name(&system, "L01.21.5 Returns").as_deref(),
+            Some("L01.21.05")
+        );
+        assert_eq!(id_in_name(&system, "Invoice.pdf"), None);
+    }
+}
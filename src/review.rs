@@ -0,0 +1,149 @@
+use core::fmt::Write as _;
+use std::{io::Write as _, path::PathBuf, sync::Arc};
+
+use color_eyre::eyre::Error;
+
+use crate::{
+    config::JohnnyDecimal,
+    jid::JohnnyId,
+    line,
+    model::{FullId as _, System},
+    output::{self, Mark},
+    resolve,
+    timestamp::Timestamp,
+};
+
+/// Mark the area or category with the full id `id` as reviewed on `day`, returning its id and
+/// topic along with its id alone
+fn review(system: &mut System, id: &str, day: &str) -> Result<(String, JohnnyId), Error> {
+    for area in &mut system.areas {
+        if area.id.matches(id) {
+            area.reviewed = Some(Arc::from(day));
+            return Ok((area.id(), area.id.clone()));
+        }
+        if let Some(category) = area
+            .categories
+            .iter_mut()
+            .find(|category| category.id.matches(id))
+        {
+            category.reviewed = Some(Arc::from(day));
+            return Ok((category.id(), category.id.clone()));
+        }
+    }
+    Err(Error::msg(format!(
+        "{id} isn't an area or category, which are the only entries that are reviewed"
+    )))
+}
+
+/// How many days `age` is, written like `90d` or just `90`
+fn days(age: &str) -> Result<u64, Error> {
+    age.strip_suffix('d')
+        .unwrap_or(age)
+        .parse()
+        .map_err(|_| Error::msg(format!("{age} isn't an age, write it in days like 90d")))
+}
+
+/// The areas and categories last reviewed before the day `cutoff`, with the day they were, and the
+/// areas that never were.
+///
+/// A category without a day of its own is reviewed along with its area, so it is only listed apart
+/// from the area when it has one.
+fn stale<'sys>(system: &'sys System, cutoff: &str) -> Vec<(String, Option<&'sys str>)> {
+    let mut stale = Vec::new();
+    for area in &system.areas {
+        let area_day = area.reviewed.as_deref();
+        if area_day.is_none_or(|day| day < cutoff) {
+            stale.push((area.id(), area_day));
+        }
+        for category in area.categories.iter().filter(|category| !category.retired) {
+            let Some(day) = category.reviewed.as_deref() else {
+                continue;
+            };
+            if day.max(area_day.unwrap_or_default()) < cutoff {
+                stale.push((category.id(), Some(day)));
+            }
+        }
+    }
+    stale
+}
+
+/// Mark the area or category `id` as reviewed today in the config file
+pub fn run(config_file: &PathBuf, id: &str) -> Result<(), Error> {
+    let mut system = System::try_from(JohnnyDecimal::from_file(config_file)?.system_config)?;
+    let full = resolve::resolve(&system, id, None)?;
+    let today = Timestamp::now().date();
+    let (entry, jid) = review(&mut system, &full, &today)?;
+    JohnnyDecimal::edit_definition(config_file, |definition| {
+        line::edit_entry(definition, &jid, |line| {
+            line::with_tag(line, &format!("reviewed={today}"))
+        })
+    })?;
+    write!(
+        anstream::stdout(),
+        "{}",
+        output::line(Mark::Changed, "Reviewed", &format!("{entry} on {today}"))
+    )?;
+    Ok(())
+}
+
+/// List the areas and categories that weren't reviewed within `age`, like `90d`
+pub fn report(system: &System, age: &str) -> Result<(), Error> {
+    let cutoff = Timestamp::now().days_before(days(age)?).date();
+    let mut listed = format!(
+        "{}\n",
+        output::heading(&format!("Not reviewed since {cutoff}"))
+    );
+    let stale = stale(system, &cutoff);
+    if stale.is_empty() {
+        listed.push_str("  none\n");
+    }
+    for (entry, day) in stale {
+        match day {
+            Some(day) => {
+                let _ = writeln!(listed, "  {entry} (last reviewed {day})");
+            }
+            None => {
+                let _ = writeln!(listed, "  {entry} (never reviewed)");
+            }
+        }
+    }
+    write!(anstream::stdout(), "{listed}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{line::format_system, test_support::system};
+
+    #[test]
+    fn test_review() {
+        let mut system = system(
+            "10-19 Home [reviewed=2025-01-10]\n\t11 House [reviewed=2024-06-01]\n\t12 Garden\n20-29 Finance\n\t21 Tax\n",
+        );
+        assert_eq!(
+            stale(&system, "2025-01-01"),
+            vec![("L01.20-29 Finance".to_owned(), None)]
+        );
+        assert_eq!(
+            stale(&system, "2025-02-01"),
+            vec![
+                ("L01.10-19 Home".to_owned(), Some("2025-01-10")),
+                ("L01.11 House".to_owned(), Some("2024-06-01")),
+                ("L01.20-29 Finance".to_owned(), None),
+            ]
+        );
+        assert_eq!(
+            review(&mut system, "L01.21", "2025-02-03").unwrap().0,
+            "L01.21 Tax"
+        );
+        assert!(review(&mut system, "L01.11.01", "2025-02-03").is_err());
+        assert_eq!(
+            format_system(&system),
+            "10-19 Home [reviewed=2025-01-10]\n\t11 House [reviewed=2024-06-01]\n\t12 Garden\n20-29 Finance\n\t21 Tax [reviewed=2025-02-03]\n"
+        );
+        assert_eq!(days("90d").unwrap(), 90);
+        assert_eq!(days("14").unwrap(), 14);
+        assert!(days("3w").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use clap::Subcommand;
+use color_eyre::eyre::Error;
+use schemars::schema_for;
+
+use crate::config::JohnnyDecimal;
+
+/// What can be done with the config file
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the JSON Schema of the config file, for editors to complete and check it with
+    Schema,
+}
+
+/// The JSON Schema of the config file, built from the same types it is read into
+pub fn schema() -> Result<String, Error> {
+    Ok(serde_json::to_string_pretty(&schema_for!(JohnnyDecimal))?)
+}
+
+/// Run `command` on the config file, which doesn't have to exist for any of them
+pub fn run(command: &ConfigCommand) -> Result<(), Error> {
+    match command {
+        ConfigCommand::Schema => println!("{}", schema()?),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema() {
+        let schema: serde_json::Value = serde_json::from_str(&schema().unwrap()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        // The system and output options are flattened into the top level of the file
+        for property in [
+            "system_id",
+            "config",
+            "base_folder",
+            "targets",
+            "format",
+            "limits",
+        ] {
+            assert!(properties.contains_key(property), "{property}");
+        }
+        assert!(schema["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|required| required == "base_folder"));
+    }
+}
@@ -0,0 +1,173 @@
+use core::fmt::Write as _;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+};
+
+use anstream::{AutoStream, ColorChoice};
+use color_eyre::eyre::Error;
+
+use crate::{
+    cache,
+    config::JohnnyDecimal,
+    label,
+    model::System,
+    output, state,
+    which::{self, entries},
+};
+
+/// The file in the state folder that the map of paths to entries is cached in
+const CACHE_FILE: &str = "prompt-segment.tsv";
+
+/// The id, without the system id, and the topic of the entry each path belongs to
+type PathMap = BTreeMap<PathBuf, (String, String)>;
+
+/// The directory of every entry in `bases`, and the note of every folder, with the id and topic of
+/// the entry.
+///
+/// A path that more than one entry has, like the directory of the system that every area shares
+/// in the flat layout, belongs to none of them.
+fn path_map(system: &System, bases: &[PathBuf], extension: &str) -> PathMap {
+    let prefix = format!("{}.", system.id.by_seperator("."));
+    let mut map: BTreeMap<PathBuf, Option<(String, String)>> = BTreeMap::new();
+    for base in bases {
+        for (entry, kind) in entries(system) {
+            let full = entry.jid().by_seperator(".");
+            let id = full.strip_prefix(&prefix).unwrap_or(&full).to_owned();
+            let segment = (id, entry.name().to_owned());
+            let directory = base.join(entry.jid().as_path());
+            let note = label::note_path(base, entry, kind, extension);
+            for path in [directory, note] {
+                let shared = map
+                    .get(&path)
+                    .is_some_and(|other| other.as_ref() != Some(&segment));
+                map.insert(path, (!shared).then(|| segment.clone()));
+            }
+        }
+    }
+    map.into_iter()
+        .filter_map(|(path, segment)| Some((path, segment?)))
+        .collect()
+}
+
+/// The id and topic of the deepest entry that `directory` is in
+fn lookup<'map>(map: &'map PathMap, directory: &Path) -> Option<&'map (String, String)> {
+    directory.ancestors().find_map(|ancestor| map.get(ancestor))
+}
+
+/// The map cached at `cache`, unless it is missing or was made from another config file than
+/// the one with `fingerprint`
+fn read_cache(cache: &Path, fingerprint: &str) -> Option<PathMap> {
+    let contents = fs::read_to_string(cache).ok()?;
+    let mut lines = contents.lines();
+    if lines.next()? != fingerprint {
+        return None;
+    }
+    lines
+        .map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let path = PathBuf::from(fields.next()?);
+            Some((path, (fields.next()?.to_owned(), fields.next()?.to_owned())))
+        })
+        .collect()
+}
+
+/// Cache `map` at `cache`, after the fingerprint of the config file it was made from
+fn write_cache(cache: &Path, fingerprint: &str, map: &PathMap) -> Result<(), Error> {
+    let mut contents = format!("{fingerprint}\n");
+    for (path, (id, topic)) in map {
+        let _ = writeln!(contents, "{}\t{id}\t{topic}", path.display());
+    }
+    if let Some(parent) = cache.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::write(cache, contents)?)
+}
+
+/// Print a segment like `[21.05 Taxes]` for a shell prompt, with the entry the current directory
+/// is in, and nothing outside of every entry.
+///
+/// The map of paths to entries is cached in the state folder until the config file changes, so
+/// the system is only read when it did.
+pub fn run(config_file: &Path, config: JohnnyDecimal, fingerprint: &str) -> Result<(), Error> {
+    let cache =
+        state::folder(config_file, config.output_config.state_folder.as_deref())?.join(CACHE_FILE);
+    let map = if let Some(map) = read_cache(&cache, fingerprint) {
+        map
+    } else {
+        let extension = config.format.extension();
+        let system = cache::load(config_file, config.system_config, fingerprint, extension)?.system;
+        let output_config = config.output_config.for_system(&system)?;
+        let map = path_map(&system, &which::bases(&output_config)?, extension);
+        write_cache(&cache, fingerprint, &map)?;
+        map
+    };
+    let Some((id, topic)) = lookup(&map, &env::current_dir()?) else {
+        return Ok(());
+    };
+    // A prompt captures the output, which isn't a terminal, but still wants the colors
+    let plain = ColorChoice::global() == ColorChoice::Never || env::var_os("NO_COLOR").is_some();
+    let choice = if plain {
+        ColorChoice::Never
+    } else {
+        ColorChoice::Always
+    };
+    writeln!(
+        AutoStream::new(io::stdout(), choice),
+        "[{} {topic}]",
+        output::id(id)
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+    use crate::{config::SystemParameters, layout::Layout, test_support::parameters};
+
+    fn laid_out(layout: Layout) -> System {
+        System::try_from(SystemParameters {
+            layout,
+            ..parameters("10-19 Home\n\t11 House\n20-29 Finance\n\t21 Tax\n\t\t05 Taxes\n")
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lookup() {
+        let map = path_map(&laid_out(Layout::Nested), &[PathBuf::from("/notes")], "md");
+        let taxes = Path::new("/notes/L01/L01.20-29 Finance/L01.21 Tax/L01.21.05 Taxes");
+        assert_eq!(
+            lookup(&map, &taxes.join("2024")),
+            Some(&("21.05".to_owned(), "Taxes".to_owned()))
+        );
+        assert_eq!(
+            lookup(&map, taxes.parent().unwrap()),
+            Some(&("21".to_owned(), "Tax".to_owned()))
+        );
+        assert_eq!(lookup(&map, Path::new("/notes/L01")), None);
+        // Every area shares the directory of the system in the flat layout
+        let flat = path_map(&laid_out(Layout::Flat), &[PathBuf::from("/notes")], "md");
+        assert_eq!(lookup(&flat, Path::new("/notes/L01")), None);
+        assert_eq!(
+            lookup(&flat, Path::new("/notes/L01/L01.11 House")),
+            Some(&("11".to_owned(), "House".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_cache() {
+        let map = path_map(&laid_out(Layout::Nested), &[PathBuf::from("/notes")], "md");
+        let cache = env::temp_dir()
+            .join(format!("jdexmd-segment-{}", process::id()))
+            .join(CACHE_FILE);
+        write_cache(&cache, "abc", &map).unwrap();
+        assert_eq!(read_cache(&cache, "abc"), Some(map));
+        assert_eq!(read_cache(&cache, "def"), None);
+        fs::remove_dir_all(cache.parent().unwrap()).unwrap();
+    }
+}
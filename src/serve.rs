@@ -0,0 +1,138 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use color_eyre::eyre::Error;
+
+use crate::{config, html, model::System};
+
+/// Polls the server for the config version and reloads the page when it changes
+const LIVE_RELOAD: &str = "<script>
+let version = null;
+setInterval(async () => {
+    const response = await fetch('/version');
+    const current = await response.text();
+    if (version !== null && version !== current) { location.reload(); }
+    version = current;
+}, 1000);
+</script>
+";
+
+/// How long a client has to send its request before the connection is given up on
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// When the file at `path` was last saved
+fn modified(path: &Path) -> String {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_millis().to_string())
+        .unwrap_or_default()
+}
+
+/// The version of the config file, which changes whenever it or its `.jdex` file is saved
+fn config_version(config_file: &Path) -> String {
+    let definition = fs::read_to_string(config_file)
+        .ok()
+        .and_then(|contents| config::JohnnyDecimal::from_toml(&contents).ok())
+        .and_then(|config| config.definition_file(config_file).ok().flatten());
+    definition.map_or_else(
+        || modified(config_file),
+        |definition| format!("{}-{}", modified(config_file), modified(&definition)),
+    )
+}
+
+/// Load the config and render it, showing any error in the page instead of stopping the server
+fn render_index(config_file: &Path) -> String {
+    let rendered = config::JohnnyDecimal::from_file(&config_file.to_path_buf())
+        .and_then(|config| System::try_from(config.system_config))
+        .map_or_else(
+            |err| {
+                (
+                    String::from("Error"),
+                    format!("<pre>{}</pre>\n", html::escape(&format!("{err:?}"))),
+                )
+            },
+            |system| (system.name.clone(), html::render(&system)),
+        );
+    html::page(&rendered.0, &rendered.1, LIVE_RELOAD)
+}
+
+/// Read a request up to the blank line that ends its headers, returning the path it asks for.
+///
+/// The headers aren't used, but closing the connection with them unread would reset it and lose
+/// the response.
+fn read_request(reader: impl BufRead) -> Result<String, Error> {
+    let mut lines = reader.lines();
+    let request_line = lines.next().transpose()?.unwrap_or_default();
+    for line in lines {
+        if line?.is_empty() {
+            break;
+        }
+    }
+    Ok(request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .to_owned())
+}
+
+/// Answer a single request
+fn respond(mut stream: TcpStream, config_file: &Path) -> Result<(), Error> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let path = read_request(BufReader::new(&stream))?;
+    let (status, content_type, body) = match path.as_str() {
+        "/" | "/index.html" => ("200 OK", "text/html", render_index(config_file)),
+        "/version" => ("200 OK", "text/plain", config_version(config_file)),
+        _ => ("404 Not Found", "text/plain", String::from("Not Found")),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+/// Serve the rendered index, reloading whenever the config file changes.
+///
+/// Each connection is answered on its own thread, so a client that stalls doesn't hold up the
+/// others.
+pub fn run(config_file: &Path, address: SocketAddr) -> Result<(), Error> {
+    let listener = TcpListener::bind(address)?;
+    println!("Serving the index at http://{address}/");
+    for stream in listener.incoming() {
+        let config_file = config_file.to_path_buf();
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || {
+                    if let Err(err) = respond(stream, &config_file) {
+                        eprintln!("Failed to answer request: {err}");
+                    }
+                });
+            }
+            Err(err) => eprintln!("Failed to answer request: {err}"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let mut request =
+            &b"GET /version HTTP/1.1\r\nHost: localhost\r\nAccept: */*\r\n\r\nleft over"[..];
+        assert_eq!(read_request(&mut request).unwrap(), "/version");
+        assert_eq!(request, b"left over");
+        assert_eq!(read_request(&b""[..]).unwrap(), "/");
+    }
+}
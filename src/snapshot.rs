@@ -0,0 +1,265 @@
+use core::fmt::Write as _;
+use std::{
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use color_eyre::eyre::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{JohnnyDecimal, SystemParameters},
+    diff,
+    line::format_system,
+    model::System,
+    output::{self, Mark},
+    resolve, state,
+    timestamp::Timestamp,
+};
+
+/// Where the snapshots are kept, inside the state folder
+const SNAPSHOT_FOLDER: &str = "snapshots";
+
+/// What can be done with snapshots
+#[derive(Debug, Subcommand)]
+pub enum SnapshotCommand {
+    /// Store the structure of the system as it is defined now
+    Save {
+        #[clap(short, long)]
+        /// A note about why the snapshot was taken
+        message: Option<String>,
+    },
+    /// List the stored snapshots, oldest first
+    List,
+    /// Replace the system definition with a snapshot, saving the current one first
+    Restore {
+        /// The name of the snapshot, as listed
+        name: String,
+    },
+    /// Show how the structure changed between two snapshots
+    Diff {
+        /// The name of the older snapshot
+        from: String,
+        /// The name of the newer snapshot, the current definition when not given
+        to: Option<String>,
+    },
+}
+
+/// A stored version of the parsed system, without anything that is filed under it
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    /// When the snapshot was taken
+    taken_at: String,
+    /// A note about why the snapshot was taken
+    message: Option<String>,
+    /// The system, with every range and template written out
+    system: SystemParameters,
+}
+
+/// The folder the snapshots for `config_file` are kept in
+fn folder(config_file: &Path, state_folder: Option<&str>) -> Result<PathBuf, Error> {
+    Ok(state::folder(config_file, state_folder)?.join(SNAPSHOT_FOLDER))
+}
+
+/// The names of the stored snapshots, oldest first
+fn names(folder: &Path) -> Result<Vec<String>, Error> {
+    if !folder.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = fs::read_dir(folder)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Read the snapshot with the given name
+fn load(folder: &Path, name: &str) -> Result<Snapshot, Error> {
+    let path = folder.join(format!("{name}.toml"));
+    if !path.is_file() {
+        return Err(Error::msg(format!(
+            "No snapshot named {name} in {}",
+            folder.display()
+        )));
+    }
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Parse the system defined in `config_file` into the parameters of a snapshot.
+///
+/// Ranges and templates are written out and the standard zeros are included, so the snapshot
+/// reads the same whatever the config file does around it.
+fn current(config_file: &PathBuf) -> Result<SystemParameters, Error> {
+    let parameters = JohnnyDecimal::from_file(config_file)?.system_config;
+    let system_id = parameters.system_id.clone();
+    let separator = parameters.separator.clone();
+    let name = parameters.name.clone();
+    let hide_system_id = parameters.hide_system_id;
+    let xfolder_pattern = parameters.xfolder_pattern.clone();
+    let folder_names = parameters.folder_names.clone();
+    let system = System::try_from(parameters)?;
+    Ok(SystemParameters {
+        system_id,
+        separator,
+        name,
+        config: format_system(&system),
+        default_folder_kind: system.default_folder_kind.clone(),
+        hide_system_id,
+        layout: system.id.layout,
+        xfolder_pattern,
+        xfolder_kind: system.xfolder_kind,
+        folder_names,
+        workspace: system.is_workspace(),
+        ..SystemParameters::default()
+    })
+}
+
+/// Store the structure currently defined in `config_file` in `folder`, returning the name of the
+/// snapshot
+fn save(config_file: &PathBuf, folder: &Path, message: Option<String>) -> Result<String, Error> {
+    fs::create_dir_all(folder)?;
+    let taken_at = Timestamp::now();
+    let stamp = taken_at.file_stamp();
+    let existing = names(folder)?;
+    // Two snapshots taken in the same second get a counter after the second one
+    let mut name = stamp.clone();
+    let mut count: usize = 1;
+    while existing.contains(&name) {
+        count = count.saturating_add(1);
+        name = format!("{stamp}-{count}");
+    }
+    let snapshot = Snapshot {
+        taken_at: taken_at.to_string(),
+        message,
+        system: current(config_file)?,
+    };
+    fs::write(
+        folder.join(format!("{name}.toml")),
+        toml::to_string(&snapshot)?,
+    )?;
+    Ok(name)
+}
+
+/// Print every stored snapshot with how many entries it has
+fn list(folder: &Path) -> Result<(), Error> {
+    let mut listed = format!(
+        "{} {}\n",
+        output::heading("Snapshots"),
+        output::path(folder)
+    );
+    let names = names(folder)?;
+    if names.is_empty() {
+        listed.push_str("  none yet, take one with `snapshot save`\n");
+    }
+    for name in names {
+        let snapshot = load(folder, &name)?;
+        let entries = resolve::all_ids(&System::try_from(snapshot.system)?).len();
+        let message = snapshot
+            .message
+            .map(|message| format!(", {message}"))
+            .unwrap_or_default();
+        let _ = writeln!(
+            listed,
+            "  {} {entries} entries, taken {}{message}",
+            output::id(&name),
+            snapshot.taken_at
+        );
+    }
+    write!(anstream::stdout(), "{listed}")?;
+    Ok(())
+}
+
+/// Run a snapshot command against the system defined in `config_file`
+pub fn run(
+    config_file: &PathBuf,
+    state_folder: Option<&str>,
+    command: &SnapshotCommand,
+) -> Result<(), Error> {
+    let folder = folder(config_file, state_folder)?;
+    let mut stdout = anstream::stdout();
+    match command {
+        SnapshotCommand::Save { message } => {
+            let name = save(config_file, &folder, message.clone())?;
+            write!(
+                stdout,
+                "{}",
+                output::line(Mark::Applied, "Saved snapshot", &output::id(&name))
+            )?;
+        }
+        SnapshotCommand::List => list(&folder)?,
+        SnapshotCommand::Restore { name } => {
+            let snapshot = load(&folder, name)?;
+            let saved = save(
+                config_file,
+                &folder,
+                Some(format!("Before restoring {name}")),
+            )?;
+            write!(
+                stdout,
+                "{}",
+                output::line(Mark::Applied, "Saved snapshot", &output::id(&saved))
+            )?;
+            JohnnyDecimal::update_definition(config_file, &snapshot.system.config)?;
+            write!(
+                stdout,
+                "{}",
+                output::line(
+                    Mark::Changed,
+                    "Restored",
+                    &format!("{} into {}", output::id(name), output::path(config_file))
+                )
+            )?;
+        }
+        SnapshotCommand::Diff { from, to } => {
+            let older = System::try_from(load(&folder, from)?.system)?;
+            let newer = match to {
+                Some(to) => load(&folder, to)?.system,
+                None => current(config_file)?,
+            };
+            diff::print(&diff::diff(&older, &System::try_from(newer)?))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let parameters = SystemParameters {
+            system_id: "L01".to_owned(),
+            name: "Life".to_owned(),
+            config: "20-29 Finance\n\t21 Tax\n\t\t01..03 Month {month}\n".to_owned(),
+            standard_zeros: true,
+            ..SystemParameters::default()
+        };
+        let system = System::try_from(parameters).unwrap();
+        let snapshot = Snapshot {
+            taken_at: Timestamp::from_seconds(0).to_string(),
+            message: Some("Before the move".to_owned()),
+            system: SystemParameters {
+                system_id: "L01".to_owned(),
+                name: "Life".to_owned(),
+                config: format_system(&system),
+                ..SystemParameters::default()
+            },
+        };
+        let read: Snapshot = toml::from_str(&toml::to_string(&snapshot).unwrap()).unwrap();
+        assert_eq!(read.message.as_deref(), Some("Before the move"));
+        assert!(diff::diff(&system, &System::try_from(read.system).unwrap()).is_empty());
+        assert_eq!(
+            folder(Path::new("/home/me/garden.toml"), Some(".jdexmd")).unwrap(),
+            Path::new("/home/me/.jdexmd/snapshots")
+        );
+    }
+}
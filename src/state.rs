@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Error, OptionExt};
+use directories::ProjectDirs;
+
+use crate::{config::JohnnyDecimal, notes};
+
+/// The folder next to the config file that state was kept in before it had a folder of its own
+const LEGACY_FOLDER: &str = ".jdexmd";
+
+/// How many characters of the hash of the config file's path keep the state of systems apart
+const KEY_LENGTH: usize = 12;
+
+/// The name of the folder for the state of the system defined in `config_file`, like
+/// `garden-3f0c5e9a1b2d`, so two config files never share one
+pub fn key(config_file: &Path) -> String {
+    let absolute = config_file
+        .canonicalize()
+        .unwrap_or_else(|_| config_file.to_path_buf());
+    let stem = config_file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let hash = JohnnyDecimal::hash(&absolute.to_string_lossy());
+    format!("{stem}-{}", &hash[..KEY_LENGTH])
+}
+
+/// The folder that what jdexmd remembers about the system defined in `config_file` is kept in.
+///
+/// `state_folder` from the config comes first, relative to the config file, for anyone who wants
+/// it in the vault. Otherwise a `.jdexmd` folder that is already next to the config file is kept,
+/// and everyone else gets a folder in the platform's place for application state, like
+/// `~/.local/state/jdexmd` on Linux.
+pub fn folder(config_file: &Path, state_folder: Option<&str>) -> Result<PathBuf, Error> {
+    let config_folder = config_file.parent().unwrap_or_else(|| Path::new("."));
+    if let Some(state_folder) = state_folder {
+        return Ok(config_folder.join(notes::expand(state_folder)?));
+    }
+    let legacy = config_folder.join(LEGACY_FOLDER);
+    if legacy.is_dir() {
+        return Ok(legacy);
+    }
+    let directories = ProjectDirs::from("", "", "jdexmd").ok_or_eyre(
+        "Unable to find the folder for application state, set `state_folder` in the config file",
+    )?;
+    let base = directories
+        .state_dir()
+        .unwrap_or_else(|| directories.data_local_dir());
+    Ok(base.join(key(config_file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_folder() {
+        assert_eq!(
+            folder(Path::new("/home/me/garden.toml"), Some(".jdexmd")).unwrap(),
+            Path::new("/home/me/.jdexmd")
+        );
+        assert_eq!(
+            folder(Path::new("/home/me/garden.toml"), Some("/var/jdexmd")).unwrap(),
+            Path::new("/var/jdexmd")
+        );
+        let garden = key(Path::new("/home/me/garden.toml"));
+        assert!(garden.starts_with("garden-"));
+        assert_eq!(garden.len(), "garden-".len() + KEY_LENGTH);
+        assert_ne!(garden, key(Path::new("/home/you/garden.toml")));
+    }
+}
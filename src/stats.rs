@@ -0,0 +1,415 @@
+use core::{cmp::Reverse, fmt::Write as _, iter};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Error;
+
+use crate::{
+    backend::{LocalFs, OutputBackend},
+    config,
+    ignore::IgnoreRules,
+    link::LINK_NAME,
+    model::{HasJohnnyId, System},
+    next_id, notes, output,
+    planner::Planner,
+    validate::{self, LimitsConfig},
+};
+
+/// How many of the heaviest folders are listed
+const HEAVIEST: usize = 5;
+
+/// The units of a size, each 1024 times the one before
+const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+
+/// The files found under a directory
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Tally {
+    /// How many files there are
+    files: usize,
+    /// How many bytes the files take up together
+    bytes: u64,
+}
+
+/// What was found on disk for a single entry of the system
+#[derive(Debug)]
+struct Row {
+    /// The full id, like `L01.21.05`
+    id: String,
+    /// The topic of the entry
+    topic: String,
+    /// How far below the areas the entry is, `0` for an area
+    depth: usize,
+    /// Whether nothing in the system is under the entry
+    leaf: bool,
+    /// Whether the entry has a directory on disk
+    exists: bool,
+    /// The files under the entry's directory
+    tally: Tally,
+}
+
+/// Write a size in bytes the way a person would read it, like `1.5 KiB`
+fn human_size(bytes: u64) -> String {
+    let mut tenths = bytes.saturating_mul(10);
+    let mut unit = 0;
+    while tenths >= 10 * 1024 && unit < UNITS.len() - 1 {
+        tenths /= 1024;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{}.{} {}", tenths / 10, tenths % 10, UNITS[unit])
+    }
+}
+
+/// Count the files under `directory` and their sizes.
+///
+/// Generated notes and ignored paths aren't contents, and links are never followed, so the
+/// `Reference` link in the notes doesn't count the archive twice.
+fn tally(directory: &Path, generated: &HashSet<PathBuf>, ignore: &IgnoreRules) -> Tally {
+    let mut total = Tally::default();
+    let Ok(entries) = fs::read_dir(directory) else {
+        return total;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_symlink()
+            || (metadata.is_file() && generated.contains(&path))
+            || ignore.is_ignored(&path, metadata.is_dir())
+        {
+            continue;
+        }
+        if metadata.is_dir() {
+            let below = tally(&path, generated, ignore);
+            total.files = total.files.saturating_add(below.files);
+            total.bytes = total.bytes.saturating_add(below.bytes);
+        } else {
+            total.files = total.files.saturating_add(1);
+            total.bytes = total.bytes.saturating_add(metadata.len());
+        }
+    }
+    total
+}
+
+/// Count the notes with `extension` under `directory`.
+///
+/// Generated notes and ignored paths aren't counted, and neither is anything in hidden folders
+/// like `.obsidian` or behind the link into the reference archive.
+fn count_notes(
+    backend: &dyn OutputBackend,
+    directory: &Path,
+    extension: &str,
+    generated: &HashSet<PathBuf>,
+    ignore: &IgnoreRules,
+) -> usize {
+    let Ok(entries) = backend.read_dir(directory) else {
+        return 0;
+    };
+    let mut count: usize = 0;
+    for path in entries {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let is_dir = backend.is_dir(&path);
+        if name.starts_with('.')
+            || name == LINK_NAME
+            || generated.contains(&path)
+            || ignore.is_ignored(&path, is_dir)
+        {
+            continue;
+        }
+        if is_dir {
+            count = count.saturating_add(count_notes(backend, &path, extension, generated, ignore));
+        } else if path
+            .extension()
+            .is_some_and(|found| found.to_string_lossy() == extension)
+        {
+            count = count.saturating_add(1);
+        }
+    }
+    count
+}
+
+/// How many notes are in the directory of each folder and extended folder of the notes, by full
+/// id, for the `note_count` of the index, with none when there is no directory yet. The notes
+/// that `actions` generate don't count.
+pub fn note_counts(
+    backend: &dyn OutputBackend,
+    base_folder: &str,
+    system: &System,
+    actions: &[notes::Action<'_>],
+    extension: &str,
+) -> Result<BTreeMap<String, usize>, Error> {
+    let base = notes::expand(base_folder)?;
+    let ignore = IgnoreRules::load(backend, &base)?;
+    let generated: HashSet<PathBuf> = actions
+        .iter()
+        .filter(|action| !matches!(action, notes::Action::CreateDirectory(_)))
+        .map(|action| action.path().clone())
+        .collect();
+    let mut counts = BTreeMap::new();
+    let folders = system
+        .areas
+        .iter()
+        .flat_map(|area| &area.categories)
+        .flat_map(|category| &category.folders);
+    for folder in folders {
+        for id in iter::once(&folder.id).chain(folder.folders.iter().map(|xfolder| &xfolder.id)) {
+            let directory = base.join(id.as_path());
+            counts.insert(
+                id.by_seperator("."),
+                count_notes(backend, &directory, extension, &generated, &ignore),
+            );
+        }
+    }
+    Ok(counts)
+}
+
+/// Tally every entry of the system that isn't retired under `base_folder`, leaving out what
+/// `actions` generate there
+fn rows(
+    base_folder: &str,
+    system: &System,
+    actions: &[notes::Action<'_>],
+) -> Result<Vec<Row>, Error> {
+    let base = notes::expand(base_folder)?;
+    let ignore = IgnoreRules::load(&LocalFs, &base)?;
+    let generated: HashSet<PathBuf> = actions.iter().map(|action| action.path().clone()).collect();
+    let row = |entry: &dyn HasJohnnyId, depth: usize, leaf: bool| {
+        let directory = base.join(entry.jid().as_path());
+        Row {
+            id: entry.jid().by_seperator("."),
+            topic: entry.name().to_owned(),
+            depth,
+            leaf,
+            exists: directory.is_dir(),
+            tally: tally(&directory, &generated, &ignore),
+        }
+    };
+    let mut rows = Vec::new();
+    for area in &system.areas {
+        rows.push(row(area, 0, area.categories.is_empty()));
+        for category in area.categories.iter().filter(|category| !category.retired) {
+            rows.push(row(category, 1, category.folders.is_empty()));
+            for folder in category.folders.iter().filter(|folder| !folder.retired) {
+                rows.push(row(folder, 2, folder.folders.is_empty()));
+                for xfolder in folder.folders.iter().filter(|xfolder| !xfolder.retired) {
+                    rows.push(row(xfolder, 3, true));
+                }
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// The folders with the most bytes in them, heaviest first
+fn heaviest(rows: &[Row]) -> Vec<&Row> {
+    let mut folders: Vec<&Row> = rows
+        .iter()
+        .filter(|row| row.depth >= 2 && row.tally.bytes > 0)
+        .collect();
+    folders.sort_by_key(|row| Reverse(row.tally.bytes));
+    folders.truncate(HEAVIEST);
+    folders
+}
+
+/// The folders on disk with nothing under them, in the system or in the directory
+fn empty_leaves(rows: &[Row]) -> Vec<&Row> {
+    rows.iter()
+        .filter(|row| row.depth >= 2 && row.leaf && row.exists && row.tally.files == 0)
+        .collect()
+}
+
+/// Describe what is on disk under `base_folder`, apart from what `actions` generate there
+fn on_disk(
+    heading: &str,
+    base_folder: &str,
+    system: &System,
+    actions: &[notes::Action<'_>],
+) -> Result<String, Error> {
+    let rows = rows(base_folder, system, actions)?;
+    let mut described = format!(
+        "\n{} {}\n",
+        output::heading(heading),
+        output::path(&notes::expand(base_folder)?)
+    );
+    for row in rows.iter().filter(|row| row.depth < 2) {
+        let _ = writeln!(
+            described,
+            "{:indent$}{} {}: {} files, {}",
+            "",
+            output::id(&row.id),
+            row.topic,
+            row.tally.files,
+            human_size(row.tally.bytes),
+            indent = 2 * (row.depth + 1)
+        );
+    }
+    let heaviest = heaviest(&rows);
+    if !heaviest.is_empty() {
+        let _ = writeln!(described, "  Heaviest folders:");
+    }
+    for row in heaviest {
+        let _ = writeln!(
+            described,
+            "    {} {}: {}",
+            output::id(&row.id),
+            row.topic,
+            human_size(row.tally.bytes)
+        );
+    }
+    let empty = empty_leaves(&rows);
+    if !empty.is_empty() {
+        let _ = writeln!(described, "  Empty folders, maybe unused numbers:");
+    }
+    for row in empty {
+        let _ = writeln!(described, "    {} {}", output::id(&row.id), row.topic);
+    }
+    Ok(described)
+}
+
+/// Count the entries of the system, without looking at the disk, and list the categories and areas
+/// that hold more than `limits` allow
+fn structure(system: &System, limits: LimitsConfig) -> String {
+    let categories: Vec<_> = system
+        .areas
+        .iter()
+        .flat_map(|area| &area.categories)
+        .collect();
+    let folders: Vec<_> = categories
+        .iter()
+        .flat_map(|category| &category.folders)
+        .collect();
+    let xfolders: Vec<_> = folders.iter().flat_map(|folder| &folder.folders).collect();
+    let retired = categories
+        .iter()
+        .filter(|category| category.retired)
+        .count()
+        + folders.iter().filter(|folder| folder.retired).count()
+        + xfolders.iter().filter(|xfolder| xfolder.retired).count();
+    let free: usize = categories
+        .iter()
+        .map(|category| {
+            let used = next_id::used_numbers(category);
+            (1..=99).filter(|number| !used.contains(number)).count()
+        })
+        .sum();
+    let mut printed = format!(
+        "{}\n  {} areas, {} categories, {} folders, {} extended folders\n  {retired} retired ids, {free} folder ids never issued\n",
+        output::heading("Structure"),
+        system.areas.len(),
+        categories.len(),
+        folders.len(),
+        xfolders.len(),
+    );
+    for finding in validate::overloaded(system, limits) {
+        let _ = writeln!(printed, "  {}", finding.message);
+    }
+    printed
+}
+
+/// Print statistics about the system, and with `on_disk` what is filed in the notes and the
+/// reference archive
+pub fn run(
+    output_config: &config::Output,
+    system: &System,
+    limits: LimitsConfig,
+    on_disk: bool,
+    extension: &str,
+) -> Result<(), Error> {
+    let mut printed = structure(system, limits);
+    if on_disk {
+        let planner = Planner::new(output_config, system, extension)?;
+        printed.push_str(&self::on_disk(
+            "Notes",
+            &output_config.base_folder,
+            system,
+            &planner.notes()?,
+        )?);
+        // A workspace has no reference archive of its own
+        if !system.is_workspace() {
+            printed.push_str(&self::on_disk(
+                "Reference",
+                &output_config.reference_folder,
+                system,
+                &planner.archive()?,
+            )?);
+        }
+    }
+    write!(anstream::stdout(), "{printed}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::MemoryFs, test_support::system};
+
+    fn row(id: &str, depth: usize, leaf: bool, files: usize, bytes: u64) -> Row {
+        Row {
+            id: id.to_owned(),
+            topic: "Topic".to_owned(),
+            depth,
+            leaf,
+            exists: true,
+            tally: Tally { files, bytes },
+        }
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn test_heaviest_and_empty_folders() {
+        let rows = vec![
+            row("L01.20-29", 0, false, 3, 600),
+            row("L01.21", 1, false, 3, 600),
+            row("L01.21.01", 2, true, 1, 100),
+            row("L01.21.02", 2, true, 0, 0),
+            row("L01.21.03", 2, false, 2, 500),
+            row("L01.21.03.0001", 3, true, 0, 0),
+        ];
+        let heaviest: Vec<&str> = heaviest(&rows).iter().map(|row| &*row.id).collect();
+        assert_eq!(heaviest, vec!["L01.21.03", "L01.21.01"]);
+        let empty: Vec<&str> = empty_leaves(&rows).iter().map(|row| &*row.id).collect();
+        assert_eq!(empty, vec!["L01.21.02", "L01.21.03.0001"]);
+    }
+
+    #[test]
+    fn test_note_counts() {
+        let system =
+            system("20-29 Finance\n\t21 Tax\n\t\t01 Returns\n\t\t\tX01 Drafts\n\t\t02 Receipts\n");
+        let returns = Path::new("/vault/L01/L01.20-29 Finance/L01.21 Tax/L01.21.01 Returns");
+        let mut memory = MemoryFs::default();
+        memory.create_dir_all(&returns.join(".obsidian")).unwrap();
+        memory.write(&returns.join("2024.md"), "").unwrap();
+        memory.write(&returns.join("2024.pdf"), "").unwrap();
+        memory
+            .write(&returns.join(".obsidian/cache.md"), "")
+            .unwrap();
+        memory
+            .create_dir_all(&returns.join("L01.21.01.X01 Drafts"))
+            .unwrap();
+        memory
+            .write(&returns.join("L01.21.01.X01 Drafts/Draft.md"), "")
+            .unwrap();
+        let actions = notes::get_all_actions(Path::new("/vault"), &system, "md");
+        let counts = note_counts(&memory, "/vault", &system, &actions, "md").unwrap();
+        assert_eq!(counts.get("L01.21.01"), Some(&2));
+        assert_eq!(counts.get("L01.21.01.X01"), Some(&1));
+        assert_eq!(counts.get("L01.21.02"), Some(&0));
+    }
+}
@@ -0,0 +1,16 @@
+use crate::{config::SystemParameters, model::System};
+
+/// The parameters of the system `L01 Life`, defined by `config`, for setting a few more of them
+pub fn parameters(config: &str) -> SystemParameters {
+    SystemParameters {
+        system_id: "L01".to_owned(),
+        name: "Life".to_owned(),
+        config: config.to_owned(),
+        ..SystemParameters::default()
+    }
+}
+
+/// The system `L01 Life`, defined by `config`
+pub fn system(config: &str) -> System {
+    System::try_from(parameters(config)).unwrap()
+}
@@ -0,0 +1,182 @@
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds in a day
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// A point in time, in whole seconds since the unix epoch, always shown in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    /// Seconds since the unix epoch
+    seconds: u64,
+}
+
+/// A calendar date and time of day in UTC
+#[derive(Debug, PartialEq, Eq)]
+struct Civil {
+    /// The year
+    year: u64,
+    /// The month, starting at 1
+    month: u64,
+    /// The day of the month, starting at 1
+    day: u64,
+    /// The hour of the day
+    hour: u64,
+    /// The minute of the hour
+    minute: u64,
+    /// The second of the minute
+    second: u64,
+}
+
+impl Timestamp {
+    /// The current time
+    pub fn now() -> Self {
+        Self::from(SystemTime::now())
+    }
+
+    /// Create a timestamp from seconds since the unix epoch
+    pub const fn from_seconds(seconds: u64) -> Self {
+        Self { seconds }
+    }
+
+    /// The seconds since the unix epoch
+    pub const fn seconds(self) -> u64 {
+        self.seconds
+    }
+
+    /// Split the timestamp into its calendar date and time of day.
+    ///
+    /// Uses the days-to-civil algorithm from <http://howardhinnant.github.io/date_algorithms.html>,
+    /// which only needs to handle dates after the epoch here.
+    #[expect(
+        clippy::arithmetic_side_effects,
+        clippy::integer_division,
+        reason = "The values are bounded by the calendar math and can't overflow"
+    )]
+    const fn civil(self) -> Civil {
+        let days = self.seconds / SECONDS_PER_DAY;
+        let time = self.seconds % SECONDS_PER_DAY;
+        let shifted = days + 719_468;
+        let era = shifted / 146_097;
+        let day_of_era = shifted - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Civil {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time % 3600 / 60,
+            second: time % 60,
+        }
+    }
+
+    /// A compact form that sorts by time and is safe to use in a file name, like `20241016T093000Z`
+    pub fn file_stamp(self) -> String {
+        let civil = self.civil();
+        format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            civil.year, civil.month, civil.day, civil.hour, civil.minute, civil.second
+        )
+    }
+
+    /// The time and date packed the way MS-DOS does, as zip archives keep them, with anything
+    /// before 1980, the earliest it can hold, as the start of 1980
+    pub fn dos(self) -> (u16, u16) {
+        let civil = self.civil();
+        if civil.year < 1980 {
+            return (0, (1 << 5) | 1);
+        }
+        let time = (civil.hour << 11) | (civil.minute << 5) | (civil.second / 2);
+        let date = ((civil.year - 1980) << 9) | (civil.month << 5) | civil.day;
+        (
+            u16::try_from(time).unwrap_or_default(),
+            u16::try_from(date).unwrap_or(u16::MAX),
+        )
+    }
+
+    /// The year, like `2024`
+    pub const fn year(self) -> u64 {
+        self.civil().year
+    }
+
+    /// The day, like `2024-10-16`
+    pub fn date(self) -> String {
+        let civil = self.civil();
+        format!("{:04}-{:02}-{:02}", civil.year, civil.month, civil.day)
+    }
+
+    /// The same time `days` days earlier, or the epoch when that is before it
+    pub const fn days_before(self, days: u64) -> Self {
+        Self::from_seconds(
+            self.seconds
+                .saturating_sub(days.saturating_mul(SECONDS_PER_DAY)),
+        )
+    }
+
+    /// The first day of the month, like `2024-10-01`
+    pub fn month_start(self) -> String {
+        let civil = self.civil();
+        format!("{:04}-{:02}-01", civil.year, civil.month)
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        Self::from_seconds(
+            time.duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+impl Display for Timestamp {
+    #[allow(
+        clippy::min_ident_chars,
+        reason = "This is the preferred default name for the variable"
+    )]
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let civil = self.civil();
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            civil.year, civil.month, civil.day, civil.hour, civil.minute, civil.second
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epoch() {
+        assert_eq!(
+            Timestamp::from_seconds(0).to_string(),
+            "1970-01-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_leap_day() {
+        let timestamp = Timestamp::from_seconds(1_709_210_096);
+        assert_eq!(timestamp.to_string(), "2024-02-29T12:34:56Z");
+        assert_eq!(timestamp.file_stamp(), "20240229T123456Z");
+        assert_eq!(timestamp.month_start(), "2024-02-01");
+        assert_eq!(timestamp.date(), "2024-02-29");
+        assert_eq!(timestamp.days_before(29).date(), "2024-01-31");
+        assert_eq!(timestamp.dos(), (25_692, 22_621));
+        // MS-DOS dates start in 1980
+        assert_eq!(Timestamp::from_seconds(0).dos(), (0, 33));
+    }
+}
@@ -0,0 +1,111 @@
+use core::fmt::Write as _;
+use std::io::Write as _;
+
+use color_eyre::eyre::Error;
+
+use crate::{
+    model::{FullId, Status, System},
+    output,
+};
+
+/// A line of the tree at `depth`, colored by the status of the entry, or of the entry above it
+/// when it has none of its own
+fn line(
+    tree: &mut String,
+    depth: usize,
+    text: &str,
+    own: Option<Status>,
+    inherited: Option<Status>,
+    retired: bool,
+) {
+    let mut text = text.to_owned();
+    if let Some(status) = own {
+        let _ = write!(text, " ({})", status.tag());
+    }
+    if retired {
+        text.push_str(" (retired)");
+    }
+    let status = if retired {
+        Some(Status::Archived)
+    } else {
+        own.or(inherited)
+    };
+    let _ = writeln!(
+        tree,
+        "{}{}",
+        "  ".repeat(depth),
+        output::status(&text, status)
+    );
+}
+
+/// The whole system as an indented tree, with the status of each entry
+fn render(system: &System) -> String {
+    let mut tree = format!("{}\n", output::heading(&system.id()));
+    for area in &system.areas {
+        line(&mut tree, 1, &area.id(), None, None, false);
+        for category in &area.categories {
+            line(
+                &mut tree,
+                2,
+                &category.id(),
+                category.status,
+                None,
+                category.retired,
+            );
+            for folder in &category.folders {
+                line(
+                    &mut tree,
+                    3,
+                    &folder.id(),
+                    folder.status,
+                    category.status,
+                    folder.retired,
+                );
+                for xfolder in &folder.folders {
+                    line(
+                        &mut tree,
+                        4,
+                        &xfolder.id(),
+                        xfolder.status,
+                        folder.status.or(category.status),
+                        xfolder.retired,
+                    );
+                }
+            }
+        }
+    }
+    tree
+}
+
+/// Print the system as a tree, colored by the status of each entry
+pub fn run(system: &System) -> Result<(), Error> {
+    write!(anstream::stdout(), "{}", render(system))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_render() {
+        let system = system(
+            "30-39 Projects\n\t31 Garden [status=active]\n\t\t01 Beds\n\t\t02 Shed [status=on-hold]\n\t32 Boat [status=archived]\n\t33 Old [retired]\n",
+        );
+        let tree = render(&system);
+        assert_eq!(
+            anstream::adapter::strip_str(&tree).to_string(),
+            "L01 Life
+  L01.30-39 Projects
+    L01.31 Garden (active)
+      L01.31.01 Beds
+      L01.31.02 Shed (on-hold)
+    L01.32 Boat (archived)
+    L01.33 Old (retired)
+"
+        );
+        // The folders without a status of their own are colored like their category
+        assert!(tree.contains(&output::status("L01.31.01 Beds", Some(Status::Active))));
+    }
+}
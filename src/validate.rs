@@ -0,0 +1,616 @@
+use core::cmp::Reverse;
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use std::collections::{BTreeMap, BTreeSet};
+
+use color_eyre::eyre::Error;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config,
+    line::{parse_single, split_attributes, ParsedKind},
+    model::{FolderKind, FullId, System},
+    naming::NamingConfig,
+};
+
+/// Topics at least this similar (0 to 1) are reported as near-duplicates
+const SIMILARITY_THRESHOLD: f64 = 0.85;
+
+/// How many entries a level can hold before it is worth splitting
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// The most folders a category should have, or `0` for no limit
+    pub max_folders: usize,
+    /// The most categories an area should have, or `0` for no limit
+    pub max_categories: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_folders: 25,
+            max_categories: 10,
+        }
+    }
+}
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Something that is probably a mistake, but doesn't stop the system from being generated
+    Warning,
+    /// Something that has to be fixed
+    Error,
+}
+
+/// A single problem found while validating the system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// How serious the problem is
+    pub severity: Severity,
+    /// What the problem is
+    pub message: String,
+}
+
+impl Finding {
+    /// Create a new warning
+    pub const fn warning(message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+        }
+    }
+
+    /// Create a new error
+    pub const fn error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+        }
+    }
+}
+
+impl Display for Finding {
+    #[allow(
+        clippy::min_ident_chars,
+        reason = "This is the preferred default name for the variable"
+    )]
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.severity {
+            Severity::Warning => write!(f, "warning: {}", self.message),
+            Severity::Error => write!(f, "error: {}", self.message),
+        }
+    }
+}
+
+/// Every entry of the system with its id, topic, and whether it is retired
+fn entries(system: &System) -> Vec<(String, &str, bool)> {
+    let mut entries = Vec::new();
+    for area in &system.areas {
+        entries.push((area.id.by_seperator("."), &*area.topic, false));
+        for category in &area.categories {
+            entries.push((
+                category.id.by_seperator("."),
+                &*category.topic,
+                category.retired,
+            ));
+            for folder in &category.folders {
+                entries.push((folder.id.by_seperator("."), &*folder.topic, folder.retired));
+                for xfolder in &folder.folders {
+                    entries.push((
+                        xfolder.id.by_seperator("."),
+                        &*xfolder.topic,
+                        xfolder.retired,
+                    ));
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Normalize a topic so that differences in case and spacing don't hide duplicates
+fn normalize(topic: &str) -> String {
+    topic
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Find topics that are used by more than one id, and topics that are nearly the same.
+///
+/// Retired entries are left out, since reusing the topic of a retired id is expected.
+pub fn duplicate_topics(system: &System) -> Vec<Finding> {
+    let mut by_topic: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (id, topic, _) in entries(system)
+        .into_iter()
+        .filter(|(_, _, retired)| !retired)
+    {
+        by_topic.entry(normalize(topic)).or_default().push(id);
+    }
+
+    let mut findings: Vec<Finding> = by_topic
+        .iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|(topic, ids)| {
+            Finding::warning(format!("the topic `{topic}` is used by {}", ids.join(", ")))
+        })
+        .collect();
+
+    let topics: Vec<(&String, &Vec<String>)> = by_topic.iter().collect();
+    for (index, (topic, ids)) in topics.iter().enumerate() {
+        for (other, other_ids) in topics.iter().skip(index.saturating_add(1)) {
+            if topic.len().abs_diff(other.len()) > 3 {
+                continue;
+            }
+            if strsim::normalized_levenshtein(topic, other) >= SIMILARITY_THRESHOLD {
+                findings.push(Finding::warning(format!(
+                    "the topic `{topic}` ({}) is very similar to `{other}` ({})",
+                    ids.join(", "),
+                    other_ids.join(", ")
+                )));
+            }
+        }
+    }
+    findings
+}
+
+/// Find ids that are declared more than once, including retired ids that were reissued
+pub fn duplicate_ids(system: &System) -> Vec<Finding> {
+    let mut by_id: BTreeMap<String, Vec<(&str, bool)>> = BTreeMap::new();
+    for (id, topic, retired) in entries(system) {
+        by_id.entry(id).or_default().push((topic, retired));
+    }
+    by_id
+        .into_iter()
+        .filter(|(_, topics)| topics.len() > 1)
+        .map(|(id, topics)| {
+            let (retired, reused): (Vec<_>, Vec<_>) =
+                topics.iter().partition(|(_, retired)| *retired);
+            let names = |topics: &[&(&str, bool)]| {
+                topics
+                    .iter()
+                    .map(|(topic, _)| *topic)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            if retired.is_empty() || reused.is_empty() {
+                Finding::error(format!(
+                    "the id {id} is declared more than once, for {}",
+                    names(&topics.iter().collect::<Vec<_>>())
+                ))
+            } else {
+                Finding::error(format!(
+                    "the id {id} was retired from {} and must not be reused for {}",
+                    names(&retired),
+                    names(&reused)
+                ))
+            }
+        })
+        .collect()
+}
+
+/// The decade that `id` falls in, like `(10, 19)` for `12`
+#[expect(
+    clippy::integer_division,
+    reason = "Rounding down to the decade is the point"
+)]
+const fn decade(id: u8) -> (u8, u8) {
+    let start = id / 10 * 10;
+    (start, start.saturating_add(9))
+}
+
+/// Find areas that aren't a single decade, that overlap, or that don't cover their categories
+pub fn area_ranges(system: &System) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for area in &system.areas {
+        let (start, end) = area.id_range;
+        let suggested = decade(start);
+        if (start, end) != suggested {
+            findings.push(Finding::warning(format!(
+                "the area {start:02}-{end:02} {} isn't a decade, try {:02}-{:02}",
+                area.topic, suggested.0, suggested.1
+            )));
+        }
+        for category in &area.categories {
+            let Some(category_id) = category.id.category.as_ref().map(|id| id.id) else {
+                continue;
+            };
+            if !(start..=end).contains(&category_id) {
+                let (decade_start, decade_end) = decade(category_id);
+                findings.push(Finding::warning(format!(
+                    "the category {category_id:02} {} is outside its area {start:02}-{end:02} {}, move it to the area {decade_start:02}-{decade_end:02}",
+                    category.topic, area.topic
+                )));
+            }
+        }
+    }
+
+    let mut ranges: Vec<(u8, u8, &str)> = system
+        .areas
+        .iter()
+        .map(|area| (area.id_range.0, area.id_range.1, &*area.topic))
+        .collect();
+    ranges.sort_unstable();
+    for pair in ranges.windows(2) {
+        if let [(start, end, topic), (next_start, next_end, next_topic)] = pair {
+            if next_start <= end {
+                findings.push(Finding::warning(format!(
+                    "the areas {start:02}-{end:02} {topic} and {next_start:02}-{next_end:02} {next_topic} overlap"
+                )));
+            }
+        }
+    }
+    findings
+}
+
+/// Find topics that break the naming rules of the config.
+///
+/// Retired entries are left out, since their topics are kept as they were.
+pub fn naming(system: &System, rules: &NamingConfig) -> Vec<Finding> {
+    entries(system)
+        .into_iter()
+        .filter(|(_, _, retired)| !retired)
+        .flat_map(|(id, topic, _)| {
+            rules.problems(topic).into_iter().map(move |problem| {
+                Finding::warning(format!("the topic `{topic}` of {id} {problem}"))
+            })
+        })
+        .collect()
+}
+
+/// Find categories with more folders than `limits` allow, and areas with more categories, which
+/// are worth splitting.
+///
+/// Retired entries are left out, since they only hold on to their ids.
+pub fn overloaded(system: &System, limits: LimitsConfig) -> Vec<Finding> {
+    let over = |count: usize, limit: usize| limit > 0 && count > limit;
+    let mut findings = Vec::new();
+    for area in &system.areas {
+        let categories = area
+            .categories
+            .iter()
+            .filter(|category| !category.retired)
+            .count();
+        if over(categories, limits.max_categories) {
+            findings.push(Finding::warning(format!(
+                "the area {} has {categories} categories, more than the limit of {}, split it into areas of their own",
+                area.id(),
+                limits.max_categories
+            )));
+        }
+        for category in area.categories.iter().filter(|category| !category.retired) {
+            let folders = category
+                .folders
+                .iter()
+                .filter(|folder| !folder.retired)
+                .count();
+            if over(folders, limits.max_folders) {
+                findings.push(Finding::warning(format!(
+                    "the category {} has {folders} folders, more than the limit of {}, split it into categories of their own",
+                    category.id(),
+                    limits.max_folders
+                )));
+            }
+        }
+    }
+    findings
+}
+
+/// Find entries under a frozen entry that aren't frozen themselves.
+///
+/// Freezing an entry freezes everything under it, so these were added after it was frozen.
+pub fn added_to_frozen(system: &System) -> Vec<Finding> {
+    let added = |entry: &dyn FullId, frozen: &dyn FullId| {
+        Finding::warning(format!(
+            "{} was added under {}, which is frozen, file it somewhere else or freeze it too",
+            entry.id(),
+            frozen.id()
+        ))
+    };
+    let mut findings = Vec::new();
+    for category in system.areas.iter().flat_map(|area| &area.categories) {
+        for folder in &category.folders {
+            if category.frozen && !folder.frozen {
+                findings.push(added(folder, category));
+            }
+            let frozen: Option<&dyn FullId> = if category.frozen {
+                Some(category)
+            } else if folder.frozen {
+                Some(folder)
+            } else {
+                None
+            };
+            let Some(frozen) = frozen else {
+                continue;
+            };
+            for xfolder in folder.folders.iter().filter(|xfolder| !xfolder.frozen) {
+                findings.push(added(xfolder, frozen));
+            }
+        }
+    }
+    findings
+}
+
+/// What an entry of `kind` is generated as, with nothing for a link since it has no kind of its
+/// own
+const fn generated_as(kind: &FolderKind) -> Option<&'static str> {
+    match kind {
+        FolderKind::Folder => Some("a folder"),
+        FolderKind::File => Some("a note"),
+        FolderKind::Both => Some("a folder with a note"),
+        FolderKind::Index | FolderKind::FullIndex => Some("an index"),
+        FolderKind::Link(_) => None,
+    }
+}
+
+/// Find topics that are a note in one place and a folder in another, which is usually a mistyped
+/// directive, pointing at the lines of the definition involved.
+///
+/// Retired entries are left out, since nothing is generated for them.
+pub fn kinds(system: &System, definition: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut by_topic: BTreeMap<String, Vec<(usize, &str, &str)>> = BTreeMap::new();
+    // The kind of the folder the extended folders that follow are in
+    let mut folder_kind = FolderKind::Folder;
+    for (index, line) in definition
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !config::is_comment(line))
+    {
+        let line_no = index.saturating_add(1);
+        let (raw, attributes) = split_attributes(line);
+        if attributes.has("retired") {
+            continue;
+        }
+        let tagged = attributes.kind();
+        let (kind, topic) = match parse_single(line_no, raw) {
+            Ok(ParsedKind::Folder(_, style, topic)) => {
+                let (kind, topic) = match (tagged, style) {
+                    (Some(kind), _) => (kind, topic),
+                    (None, FolderKind::Folder) => (system.default_folder_kind.clone(), topic),
+                    (None, style) => {
+                        let directive = style.directive().map_or(0, str::len);
+                        (style, &topic[directive..])
+                    }
+                };
+                folder_kind = kind.clone();
+                (kind, topic)
+            }
+            Ok(ParsedKind::ExtendedFolder(_, style, topic)) => match (tagged, style) {
+                (Some(kind), _) => (kind, topic),
+                (None, FolderKind::Folder) => (
+                    system
+                        .xfolder_kind
+                        .kind(&folder_kind, &system.default_folder_kind),
+                    topic,
+                ),
+                (None, style) => {
+                    let directive = style.directive().map_or(0, str::len);
+                    (style, &topic[directive..])
+                }
+            },
+            _ => continue,
+        };
+        if let Some(generated) = generated_as(&kind) {
+            by_topic
+                .entry(normalize(topic))
+                .or_default()
+                .push((line_no, raw.trim(), generated));
+        }
+    }
+    for (topic, uses) in &by_topic {
+        let generated: BTreeSet<&str> = uses.iter().map(|(_, _, generated)| *generated).collect();
+        if generated.len() < 2 {
+            continue;
+        }
+        let lines: Vec<String> = uses
+            .iter()
+            .map(|(line_no, line, generated)| format!("{generated} on line {line_no} (`{line}`)"))
+            .collect();
+        findings.push(Finding::warning(format!(
+            "the topic `{topic}` is {}, check the `-`, `+`, or `!` at the start of their topics",
+            lines.join(" and ")
+        )));
+    }
+    findings
+}
+
+/// Run every check against the system and the `definition` it was read from
+pub fn validate(
+    system: &System,
+    definition: &str,
+    rules: &NamingConfig,
+    limits: LimitsConfig,
+) -> Vec<Finding> {
+    let mut findings = duplicate_ids(system);
+    findings.extend(duplicate_topics(system));
+    findings.extend(kinds(system, definition));
+    findings.extend(area_ranges(system));
+    findings.extend(naming(system, rules));
+    findings.extend(overloaded(system, limits));
+    findings.extend(added_to_frozen(system));
+    findings.sort_by_key(|finding| Reverse(finding.severity));
+    findings
+}
+
+/// Print every finding, failing when any of them is an error.
+///
+/// With `strict`, every warning is treated as an error.
+pub fn run(
+    system: &System,
+    definition: &str,
+    rules: &NamingConfig,
+    limits: LimitsConfig,
+    strict: bool,
+) -> Result<(), Error> {
+    let mut findings = validate(system, definition, rules, limits);
+    if strict {
+        for finding in &mut findings {
+            finding.severity = Severity::Error;
+        }
+    }
+    for finding in &findings {
+        println!("{finding}");
+    }
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(Error::msg(format!("The system has {errors} errors")));
+    }
+    if findings.is_empty() {
+        println!("No problems found");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::system;
+
+    #[test]
+    fn test_duplicate_topics() {
+        let findings = duplicate_topics(&system(
+            "10-19 Life\n\t11 Home\n\t\t01 Inbox\n\t12 Work\n\t\t01 inbox\n",
+        ));
+        assert_eq!(
+            findings,
+            vec![Finding::warning(
+                "the topic `inbox` is used by L01.11.01, L01.12.01".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_ids() {
+        let findings = duplicate_ids(&system("10-19 Life\n\t11 Home\n\t11 House\n"));
+        assert_eq!(
+            findings,
+            vec![Finding::error(
+                "the id L01.11 is declared more than once, for Home, House".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_reused_retired_id() {
+        let findings = duplicate_ids(&system(
+            "10-19 Life\n\t11 Home\n\t\t01 Inbox [retired]\n\t\t01 Mail\n",
+        ));
+        assert_eq!(
+            findings,
+            vec![Finding::error(
+                "the id L01.11.01 was retired from Inbox and must not be reused for Mail"
+                    .to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_area_ranges() {
+        let findings = area_ranges(&system("00-09 System\n\t00 Meta\n05-14 Life\n\t15 Home\n"));
+        assert_eq!(
+            findings,
+            vec![
+                Finding::warning("the area 05-14 Life isn't a decade, try 00-09".to_owned()),
+                Finding::warning(
+                    "the category 15 Home is outside its area 05-14 Life, move it to the area 10-19"
+                        .to_owned()
+                ),
+                Finding::warning(
+                    "the areas 00-09 System and 05-14 Life overlap".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_naming() {
+        let rules = NamingConfig {
+            case: Some(crate::naming::Case::Title),
+            ..NamingConfig::default()
+        };
+        let findings = naming(
+            &system("10-19 Life\n\t11 Home\n\t\t01 tax returns\n\t\t02 old stuff [retired]\n"),
+            &rules,
+        );
+        assert_eq!(
+            findings,
+            vec![Finding::warning(
+                "the topic `tax returns` of L01.11.01 isn't in title case, try `Tax Returns`"
+                    .to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_overloaded() {
+        let limits = LimitsConfig {
+            max_folders: 2,
+            max_categories: 1,
+        };
+        let findings = overloaded(
+            &system(
+                "10-19 Life\n\t11 Home\n\t\t01 Inbox\n\t\t02 Mail\n\t\t03 Old [retired]\n\t12 Work\n\t\t01 Inbox\n\t\t02 Mail\n\t\t03 Desk\n",
+            ),
+            limits,
+        );
+        assert_eq!(
+            findings,
+            vec![
+                Finding::warning("the area L01.10-19 Life has 2 categories, more than the limit of 1, split it into areas of their own".to_owned()),
+                Finding::warning("the category L01.12 Work has 3 folders, more than the limit of 2, split it into categories of their own".to_owned()),
+            ]
+        );
+        let unlimited = LimitsConfig {
+            max_folders: 0,
+            max_categories: 0,
+        };
+        assert!(overloaded(&system("10-19 Life\n\t11 Home\n\t12 Work\n"), unlimited).is_empty());
+    }
+
+    #[test]
+    fn test_added_to_frozen() {
+        let findings = added_to_frozen(&system(
+            "10-19 Life\n\t11 Home [frozen]\n\t\t01 Inbox [frozen]\n\t\t02 Mail\n\t12 Work\n\t\t01 Desk [frozen]\n\t\t\tX01 Drafts\n",
+        ));
+        assert_eq!(
+            findings,
+            vec![
+                Finding::warning("L01.11.02 Mail was added under L01.11 Home, which is frozen, file it somewhere else or freeze it too".to_owned()),
+                Finding::warning("L01.12.01.X01 Drafts was added under L01.12.01 Desk, which is frozen, file it somewhere else or freeze it too".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_similar_topics() {
+        let findings = duplicate_topics(&system(
+            "10-19 Life\n\t11 Insurance\n\t12 Insurances\n\t13 Travel\n",
+        ));
+        assert_eq!(
+            findings,
+            vec![Finding::warning(
+                "the topic `insurance` (L01.11) is very similar to `insurances` (L01.12)"
+                    .to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_kinds() {
+        let definition = "10-19 Life\n\t11 Home\n\t\t01 -Returns\n\t\t02 Mail\n\t\t\tX01 +Letters\n\t12 Tax\n\t\t01 Returns\n\t\t02 -Mail [retired]\n";
+        assert_eq!(
+            kinds(&system(definition), definition),
+            vec![Finding::warning(
+                "the topic `returns` is a note on line 3 (`01 -Returns`) and a folder on line 7 (`01 Returns`), check the `-`, `+`, or `!` at the start of their topics"
+                    .to_owned()
+            )]
+        );
+    }
+}
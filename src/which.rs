@@ -0,0 +1,130 @@
+use std::{
+    env,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Error, OptionExt};
+
+use crate::{
+    cache::PathIndex,
+    config,
+    model::{FolderKind, FullId, HasJohnnyId, System},
+    notes,
+};
+
+/// Every entry of the system that has a place on disk, along with its folder kind
+pub fn entries(system: &System) -> Vec<(&dyn HasJohnnyId, Option<&FolderKind>)> {
+    let mut entries: Vec<(&dyn HasJohnnyId, Option<&FolderKind>)> = Vec::new();
+    for area in &system.areas {
+        entries.push((area, None));
+        for category in &area.categories {
+            entries.push((category, None));
+            for folder in &category.folders {
+                entries.push((folder, Some(&folder.kind)));
+                for xfolder in &folder.folders {
+                    entries.push((xfolder, Some(&xfolder.kind)));
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// The entry that `path` belongs to in one of `bases`, which is the deepest entry whose directory
+/// it is in, or the entry whose note it is
+fn owner<'sys>(
+    system: &'sys System,
+    paths: &PathIndex,
+    bases: &[PathBuf],
+    path: &Path,
+) -> Option<&'sys dyn FullId> {
+    bases
+        .iter()
+        .filter_map(|base| path.strip_prefix(base).ok())
+        .filter_map(|relative| {
+            relative.ancestors().find_map(|ancestor| {
+                paths
+                    .get(ancestor)
+                    .map(|id| (ancestor.components().count(), id))
+            })
+        })
+        .max_by_key(|&(depth, _)| depth)
+        .and_then(|(_, id)| system.find(id))
+}
+
+/// `path` as an absolute path with every link resolved, when it exists, so it can be compared
+/// with the folders of the config file however it was written
+fn absolute(path: &Path) -> Result<PathBuf, Error> {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()?.join(path)
+    };
+    Ok(path.canonicalize().unwrap_or(path))
+}
+
+/// The notes and the reference archive, as absolute paths with every link resolved
+pub fn bases(output_config: &config::Output) -> Result<Vec<PathBuf>, Error> {
+    [&output_config.base_folder, &output_config.reference_folder]
+        .into_iter()
+        .map(|folder| absolute(&notes::expand(folder)?))
+        .collect()
+}
+
+/// Print the id and topic of the entry that `path` belongs to in the notes or the reference
+/// archive, the reverse of looking up the path of an id
+pub fn run(
+    output_config: &config::Output,
+    system: &System,
+    paths: &PathIndex,
+    path: &Path,
+) -> Result<(), Error> {
+    let bases = bases(output_config)?;
+    let entry = owner(system, paths, &bases, &absolute(path)?).ok_or_eyre(format!(
+        "{} isn't in any entry of {}",
+        path.display(),
+        system.name
+    ))?;
+    writeln!(
+        anstream::stdout(),
+        "{} {}",
+        entry.jid().by_seperator("."),
+        entry.name()
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache, test_support::system};
+
+    #[test]
+    fn test_owner() {
+        let system =
+            system("20-29 Finance\n\t21 Tax\n\t\t05 Returns\n\t\t\tX01 Drafts\n\t\t06 -Receipts\n");
+        let paths = cache::index(&system, "md");
+        let bases = [PathBuf::from("/notes"), PathBuf::from("/archive")];
+        let id = |path: &str| {
+            owner(&system, &paths, &bases, Path::new(path))
+                .map(|entry| format!("{} {}", entry.jid().by_seperator("."), entry.name()))
+        };
+        let tax = "/archive/L01/L01.20-29 Finance/L01.21 Tax";
+        assert_eq!(
+            id(&format!("{tax}/L01.21.05 Returns/2024/return.pdf")).as_deref(),
+            Some("L01.21.05 Returns")
+        );
+        assert_eq!(
+            id(&format!("{tax}/L01.21.05 Returns/L01.21.05.X01 Drafts")).as_deref(),
+            Some("L01.21.05.X01 Drafts")
+        );
+        assert_eq!(id(tax).as_deref(), Some("L01.21 Tax"));
+        assert_eq!(
+            id("/notes/L01/L01.20-29 Finance/L01.21 Tax/L01.21.06 Receipts.md").as_deref(),
+            Some("L01.21.06 Receipts")
+        );
+        assert_eq!(id("/notes/L01"), None);
+        assert_eq!(id("/elsewhere/L01.21.05 Returns"), None);
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::JohnnyDecimal, notes, state};
+
+/// The file the hashes of the written indexes are kept in, inside the state folder
+const WRITTEN_FILE: &str = "written.toml";
+
+/// The hash of the managed part of every index and map of content as jdexmd last wrote it, so an
+/// index edited since, like on another device of a synced vault, isn't overwritten unnoticed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Written {
+    /// The hash of each file, keyed by its path
+    #[serde(default)]
+    files: BTreeMap<String, String>,
+}
+
+impl Written {
+    /// The file the hashes of the system defined in `config_file` are kept in
+    fn path(config_file: &Path, state_folder: Option<&str>) -> Result<PathBuf, Error> {
+        Ok(state::folder(config_file, state_folder)?.join(WRITTEN_FILE))
+    }
+
+    /// Read the hashes of the system defined in `config_file`, which are empty until an index is
+    /// first written
+    pub fn load(config_file: &Path, state_folder: Option<&str>) -> Result<Self, Error> {
+        let path = Self::path(config_file, state_folder)?;
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Keep the hashes of the system defined in `config_file`
+    pub fn save(&self, config_file: &Path, state_folder: Option<&str>) -> Result<(), Error> {
+        let path = Self::path(config_file, state_folder)?;
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// The hash of what jdexmd manages of `contents`, leaving out what was written by hand below
+    /// the end marker
+    fn hash(contents: &str) -> String {
+        JohnnyDecimal::hash(notes::managed_section(contents))
+    }
+
+    /// Whether the file at `path` was changed since jdexmd last wrote it, going by its `contents`
+    /// now. A file jdexmd never wrote doesn't count as changed.
+    pub fn edited(&self, path: &Path, contents: &str) -> bool {
+        self.files
+            .get(&path.display().to_string())
+            .is_some_and(|hash| *hash != Self::hash(contents))
+    }
+
+    /// Remember that jdexmd wrote `contents` to the file at `path`
+    pub fn record(&mut self, path: &Path, contents: &str) {
+        self.files
+            .insert(path.display().to_string(), Self::hash(contents));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edited() {
+        let path = Path::new("/notes/L01/L01 Life.md");
+        let mut written = Written::default();
+        assert!(!written.edited(path, "# Life\n"));
+        written.record(path, "# Life\n<!-- jdexmd:end -->\nMine\n");
+        assert!(!written.edited(path, "# Life\n<!-- jdexmd:end -->\nMine, edited\n"));
+        assert!(written.edited(
+            path,
+            "# Life, edited elsewhere\n<!-- jdexmd:end -->\nMine\n"
+        ));
+        let read: Written = toml::from_str(&toml::to_string(&written).unwrap()).unwrap();
+        assert!(read.edited(path, "# Life, edited elsewhere\n"));
+    }
+}